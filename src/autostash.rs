@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+use crate::output::Traced;
+
+/// Marker in the message of every stash git-surgeon creates, so stranded ones
+/// can be told apart from the user's own stashes.
+const MARKER: &str = "git-surgeon autostash";
+/// Message used before stashes were tracked by SHA
+const LEGACY_MESSAGE: &str = "git-surgeon squash autostash";
+
+/// A git-surgeon stash entry.
+struct Entry {
+    /// `stash@{n}`
+    selector: String,
+    sha: String,
+    message: String,
+}
+
+/// Stash uncommitted changes to tracked files before `operation` rewrites
+/// history. Returns the stash commit's SHA, or None if the tree was clean.
+pub fn push(operation: &str) -> Result<Option<String>> {
+    let status = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .traced_output()
+        .context("failed to check git status")?;
+    if String::from_utf8_lossy(&status.stdout).trim().is_empty() {
+        return Ok(None);
+    }
+
+    let output = Command::new("git")
+        .args([
+            "stash",
+            "push",
+            "-m",
+            &format!("{} ({})", MARKER, operation),
+        ])
+        .traced_output()
+        .context("failed to stash changes")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git stash failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "refs/stash"]))?;
+    Ok(Some(sha.trim().to_string()))
+}
+
+/// Restore the stash created by `push`, wherever it now sits in the stash
+/// list. A failed restore only warns: the stash is kept for
+/// `restore-autostash`.
+pub fn pop(sha: &str) -> Result<()> {
+    let short = &sha[..7.min(sha.len())];
+    let Some(entry) = entries()?.into_iter().find(|e| e.sha == sha) else {
+        eprintln!(
+            "warning: autostash {} is no longer in the stash list; \
+             run 'git-surgeon restore-autostash --list' to look for it",
+            short
+        );
+        return Ok(());
+    };
+    if let Err(e) = pop_entry(&entry) {
+        eprintln!(
+            "warning: could not restore autostash {} (conflicts?), it is kept as {}: {}",
+            short, entry.selector, e
+        );
+        eprintln!("once the working tree is clean, run: git-surgeon restore-autostash");
+    }
+    Ok(())
+}
+
+/// Pop the git-surgeon stashes that no operation in progress will restore,
+/// oldest first. With `list`, only print them.
+pub fn restore_orphaned(list: bool) -> Result<()> {
+    let pending = crate::state::State::load()?.and_then(|s| s.get("stash").map(str::to_string));
+    let mut orphans: Vec<Entry> = entries()?
+        .into_iter()
+        .filter(|e| pending.as_deref() != Some(e.sha.as_str()))
+        .collect();
+    orphans.reverse();
+
+    if orphans.is_empty() {
+        eprintln!("no orphaned git-surgeon autostash");
+        return Ok(());
+    }
+    if list {
+        for entry in &orphans {
+            println!("{} {} {}", entry.selector, &entry.sha[..7], entry.message);
+        }
+        return Ok(());
+    }
+
+    for orphan in orphans {
+        // Popping renumbers the stash list, so look the entry up again
+        let entry = entries()?
+            .into_iter()
+            .find(|e| e.sha == orphan.sha)
+            .ok_or_else(|| anyhow::anyhow!("stash {} disappeared", &orphan.sha[..7]))?;
+        pop_entry(&entry).with_context(|| {
+            format!(
+                "failed to restore autostash {} ({}); resolve and run 'git stash drop {}'",
+                &entry.sha[..7],
+                entry.selector,
+                entry.selector
+            )
+        })?;
+        eprintln!("restored autostash {} ({})", &entry.sha[..7], entry.message);
+    }
+    Ok(())
+}
+
+fn pop_entry(entry: &Entry) -> Result<()> {
+    let output = Command::new("git")
+        .args(["stash", "pop", "--quiet", &entry.selector])
+        .traced_output()
+        .context("failed to pop stash")?;
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// The git-surgeon entries of the stash list, newest first.
+fn entries() -> Result<Vec<Entry>> {
+    let out = crate::diff::run_git_cmd(Command::new("git").args([
+        "stash",
+        "list",
+        "--format=%gd%x00%H%x00%gs",
+    ]))?;
+    Ok(out
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\0');
+            let selector = fields.next()?.to_string();
+            let sha = fields.next()?.to_string();
+            // "On <branch>: <message>"
+            let subject = fields.next()?;
+            let message = subject.split_once(": ").map_or(subject, |(_, m)| m);
+            (message.starts_with(MARKER) || message == LEGACY_MESSAGE).then(|| Entry {
+                selector,
+                sha,
+                message: message.to_string(),
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,273 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::output::Traced;
+
+const BACKUP_PREFIX: &str = "refs/surgeon/backup/";
+/// Where `--update-refs` found the branches it moved:
+/// `refs/surgeon/backup-branches/<backup>/<branch>`.
+const BRANCHES_PREFIX: &str = "refs/surgeon/backup-branches/";
+
+/// Point a new `refs/surgeon/backup/<timestamp>-<operation>` ref at HEAD so a
+/// rewrite can be undone with `restore-backup`. Returns the short name.
+pub fn create(operation: &str) -> Result<String> {
+    let head = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head = head.trim();
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    // Milliseconds keep several backups from one plan in creation order
+    let stamp = format!(
+        "{}.{:03}",
+        format_timestamp(now.as_secs()),
+        now.subsec_millis()
+    );
+    let mut name = format!("{}-{}", stamp, operation);
+    let mut n = 2;
+    while ref_exists(&format!("{}{}", BACKUP_PREFIX, name)) {
+        name = format!("{}-{}-{}", stamp, operation, n);
+        n += 1;
+    }
+
+    crate::diff::run_git_cmd(Command::new("git").args([
+        "update-ref",
+        "-m",
+        &format!("git-surgeon {}", operation),
+        &format!("{}{}", BACKUP_PREFIX, name),
+        head,
+        "",
+    ]))?;
+    eprintln!("backup: {} ({})", name, &head[..7]);
+    Ok(name)
+}
+
+/// Record in the newest backup of `old_head` where each of `branches` was,
+/// as (branch, sha), before `--update-refs` moved it, so `restore-backup`
+/// moves it back. Nothing is recorded if no backup points at `old_head`.
+pub fn record_branches(old_head: &str, branches: &[(String, String)]) -> Result<()> {
+    if branches.is_empty() {
+        return Ok(());
+    }
+    let Some((name, _, _)) = list_backups()?
+        .into_iter()
+        .find(|(_, sha, _)| sha == old_head)
+    else {
+        return Ok(());
+    };
+    for (branch, sha) in branches {
+        crate::diff::run_git_cmd(Command::new("git").args([
+            "update-ref",
+            "-m",
+            "git-surgeon update-refs",
+            &format!("{}{}/{}", BRANCHES_PREFIX, name, branch),
+            sha,
+        ]))?;
+    }
+    Ok(())
+}
+
+/// The branches recorded in backup `name`, as (branch, sha).
+fn recorded_branches(name: &str) -> Result<Vec<(String, String)>> {
+    let prefix = format!("{}{}/", BRANCHES_PREFIX, name);
+    let out = crate::diff::run_git_cmd(Command::new("git").args([
+        "for-each-ref",
+        "--format=%(refname)%00%(objectname)",
+        &prefix,
+    ]))?;
+    Ok(out
+        .lines()
+        .filter_map(|line| {
+            let (refname, sha) = line.split_once('\0')?;
+            Some((refname.strip_prefix(&prefix)?.to_string(), sha.to_string()))
+        })
+        .collect())
+}
+
+/// Move the branches recorded in backup `name` back, except those checked
+/// out in a worktree. Their current positions go into the newest backup, the
+/// one `restore` just made, so the restore can be undone.
+fn restore_branches(name: &str) -> Result<()> {
+    let recorded = recorded_branches(name)?;
+    let mut restore = Vec::new();
+    let mut current = Vec::new();
+    for (branch, sha) in recorded {
+        let listed = crate::diff::run_git_cmd(Command::new("git").args([
+            "for-each-ref",
+            "--format=%(objectname)%00%(worktreepath)",
+            &format!("refs/heads/{}", branch),
+        ]))?;
+        let (now, worktree) = listed.trim_end().split_once('\0').unwrap_or(("", ""));
+        if now == sha {
+            continue;
+        }
+        if !worktree.is_empty() {
+            eprintln!(
+                "note: {} is checked out in {}; left at {}",
+                branch,
+                worktree,
+                &now[..7.min(now.len())]
+            );
+            continue;
+        }
+        if !now.is_empty() {
+            current.push((branch.clone(), now.to_string()));
+        }
+        restore.push((branch, sha));
+    }
+    if let Some((newest, _, _)) = list_backups()?.into_iter().next()
+        && newest != name
+    {
+        for (branch, sha) in &current {
+            crate::diff::run_git_cmd(Command::new("git").args([
+                "update-ref",
+                "-m",
+                "git-surgeon restore",
+                &format!("{}{}/{}", BRANCHES_PREFIX, newest, branch),
+                sha,
+            ]))?;
+        }
+    }
+    for (branch, sha) in &restore {
+        crate::diff::run_git_cmd(Command::new("git").args([
+            "update-ref",
+            "-m",
+            "git-surgeon restore-backup",
+            &format!("refs/heads/{}", branch),
+            sha,
+        ]))?;
+        eprintln!("restored {} to {}", branch, &sha[..7]);
+    }
+    Ok(())
+}
+
+fn ref_exists(name: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", name])
+        .traced_output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Backups as (short name, sha, subject), newest first.
+fn list_backups() -> Result<Vec<(String, String, String)>> {
+    let out = crate::diff::run_git_cmd(Command::new("git").args([
+        "for-each-ref",
+        "--sort=-refname",
+        "--format=%(refname)%00%(objectname)%00%(subject)",
+        BACKUP_PREFIX,
+    ]))?;
+    Ok(out
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\0');
+            let name = fields.next()?.strip_prefix(BACKUP_PREFIX)?.to_string();
+            let sha = fields.next()?.to_string();
+            let subject = fields.next().unwrap_or("").to_string();
+            Some((name, sha, subject))
+        })
+        .collect())
+}
+
+/// Print backups, newest first.
+pub fn print_backups() -> Result<()> {
+    let backups = list_backups()?;
+    if backups.is_empty() {
+        eprintln!("no backups");
+        return Ok(());
+    }
+    for (name, sha, subject) in backups {
+        println!("{} {} {}", name, &sha[..7], subject);
+    }
+    Ok(())
+}
+
+/// Reset the current branch to a backup (the newest if `name` is `None`),
+/// keeping uncommitted changes, and move back the branches `--update-refs`
+/// moved after it. The current HEAD is backed up first.
+pub fn restore(name: Option<&str>) -> Result<()> {
+    crate::hunk::check_no_rebase_in_progress()?;
+
+    let backups = list_backups()?;
+    let (name, sha, _) = match name {
+        Some(n) => {
+            let n = n.strip_prefix(BACKUP_PREFIX).unwrap_or(n);
+            backups
+                .into_iter()
+                .find(|(name, _, _)| name == n)
+                .ok_or_else(|| {
+                    anyhow::anyhow!("no backup named '{}' (see 'git-surgeon backups')", n)
+                })?
+        }
+        None => backups
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("no backups to restore"))?,
+    };
+
+    create("restore")?;
+    let output = Command::new("git")
+        .args(["reset", "--quiet", "--keep", &sha])
+        .traced_output()
+        .context("failed to run git reset")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git reset failed (commit or stash local changes first): {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    eprintln!("restored {} from {}", &sha[..7], name);
+    restore_branches(&name)
+}
+
+/// Format Unix seconds as a UTC `YYYYMMDD-HHMMSS` stamp, which sorts
+/// chronologically.
+fn format_timestamp(secs: u64) -> String {
+    let (year, month, day) = civil_date(secs);
+    let rem = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// Format Unix seconds as a UTC `YYYY-MM-DD` date.
+pub fn format_date(secs: u64) -> String {
+    let (year, month, day) = civil_date(secs);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The UTC (year, month, day) of Unix seconds.
+fn civil_date(secs: u64) -> (i64, i64, i64) {
+    let days = (secs / 86_400) as i64;
+    // Days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(format_timestamp(0), "19700101-000000");
+        assert_eq!(format_timestamp(951_782_400), "20000229-000000");
+        assert_eq!(format_timestamp(1_760_452_212), "20251014-143012");
+        assert_eq!(format_date(1_760_452_212), "2025-10-14");
+    }
+}
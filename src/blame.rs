@@ -1,5 +1,11 @@
 use anyhow::{Context, Result};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Mutex;
+
+use crate::output::Traced;
 
 /// Parse @@ header to extract line ranges.
 /// Returns (old_from, old_count, new_from, new_count).
@@ -33,26 +39,208 @@ fn parse_range(s: &str) -> Option<(usize, usize)> {
     }
 }
 
-/// Get blame hashes for a line range in a file.
-/// Returns Vec of 7-char hashes, one per line.
-/// If revision is None, blames the working tree.
-pub fn get_blame(
+/// How much `hunks --blame` prints per line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlameMode {
+    /// The commit hash
+    Short,
+    /// The commit hash, author date, author and subject
+    Full,
+}
+
+pub fn parse_blame_mode(s: &str) -> Result<BlameMode, String> {
+    match s {
+        "short" => Ok(BlameMode::Short),
+        "full" => Ok(BlameMode::Full),
+        _ => Err("expected short or full".to_string()),
+    }
+}
+
+/// Who last touched a line, from `git blame --line-porcelain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameLine {
+    /// 7-char commit hash, `0000000` for uncommitted lines
+    pub hash: String,
+    pub author: String,
+    /// Author time, Unix seconds
+    pub time: u64,
+    /// Commit subject
+    pub summary: String,
+}
+
+/// A line range to blame: `count` lines of `file` from line `from`, as of
+/// `revision`, or the working tree when None.
+pub struct BlameRange<'a> {
+    pub file: &'a str,
+    pub from: usize,
+    pub count: usize,
+    pub revision: Option<String>,
+}
+
+/// (commit SHA, file, the `--ignore-rev` arguments it was blamed with and a
+/// digest of each `blame.ignoreRevsFile`)
+type BlameKey = (String, String, Vec<String>);
+
+/// Blame of committed content never changes, so the daemon keeps it: the
+/// blamed lines by line number of each (commit SHA, file) blamed so far.
+static BLAME_CACHE: Mutex<Option<HashMap<BlameKey, HashMap<usize, BlameLine>>>> = Mutex::new(None);
+const BLAME_CACHE_SIZE: usize = 4096;
+
+/// A revision (None for the working tree) and a file in it.
+type Source = (Option<String>, String);
+
+/// Blamed lines by line number, for each (revision, file) a listing needs.
+pub struct Blame {
+    lines: HashMap<Source, HashMap<usize, BlameLine>>,
+}
+
+impl Blame {
+    /// Blame all of `ranges` up front, with one `git blame` per (file,
+    /// revision) that gets every range of that file as a `-L`, rather than
+    /// one run per range. Commits in `blame.ignoreRevsFile` and in
+    /// `ignore_revs` are looked through, as `git blame` does.
+    pub fn collect(ranges: &[BlameRange], ignore_revs: &[String]) -> Result<Blame> {
+        // Hunk paths are relative to the root, and so are ignore files
+        let root = PathBuf::from(
+            crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--show-toplevel"]))?
+                .trim(),
+        );
+        let ignore = Ignore::new(&root, ignore_revs)?;
+        let mut wanted: HashMap<Source, Vec<(usize, usize)>> = HashMap::new();
+        for range in ranges.iter().filter(|r| r.count > 0) {
+            wanted
+                .entry((range.revision.clone(), range.file.to_string()))
+                .or_default()
+                .push((range.from, range.count));
+        }
+        let sources: Vec<(Option<&str>, &str)> = wanted
+            .keys()
+            .map(|(revision, file)| (revision.as_deref(), file.as_str()))
+            .collect();
+        crate::promisor::prefetch_history(&sources)?;
+        let lines = wanted
+            .into_iter()
+            .map(|((revision, file), ranges)| {
+                // Graceful degradation: callers show 0000000 where blame is missing
+                let lines = blame_lines(&root, &file, &ranges, revision.as_deref(), &ignore)
+                    .unwrap_or_default();
+                ((revision, file), lines)
+            })
+            .collect();
+        Ok(Blame { lines })
+    }
+
+    /// The blame of each line of `range`, None where it is unknown (blame
+    /// failed).
+    pub fn get(&self, range: &BlameRange) -> Vec<Option<&BlameLine>> {
+        let lines = self
+            .lines
+            .get(&(range.revision.clone(), range.file.to_string()));
+        (range.from..range.from + range.count)
+            .map(|n| lines.and_then(|l| l.get(&n)))
+            .collect()
+    }
+}
+
+/// The commits blame looks through.
+struct Ignore {
+    /// `--ignore-rev` arguments
+    args: Vec<String>,
+    /// Each `blame.ignoreRevsFile` and a digest of its contents, which git
+    /// reads itself, so cached blame is redone when one changes
+    files: Vec<String>,
+}
+
+impl Ignore {
+    /// The `--ignore-rev` arguments for `ignore_revs`. git adds the commits
+    /// listed in `blame.ignoreRevsFile` itself, reading relative paths from
+    /// the repository root that blame runs in; a file that is missing there
+    /// makes every blame fail, so that is reported up front instead.
+    fn new(root: &Path, ignore_revs: &[String]) -> Result<Ignore> {
+        let configured = Command::new("git")
+            .args(["config", "--get-all", "blame.ignoreRevsFile"])
+            .traced_output()
+            .context("failed to run git config")?;
+        let mut files = Vec::new();
+        for file in String::from_utf8_lossy(&configured.stdout)
+            .lines()
+            .filter(|f| !f.is_empty())
+        {
+            let Ok(contents) = std::fs::read(root.join(file)) else {
+                anyhow::bail!(
+                    "blame.ignoreRevsFile '{}' does not exist in {}; create it or unset the setting",
+                    file,
+                    root.display()
+                );
+            };
+            files.push(format!("{} {}", file, hex::encode(Sha1::digest(&contents))));
+        }
+        let args = ignore_revs
+            .iter()
+            .map(|rev| Ok(format!("--ignore-rev={}", crate::rev::resolve_commit(rev)?)))
+            .collect::<Result<_>>()?;
+        Ok(Ignore { args, files })
+    }
+}
+
+/// Blame `ranges` of `file`, through the daemon's cache for commits.
+fn blame_lines(
+    root: &Path,
     file: &str,
-    from: usize,
-    count: usize,
+    ranges: &[(usize, usize)],
     revision: Option<&str>,
-) -> Result<Vec<String>> {
-    if count == 0 {
-        return Ok(Vec::new());
+    ignore: &Ignore,
+) -> Result<HashMap<usize, BlameLine>> {
+    // The working tree can change between requests; commits cannot
+    let key = match revision {
+        Some(rev) if crate::daemon::caching() => crate::rev::resolve_commit(rev).ok().map(|sha| {
+            let ignored = ignore.args.iter().chain(&ignore.files).cloned().collect();
+            (sha, file.to_string(), ignored)
+        }),
+        _ => None,
+    };
+    let Some(key) = key else {
+        return run_blame(root, file, ranges, revision, &ignore.args);
+    };
+    let covered = |lines: &HashMap<usize, BlameLine>| {
+        ranges
+            .iter()
+            .all(|&(from, count)| (from..from + count).all(|n| lines.contains_key(&n)))
+    };
+    {
+        let cache = BLAME_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(lines) = cache.as_ref().and_then(|c| c.get(&key))
+            && covered(lines)
+        {
+            return Ok(lines.clone());
+        }
     }
 
+    let lines = run_blame(root, file, ranges, revision, &ignore.args)?;
+    let mut cache = BLAME_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if cache.len() >= BLAME_CACHE_SIZE {
+        cache.clear();
+    }
+    let cached = cache.entry(key).or_default();
+    cached.extend(lines);
+    Ok(cached.clone())
+}
+
+fn run_blame(
+    root: &Path,
+    file: &str,
+    ranges: &[(usize, usize)],
+    revision: Option<&str>,
+    ignore: &[String],
+) -> Result<HashMap<usize, BlameLine>> {
     let mut cmd = Command::new("git");
-    cmd.args([
-        "blame",
-        "--line-porcelain",
-        "-L",
-        &format!("{},+{}", from, count),
-    ]);
+    cmd.current_dir(root)
+        .args(["blame", "--line-porcelain"])
+        .args(ignore);
+    for (from, count) in ranges {
+        cmd.arg("-L").arg(format!("{},+{}", from, count));
+    }
 
     if let Some(rev) = revision {
         cmd.arg(rev);
@@ -60,38 +248,60 @@ pub fn get_blame(
 
     cmd.arg("--").arg(file);
 
-    let output = cmd.output().context("failed to run git blame")?;
+    let output = cmd.traced_output().context("failed to run git blame")?;
 
     if !output.status.success() {
-        // Return empty vec on failure (graceful degradation)
-        // Caller will use fallback "0000000" markers
-        return Ok(Vec::new());
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-
-    // --line-porcelain outputs one header line per blamed line
-    // Header lines start with 40-char hex hash
-    // Content lines start with \t, skip those to avoid false matches
-    let hashes: Vec<String> = stdout
-        .lines()
-        .filter_map(|line| {
-            // Content lines in porcelain format start with a tab
-            if line.starts_with('\t') {
-                return None;
+        anyhow::bail!(
+            "git blame failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Read `--line-porcelain` output into blamed lines by final line number.
+/// Each blamed line is a header `<hash> <orig-line> <final-line> [<count>]`,
+/// `<key> <value>` lines such as `author` and `summary`, and the content
+/// after a tab, which is skipped so it can't be mistaken for a header.
+fn parse_porcelain(output: &str) -> HashMap<usize, BlameLine> {
+    let mut lines = HashMap::new();
+    let mut current: Option<(usize, BlameLine)> = None;
+    for line in output.lines() {
+        if line.starts_with('\t') {
+            if let Some((n, blamed)) = current.take() {
+                lines.insert(n, blamed);
             }
-            let first_token = line.split_whitespace().next()?;
-            // Strip leading ^ for boundary commits
-            let hash = first_token.trim_start_matches('^');
-            if hash.len() >= 40 && hash.chars().take(40).all(|c| c.is_ascii_hexdigit()) {
-                Some(hash[..7].to_string())
-            } else {
-                None
+            continue;
+        }
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        match (key, current.as_mut()) {
+            ("author", Some((_, blamed))) => blamed.author = value.to_string(),
+            ("author-time", Some((_, blamed))) => blamed.time = value.parse().unwrap_or(0),
+            ("summary", Some((_, blamed))) => blamed.summary = value.to_string(),
+            _ => {
+                // Strip leading ^ for boundary commits
+                let hash = key.trim_start_matches('^');
+                if hash.len() >= 40
+                    && hash.chars().take(40).all(|c| c.is_ascii_hexdigit())
+                    && let Some(Ok(n)) = value.split(' ').nth(1).map(str::parse)
+                {
+                    current = Some((
+                        n,
+                        BlameLine {
+                            hash: hash[..7].to_string(),
+                            author: String::new(),
+                            time: 0,
+                            summary: String::new(),
+                        },
+                    ));
+                }
             }
-        })
-        .collect();
-
-    Ok(hashes)
+        }
+    }
+    lines
 }
 
 #[cfg(test)]
@@ -123,6 +333,22 @@ mod tests {
         assert_eq!(result, Some((0, 0, 1, 3)));
     }
 
+    #[test]
+    fn test_parse_porcelain() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let output = format!(
+            "{sha} 3 10 2\nauthor A\nauthor-time 100\nfilename f\n\tline ten\n\
+             {sha} 4 11\nauthor A\nfilename f\n\tline eleven\n\
+             {sha} 9 40 1\nsummary {sha} 1 2\n\t{sha} 1 2\n"
+        );
+        let lines = parse_porcelain(&output);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[&10].hash, "0123456");
+        assert_eq!(lines[&10].author, "A");
+        assert_eq!(lines[&11].hash, "0123456");
+        assert_eq!(lines[&40].summary, format!("{sha} 1 2"));
+    }
+
     #[test]
     fn test_parse_hunk_header_invalid() {
         assert_eq!(parse_hunk_header("not a header"), None);
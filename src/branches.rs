@@ -0,0 +1,87 @@
+//! `--update-refs`: after a rewrite, move the other local branches that
+//! pointed at a rewritten commit to its replacement, as `git rebase
+//! --update-refs` does for the commits a rebase replays.
+
+use anyhow::Result;
+use std::process::Command;
+
+use crate::range_diff::Status;
+
+/// Whether rewrites move branches along: `--update-refs`, or
+/// `surgeon.updateRefs`.
+pub fn enabled(flag: bool) -> Result<bool> {
+    Ok(flag || crate::config::get_bool("surgeon.updateRefs")?.unwrap_or(false))
+}
+
+/// After a rewrite from `old_head`, point each local branch that was at one
+/// of the rewritten commits at the commit that replaced it, and report the
+/// moves on stderr. The branch at `old_head` itself, and branches checked out
+/// in any worktree, are left alone; a branch at a commit that no longer
+/// exists is only reported. Where the moved branches were is recorded in the
+/// rewrite's backup.
+pub fn update_moved(old_head: &str) -> Result<()> {
+    let entries = crate::range_diff::compute(old_head)?;
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let listed = crate::diff::run_git_cmd(Command::new("git").args([
+        "for-each-ref",
+        "--format=%(refname)%00%(objectname)%00%(worktreepath)",
+        "refs/heads/",
+    ]))?;
+    let mut moved = Vec::new();
+    let mut stranded = Vec::new();
+    for line in listed.lines() {
+        let mut fields = line.splitn(3, '\0');
+        let (Some(name), Some(sha), worktree) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        if sha == old_head || worktree.is_some_and(|w| !w.is_empty()) {
+            continue;
+        }
+        let Some(entry) = entries.iter().find(|e| e.old.as_deref() == Some(sha)) else {
+            continue;
+        };
+        let short_name = name.strip_prefix("refs/heads/").unwrap_or(name);
+        match (&entry.new, entry.status) {
+            (Some(new), Status::Unchanged | Status::Modified) if new != sha => {
+                moved.push((short_name.to_string(), sha.to_string(), new.clone()));
+            }
+            (None, Status::Removed) => stranded.push((short_name.to_string(), sha.to_string())),
+            _ => {}
+        }
+    }
+
+    // Recorded first, so `restore-backup` can move back any that moved
+    let recorded: Vec<(String, String)> = moved
+        .iter()
+        .map(|(name, old, _)| (name.clone(), old.clone()))
+        .collect();
+    crate::backup::record_branches(old_head, &recorded)?;
+    for (name, old, new) in &moved {
+        crate::diff::run_git_cmd(Command::new("git").args([
+            "update-ref",
+            "-m",
+            "git-surgeon: update-refs",
+            &format!("refs/heads/{}", name),
+            new,
+            old,
+        ]))?;
+    }
+
+    if !moved.is_empty() {
+        eprintln!("updated refs:");
+        for (name, old, new) in &moved {
+            eprintln!("  {}: {} -> {}", name, &old[..7], &new[..7]);
+        }
+    }
+    for (name, sha) in &stranded {
+        eprintln!(
+            "note: {} points at {}, which the rewrite removed; left in place",
+            name,
+            &sha[..7]
+        );
+    }
+    Ok(())
+}
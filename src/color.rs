@@ -0,0 +1,101 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set from `--color` before `hunks`/`show` print anything.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+const RESET: &str = "\x1b[m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+pub fn parse_color(s: &str) -> Result<ColorChoice, String> {
+    match s {
+        "auto" => Ok(ColorChoice::Auto),
+        "always" => Ok(ColorChoice::Always),
+        "never" => Ok(ColorChoice::Never),
+        _ => Err("expected auto, always or never".to_string()),
+    }
+}
+
+/// The `surgeon.color` config, else auto.
+pub fn configured() -> anyhow::Result<ColorChoice> {
+    match crate::config::get("surgeon.color") {
+        None => Ok(ColorChoice::Auto),
+        Some(value) => parse_color(&value)
+            .map_err(|e| anyhow::anyhow!("invalid surgeon.color '{}': {}", value, e)),
+    }
+}
+
+impl ColorChoice {
+    /// Whether to color: with `auto`, only when stdout is a terminal that is not
+    /// `TERM=dumb` and `NO_COLOR` is unset.
+    pub fn resolve(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal()
+                    && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::env::var("TERM").is_ok_and(|t| t != "dumb")
+            }
+        }
+    }
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        format!("{}{}{}", code, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// A hunk ID.
+pub fn id(text: &str) -> String {
+    paint(BOLD, text)
+}
+
+/// An `@@` header.
+pub fn header(text: &str) -> String {
+    paint(CYAN, text)
+}
+
+/// A diff line, colored by its `+`/`-` marker.
+pub fn diff_line(line: &str) -> String {
+    if line.starts_with('+') {
+        paint(GREEN, line)
+    } else if line.starts_with('-') {
+        paint(RED, line)
+    } else {
+        line.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        set_enabled(false);
+        assert_eq!(diff_line("+added"), "+added");
+        set_enabled(true);
+        assert_eq!(diff_line("+added"), "\x1b[32m+added\x1b[m");
+        assert_eq!(diff_line("-gone"), "\x1b[31m-gone\x1b[m");
+        assert_eq!(diff_line(" same"), " same");
+        set_enabled(false);
+    }
+}
@@ -0,0 +1,210 @@
+//! `--committer-date-is-author-date` / `--preserve-committer`: what committer
+//! date a rewrite gives the commits it creates and replays, instead of the
+//! current time for all of them.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::output::Traced;
+
+/// The committer flags of the commands that rewrite history.
+#[derive(clap::Args, Clone, Default)]
+pub struct CommitterArgs {
+    /// Give each rewritten commit its author date as committer date (like
+    /// git rebase's option of the same name)
+    #[arg(long, conflicts_with = "preserve_committer")]
+    pub committer_date_is_author_date: bool,
+    /// Keep each rewritten commit's committer and committer date
+    #[arg(long)]
+    pub preserve_committer: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    /// The current user and time, as git commits
+    Now,
+    AuthorDate,
+    Preserve,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Now => "now",
+            Mode::AuthorDate => "author-date",
+            Mode::Preserve => "preserve",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Mode> {
+        match s {
+            "now" => Some(Mode::Now),
+            "author-date" => Some(Mode::AuthorDate),
+            "preserve" => Some(Mode::Preserve),
+            _ => None,
+        }
+    }
+}
+
+static MODE: AtomicU8 = AtomicU8::new(0);
+
+impl CommitterArgs {
+    pub fn mode(&self) -> Result<Mode> {
+        match (self.committer_date_is_author_date, self.preserve_committer) {
+            (true, true) => crate::exit::fail!(
+                Usage,
+                "--committer-date-is-author-date and --preserve-committer conflict"
+            ),
+            (true, false) => Ok(Mode::AuthorDate),
+            (false, true) => Ok(Mode::Preserve),
+            (false, false) => Ok(Mode::Now),
+        }
+    }
+
+    /// Apply the flags to every commit created or replayed from here on.
+    pub fn configure(&self) -> Result<()> {
+        set_mode(self.mode()?);
+        Ok(())
+    }
+}
+
+pub fn set_mode(mode: Mode) {
+    MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn mode() -> Mode {
+    match MODE.load(Ordering::Relaxed) {
+        1 => Mode::AuthorDate,
+        2 => Mode::Preserve,
+        _ => Mode::Now,
+    }
+}
+
+/// Have a rebase give the commits it replays the configured committer: git's
+/// own `--committer-date-is-author-date`, or an `exec` after each commit that
+/// puts back the committer of the commit it replaced.
+pub fn configure_rebase(cmd: &mut Command) -> Result<()> {
+    match mode() {
+        Mode::Now => {}
+        Mode::AuthorDate => {
+            cmd.arg("--committer-date-is-author-date");
+        }
+        Mode::Preserve => {
+            let exe = std::env::current_exe()
+                .context("could not determine git-surgeon executable path")?;
+            let exe = crate::sequence::shell_quote(&exe.to_string_lossy());
+            cmd.arg(format!("--exec={} internal-restore-committer", exe));
+        }
+    }
+    Ok(())
+}
+
+/// Set the committer of a commit git-surgeon makes itself in place of
+/// `original`: its committer when preserving, or `author_date` (the new
+/// commit's author date, None for now) as committer date.
+pub fn configure_commit(
+    cmd: &mut Command,
+    original: &str,
+    author_date: Option<&str>,
+) -> Result<()> {
+    match mode() {
+        Mode::Now => {}
+        Mode::AuthorDate => {
+            if let Some(date) = author_date {
+                cmd.env("GIT_COMMITTER_DATE", date);
+            }
+        }
+        Mode::Preserve => {
+            let (name, email, date) = committer_of(original)?;
+            cmd.env("GIT_COMMITTER_NAME", name)
+                .env("GIT_COMMITTER_EMAIL", email)
+                .env("GIT_COMMITTER_DATE", date);
+        }
+    }
+    Ok(())
+}
+
+/// Name, email, and strict ISO date of `sha`'s committer.
+fn committer_of(sha: &str) -> Result<(String, String, String)> {
+    let output = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%cn%x00%ce%x00%cI",
+        sha,
+    ]))?;
+    let mut fields = output.trim_end_matches('\n').splitn(3, '\0');
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(name), Some(email), Some(date)) => {
+            Ok((name.to_string(), email.to_string(), date.to_string()))
+        }
+        _ => anyhow::bail!("could not read the committer of {}", sha),
+    }
+}
+
+/// Entry point for `git-surgeon internal-restore-committer`, run by a rebase
+/// as the `exec` after each commit: amend HEAD to carry the committer of the
+/// commit the last pick (or edit, or reword) in the todo list replayed.
+pub fn restore_from_rebase() -> Result<()> {
+    let done_path = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-parse",
+        "--git-path",
+        "rebase-merge/done",
+    ]))?;
+    let done =
+        std::fs::read_to_string(done_path.trim()).context("failed to read the rebase todo")?;
+    let Some(original) = done.lines().rev().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let cmd = parts.next()?;
+        matches!(cmd, "pick" | "p" | "reword" | "r" | "edit" | "e")
+            .then(|| parts.next())
+            .flatten()
+    }) else {
+        return Ok(());
+    };
+    let wanted = committer_of(original)?;
+    // A commit the rebase kept as it was already has it
+    if committer_of("HEAD")? == wanted {
+        return Ok(());
+    }
+    let (name, email, date) = wanted;
+    let output = Command::new("git")
+        .args([
+            "commit",
+            "--amend",
+            "--no-edit",
+            "--allow-empty",
+            "--no-verify",
+            "--quiet",
+        ])
+        .env("GIT_COMMITTER_NAME", name)
+        .env("GIT_COMMITTER_EMAIL", email)
+        .env("GIT_COMMITTER_DATE", date)
+        .traced_output()
+        .context("failed to run git commit")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git commit --amend failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_from_flags() {
+        let mut args = CommitterArgs::default();
+        assert_eq!(args.mode().unwrap(), Mode::Now);
+        args.preserve_committer = true;
+        assert_eq!(args.mode().unwrap(), Mode::Preserve);
+        args.committer_date_is_author_date = true;
+        assert!(args.mode().is_err());
+        for mode in [Mode::Now, Mode::AuthorDate, Mode::Preserve] {
+            assert_eq!(Mode::parse(mode.name()), Some(mode));
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use anyhow::{Context, Result, bail};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::output::Traced;
+
+/// The repository's config file, at the root of the working tree.
+const REPO_FILE: &str = ".git-surgeon.toml";
+
+/// The settings a config file may set, and the git config key each provides
+/// a default for. Only these are read, so a cloned repository's file cannot
+/// set arbitrary git config (such as an external diff command).
+const SETTINGS: &[(&str, &str)] = &[
+    ("diff-algorithm", "diff.algorithm"),
+    ("diff-context", "surgeon.diffContext"),
+    ("preview-lines", "surgeon.previewLines"),
+    ("protected-refs", "surgeon.protectedRef"),
+    ("id-scheme", "surgeon.idScheme"),
+    ("id-length", "surgeon.idLength"),
+    ("color", "surgeon.color"),
+    ("json", "surgeon.json"),
+    ("three-way", "surgeon.threeWay"),
+    ("message-template", "surgeon.messageTemplate"),
+    ("sign", "commit.gpgSign"),
+    ("signing-key", "user.signingKey"),
+    ("timeout", "surgeon.timeout"),
+    ("update-refs", "surgeon.updateRefs"),
+    ("scopes", "surgeon.scope"),
+];
+
+/// The settings that take a list, one git config value per item.
+const LIST_SETTINGS: &[&str] = &["protected-refs", "scopes"];
+
+/// A value of the TOML subset config files are written in.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+impl Value {
+    /// The value as git config would spell it; arrays are one entry per item.
+    fn git_values(&self) -> Vec<String> {
+        match self {
+            Value::String(s) => vec![s.clone()],
+            Value::Integer(n) => vec![n.to_string()],
+            Value::Boolean(b) => vec![b.to_string()],
+            Value::Array(items) => items.iter().flat_map(Value::git_values).collect(),
+        }
+    }
+}
+
+/// Apply the settings of the repository's `.git-surgeon.toml` and the user's
+/// `~/.git-surgeon.toml` as git config for this process and the git commands
+/// it runs, below anything git config itself sets: a key set in git config
+/// (or by a flag) keeps its value, the repository's file wins over the
+/// user's, and `protected-refs` adds to the configured refs. `GIT_SURGEON_*`
+/// environment variables (see `env_settings`) win over all of them but flags.
+pub fn load() -> Result<()> {
+    BASE_COUNT.get_or_init(config_count);
+    let env = env_settings()?;
+    let mut files = Vec::new();
+    if let Some(root) = repo_root() {
+        files.push(root.join(REPO_FILE));
+    }
+    if let Some(home) = dirs::home_dir() {
+        files.push(home.join(REPO_FILE));
+    }
+
+    let mut chosen: Vec<(&str, Value)> = Vec::new();
+    for path in &files {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        for (setting, value) in parse_file(path, &text)? {
+            let key = git_key(setting).expect("parse_file checks settings");
+            if !chosen.iter().any(|(k, _)| *k == key) {
+                chosen.push((key, value));
+            }
+        }
+    }
+    if chosen.is_empty() && env.is_empty() {
+        return Ok(());
+    }
+
+    let set = if chosen.is_empty() {
+        HashSet::new()
+    } else {
+        git_config_keys()
+    };
+    // Later entries win, so the environment's come last
+    let entries: Vec<(&str, String)> = chosen
+        .iter()
+        .filter(|(key, _)| *key == "surgeon.protectedRef" || !set.contains(&key.to_lowercase()))
+        .chain(&env)
+        .flat_map(|(key, value)| value.git_values().into_iter().map(move |v| (*key, v)))
+        .collect();
+    let entries: Vec<(&str, &str)> = entries.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    set_git_config(&entries);
+    Ok(())
+}
+
+/// GIT_CONFIG_COUNT as this process was started with, before `load` or
+/// anything else added entries.
+static BASE_COUNT: OnceLock<usize> = OnceLock::new();
+
+fn config_count() -> usize {
+    std::env::var("GIT_CONFIG_COUNT")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(0)
+}
+
+/// `load` again from scratch, for the daemon: drop the git config entries
+/// added since the process started, then read the config files as they are
+/// now, for the repository in the current directory.
+pub fn reload() -> Result<()> {
+    let base = *BASE_COUNT.get_or_init(config_count);
+    // SAFETY: the daemon handles one request at a time, before it starts the
+    // threads that read the command's output
+    unsafe {
+        for i in base..config_count() {
+            std::env::remove_var(format!("GIT_CONFIG_KEY_{}", i));
+            std::env::remove_var(format!("GIT_CONFIG_VALUE_{}", i));
+        }
+        if base == 0 {
+            std::env::remove_var("GIT_CONFIG_COUNT");
+        } else {
+            std::env::set_var("GIT_CONFIG_COUNT", base.to_string());
+        }
+    }
+    load()
+}
+
+/// Set `entries` as git config for the git commands this process runs, after
+/// any GIT_CONFIG_* entries the caller already set.
+pub fn set_git_config(entries: &[(&str, &str)]) {
+    if entries.is_empty() {
+        return;
+    }
+    let start = config_count();
+    // SAFETY: runs on the main thread before any threads are spawned
+    unsafe {
+        for (i, (key, value)) in entries.iter().enumerate() {
+            std::env::set_var(format!("GIT_CONFIG_KEY_{}", start + i), key);
+            std::env::set_var(format!("GIT_CONFIG_VALUE_{}", start + i), value);
+        }
+        std::env::set_var("GIT_CONFIG_COUNT", (start + entries.len()).to_string());
+    }
+}
+
+/// The value of git config `key`, if set (by git config, a config file, or
+/// a flag).
+pub fn get(key: &str) -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get", key])
+        .traced_output();
+    let value = output
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default();
+    (!value.is_empty()).then_some(value)
+}
+
+/// Every value of the multi-valued git config `key`.
+pub fn get_all(key: &str) -> Vec<String> {
+    let output = Command::new("git")
+        .args(["config", "--get-all", key])
+        .traced_output();
+    output
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// `get` for a boolean key.
+pub fn get_bool(key: &str) -> Result<Option<bool>> {
+    match get(key).as_deref() {
+        None => Ok(None),
+        Some("true" | "yes" | "on" | "1") => Ok(Some(true)),
+        Some("false" | "no" | "off" | "0") => Ok(Some(false)),
+        Some(other) => bail!("invalid {} '{}': expected true or false", key, other),
+    }
+}
+
+/// `get` for a numeric key.
+pub fn get_usize(key: &str) -> Result<Option<usize>> {
+    get(key)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid {} '{}': expected a number", key, value))
+        })
+        .transpose()
+}
+
+/// Settings from the environment, as git config keys: `GIT_SURGEON_<NAME>`
+/// for each config file setting (`GIT_SURGEON_PREVIEW_LINES=8`, comma-
+/// separated for `GIT_SURGEON_PROTECTED_REFS` and `GIT_SURGEON_SCOPES`), plus
+/// `GIT_SURGEON_OUTPUT` (`json` or `text`) and `GIT_SURGEON_NO_COLOR`.
+fn env_settings() -> Result<Vec<(&'static str, Value)>> {
+    let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+    let mut settings = Vec::new();
+    for (setting, key) in SETTINGS {
+        let name = format!("GIT_SURGEON_{}", setting.to_uppercase().replace('-', "_"));
+        let Some(value) = var(&name) else {
+            continue;
+        };
+        let value = if LIST_SETTINGS.contains(setting) {
+            Value::Array(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|r| !r.is_empty())
+                    .map(|r| Value::String(r.to_string()))
+                    .collect(),
+            )
+        } else {
+            Value::String(value)
+        };
+        settings.push((*key, value));
+    }
+    if let Some(output) = var("GIT_SURGEON_OUTPUT") {
+        let json = match output.as_str() {
+            "json" => true,
+            "text" => false,
+            _ => bail!(
+                "invalid GIT_SURGEON_OUTPUT '{}': expected json or text",
+                output
+            ),
+        };
+        settings.push(("surgeon.json", Value::Boolean(json)));
+    }
+    if var("GIT_SURGEON_NO_COLOR").is_some() {
+        settings.push(("surgeon.color", Value::String("never".to_string())));
+    }
+    Ok(settings)
+}
+
+/// Whether any setting comes from a `GIT_SURGEON_*` environment variable.
+pub fn from_environment() -> bool {
+    env_settings().is_ok_and(|settings| !settings.is_empty())
+}
+
+fn git_key(setting: &str) -> Option<&'static str> {
+    SETTINGS
+        .iter()
+        .find(|(name, _)| *name == setting)
+        .map(|(_, key)| *key)
+}
+
+fn repo_root() -> Option<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .traced_output()
+        .ok()?;
+    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !root.is_empty()).then(|| PathBuf::from(root))
+}
+
+/// The (lowercased) keys git config sets, before this process adds any.
+fn git_config_keys() -> HashSet<String> {
+    let output = Command::new("git")
+        .args(["config", "--list", "-z"])
+        .traced_output();
+    let Ok(output) = output else {
+        return HashSet::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|entry| entry.split('\n').next())
+        .filter(|key| !key.is_empty())
+        .map(|key| key.to_lowercase())
+        .collect()
+}
+
+/// Parse a config file into its settings, checking each is a known one.
+fn parse_file<'a>(path: &Path, text: &'a str) -> Result<Vec<(&'a str, Value)>> {
+    let settings =
+        parse_toml(text).with_context(|| format!("invalid config file {}", path.display()))?;
+    for (setting, value) in &settings {
+        if git_key(setting).is_none() {
+            bail!(
+                "unknown setting '{}' in {} (known: {})",
+                setting,
+                path.display(),
+                SETTINGS
+                    .iter()
+                    .map(|(name, _)| *name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        if matches!(value, Value::Array(_)) && !LIST_SETTINGS.contains(setting) {
+            bail!("'{}' in {} takes a single value", setting, path.display());
+        }
+    }
+    Ok(settings)
+}
+
+/// Parse the TOML subset config files use: top-level `key = value` lines
+/// with string, integer, boolean, and single-line array values, and `#`
+/// comments.
+fn parse_toml(text: &str) -> Result<Vec<(&str, Value)>> {
+    let mut settings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') {
+            bail!("line {}: tables are not supported", i + 1);
+        }
+        let Some((key, rest)) = line.split_once('=') else {
+            bail!("line {}: expected key = value", i + 1);
+        };
+        let key = key.trim();
+        let (value, rest) =
+            parse_value(rest.trim()).map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?;
+        let rest = rest.trim();
+        if !(rest.is_empty() || rest.starts_with('#')) {
+            bail!("line {}: unexpected '{}' after value", i + 1, rest);
+        }
+        if settings.iter().any(|(k, _)| *k == key) {
+            bail!("line {}: '{}' is set more than once", i + 1, key);
+        }
+        settings.push((key, value));
+    }
+    Ok(settings)
+}
+
+/// Parse the value at the start of `s`, returning it and what follows.
+fn parse_value(s: &str) -> Result<(Value, &str), String> {
+    if let Some(rest) = s.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next().map(|(_, c)| c) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ ('"' | '\\')) => value.push(c),
+                    _ => return Err("unsupported escape in string".to_string()),
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unterminated string".to_string());
+    }
+    if let Some(rest) = s.strip_prefix('\'') {
+        let end = rest.find('\'').ok_or("unterminated string")?;
+        return Ok((Value::String(rest[..end].to_string()), &rest[end + 1..]));
+    }
+    if let Some(mut rest) = s.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+    let end = s
+        .find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#')
+        .unwrap_or(s.len());
+    let (word, rest) = s.split_at(end);
+    match word {
+        "true" => Ok((Value::Boolean(true), rest)),
+        "false" => Ok((Value::Boolean(false), rest)),
+        _ => word
+            .replace('_', "")
+            .parse()
+            .map(|n| (Value::Integer(n), rest))
+            .map_err(|_| format!("invalid value '{}'", word)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let text = "\
+# defaults for this repo
+diff-algorithm = \"histogram\"
+preview-lines = 8   # more context
+json = true
+protected-refs = ['main', \"release/*\",]
+";
+        assert_eq!(
+            parse_toml(text).unwrap(),
+            [
+                ("diff-algorithm", Value::String("histogram".into())),
+                ("preview-lines", Value::Integer(8)),
+                ("json", Value::Boolean(true)),
+                (
+                    "protected-refs",
+                    Value::Array(vec![
+                        Value::String("main".into()),
+                        Value::String("release/*".into())
+                    ])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_toml_errors() {
+        assert!(parse_toml("[surgeon]\n").is_err());
+        assert!(parse_toml("color = always\n").is_err());
+        assert!(parse_toml("color = \"always\" extra\n").is_err());
+        assert!(parse_toml("json = true\njson = false\n").is_err());
+        let path = Path::new(".git-surgeon.toml");
+        assert!(parse_file(path, "colour = \"never\"\n").is_err());
+        assert!(parse_file(path, "color = [\"never\"]\n").is_err());
+    }
+}
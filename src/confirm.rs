@@ -0,0 +1,23 @@
+use anyhow::Result;
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Go ahead with a bulk destructive command, once what it will destroy has
+/// been printed to stderr, only if confirmed: by `--yes`, or by answering the
+/// prompt when run from a terminal. Anything else refuses, so a misfired
+/// command from an agent destroys nothing.
+pub fn confirm(action: &str, yes: bool) -> Result<()> {
+    if yes {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() || !std::io::stderr().is_terminal() {
+        crate::exit::fail!(Usage, "refusing to {} without --yes", action);
+    }
+    eprint!("{}? [y/N] ", action);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    match answer.trim().to_ascii_lowercase().as_str() {
+        "y" | "yes" => Ok(()),
+        _ => crate::exit::fail!(Usage, "not confirmed; did not {}", action),
+    }
+}
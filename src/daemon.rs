@@ -0,0 +1,266 @@
+//! Long-running server that keeps parsed diffs and blame results warm.
+//!
+//! `git-surgeon daemon` listens on `.git/surgeon/daemon.sock`. Read-only
+//! commands (`hunks`, `show`) check for the socket and, when a daemon answers,
+//! send their arguments to it instead of doing the work themselves. The daemon
+//! runs the command in-process with stdout and stderr captured, and replies
+//! with the exit code and both streams.
+//!
+//! Wire format: a request is NUL-terminated fields ending with an empty field:
+//! the verb (`run` or `stop`), the client's working directory, then the
+//! arguments. The reply is `<exit code> <stdout length> <stderr length>\n`
+//! followed by the two streams.
+
+use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static CACHING: AtomicBool = AtomicBool::new(false);
+
+/// Whether results may be memoized across requests (only inside the daemon).
+pub fn caching() -> bool {
+    CACHING.load(Ordering::Relaxed)
+}
+
+/// Set this to run every command locally, even when a daemon is listening.
+pub const NO_DAEMON_ENV: &str = "GIT_SURGEON_NO_DAEMON";
+
+#[cfg(unix)]
+mod imp {
+    use super::*;
+    use crate::output::Redirect;
+    use anyhow::Context;
+    use std::ffi::OsString;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::time::Instant;
+
+    fn socket_path() -> Result<PathBuf> {
+        crate::state::surgeon_path("daemon.sock")
+    }
+
+    fn write_request(stream: &mut UnixStream, verb: &str, args: &[OsString]) -> Result<()> {
+        let cwd = std::env::current_dir().context("failed to get current directory")?;
+        let mut request = Vec::new();
+        for field in [verb.as_bytes(), cwd.as_os_str().as_bytes()]
+            .into_iter()
+            .chain(args.iter().map(|a| a.as_bytes()))
+        {
+            request.extend_from_slice(field);
+            request.push(0);
+        }
+        request.push(0);
+        stream.write_all(&request)?;
+        Ok(())
+    }
+
+    fn read_request(stream: &mut UnixStream) -> Result<Vec<Vec<u8>>> {
+        let mut reader = BufReader::new(stream);
+        let mut fields = Vec::new();
+        loop {
+            let mut field = Vec::new();
+            reader.read_until(0, &mut field)?;
+            if field.is_empty() && fields.is_empty() {
+                // Connected and closed without a request, e.g. a liveness probe
+                return Ok(fields);
+            }
+            if field.pop() != Some(0) {
+                anyhow::bail!("truncated request");
+            }
+            if field.is_empty() {
+                return Ok(fields);
+            }
+            fields.push(field);
+        }
+    }
+
+    /// Send the current invocation, plus `extra` arguments, to a running
+    /// daemon. Returns `None` if no daemon is listening, in which case the
+    /// caller runs the command itself.
+    pub fn forward(extra: &[String]) -> Option<i32> {
+        if std::env::var_os(NO_DAEMON_ENV).is_some() {
+            return None;
+        }
+        let path = socket_path().ok()?;
+        let mut stream = UnixStream::connect(path).ok()?;
+        let args: Vec<OsString> = std::env::args_os()
+            .skip(1)
+            .chain(extra.iter().map(OsString::from))
+            .collect();
+        write_request(&mut stream, "run", &args).ok()?;
+
+        let mut reply = BufReader::new(stream);
+        let mut header = String::new();
+        reply.read_line(&mut header).ok()?;
+        let mut parts = header.split_whitespace().map(|p| p.parse::<i64>().ok());
+        let (Some(Some(code)), Some(Some(out_len)), Some(Some(err_len))) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let mut out = vec![0; out_len as usize];
+        let mut err = vec![0; err_len as usize];
+        reply.read_exact(&mut out).ok()?;
+        reply.read_exact(&mut err).ok()?;
+        let _ = std::io::stdout().write_all(&out);
+        let _ = std::io::stderr().write_all(&err);
+        Some(code as i32)
+    }
+
+    /// Run one command with stdout and stderr captured.
+    fn run_captured(args: Vec<OsString>) -> Result<(i32, Vec<u8>, Vec<u8>)> {
+        // Config files may have changed since the last request, and this one
+        // may be for another repository
+        if let Err(e) = crate::config::reload() {
+            return Ok((1, Vec::new(), format!("Error: {:?}\n", e).into_bytes()));
+        }
+        let (mut out_r, out_w) = std::io::pipe()?;
+        let (mut err_r, err_w) = std::io::pipe()?;
+        // Drain both pipes while the command runs so large output cannot block it
+        let out_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            out_r.read_to_end(&mut buf).map(|_| buf)
+        });
+        let err_reader = std::thread::spawn(move || {
+            let mut buf = Vec::new();
+            err_r.read_to_end(&mut buf).map(|_| buf)
+        });
+
+        let code = {
+            let _out = Redirect::new(1, &out_w)?;
+            let _err = Redirect::new(2, &err_w)?;
+            drop((out_w, err_w));
+            let argv = std::iter::once(OsString::from("git-surgeon")).chain(args);
+            match <crate::Cli as clap::Parser>::try_parse_from(argv) {
+                Ok(cli) => match crate::run(cli) {
+                    Ok(()) => 0,
+                    Err(e) => {
+                        eprintln!("Error: {:?}", e);
+                        crate::exit::code(&e)
+                    }
+                },
+                Err(e) => {
+                    let _ = e.print();
+                    e.exit_code()
+                }
+            }
+        };
+
+        let out = out_reader.join().expect("stdout reader panicked")?;
+        let err = err_reader.join().expect("stderr reader panicked")?;
+        Ok((code, out, err))
+    }
+
+    fn handle(mut stream: UnixStream, started: &Path) -> Result<bool> {
+        let fields = read_request(&mut stream)?;
+        if fields.is_empty() {
+            return Ok(true);
+        }
+        let [verb, cwd, args @ ..] = fields.as_slice() else {
+            anyhow::bail!("malformed request");
+        };
+        match verb.as_slice() {
+            b"stop" => {
+                stream.write_all(b"0 0 0\n")?;
+                return Ok(false);
+            }
+            b"run" => {}
+            other => anyhow::bail!("unknown request '{}'", String::from_utf8_lossy(other)),
+        }
+
+        let cwd = PathBuf::from(OsString::from_vec(cwd.clone()));
+        let args: Vec<OsString> = args.iter().map(|a| OsString::from_vec(a.clone())).collect();
+        let label = args
+            .first()
+            .map(|a| a.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let start = Instant::now();
+        let (code, out, err) = match std::env::set_current_dir(&cwd) {
+            Ok(()) => run_captured(args)?,
+            Err(e) => (
+                1,
+                Vec::new(),
+                format!("Error: {}: {}\n", cwd.display(), e).into_bytes(),
+            ),
+        };
+        // Relative paths in later log lines stay meaningful
+        let _ = std::env::set_current_dir(started);
+
+        stream.write_all(format!("{} {} {}\n", code, out.len(), err.len()).as_bytes())?;
+        stream.write_all(&out)?;
+        stream.write_all(&err)?;
+        eprintln!("{} -> {} ({} ms)", label, code, start.elapsed().as_millis());
+        Ok(true)
+    }
+
+    pub fn serve() -> Result<()> {
+        let path = socket_path()?;
+        if UnixStream::connect(&path).is_ok() {
+            anyhow::bail!("a daemon is already listening on {}", path.display());
+        }
+        if path.exists() {
+            // Left behind by a daemon that did not shut down cleanly
+            std::fs::remove_file(&path)
+                .with_context(|| format!("failed to remove stale {}", path.display()))?;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let listener = UnixListener::bind(&path)
+            .with_context(|| format!("failed to listen on {}", path.display()))?;
+        let path = std::fs::canonicalize(&path)?;
+        let started = std::env::current_dir()?;
+
+        CACHING.store(true, Ordering::Relaxed);
+        eprintln!("listening on {}", path.display());
+        for stream in listener.incoming() {
+            let keep_going = match stream {
+                Ok(stream) => handle(stream, &started).unwrap_or_else(|e| {
+                    eprintln!("error: {:#}", e);
+                    true
+                }),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    true
+                }
+            };
+            if !keep_going {
+                break;
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+        eprintln!("daemon stopped");
+        Ok(())
+    }
+
+    pub fn stop() -> Result<()> {
+        let path = socket_path()?;
+        let mut stream = UnixStream::connect(&path)
+            .map_err(|_| anyhow::anyhow!("no daemon is listening on {}", path.display()))?;
+        write_request(&mut stream, "stop", &[])?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply)?;
+        eprintln!("stopped daemon");
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+pub use imp::{forward, serve, stop};
+
+#[cfg(not(unix))]
+pub fn forward(_extra: &[String]) -> Option<i32> {
+    None
+}
+
+#[cfg(not(unix))]
+pub fn serve() -> Result<()> {
+    anyhow::bail!("the daemon is only supported on Unix")
+}
+
+#[cfg(not(unix))]
+pub fn stop() -> Result<()> {
+    anyhow::bail!("the daemon is only supported on Unix")
+}
@@ -1,5 +1,14 @@
 use anyhow::{Context, Result};
+use sha1::{Digest, Sha1};
 use std::process::Command;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::output::Traced;
+
+/// Context lines per hunk, set from `-U` or `surgeon.diffContext`.
+/// `usize::MAX` leaves it to git (`diff.context`, 3 by default).
+static CONTEXT: AtomicUsize = AtomicUsize::new(usize::MAX);
 
 #[derive(Debug, Clone)]
 pub struct DiffHunk {
@@ -20,17 +29,71 @@ pub struct DiffHunk {
     pub unsupported_metadata: Option<String>,
 }
 
+/// Diffs are read as patches to apply, so they show the raw content even for
+/// files with a textconv driver; `run_git_diff_view` is for display.
 const DIFF_FORMAT_ARGS: &[&str] = &[
     "--no-color",
     "--no-ext-diff",
+    "--no-textconv",
+    "--submodule=short",
     "--src-prefix=a/",
     "--dst-prefix=b/",
 ];
 
-pub fn run_git_diff(staged: bool, file: Option<&str>) -> Result<String> {
+/// Use `flag`, or else the `surgeon.diffContext` config, as the number of
+/// context lines of every diff this command reads.
+pub fn configure_context(flag: Option<usize>) -> Result<()> {
+    let context = match flag {
+        Some(n) => Some(n),
+        None => {
+            let output = Command::new("git")
+                .args(["config", "--get", "surgeon.diffContext"])
+                .traced_output();
+            let value = output
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .unwrap_or_default();
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.parse::<usize>().map_err(|_| {
+                    anyhow::anyhow!("invalid surgeon.diffContext '{}': expected a number", value)
+                })?)
+            }
+        }
+    };
+    CONTEXT.store(context.unwrap_or(usize::MAX), Ordering::Relaxed);
+    Ok(())
+}
+
+/// The configured context width, if any.
+pub fn context_lines() -> Option<usize> {
+    match CONTEXT.load(Ordering::Relaxed) {
+        usize::MAX => None,
+        n => Some(n),
+    }
+}
+
+/// Output format shared by every diff hunks are parsed from.
+fn format_args(cmd: &mut Command) {
+    cmd.args(DIFF_FORMAT_ARGS);
+    if let Some(n) = context_lines() {
+        cmd.arg(format!("-U{}", n));
+    }
+}
+
+/// `git`, set up to quote unusual paths in diff headers as `"a/\303\244"`
+/// whatever `core.quotePath` says, so headers are ASCII and survive being
+/// read as text and passed back to `git apply` unchanged.
+fn git_diff_command() -> Command {
     let mut cmd = Command::new("git");
+    cmd.args(["-c", "core.quotePath=true"]);
+    cmd
+}
+
+pub fn run_git_diff(staged: bool, file: Option<&str>) -> Result<String> {
+    let mut cmd = git_diff_command();
     cmd.arg("diff");
-    cmd.args(DIFF_FORMAT_ARGS);
+    format_args(&mut cmd);
     if staged {
         cmd.arg("--cached");
     }
@@ -41,9 +104,10 @@ pub fn run_git_diff(staged: bool, file: Option<&str>) -> Result<String> {
 }
 
 pub fn run_git_diff_commit(commit: &str, file: Option<&str>) -> Result<String> {
-    let mut cmd = Command::new("git");
+    crate::promisor::prefetch_commit(commit, file)?;
+    let mut cmd = git_diff_command();
     cmd.args(["show", "--pretty="]);
-    cmd.args(DIFF_FORMAT_ARGS);
+    format_args(&mut cmd);
     cmd.arg(commit);
     if let Some(f) = file {
         cmd.arg("--").arg(f);
@@ -51,8 +115,169 @@ pub fn run_git_diff_commit(commit: &str, file: Option<&str>) -> Result<String> {
     run_git_cmd(&mut cmd)
 }
 
+/// The same diff as `run_git_diff`, or `run_git_diff_commit` when `commit`
+/// is given, with each hunk widened to its whole enclosing function (`-W`).
+pub fn run_git_diff_function(
+    staged: bool,
+    commit: Option<&str>,
+    file: Option<&str>,
+) -> Result<String> {
+    if let Some(c) = commit {
+        crate::promisor::prefetch_commit(c, file)?;
+    }
+    let mut cmd = git_diff_command();
+    match commit {
+        Some(c) => cmd.args(["show", "--pretty=", "--function-context", c]),
+        None => cmd.args(["diff", "--function-context"]),
+    };
+    format_args(&mut cmd);
+    if staged && commit.is_none() {
+        cmd.arg("--cached");
+    }
+    if let Some(f) = file {
+        cmd.arg("--").arg(f);
+    }
+    run_git_cmd(&mut cmd)
+}
+
+/// `git log --patch` of `range` (HEAD if None), each commit introduced by a
+/// `\0<sha>\0<subject>` line; merges have no patch. `file` limits both the
+/// commits and their patches to that path, `grep` the commits to messages
+/// matching it.
+pub fn run_git_log_patches(
+    range: Option<&str>,
+    file: Option<&str>,
+    grep: Option<&str>,
+    max_count: Option<usize>,
+) -> Result<String> {
+    let mut cmd = git_diff_command();
+    cmd.args(["log", "--patch", "--format=%x00%H%x00%s"]);
+    format_args(&mut cmd);
+    if let Some(pattern) = grep {
+        cmd.arg(format!("--grep={}", pattern));
+    }
+    if let Some(n) = max_count {
+        cmd.arg(format!("--max-count={}", n));
+    }
+    cmd.arg(range.unwrap_or("HEAD"));
+    cmd.arg("--");
+    if let Some(f) = file {
+        cmd.arg(f);
+    }
+    run_git_cmd(&mut cmd)
+}
+
+/// The diff from tree-ish `from` to `to`, leaving out `excluded` paths.
+pub fn run_git_diff_trees(from: &str, to: &str, excluded: &[String]) -> Result<String> {
+    let mut cmd = git_diff_command();
+    cmd.arg("diff");
+    format_args(&mut cmd);
+    cmd.args([from, to]);
+    if !excluded.is_empty() {
+        cmd.args(["--", ":/"]);
+        cmd.args(
+            excluded
+                .iter()
+                .map(|f| format!(":(top,exclude,literal){}", f)),
+        );
+    }
+    run_git_cmd(&mut cmd)
+}
+
+/// The unstaged diff of the repository at `dir`, a submodule.
+pub fn run_git_diff_in(dir: &str, file: Option<&str>) -> Result<String> {
+    let mut cmd = git_diff_command();
+    cmd.args(["-C", dir, "diff"]);
+    format_args(&mut cmd);
+    if let Some(f) = file {
+        cmd.arg("--").arg(f);
+    }
+    run_git_cmd(&mut cmd)
+}
+
+/// `--name-status -z` output for the same diff as `run_git_diff` or
+/// `run_git_diff_commit`.
+pub fn run_git_name_status(staged: bool, commit: Option<&str>) -> Result<String> {
+    let mut cmd = Command::new("git");
+    match commit {
+        Some(c) => cmd.args(["show", "--pretty=", "--name-status", "-z", c]),
+        None => cmd.args(["diff", "--name-status", "-z"]),
+    };
+    if staged && commit.is_none() {
+        cmd.arg("--cached");
+    }
+    run_git_cmd(&mut cmd)
+}
+
+/// The files changed in the same diff as `run_git_diff`, or
+/// `run_git_diff_commit` when `commit` is given, whose `diff` attribute names
+/// a driver with `diff.<driver>.textconv` configured.
+pub fn textconv_files(
+    staged: bool,
+    commit: Option<&str>,
+    file: Option<&str>,
+) -> Result<Vec<String>> {
+    let mut cmd = Command::new("git");
+    match commit {
+        Some(c) => cmd.args(["show", "--pretty=", "--name-only", "-z", c]),
+        None => cmd.args(["diff", "--name-only", "-z"]),
+    };
+    if staged && commit.is_none() {
+        cmd.arg("--cached");
+    }
+    if let Some(f) = file {
+        cmd.arg("--").arg(f);
+    }
+    let changed = run_git_cmd(&mut cmd)?;
+    let paths: Vec<&str> = changed.split('\0').filter(|p| !p.is_empty()).collect();
+    if paths.is_empty() {
+        return Ok(Vec::new());
+    }
+    let output = run_git_cmd(
+        Command::new("git")
+            .args(["check-attr", "-z", "diff", "--"])
+            .args(paths),
+    )?;
+    // path NUL attribute NUL value NUL, for each path
+    let fields: Vec<&str> = output.split('\0').collect();
+    let mut files = Vec::new();
+    for entry in fields.chunks_exact(3) {
+        let (path, driver) = (entry[0], entry[2]);
+        if matches!(driver, "unspecified" | "set" | "unset") {
+            continue;
+        }
+        let textconv = Command::new("git")
+            .args(["config", "--get", &format!("diff.{}.textconv", driver)])
+            .traced_output()
+            .context("failed to run git config")?;
+        if textconv.status.success() && !textconv.stdout.trim_ascii().is_empty() {
+            files.push(path.to_string());
+        }
+    }
+    Ok(files)
+}
+
+/// The same diff as `run_git_diff`, or `run_git_diff_commit` when `commit`
+/// is given, limited to `files` and with their textconv drivers applied.
+/// For display only: the lines are not the file's content, so hunks parsed
+/// from it can't be applied.
+pub fn run_git_diff_view(staged: bool, commit: Option<&str>, files: &[String]) -> Result<String> {
+    let mut cmd = git_diff_command();
+    match commit {
+        Some(c) => cmd.args(["show", "--pretty=", c]),
+        None => cmd.arg("diff"),
+    };
+    format_args(&mut cmd);
+    cmd.arg("--textconv");
+    if staged && commit.is_none() {
+        cmd.arg("--cached");
+    }
+    cmd.arg("--").args(files);
+    run_git_cmd(&mut cmd)
+}
+
 pub fn run_git_cmd(cmd: &mut Command) -> Result<String> {
-    let output = cmd.output().context("failed to run git command")?;
+    let output = cmd.traced_output().context("failed to run git command")?;
     if !output.status.success() {
         anyhow::bail!(
             "git command failed: {}",
@@ -62,16 +287,62 @@ pub fn run_git_cmd(cmd: &mut Command) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).into_owned())
 }
 
-/// Extract a file path from a `--- a/...` or `+++ b/...` line.
-fn strip_diff_prefix(line: &str) -> &str {
-    line.strip_prefix("--- a/")
-        .or_else(|| line.strip_prefix("+++ b/"))
-        .or_else(|| line.strip_prefix("--- /"))
-        .or_else(|| line.strip_prefix("+++ /"))
-        .or_else(|| line.strip_prefix("+++ a/"))
-        .or_else(|| line.strip_prefix("--- "))
-        .or_else(|| line.strip_prefix("+++ "))
-        .unwrap_or(line)
+/// Extract a file path from a `--- a/...` or `+++ b/...` line, decoding
+/// git's quoting.
+fn strip_diff_prefix(line: &str) -> String {
+    let (path, side) = match line.strip_prefix("--- ") {
+        Some(path) => (path, "a/"),
+        None => (line.strip_prefix("+++ ").unwrap_or(line), "b/"),
+    };
+    // git ends the line with a tab when the path has a space in it
+    let path = unquote_path(path.trim_end_matches('\t'));
+    path.strip_prefix(side)
+        .or_else(|| path.strip_prefix('/'))
+        .or_else(|| path.strip_prefix("a/"))
+        .unwrap_or(&path)
+        .to_string()
+}
+
+/// Decode a path git quoted C-style (`"a/\303\244.txt"`, `"a/q\"t.txt"`).
+/// Paths that aren't UTF-8 keep a replacement character for each bad byte;
+/// their hunks still apply, since patches reuse git's own header.
+fn unquote_path(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        let Some(escaped) = chars.next() else { break };
+        bytes.push(match escaped {
+            b'a' => 0x07,
+            b'b' => 0x08,
+            b't' => b'\t',
+            b'n' => b'\n',
+            b'v' => 0x0b,
+            b'f' => 0x0c,
+            b'r' => b'\r',
+            b'0'..=b'7' => {
+                let mut value = escaped - b'0';
+                for _ in 0..2 {
+                    match chars.peek() {
+                        Some(d @ b'0'..=b'7') => {
+                            value = value.wrapping_mul(8) + (d - b'0');
+                            chars.next();
+                        }
+                        _ => break,
+                    }
+                }
+                value
+            }
+            other => other,
+        });
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
 }
 
 /// Preamble lines that indicate unsupported metadata operations.
@@ -88,7 +359,32 @@ const UNSUPPORTED_PREAMBLE_PREFIXES: &[&str] = &[
     "dissimilarity index ",
 ];
 
+/// Recently parsed diffs, keyed by a hash of the diff text. Only used inside
+/// the daemon, where the same diff is typically listed and then shown.
+static PARSE_CACHE: Mutex<Vec<([u8; 20], Vec<DiffHunk>)>> = Mutex::new(Vec::new());
+const PARSE_CACHE_SIZE: usize = 8;
+
 pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
+    if !crate::daemon::caching() {
+        return parse_diff_uncached(input);
+    }
+    let key: [u8; 20] = Sha1::digest(input.as_bytes()).into();
+    let mut cache = PARSE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(pos) = cache.iter().position(|(k, _)| *k == key) {
+        let entry = cache.remove(pos);
+        let hunks = entry.1.clone();
+        cache.push(entry);
+        return hunks;
+    }
+    let hunks = parse_diff_uncached(input);
+    if cache.len() == PARSE_CACHE_SIZE {
+        cache.remove(0);
+    }
+    cache.push((key, hunks.clone()));
+    hunks
+}
+
+fn parse_diff_uncached(input: &str) -> Vec<DiffHunk> {
     let mut hunks = Vec::new();
     let mut current_old_file = String::new();
     let mut current_new_file = String::new();
@@ -96,8 +392,18 @@ pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
     let mut current_header: Option<String> = None;
     let mut current_lines: Vec<String> = Vec::new();
     let mut current_unsupported: Option<String> = None;
+    // The `diff --git` line and what follows it up to the `---` line, and
+    // whether the file had any `@@` hunk, for files whose mode alone changed
+    let mut current_diff_line = String::new();
+    let mut current_preamble: Vec<String> = Vec::new();
+    let mut current_has_hunks = false;
 
-    for line in input.lines() {
+    // Split on \n alone: in files checked in with CRLF endings the \r is part
+    // of the content, and patches built without it don't apply
+    for line in input
+        .split_inclusive('\n')
+        .map(|l| l.strip_suffix('\n').unwrap_or(l))
+    {
         if line.starts_with("diff --git") {
             // Flush previous hunk
             if let Some(header) = current_header.take() {
@@ -111,10 +417,19 @@ pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
                     unsupported_metadata: current_unsupported.clone(),
                 });
             }
+            if !current_has_hunks
+                && let Some(hunk) = mode_only_hunk(&current_diff_line, &current_preamble)
+            {
+                hunks.push(hunk);
+            }
+            current_diff_line = line.to_string();
+            current_preamble.clear();
+            current_has_hunks = false;
             current_file_header.clear();
             current_old_file.clear();
             current_new_file.clear();
             current_unsupported = None;
+            continue;
         } else if current_unsupported.is_none() {
             // Check for unsupported preamble metadata before --- line
             if let Some(prefix) = UNSUPPORTED_PREAMBLE_PREFIXES
@@ -125,14 +440,19 @@ pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
             }
         }
 
+        if current_file_header.is_empty() && !line.starts_with("--- ") {
+            current_preamble.push(line.to_string());
+        }
+
         if line.starts_with("--- ") {
             current_file_header = line.to_string();
-            current_old_file = strip_diff_prefix(line).to_string();
+            current_old_file = strip_diff_prefix(line);
         } else if line.starts_with("+++ ") {
             current_file_header.push('\n');
             current_file_header.push_str(line);
-            current_new_file = strip_diff_prefix(line).to_string();
+            current_new_file = strip_diff_prefix(line);
         } else if line.starts_with("@@ ") {
+            current_has_hunks = true;
             // Flush previous hunk in same file
             if let Some(header) = current_header.take() {
                 hunks.push(DiffHunk {
@@ -145,7 +465,8 @@ pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
                     unsupported_metadata: current_unsupported.clone(),
                 });
             }
-            current_header = Some(line.to_string());
+            // The function name git appends comes from the file, \r and all
+            current_header = Some(line.trim_end_matches('\r').to_string());
         } else if current_header.is_some() {
             current_lines.push(line.to_string());
         }
@@ -162,11 +483,111 @@ pub fn parse_diff(input: &str) -> Vec<DiffHunk> {
             lines: current_lines,
             unsupported_metadata: current_unsupported,
         });
+    } else if !current_has_hunks
+        && let Some(hunk) = mode_only_hunk(&current_diff_line, &current_preamble)
+    {
+        hunks.push(hunk);
+    }
+
+    // A submodule with uncommitted changes shows as `<sha>-dirty`. Only its
+    // commit can be staged, so drop the marker, and the hunk when the commit
+    // itself is unchanged; this also keeps the ID from changing as the
+    // submodule's work tree does.
+    for hunk in &mut hunks {
+        if submodule_change(hunk).is_some()
+            && let Some(line) = hunk.lines.iter_mut().find(|l| l.starts_with('+'))
+            && let Some(clean) = line.strip_suffix("-dirty")
+        {
+            *line = clean.to_string();
+        }
     }
+    hunks.retain(|hunk| submodule_change(hunk).is_none_or(|change| change.old != change.new));
 
     hunks
 }
 
+/// The hunk of a file whose only change is its mode (`chmod +x`): no lines,
+/// and its `old mode`/`new mode` lines as the header, so that `build_patch`
+/// gives the patch that makes the change and `show` prints it as git does.
+fn mode_only_hunk(diff_line: &str, preamble: &[String]) -> Option<DiffHunk> {
+    let [old, new] = preamble else {
+        return None;
+    };
+    if !old.starts_with("old mode ") || !new.starts_with("new mode ") {
+        return None;
+    }
+    // Both sides name the same path, so the second half of the line is it
+    let paths = diff_line.strip_prefix("diff --git ")?;
+    let file = strip_diff_prefix(&format!("+++ {}", paths.get(paths.len().div_ceil(2)..)?));
+    Some(DiffHunk {
+        file: file.clone(),
+        old_file: file.clone(),
+        new_file: file,
+        file_header: diff_line.to_string(),
+        header: format!("{}\n{}", old, new),
+        lines: Vec::new(),
+        unsupported_metadata: None,
+    })
+}
+
+/// A change to a file's mode alone, as octal modes (`100644`, `100755`).
+pub struct ModeChange<'a> {
+    pub old: &'a str,
+    pub new: &'a str,
+}
+
+/// The mode change `hunk` makes, if it changes nothing else.
+pub fn mode_change(hunk: &DiffHunk) -> Option<ModeChange<'_>> {
+    let (old, new) = hunk
+        .header
+        .strip_prefix("old mode ")?
+        .split_once("\nnew mode ")?;
+    Some(ModeChange { old, new })
+}
+
+/// A change to the commit a submodule points to. `old` is None when the
+/// submodule is added and `new` when it is removed.
+pub struct SubmoduleChange<'a> {
+    pub old: Option<&'a str>,
+    pub new: Option<&'a str>,
+}
+
+/// The submodule pointer change `hunk` makes, if it is one: a hunk of
+/// `Subproject commit <sha>` lines.
+pub fn submodule_change(hunk: &DiffHunk) -> Option<SubmoduleChange<'_>> {
+    const MARKER: &str = "Subproject commit ";
+    let mut change = SubmoduleChange {
+        old: None,
+        new: None,
+    };
+    for line in &hunk.lines {
+        if let Some(sha) = line.strip_prefix('-').and_then(|l| l.strip_prefix(MARKER)) {
+            change.old = Some(sha);
+        } else if let Some(sha) = line.strip_prefix('+').and_then(|l| l.strip_prefix(MARKER)) {
+            change.new = Some(sha);
+        } else {
+            return None;
+        }
+    }
+    (change.old.is_some() || change.new.is_some()).then_some(change)
+}
+
+/// Submodules whose work tree has changes to tracked files, from
+/// `git status`.
+pub fn dirty_submodules() -> Result<Vec<String>> {
+    let status = run_git_cmd(Command::new("git").args(["status", "--porcelain=v2", "-z"]))?;
+    let mut dirty = Vec::new();
+    for entry in status.split('\0') {
+        // 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>, where <sub> is
+        // S<c><m><u> for a submodule and <m> is M when it has changes
+        let fields: Vec<&str> = entry.splitn(9, ' ').collect();
+        if fields.len() == 9 && fields[0] == "1" && fields[2].as_bytes().get(2) == Some(&b'M') {
+            dirty.push(fields[8].to_string());
+        }
+    }
+    Ok(dirty)
+}
+
 /// Choose the display path for a hunk. Prefer new-side, fall back to old-side
 /// for deletions where new is /dev/null.
 fn display_file(old: &str, new: &str) -> String {
@@ -188,3 +609,46 @@ pub fn check_supported(hunk: &DiffHunk, id: &str) -> Result<()> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_diff_prefix() {
+        assert_eq!(strip_diff_prefix("--- a/src/main.rs"), "src/main.rs");
+        assert_eq!(strip_diff_prefix("+++ b/sp ace.txt\t"), "sp ace.txt");
+        assert_eq!(strip_diff_prefix("--- /dev/null"), "dev/null");
+        assert_eq!(strip_diff_prefix(r#"+++ "b/\303\244.txt""#), "ä.txt");
+        assert_eq!(
+            strip_diff_prefix(r#"--- "a/q\"t\\x\t.txt""#),
+            "q\"t\\x\t.txt"
+        );
+        assert_eq!(strip_diff_prefix(r#"--- "a/l\344t""#), "l\u{fffd}t");
+    }
+
+    #[test]
+    fn test_parse_mode_only_change() {
+        let diff = "diff --git a/sp ace.sh b/sp ace.sh\n\
+                    old mode 100644\n\
+                    new mode 100755\n\
+                    diff --git a/b.txt b/b.txt\n\
+                    old mode 100644\n\
+                    new mode 100755\n\
+                    index 7898192..6178079\n\
+                    --- a/b.txt\n\
+                    +++ b/b.txt\n\
+                    @@ -1 +1 @@\n\
+                    -a\n\
+                    +b\n";
+        let hunks = parse_diff_uncached(diff);
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].file, "sp ace.sh");
+        let change = mode_change(&hunks[0]).unwrap();
+        assert_eq!((change.old, change.new), ("100644", "100755"));
+        assert!(hunks[0].unsupported_metadata.is_none());
+        // With content changes too, the mode is not a hunk of its own
+        assert!(mode_change(&hunks[1]).is_none());
+        assert_eq!(hunks[1].unsupported_metadata.as_deref(), Some("old mode"));
+    }
+}
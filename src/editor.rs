@@ -0,0 +1,82 @@
+//! `--edit`: finishing a commit message in the editor, as `git commit` does,
+//! starting from the message the command would otherwise have used.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::process::Command;
+
+/// Open `$GIT_EDITOR` (as `git var` resolves it) on `message`, followed by
+/// `summary` (the lines of what the commit changes) as comments, and return
+/// the saved message without its comment lines. An empty message aborts.
+pub fn edit(message: &str, summary: &[String]) -> Result<String> {
+    let editor = crate::diff::run_git_cmd(Command::new("git").args(["var", "GIT_EDITOR"]))?;
+    let editor = editor.trim();
+    let path = crate::state::surgeon_path("COMMIT_EDITMSG")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, template(message, summary))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    // Not traced: the editor waits on a person, which --timeout must not cut
+    // short, and -v has nothing to add to it
+    let status = Command::new("sh")
+        .args(["-c", &format!("{} \"$@\"", editor), editor])
+        .arg(&path)
+        .status()
+        .with_context(|| format!("failed to run editor '{}'", editor))?;
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    if !status.success() {
+        anyhow::bail!("editor '{}' failed ({})", editor, status);
+    }
+    let message = strip_comments(&edited.context("failed to read the edited message")?);
+    if message.is_empty() {
+        crate::exit::fail!(Usage, "empty commit message; aborting");
+    }
+    Ok(message)
+}
+
+fn template(message: &str, summary: &[String]) -> String {
+    let mut text = format!(
+        "{}\n\n# Enter the commit message. Lines starting with '#' are ignored,\n\
+         # and an empty message aborts.\n",
+        message.trim_end()
+    );
+    if !summary.is_empty() {
+        text.push_str("#\n# Changes:\n");
+        for line in summary {
+            text.push_str(&format!("#   {}\n", line));
+        }
+    }
+    text
+}
+
+/// `message` without its comment lines, trailing whitespace or surrounding
+/// blank lines.
+fn strip_comments(message: &str) -> String {
+    let lines: Vec<&str> = message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_template_round_trip() {
+        let summary = vec!["a.txt | 1 hunk  +1 -0".to_string()];
+        let text = template("Subject\n\nBody", &summary);
+        assert!(text.contains("#   a.txt | 1 hunk  +1 -0\n"));
+        assert_eq!(strip_comments(&text), "Subject\n\nBody");
+    }
+
+    #[test]
+    fn test_strip_comments_empty() {
+        assert_eq!(strip_comments(&template("", &[])), "");
+    }
+}
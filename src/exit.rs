@@ -0,0 +1,100 @@
+//! Exit codes by class of failure, so callers can branch on why a command
+//! failed without parsing its message:
+//!
+//! | Code | Meaning                                                     |
+//! | ---- | ----------------------------------------------------------- |
+//! | 0    | success                                                     |
+//! | 1    | any other error                                             |
+//! | 2    | usage error: bad flags or arguments                         |
+//! | 3    | a hunk, commit or file was not found                        |
+//! | 4    | a patch or rebase did not apply cleanly                     |
+//! | 5    | the repository is in the wrong state (dirty tree, staged    |
+//! |      | changes, operation in progress, stale `--if-match` token)   |
+//! | 6    | rewrite refused: the commit is published                    |
+//! | 7    | another git-surgeon holds the repository lock               |
+//! | 8    | a git command outlived `--timeout` and was killed           |
+
+use std::fmt;
+
+pub const USAGE: i32 = 2;
+pub const NOT_FOUND: i32 = 3;
+pub const CONFLICT: i32 = 4;
+pub const REPO_STATE: i32 = 5;
+pub const REFUSED: i32 = 6;
+pub const LOCKED: i32 = 7;
+pub const TIMEOUT: i32 = 8;
+
+/// The class of a failure, which picks its exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Usage,
+    NotFound,
+    Conflict,
+    RepoState,
+    Refused,
+    Timeout,
+}
+
+impl Kind {
+    fn code(self) -> i32 {
+        match self {
+            Kind::Usage => USAGE,
+            Kind::NotFound => NOT_FOUND,
+            Kind::Conflict => CONFLICT,
+            Kind::RepoState => REPO_STATE,
+            Kind::Refused => REFUSED,
+            Kind::Timeout => TIMEOUT,
+        }
+    }
+}
+
+/// An error of a known class; displays as its message alone.
+#[derive(Debug)]
+pub struct Failure {
+    pub kind: Kind,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// `anyhow!` for a `Failure` of the given `Kind` variant.
+macro_rules! failure {
+    ($kind:ident, $($arg:tt)+) => {
+        anyhow::Error::new($crate::exit::Failure {
+            kind: $crate::exit::Kind::$kind,
+            message: format!($($arg)+),
+        })
+    };
+}
+
+/// `bail!` with a `Failure` of the given `Kind` variant.
+macro_rules! fail {
+    ($kind:ident, $($arg:tt)+) => {
+        return Err($crate::exit::failure!($kind, $($arg)+))
+    };
+}
+
+pub(crate) use {fail, failure};
+
+/// The exit code for `e`: that of the first classified error in its chain,
+/// so added context keeps the class.
+pub fn code(e: &anyhow::Error) -> i32 {
+    for cause in e.chain() {
+        if let Some(failure) = cause.downcast_ref::<Failure>() {
+            return failure.kind.code();
+        }
+        if cause.is::<crate::token::StaleToken>() {
+            return REPO_STATE;
+        }
+        if cause.is::<crate::lock::Locked>() {
+            return LOCKED;
+        }
+    }
+    1
+}
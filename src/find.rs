@@ -0,0 +1,90 @@
+//! `find-commit`: look up the commits a rewrite could target by their
+//! message, author, date or files, among those not yet published.
+
+use anyhow::Result;
+use std::process::Command;
+
+use crate::json::{self, Value};
+
+/// What `find-commit` filters on; every given filter must match.
+pub struct Query<'a> {
+    /// Extended regex matched against the whole message, as `:/pattern` is
+    pub grep: &'a str,
+    pub author: Option<&'a str>,
+    pub since: Option<&'a str>,
+    pub file: Option<&'a str>,
+}
+
+struct Found {
+    sha: String,
+    author: String,
+    date: String,
+    subject: String,
+}
+
+/// List the commits of HEAD that match `query` and are not reachable from
+/// the upstream or a `surgeon.protectedRef`, newest first: `<sha> <subject>`
+/// lines, or a JSON array with `json`. Fails with NotFound if none match.
+pub fn find_commits(query: &Query, json: bool) -> Result<()> {
+    let protected = crate::rev::protected_refs();
+    if protected.is_empty() {
+        eprintln!("note: no upstream or protected refs; searching all of HEAD");
+    }
+    let mut cmd = Command::new("git");
+    cmd.args(["log", "-E", "--format=%H%x00%an <%ae>%x00%aI%x00%s"])
+        .arg(format!("--grep={}", query.grep));
+    if let Some(author) = query.author {
+        cmd.arg(format!("--author={}", author));
+    }
+    if let Some(since) = query.since {
+        cmd.arg(format!("--since={}", since));
+    }
+    cmd.arg("HEAD");
+    for name in &protected {
+        cmd.arg(format!("^{}", name));
+    }
+    cmd.arg("--");
+    if let Some(file) = query.file {
+        cmd.arg(file);
+    }
+    let listed = crate::diff::run_git_cmd(&mut cmd)?;
+    let found = parse(&listed);
+    if found.is_empty() {
+        crate::exit::fail!(NotFound, "no unpublished commit matches '{}'", query.grep);
+    }
+
+    if json {
+        let commits: Vec<Value> = found
+            .iter()
+            .map(|f| {
+                json::object([
+                    ("commit", f.sha.as_str().into()),
+                    ("subject", f.subject.as_str().into()),
+                    ("author", f.author.as_str().into()),
+                    ("date", f.date.as_str().into()),
+                ])
+            })
+            .collect();
+        println!("{}", Value::Array(commits));
+        return Ok(());
+    }
+    for f in &found {
+        println!("{} {}", crate::color::id(&f.sha[..7]), f.subject);
+    }
+    Ok(())
+}
+
+fn parse(listed: &str) -> Vec<Found> {
+    listed
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(4, '\0');
+            Some(Found {
+                sha: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                date: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            })
+        })
+        .collect()
+}
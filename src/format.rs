@@ -0,0 +1,135 @@
+use anyhow::Result;
+
+use crate::diff::DiffHunk;
+
+/// Fields of `hunks --format`.
+const FIELDS: &[&str] = &[
+    "id",
+    "file",
+    "func",
+    "adds",
+    "dels",
+    "first_line",
+    "header",
+    "location",
+];
+
+enum Part {
+    Literal(String),
+    Field(&'static str),
+}
+
+/// A `hunks --format` template: `%<field>` placeholders, `%n` for a newline
+/// and `%%` for a literal percent sign, like `git log --format`.
+pub struct HunkFormat {
+    parts: Vec<Part>,
+}
+
+impl HunkFormat {
+    pub fn parse(template: &str) -> Result<HunkFormat> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut rest = template;
+        while let Some(pos) = rest.find('%') {
+            literal.push_str(&rest[..pos]);
+            rest = &rest[pos + 1..];
+            if let Some(after) = rest.strip_prefix('%') {
+                literal.push('%');
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('n') {
+                literal.push('\n');
+                rest = after;
+                continue;
+            }
+            // Longest match, so %first_line is not read as %file + "irst_line"
+            let field = FIELDS
+                .iter()
+                .filter(|f| rest.starts_with(**f))
+                .max_by_key(|f| f.len())
+                .ok_or_else(|| {
+                    let name: String = rest
+                        .chars()
+                        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                        .collect();
+                    anyhow::anyhow!(
+                        "unknown format field %{} (available: {}, %n, %%)",
+                        name,
+                        FIELDS
+                            .iter()
+                            .map(|f| format!("%{}", f))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                })?;
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Field(field));
+            rest = &rest[field.len()..];
+        }
+        literal.push_str(rest);
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+        Ok(HunkFormat { parts })
+    }
+
+    /// The line for hunk `id`, listed from `location` (staged, unstaged or
+    /// commit).
+    pub fn render(&self, id: &str, hunk: &DiffHunk, location: &str) -> String {
+        let (additions, deletions) = crate::hunk::count_changes(hunk);
+        let mut out = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(text) => out.push_str(text),
+                Part::Field(field) => match *field {
+                    "id" => out.push_str(id),
+                    "file" => out.push_str(&hunk.file),
+                    "func" => out.push_str(crate::hunk::function_context(hunk)),
+                    "adds" => out.push_str(&additions.to_string()),
+                    "dels" => out.push_str(&deletions.to_string()),
+                    "first_line" => out.push_str(
+                        hunk.lines
+                            .iter()
+                            .find(|l| l.starts_with('+') || l.starts_with('-'))
+                            .map_or("", |l| l.as_str()),
+                    ),
+                    "header" => out.push_str(&hunk.header),
+                    "location" => out.push_str(location),
+                    _ => unreachable!("unlisted format field"),
+                },
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hunk() -> DiffHunk {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n\
+                    @@ -1,2 +1,2 @@ fn main()\n keep\n-old\n+new\n";
+        crate::diff::parse_diff(diff).remove(0)
+    }
+
+    #[test]
+    fn test_render() {
+        let format =
+            HunkFormat::parse("%id\t%file:%first_line (+%adds -%dels) 100%% %func%n%location")
+                .unwrap();
+        assert_eq!(
+            format.render("abc1234", &hunk(), "staged"),
+            "abc1234\tsrc/lib.rs:-old (+1 -1) 100% fn main()\nstaged"
+        );
+    }
+
+    #[test]
+    fn test_unknown_field() {
+        let err = HunkFormat::parse("%id %lines").err().unwrap().to_string();
+        assert!(err.contains("unknown format field %lines"), "{}", err);
+    }
+}
@@ -0,0 +1,118 @@
+//! Path patterns, as `undo-file`, `--include` and `--exclude` take them: `*`
+//! and `?` match within a path segment and `**` across segments. A pattern
+//! matches a file if it matches the file's path or one of its directories, so
+//! a directory stands for every file under it; a wildcard pattern without a
+//! `/` (`*.lock`) matches a file or directory name at any depth, as in
+//! `.gitignore`.
+
+/// `--include`/`--exclude`: the files a command takes hunks from.
+#[derive(clap::Args, Clone, Default)]
+pub struct PathFilter {
+    /// Only take hunks of files matching GLOB, a path, directory or pattern
+    /// like 'src/**' (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub include: Vec<String>,
+    /// Leave out hunks of files matching GLOB, e.g. '*.lock' (repeatable;
+    /// wins over --include)
+    #[arg(long, value_name = "GLOB")]
+    pub exclude: Vec<String>,
+}
+
+impl PathFilter {
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn allows(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| matches(p, path)))
+            && !self.exclude.iter().any(|p| matches(p, path))
+    }
+}
+
+/// Whether `pattern` has wildcards, rather than naming a path.
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let anywhere = is_glob(pattern) && !pattern.trim_end_matches('/').contains('/');
+    let pattern: Vec<char> = pattern.trim_end_matches('/').chars().collect();
+    let path: Vec<char> = path.chars().collect();
+    // `path` itself, then each of its directories
+    let mut ends: Vec<usize> = vec![path.len()];
+    ends.extend((0..path.len()).filter(|&i| path[i] == '/'));
+    ends.into_iter().any(|end| {
+        let start = if anywhere {
+            path[..end]
+                .iter()
+                .rposition(|&c| c == '/')
+                .map_or(0, |i| i + 1)
+        } else {
+            0
+        };
+        wildcard(&pattern, &path[start..end])
+    })
+}
+
+fn wildcard(pattern: &[char], s: &[char]) -> bool {
+    match pattern {
+        [] => s.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            // Zero or more whole segments
+            wildcard(rest, s) || (0..s.len()).any(|i| s[i] == '/' && wildcard(rest, &s[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=s.len()).any(|i| wildcard(rest, &s[i..])),
+        ['*', rest @ ..] => (0..=s.len())
+            .take_while(|&i| i == 0 || s[i - 1] != '/')
+            .any(|i| wildcard(rest, &s[i..])),
+        ['?', rest @ ..] => s.first().is_some_and(|&c| c != '/') && wildcard(rest, &s[1..]),
+        [c, rest @ ..] => s.first() == Some(c) && wildcard(rest, &s[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_paths_and_directories() {
+        assert!(matches("src/main.rs", "src/main.rs"));
+        assert!(matches("src", "src/a/b.rs"));
+        assert!(matches("src/", "src/a/b.rs"));
+        assert!(!matches("sr", "src/a.rs"));
+        assert!(!matches("src/a", "src/ab.rs"));
+    }
+
+    #[test]
+    fn test_matches_wildcards() {
+        assert!(matches("src/*.rs", "src/a.rs"));
+        assert!(!matches("src/*.rs", "src/sub/a.rs"));
+        assert!(matches("src/*", "src/sub/a.rs"));
+        assert!(matches("src/migrations/**", "src/migrations/2024/01.sql"));
+        assert!(matches("src/**/a.rs", "src/a.rs"));
+        assert!(matches("src/**/a.rs", "src/x/y/a.rs"));
+        assert!(matches("src/?.rs", "src/a.rs"));
+        assert!(!matches("src/?.rs", "src/ab.rs"));
+    }
+
+    #[test]
+    fn test_matches_names_at_any_depth() {
+        assert!(matches("*.lock", "Cargo.lock"));
+        assert!(matches("*.lock", "web/yarn.lock"));
+        assert!(matches("gen*", "src/generated/x.rs"));
+        assert!(!matches("*.lock", "lock.txt"));
+        assert!(!matches("Makefile", "sub/Makefile"));
+    }
+
+    #[test]
+    fn test_path_filter() {
+        let filter = PathFilter {
+            include: vec!["src".to_string()],
+            exclude: vec!["*.snap".to_string()],
+        };
+        assert!(filter.allows("src/a.rs"));
+        assert!(!filter.allows("src/tests/a.snap"));
+        assert!(!filter.allows("README.md"));
+        assert!(PathFilter::default().allows("README.md"));
+    }
+}
@@ -1,1136 +1,4739 @@
 use anyhow::{Context, Result};
 use std::collections::{HashMap, HashSet};
 use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use crate::blame::{Blame, BlameLine, BlameMode, BlameRange};
 use crate::diff::DiffHunk;
-use crate::hunk_id::assign_ids;
-use crate::patch::{
-    ApplyMode, apply_patch, build_patch, slice_hunk, slice_hunk_multi, slice_hunk_with_state,
-};
+use crate::hunk_id::{assign_ids, resolve_ref, split_range};
+use crate::json::{self, Value};
+use crate::output::Traced;
+use crate::patch::{ApplyMode, apply_patch, build_patch, slice_hunk_multi, slice_hunk_with_state};
+use crate::state::{Operation, State};
+
+pub const DEFAULT_PREVIEW_LINES: usize = 4;
+
+/// Prefix of staged hunk IDs in `hunks --all`, so a hunk that is both staged
+/// and unstaged with the same content gets two distinct IDs.
+pub const STAGED_ID_PREFIX: &str = "s-";
+
+/// Which lines `hunks` previews under each hunk header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preview {
+    /// Headers only
+    None,
+    /// The first changed (+/-) lines
+    Changes,
+    /// The first lines of the hunk as-is, context included
+    Context,
+}
 
-const MAX_PREVIEW_LINES: usize = 4;
+pub fn parse_preview(s: &str) -> Result<Preview, String> {
+    match s {
+        "none" => Ok(Preview::None),
+        "changes" => Ok(Preview::Changes),
+        "context" => Ok(Preview::Context),
+        _ => Err("expected none, changes or context".to_string()),
+    }
+}
 
-pub fn list_hunks(
-    staged: bool,
-    file: Option<&str>,
-    commit: Option<&str>,
-    full: bool,
-    blame: bool,
-) -> Result<()> {
+/// Set by `--no-verify`: skip the commit hooks and the pre-rebase hook.
+static NO_VERIFY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_no_verify(no_verify: bool) {
+    NO_VERIFY.store(no_verify, Ordering::Relaxed);
+}
+
+/// `git commit`, with `--no-verify` when hooks are being skipped.
+fn git_commit() -> Command {
+    let mut cmd = Command::new("git");
+    cmd.arg("commit");
+    if NO_VERIFY.load(Ordering::Relaxed) {
+        cmd.arg("--no-verify");
+    }
+    cmd
+}
+
+/// `git rebase`, with `--no-verify` when hooks are being skipped and the
+/// replayed commits' committer as configured. rerere is enabled so repeated
+/// rewrites of a branch reuse recorded resolutions.
+fn git_rebase() -> Result<Command> {
+    let mut cmd = Command::new("git");
+    cmd.args(RERERE_CONFIG).arg("rebase");
+    if NO_VERIFY.load(Ordering::Relaxed) {
+        cmd.arg("--no-verify");
+    }
+    crate::committer::configure_rebase(&mut cmd)?;
+    Ok(cmd)
+}
+
+const RERERE_CONFIG: &[&str] = &["-c", "rerere.enabled=true", "-c", "rerere.autoUpdate=true"];
+
+/// `git rebase --continue`, keeping the message of the commit being resolved.
+fn git_rebase_continue() -> Command {
+    let mut cmd = Command::new("git");
+    cmd.args(RERERE_CONFIG)
+        .args(["rebase", "--continue"])
+        .env("GIT_EDITOR", "true");
+    cmd
+}
+
+/// Run a rebase command. Whenever it stops on conflicts that rerere resolved
+/// entirely from recorded resolutions, report them and continue; the output
+/// returned is that of the last step.
+fn run_rebase(cmd: &mut Command) -> Result<std::process::Output> {
+    let mut output = cmd.traced_output().context("failed to run rebase")?;
+    while !output.status.success() {
+        let resolved = rerere_resolved(&output);
+        for path in &resolved {
+            eprintln!("resolved {} using a recorded resolution (rerere)", path);
+        }
+        let unmerged = crate::diff::run_git_cmd(Command::new("git").args([
+            "diff",
+            "--name-only",
+            "--diff-filter=U",
+        ]))?;
+        if resolved.is_empty() || !unmerged.trim().is_empty() || !rebase_in_progress()? {
+            break;
+        }
+        output = git_rebase_continue()
+            .traced_output()
+            .context("failed to continue rebase")?;
+    }
+    Ok(output)
+}
+
+/// Paths that rerere reports resolving in a git command's output.
+fn rerere_resolved(output: &std::process::Output) -> Vec<String> {
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+    text.lines()
+        .filter_map(|line| {
+            let rest = line
+                .strip_prefix("Resolved '")
+                .or_else(|| line.strip_prefix("Staged '"))?;
+            rest.strip_suffix("' using previous resolution.")
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Order in which `hunks` lists hunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sort {
+    /// As they appear in the diff
+    PathOrder,
+    /// By file path, then diff order within a file
+    File,
+    /// Most changed lines first
+    Size,
+}
+
+pub fn parse_sort(s: &str) -> Result<Sort, String> {
+    match s {
+        "path-order" => Ok(Sort::PathOrder),
+        "file" => Ok(Sort::File),
+        "size" => Ok(Sort::Size),
+        _ => Err("expected file, size or path-order".to_string()),
+    }
+}
+
+/// What `hunks` lists and how.
+pub struct ListOptions<'a> {
+    pub staged: bool,
+    /// Staged and unstaged hunks together
+    pub all: bool,
+    pub file: Option<&'a str>,
+    pub commit: Option<&'a str>,
+    pub full: bool,
+    pub blame: Option<BlameMode>,
+    /// Commits `--blame` looks through, on top of `blame.ignoreRevsFile`
+    pub ignore_revs: &'a [String],
+    pub sample: Option<usize>,
+    pub json: bool,
+    /// `--format` template: one line per hunk, no preview
+    pub format: Option<&'a str>,
+    pub preview: Preview,
+    /// Preview at most this many lines per hunk
+    pub preview_lines: usize,
+    /// Headers only, followed by per-file and total counts
+    pub stat: bool,
+    pub sort: Sort,
+    /// Only hunks with at least this many changed lines
+    pub min_changes: Option<usize>,
+    /// Only hunks with at most this many changed lines
+    pub max_changes: Option<usize>,
+    /// Leave out hunks that only change whitespace
+    pub ignore_whitespace: bool,
+    /// Print each hunk whole, inside its enclosing function
+    pub function_context: bool,
+    /// Also list the hunks inside submodules with changes
+    pub recurse_submodules: bool,
+    /// Only hunks of the files `--include`/`--exclude` allow
+    pub filter: &'a crate::glob::PathFilter,
+    /// Cluster hunks by file and enclosing function
+    pub group_by_function: bool,
+}
+
+pub fn list_hunks(opts: &ListOptions) -> Result<()> {
+    let ListOptions {
+        staged,
+        all,
+        file,
+        commit,
+        full,
+        blame,
+        ignore_revs,
+        sample,
+        json,
+        format,
+        preview,
+        preview_lines,
+        stat,
+        sort,
+        min_changes,
+        max_changes,
+        ignore_whitespace,
+        function_context: with_function,
+        recurse_submodules,
+        group_by_function,
+        filter,
+    } = *opts;
+    let format = format.map(crate::format::HunkFormat::parse).transpose()?;
+    let commit_sha = commit.map(crate::rev::resolve_commit).transpose()?;
+    let commit = commit_sha.as_deref();
     let diff_output = match commit {
         Some(c) => crate::diff::run_git_diff_commit(c, file)?,
         None => crate::diff::run_git_diff(staged, file)?,
     };
     let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
+    let staged_hunks = if all {
+        crate::diff::parse_diff(&crate::diff::run_git_diff(true, file)?)
+    } else {
+        Vec::new()
+    };
+    let location = match commit {
+        Some(_) => "commit",
+        None if staged => "staged",
+        None => "unstaged",
+    };
+    let mut listed: Vec<(String, &DiffHunk, &str)> = assign_ids(&staged_hunks)
+        .into_iter()
+        .map(|(id, hunk)| (format!("{}{}", STAGED_ID_PREFIX, id), hunk, "staged"))
+        .collect();
+    listed.extend(
+        assign_ids(&hunks)
+            .into_iter()
+            .map(|(id, hunk)| (id, hunk, location)),
+    );
+    // IDs are assigned over the whole diff, so filtering does not change them
+    listed.retain(|(_, hunk, _)| {
+        let size = change_size(hunk);
+        min_changes.is_none_or(|min| size >= min)
+            && max_changes.is_none_or(|max| size <= max)
+            && filter.allows(&hunk.file)
+    });
+    if ignore_whitespace {
+        let before = listed.len();
+        listed.retain(|(_, hunk, _)| !is_whitespace_only(hunk));
+        if listed.len() < before {
+            eprintln!("({} whitespace-only hunk(s) hidden)", before - listed.len());
+        }
+    }
+    let locations: Vec<&str> = listed.iter().map(|(_, _, location)| *location).collect();
+    let (widened, widened_staged) = if with_function {
+        let main = crate::diff::run_git_diff_function(staged, commit, file)?;
+        let staged = match all {
+            true => crate::diff::run_git_diff_function(true, None, file)?,
+            false => String::new(),
+        };
+        (
+            crate::diff::parse_diff(&main),
+            crate::diff::parse_diff(&staged),
+        )
+    } else {
+        (Vec::new(), Vec::new())
+    };
+    let enclosing = |idx: usize, hunk: &DiffHunk| {
+        let widened = if all && locations[idx] == "staged" {
+            &widened_staged
+        } else {
+            &widened
+        };
+        enclosing_function(hunk, widened)
+    };
+    let identified: Vec<(String, &DiffHunk)> =
+        listed.into_iter().map(|(id, hunk, _)| (id, hunk)).collect();
 
-    if identified.is_empty() {
-        return Ok(());
+    let mut selected: Vec<usize> = match sample {
+        Some(n) => sample_hunks(&identified, n),
+        None => (0..identified.len()).collect(),
+    };
+    match sort {
+        Sort::PathOrder => {}
+        Sort::File => selected.sort_by(|&a, &b| identified[a].1.file.cmp(&identified[b].1.file)),
+        Sort::Size => selected.sort_by_key(|&i| std::cmp::Reverse(change_size(identified[i].1))),
     }
+    let groups = if group_by_function {
+        // Each file as the listed hunks leave it, read once
+        let mut sources: HashMap<(&str, &str), Option<std::rc::Rc<String>>> = HashMap::new();
+        let groups = function_groups(&identified, &selected, |idx| {
+            let file = identified[idx].1.new_file.as_str();
+            sources
+                .entry((locations[idx], file))
+                .or_insert_with(|| {
+                    let source = match (locations[idx], commit) {
+                        (_, Some(sha)) => HunkSource::Commit(sha.to_string()),
+                        ("staged", None) => HunkSource::Index,
+                        _ => HunkSource::WorkingTree,
+                    };
+                    // Deleted files and unreadable ones fall back to the @@ context
+                    source.new_content(file).ok().map(std::rc::Rc::new)
+                })
+                .clone()
+        });
+        selected = groups.iter().flat_map(|g| g.hunks.clone()).collect();
+        groups
+    } else {
+        Vec::new()
+    };
+    let shown: Vec<(&str, &DiffHunk, &str)> = selected
+        .iter()
+        .map(|&i| (identified[i].0.as_str(), identified[i].1, locations[i]))
+        .collect();
+    // Only a convenience for later lookups; listing must not fail over it
+    let _ = crate::registry::record(&shown, commit);
+    let blamed = blame
+        .map(|_| {
+            let hunks: Vec<&DiffHunk> = selected.iter().map(|&i| identified[i].1).collect();
+            collect_blame(&hunks, commit, ignore_revs)
+        })
+        .transpose()?;
 
-    for (id, hunk) in &identified {
-        let additions = hunk.lines.iter().filter(|l| l.starts_with('+')).count();
-        let deletions = hunk.lines.iter().filter(|l| l.starts_with('-')).count();
-
-        // Extract function context from @@ header (text after the closing @@)
-        let func_ctx = hunk
-            .header
-            .find("@@ ")
-            .and_then(|start| {
-                let rest = &hunk.header[start + 3..];
-                rest.find("@@ ").map(|end| rest[end + 3..].trim())
+    if json {
+        let entries: Vec<Value> = selected
+            .iter()
+            .map(|&idx| {
+                let (id, hunk) = &identified[idx];
+                let (additions, deletions) = count_changes(hunk);
+                let mut entry = json::object([
+                    ("id", id.as_str().into()),
+                    ("location", locations[idx].into()),
+                    ("file", hunk.file.as_str().into()),
+                    ("header", hunk.header.as_str().into()),
+                    ("context", function_context(hunk).into()),
+                    ("additions", additions.into()),
+                    ("deletions", deletions.into()),
+                    ("lines", hunk.lines.clone().into()),
+                ]);
+                if with_function {
+                    enclosing(idx, hunk).add_to_json(&mut entry);
+                }
+                if let (Some(blamed), Value::Object(fields)) = (&blamed, &mut entry) {
+                    fields.push(("blame".to_string(), blame_json(hunk, commit, blamed)));
+                }
+                entry
             })
-            .unwrap_or("");
+            .collect();
+        let mut output = json::object([
+            ("token", crate::token::current()?.into()),
+            ("hunks", Value::Array(entries)),
+        ]);
+        if let (true, Value::Object(fields)) = (group_by_function, &mut output) {
+            let groups: Vec<Value> = groups
+                .iter()
+                .map(|group| {
+                    let ids: Vec<String> = group
+                        .hunks
+                        .iter()
+                        .map(|&i| identified[i].0.clone())
+                        .collect();
+                    json::object([
+                        ("file", group.file.into()),
+                        ("function", group.function.as_str().into()),
+                        ("hunks", ids.into()),
+                    ])
+                })
+                .collect();
+            fields.push(("groups".to_string(), Value::Array(groups)));
+        }
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if let Some(format) = format {
+        for &idx in &selected {
+            let (id, hunk) = &identified[idx];
+            println!("{}", format.render(id, hunk, locations[idx]));
+        }
+        return Ok(());
+    }
+
+    let mut headings: HashMap<usize, &FunctionGroup> =
+        groups.iter().map(|g| (g.hunks[0], g)).collect();
+    for &idx in &selected {
+        let (id, hunk) = &identified[idx];
+        let func_ctx = function_context(hunk);
+        if let Some(group) = headings.remove(&idx) {
+            println!(
+                "# {}: {} ({} hunk{})",
+                group.file,
+                if group.function.is_empty() {
+                    "(top level)"
+                } else {
+                    &group.function
+                },
+                group.hunks.len(),
+                if group.hunks.len() == 1 { "" } else { "s" }
+            );
+        }
 
         let func_part = if func_ctx.is_empty() {
             String::new()
         } else {
             format!(" {}", func_ctx)
         };
+        let location_part = if all {
+            format!(" [{}]", locations[idx])
+        } else {
+            String::new()
+        };
 
         println!(
-            "{} {}{} (+{} -{})",
-            id, hunk.file, func_part, additions, deletions
+            "{} {}{} {}{}",
+            crate::color::id(id),
+            hunk.file,
+            func_part,
+            change_summary(hunk),
+            location_part
         );
 
-        if blame {
+        if let (Some(mode), Some(blamed)) = (blame, &blamed) {
             // Blame mode: show all lines with blame hashes (takes precedence over full)
-            print_blamed_lines(hunk, commit)?;
+            print_blamed_lines(hunk, commit, blamed, mode);
+        } else if with_function {
+            print_in_function(hunk, &enclosing(idx, hunk));
         } else if full {
             // Full mode: show all lines with line numbers (like show command)
             let width = hunk.lines.len().to_string().len();
             for (i, line) in hunk.lines.iter().enumerate() {
-                println!("{:>w$}:{}", i + 1, line, w = width);
-            }
-        } else {
-            // Preview mode: show up to MAX_PREVIEW_LINES changed lines
-            let changed: Vec<&String> = hunk
-                .lines
-                .iter()
-                .filter(|l| l.starts_with('+') || l.starts_with('-'))
-                .collect();
-
-            let show = changed.len().min(MAX_PREVIEW_LINES);
-            for line in &changed[..show] {
-                println!("  {}", line);
-            }
-            if changed.len() > MAX_PREVIEW_LINES {
-                println!("  ... (+{} more lines)", changed.len() - MAX_PREVIEW_LINES);
+                println!("{:>w$}:{}", i + 1, crate::color::diff_line(line), w = width);
             }
+        } else if stat || preview == Preview::None {
+            continue;
+        } else if crate::diff::submodule_change(hunk).is_none() {
+            print_preview(hunk, preview, preview_lines);
         }
         println!();
     }
 
+    if stat {
+        if !identified.is_empty() {
+            print_stat(&identified, &selected);
+        }
+    } else {
+        print_textconv_views(staged, commit, file, full, preview_lines)?;
+        if recurse_submodules {
+            print_submodule_hunks(file, full, preview, preview_lines)?;
+        }
+    }
+    if sample.is_some() && !identified.is_empty() {
+        print_sample_totals(&identified, selected.len());
+    }
+
     Ok(())
 }
 
-fn print_blamed_lines(hunk: &crate::diff::DiffHunk, commit: Option<&str>) -> Result<()> {
-    use crate::blame::{get_blame, parse_hunk_header};
+/// Hunks of `hunks --group-by-function` that change the same function.
+struct FunctionGroup<'a> {
+    file: &'a str,
+    /// The declaration line, empty for hunks outside any function
+    function: String,
+    /// Indices into the listed hunks, in listing order
+    hunks: Vec<usize>,
+}
 
-    let (old_from, old_count, new_from, new_count) =
-        parse_hunk_header(&hunk.header).unwrap_or((1, 0, 1, 0));
+/// Cluster `selected` by file and enclosing function, keeping the listing
+/// order of each cluster's first hunk and of the hunks within it. The
+/// function is the innermost block of the changed file that contains the
+/// hunk's first change, found with `symbol::enclosing` in `source(idx)`; the
+/// `@@` context stands in where that finds none, as it names the line above
+/// the hunk rather than the one around its changes.
+fn function_groups<'a>(
+    identified: &[(String, &'a DiffHunk)],
+    selected: &[usize],
+    mut source: impl FnMut(usize) -> Option<std::rc::Rc<String>>,
+) -> Vec<FunctionGroup<'a>> {
+    let mut groups: Vec<FunctionGroup> = Vec::new();
+    for &idx in selected {
+        let hunk = identified[idx].1;
+        let function = source(idx)
+            .and_then(|text| {
+                crate::symbol::enclosing(&text, first_change_line(hunk)).map(str::to_string)
+            })
+            .unwrap_or_else(|| function_context(hunk).to_string());
+        match groups
+            .iter_mut()
+            .find(|g| g.file == hunk.file && g.function == function)
+        {
+            Some(group) => group.hunks.push(idx),
+            None => groups.push(FunctionGroup {
+                file: &hunk.file,
+                function,
+                hunks: vec![idx],
+            }),
+        }
+    }
+    groups
+}
 
-    // Determine blame revisions based on diff type
-    // For commit diffs: old = commit^, new = commit
-    // For unstaged/staged: old = HEAD, new = working tree (returns 0000000)
-    let (old_rev_str, new_rev): (String, Option<&str>) = match commit {
-        Some(c) => (format!("{}^", c), Some(c)),
-        None => ("HEAD".to_string(), None),
-    };
+/// The new-side line number of `hunk`'s first added or removed line; a
+/// removed line counts as the line that follows it.
+fn first_change_line(hunk: &DiffHunk) -> usize {
+    let mut line = crate::blame::parse_hunk_header(&hunk.header).map_or(1, |(_, _, from, _)| from);
+    for text in &hunk.lines {
+        if text.starts_with('+') || text.starts_with('-') {
+            break;
+        }
+        if text.starts_with(' ') {
+            line += 1;
+        }
+    }
+    line
+}
 
-    // Get blame for old side (for context and removed lines)
-    let old_blame = if hunk.old_file != "dev/null" && old_count > 0 {
-        get_blame(&hunk.old_file, old_from, old_count, Some(&old_rev_str)).unwrap_or_default()
-    } else {
-        Vec::new()
-    };
+/// Preview mode: up to `preview_lines` changed lines, or any lines with
+/// `--preview=context`.
+fn print_preview(hunk: &DiffHunk, preview: Preview, preview_lines: usize) {
+    let lines: Vec<&String> = hunk
+        .lines
+        .iter()
+        .filter(|l| preview == Preview::Context || l.starts_with('+') || l.starts_with('-'))
+        .collect();
 
-    // Get blame for new side (for context and added lines)
-    let new_blame = if hunk.new_file != "dev/null" && new_count > 0 {
-        get_blame(&hunk.new_file, new_from, new_count, new_rev).unwrap_or_default()
-    } else {
-        Vec::new()
-    };
+    let show = lines.len().min(preview_lines);
+    for line in &lines[..show] {
+        println!("  {}", crate::color::diff_line(line));
+    }
+    if lines.len() > preview_lines {
+        println!("  ... (+{} more lines)", lines.len() - preview_lines);
+    }
+}
 
-    // Walk through lines with indices
-    let mut old_idx = 0usize;
-    let mut new_idx = 0usize;
+/// `(+3 -1)`, or for a submodule pointer change the commits it moves
+/// between, as `Subproject commit a1b2c3d -> e4f5a6b`, and for a mode change
+/// the modes, as `mode 100644 -> 100755`.
+fn change_summary(hunk: &DiffHunk) -> String {
+    if let Some(change) = crate::diff::mode_change(hunk) {
+        return format!("mode {} -> {}", change.old, change.new);
+    }
+    match crate::diff::submodule_change(hunk) {
+        Some(change) => {
+            let short =
+                |sha: Option<&str>| sha.map_or("none".to_string(), |s| s.chars().take(7).collect());
+            format!(
+                "Subproject commit {} -> {}",
+                short(change.old),
+                short(change.new)
+            )
+        }
+        None => {
+            let (additions, deletions) = count_changes(hunk);
+            format!("(+{} -{})", additions, deletions)
+        }
+    }
+}
 
-    for line in &hunk.lines {
-        let hash = if line.starts_with(' ') {
-            // Context line: use new side blame (exists in both)
-            let h = new_blame
-                .get(new_idx)
-                .map(|s| s.as_str())
-                .unwrap_or("0000000");
-            old_idx += 1;
-            new_idx += 1;
-            h.to_string()
-        } else if line.starts_with('-') {
-            // Removed line: use old side blame
-            let h = old_blame
-                .get(old_idx)
-                .map(|s| s.as_str())
-                .unwrap_or("0000000");
-            old_idx += 1;
-            h.to_string()
-        } else if line.starts_with('+') {
-            // Added line: use new side blame (0000000 for uncommitted)
-            let h = new_blame
-                .get(new_idx)
-                .map(|s| s.as_str())
-                .unwrap_or("0000000");
-            new_idx += 1;
-            h.to_string()
-        } else {
-            // Unknown line type (e.g., "\ No newline"), skip blame
-            println!("  {}", line);
-            continue;
+/// `--recurse-submodules`: after the listing, the unstaged hunks inside each
+/// submodule with changes, under the submodule's path. Their IDs are the
+/// submodule's own, for running git-surgeon inside it.
+fn print_submodule_hunks(
+    file: Option<&str>,
+    full: bool,
+    preview: Preview,
+    preview_lines: usize,
+) -> Result<()> {
+    let toplevel =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--show-toplevel"]))?;
+    for submodule in crate::diff::dirty_submodules()? {
+        let inner_file = match file {
+            Some(f) => match f.strip_prefix(&submodule) {
+                Some(rest) => Some(rest.trim_start_matches('/')).filter(|r| !r.is_empty()),
+                None if submodule.starts_with(f.trim_end_matches('/')) => None,
+                None => continue,
+            },
+            None => None,
         };
-
-        // Keep indentation to match existing preview line style
-        println!("  {} {}", hash, line);
+        let diff_output = crate::diff::run_git_diff_in(
+            &format!("{}/{}", toplevel.trim(), submodule),
+            inner_file,
+        )?;
+        let hunks = crate::diff::parse_diff(&diff_output);
+        if hunks.is_empty() {
+            continue;
+        }
+        eprintln!(
+            "note: hunks inside submodule {}; stage them from there ('cd {} && git-surgeon stage <id>')",
+            submodule, submodule
+        );
+        for (id, hunk) in assign_ids(&hunks) {
+            let func_ctx = function_context(hunk);
+            let func_part = if func_ctx.is_empty() {
+                String::new()
+            } else {
+                format!(" {}", func_ctx)
+            };
+            println!(
+                "{} {}/{}{} {} [submodule]",
+                crate::color::id(&id),
+                submodule,
+                hunk.file,
+                func_part,
+                change_summary(hunk)
+            );
+            if full {
+                let width = hunk.lines.len().to_string().len();
+                for (i, line) in hunk.lines.iter().enumerate() {
+                    println!("{:>w$}:{}", i + 1, crate::color::diff_line(line), w = width);
+                }
+            } else if preview == Preview::None {
+                continue;
+            } else if crate::diff::submodule_change(hunk).is_none() {
+                print_preview(hunk, preview, preview_lines);
+            }
+            println!();
+        }
     }
+    Ok(())
+}
 
+/// After the listing, show files with a textconv driver the way `git diff`
+/// does, marked `(view)`: hunks are listed and applied from the raw content,
+/// which for such files is often binary or unreadable.
+fn print_textconv_views(
+    staged: bool,
+    commit: Option<&str>,
+    file: Option<&str>,
+    full: bool,
+    preview_lines: usize,
+) -> Result<()> {
+    let files = crate::diff::textconv_files(staged, commit, file)?;
+    if files.is_empty() {
+        return Ok(());
+    }
+    let view = crate::diff::run_git_diff_view(staged, commit, &files)?;
+    let raw = match commit {
+        Some(c) => crate::diff::run_git_diff_commit(c, None)?,
+        None => crate::diff::run_git_diff(staged, None)?,
+    };
+    let raw_hunks = crate::diff::parse_diff(&raw);
+    let view_hunks = crate::diff::parse_diff(&view);
+    for path in &files {
+        let mut hunks = view_hunks.iter().filter(|h| &h.file == path).peekable();
+        if hunks.peek().is_none() {
+            continue;
+        }
+        if raw_hunks.iter().any(|h| &h.file == path) {
+            eprintln!(
+                "note: {} is shown through its textconv driver as (view); use the IDs of its raw hunks",
+                path
+            );
+        } else {
+            eprintln!(
+                "note: {} is shown through its textconv driver as (view); it has no raw hunks, so stage it whole with 'git add'",
+                path
+            );
+        }
+        for hunk in hunks {
+            let (additions, deletions) = count_changes(hunk);
+            println!("(view) {} (+{} -{})", hunk.file, additions, deletions);
+            let lines: Vec<&String> = hunk
+                .lines
+                .iter()
+                .filter(|l| full || l.starts_with('+') || l.starts_with('-'))
+                .collect();
+            let show = if full {
+                lines.len()
+            } else {
+                lines.len().min(preview_lines)
+            };
+            for line in &lines[..show] {
+                println!("  {}", crate::color::diff_line(line));
+            }
+            if lines.len() > show {
+                println!("  ... (+{} more lines)", lines.len() - show);
+            }
+            println!();
+        }
+    }
     Ok(())
 }
 
-pub fn show_hunk(id: &str, commit: Option<&str>) -> Result<()> {
-    let hunk = match commit {
-        Some(c) => find_hunk_in_commit(id, c)?,
-        None => find_hunk_by_id(id, false).or_else(|_| find_hunk_by_id(id, true))?,
+/// List the changed files with their change type, hunk count, and line counts.
+pub fn list_files(staged: bool, commit: Option<&str>, json: bool) -> Result<()> {
+    let commit_sha = commit.map(crate::rev::resolve_commit).transpose()?;
+    let commit = commit_sha.as_deref();
+    let diff_output = match commit {
+        Some(c) => crate::diff::run_git_diff_commit(c, None)?,
+        None => crate::diff::run_git_diff(staged, None)?,
     };
+    let hunks = crate::diff::parse_diff(&diff_output);
 
-    println!("{}", hunk.header);
-    let width = hunk.lines.len().to_string().len();
-    for (i, line) in hunk.lines.iter().enumerate() {
-        println!("{:>w$}:{}", i + 1, line, w = width);
+    // (status, path, renamed-from path)
+    let status = crate::diff::run_git_name_status(staged, commit)?;
+    let mut fields = status.split('\0').filter(|f| !f.is_empty());
+    let mut files: Vec<(&str, &str, Option<&str>)> = Vec::new();
+    while let Some(code) = fields.next() {
+        let kind = match code.chars().next() {
+            Some('M') => "modified",
+            Some('A') => "added",
+            Some('D') => "deleted",
+            Some('R') => "renamed",
+            Some('C') => "copied",
+            Some('T') => "typechange",
+            _ => "unmerged",
+        };
+        let Some(path) = fields.next() else { break };
+        if matches!(kind, "renamed" | "copied") {
+            let Some(new_path) = fields.next() else { break };
+            files.push((kind, new_path, Some(path)));
+        } else {
+            files.push((kind, path, None));
+        }
+    }
+
+    let counts = |path: &str| -> (usize, usize, usize) {
+        hunks
+            .iter()
+            .filter(|h| h.file == path)
+            .fold((0, 0, 0), |(n, a, d), h| {
+                let (additions, deletions) = count_changes(h);
+                (n + 1, a + additions, d + deletions)
+            })
+    };
+
+    if json {
+        let entries: Vec<Value> = files
+            .iter()
+            .map(|&(kind, path, from)| {
+                let (n, additions, deletions) = counts(path);
+                json::object([
+                    ("file", path.into()),
+                    ("status", kind.into()),
+                    ("old_file", from.into()),
+                    ("hunks", n.into()),
+                    ("additions", additions.into()),
+                    ("deletions", deletions.into()),
+                ])
+            })
+            .collect();
+        println!("{}", json::object([("files", Value::Array(entries))]));
+        return Ok(());
+    }
+
+    for (kind, path, from) in files {
+        let (n, additions, deletions) = counts(path);
+        let renamed = from.map(|f| format!(" (from {})", f)).unwrap_or_default();
+        println!(
+            "{:<10} {}{} {} hunk{} (+{} -{})",
+            kind,
+            path,
+            renamed,
+            n,
+            if n == 1 { "" } else { "s" },
+            additions,
+            deletions
+        );
     }
     Ok(())
 }
 
-fn find_hunk_in_commit(id: &str, commit: &str) -> Result<DiffHunk> {
-    let diff_output = crate::diff::run_git_diff_commit(commit, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
-    identified
-        .into_iter()
-        .find(|(hunk_id, _)| hunk_id == id)
-        .map(|(_, hunk)| hunk.clone())
-        .ok_or_else(|| anyhow::anyhow!("hunk {} not found in commit {}", id, commit))
+/// Hunk and line counts of one file.
+pub struct FileStat<'a> {
+    pub file: &'a str,
+    pub hunks: usize,
+    pub additions: usize,
+    pub deletions: usize,
 }
 
-/// Find a hunk by ID in either staged or unstaged diff.
-fn find_hunk_by_id(id: &str, staged: bool) -> Result<DiffHunk> {
-    let diff_output = crate::diff::run_git_diff(staged, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
+/// Counts per file of `hunks`, in the order the files first appear.
+pub fn file_stats<'a>(hunks: impl IntoIterator<Item = &'a DiffHunk>) -> Vec<FileStat<'a>> {
+    let mut files: Vec<FileStat> = Vec::new();
+    for hunk in hunks {
+        let (additions, deletions) = count_changes(hunk);
+        let pos = match files.iter().position(|f| f.file == hunk.file) {
+            Some(pos) => pos,
+            None => {
+                files.push(FileStat {
+                    file: &hunk.file,
+                    hunks: 0,
+                    additions: 0,
+                    deletions: 0,
+                });
+                files.len() - 1
+            }
+        };
+        files[pos].hunks += 1;
+        files[pos].additions += additions;
+        files[pos].deletions += deletions;
+    }
+    files
+}
 
-    identified
-        .into_iter()
-        .find(|(hunk_id, _)| hunk_id == id)
-        .map(|(_, hunk)| hunk.clone())
-        .ok_or_else(|| anyhow::anyhow!("hunk {} not found (re-run 'hunks')", id))
+/// `<file> | <n> hunks +a -d` for each of `files`, aligned, like `git diff
+/// --stat`.
+pub fn file_stat_lines(files: &[FileStat]) -> Vec<String> {
+    let width = files.iter().map(|f| f.file.len()).max().unwrap_or(0);
+    files
+        .iter()
+        .map(|f| {
+            format!(
+                "{:<w$} | {} {:<5} +{} -{}",
+                f.file,
+                f.hunks,
+                if f.hunks == 1 { "hunk" } else { "hunks" },
+                f.additions,
+                f.deletions,
+                w = width
+            )
+        })
+        .collect()
 }
 
-pub fn apply_hunks(ids: &[String], mode: ApplyMode, lines: Option<(usize, usize)>) -> Result<()> {
-    if lines.is_some() && ids.len() != 1 {
-        anyhow::bail!("--lines requires exactly one hunk ID");
+fn print_stat(identified: &[(String, &DiffHunk)], selected: &[usize]) {
+    let files = file_stats(selected.iter().map(|&idx| identified[idx].1));
+    let plural = |n: usize| if n == 1 { "hunk" } else { "hunks" };
+    println!();
+    for line in file_stat_lines(&files) {
+        println!(" {}", line);
     }
+    let additions: usize = files.iter().map(|f| f.additions).sum();
+    let deletions: usize = files.iter().map(|f| f.deletions).sum();
+    println!(
+        " {} {} in {} file{}, +{} -{}",
+        selected.len(),
+        plural(selected.len()),
+        files.len(),
+        if files.len() == 1 { "" } else { "s" },
+        additions,
+        deletions
+    );
+}
 
-    let staged = matches!(mode, ApplyMode::Unstage);
-    let diff_output = crate::diff::run_git_diff(staged, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
+pub fn count_changes(hunk: &DiffHunk) -> (usize, usize) {
+    let additions = hunk.lines.iter().filter(|l| l.starts_with('+')).count();
+    let deletions = hunk.lines.iter().filter(|l| l.starts_with('-')).count();
+    (additions, deletions)
+}
 
-    let mut combined_patch = String::new();
-    for id in ids {
-        let (_, hunk) = identified
-            .iter()
-            .find(|(hunk_id, _)| hunk_id == id)
-            .ok_or_else(|| anyhow::anyhow!("hunk {} not found (re-run 'hunks')", id))?;
+/// Function context from the @@ header (text after the closing @@).
+pub fn function_context(hunk: &DiffHunk) -> &str {
+    hunk.header
+        .find("@@ ")
+        .and_then(|start| {
+            let rest = &hunk.header[start + 3..];
+            rest.find("@@ ").map(|end| rest[end + 3..].trim())
+        })
+        .unwrap_or("")
+}
 
-        crate::diff::check_supported(hunk, id)?;
+fn change_size(hunk: &DiffHunk) -> usize {
+    hunk.lines
+        .iter()
+        .filter(|l| l.starts_with('+') || l.starts_with('-'))
+        .count()
+}
 
-        let reverse = matches!(mode, ApplyMode::Unstage | ApplyMode::Discard);
-        let patched_hunk = if let Some((start, end)) = lines {
-            slice_hunk(hunk, start, end, reverse)?
-        } else {
-            (*hunk).clone()
-        };
-        combined_patch.push_str(&build_patch(&patched_hunk));
-        eprintln!("{}", id);
-    }
+/// Whether the hunk's removed and added lines are the same once whitespace
+/// is dropped: reindenting, reflowing spaces, and adding or removing blank
+/// lines.
+pub fn is_whitespace_only(hunk: &DiffHunk) -> bool {
+    let squeezed = |marker: char| -> Vec<String> {
+        hunk.lines
+            .iter()
+            .filter_map(|l| l.strip_prefix(marker))
+            .map(|l| l.split_whitespace().collect::<String>())
+            .filter(|l| !l.is_empty())
+            .collect()
+    };
+    change_size(hunk) > 0 && squeezed('-') == squeezed('+')
+}
 
-    apply_patch(&combined_patch, &mode)?;
-    Ok(())
+/// IDs of the unstaged hunks that only change whitespace, for
+/// `stage --whitespace-only`.
+pub fn whitespace_only_ids() -> Result<Vec<String>> {
+    let diff_output = crate::diff::run_git_diff(false, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let ids: Vec<String> = assign_ids(&hunks)
+        .into_iter()
+        .filter(|(_, hunk)| is_whitespace_only(hunk))
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        anyhow::bail!("no unstaged hunk only changes whitespace");
+    }
+    Ok(ids)
 }
 
-/// Parse an ID that may contain inline range suffixes.
-/// Supports: "id", "id:5", "id:1-11", "id:2,5-6,34" (comma-separated).
-/// Returns (id, vector of ranges). Empty vector means "whole hunk".
-fn parse_id_range(raw: &str) -> Result<(&str, Vec<(usize, usize)>)> {
-    if let Some((id, range_str)) = raw.split_once(':') {
-        let mut ranges = Vec::new();
-        for part in range_str.split(',') {
-            let part = part.trim();
-            if part.is_empty() {
-                continue;
+/// The IDs of every listed hunk of the staged or unstaged diff that can be
+/// applied and that `filter` allows, for `stage --all`, `unstage --all` and
+/// `commit --include`.
+pub fn listed_ids(staged: bool, filter: &crate::glob::PathFilter) -> Result<Vec<String>> {
+    let diff_output = crate::diff::run_git_diff(staged, None)?;
+    let location = if staged { "staged" } else { "unstaged" };
+    filtered_ids(&crate::diff::parse_diff(&diff_output), location, filter)
+}
+
+/// The IDs of the hunks of `commit` that `filter` allows, for a split group
+/// picked by `--include`/`--exclude`.
+pub fn commit_ids(commit: &str, filter: &crate::glob::PathFilter) -> Result<Vec<String>> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let diff_output = crate::diff::run_git_diff_commit(&sha, None)?;
+    filtered_ids(&crate::diff::parse_diff(&diff_output), "commit", filter)
+}
+
+fn filtered_ids(
+    hunks: &[DiffHunk],
+    location: &str,
+    filter: &crate::glob::PathFilter,
+) -> Result<Vec<String>> {
+    let supported: Vec<(String, &DiffHunk)> = assign_ids(hunks)
+        .into_iter()
+        .filter(|(_, hunk)| hunk.unsupported_metadata.is_none())
+        .collect();
+    if supported.is_empty() {
+        anyhow::bail!("no {} hunks", location);
+    }
+    let ids: Vec<String> = supported
+        .into_iter()
+        .filter(|(_, hunk)| filter.allows(&hunk.file))
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        crate::exit::fail!(
+            NotFound,
+            "no {} hunks in files that --include/--exclude allow",
+            location
+        );
+    }
+    Ok(ids)
+}
+
+/// `stage-file`/`unstage-file`: stage (or with `unstage`, unstage) whole
+/// files, whatever their changes, including new, deleted and binary files,
+/// and list the hunks that moved.
+pub fn stage_files(paths: &[String], unstage: bool) -> Result<()> {
+    let diff_args: &[&str] = if unstage {
+        &["diff", "--cached", "--name-only", "--no-renames"]
+    } else {
+        &["diff", "--name-only", "--no-renames"]
+    };
+    let mut changed: Vec<String> = Vec::new();
+    let mut untracked: Vec<String> = Vec::new();
+    for path in paths {
+        let files = name_only(diff_args, path)?;
+        let new_files = if unstage {
+            Vec::new()
+        } else {
+            name_only(&["ls-files", "--others", "--exclude-standard"], path)?
+        };
+        if files.is_empty() && new_files.is_empty() {
+            let location = if unstage { "staged" } else { "unstaged" };
+            crate::exit::fail!(NotFound, "no {} changes to {}", location, path);
+        }
+        for file in files {
+            if !changed.contains(&file) {
+                changed.push(file);
             }
-            let (start, end) = if let Some((a, b)) = part.split_once('-') {
-                let start: usize = a
-                    .parse()
-                    .map_err(|_| anyhow::anyhow!("invalid start number in '{}'", raw))?;
-                let end: usize = b
-                    .parse()
-                    .map_err(|_| anyhow::anyhow!("invalid end number in '{}'", raw))?;
-                (start, end)
-            } else {
-                let n: usize = part
-                    .parse()
-                    .map_err(|_| anyhow::anyhow!("invalid line number in '{}'", raw))?;
-                (n, n)
-            };
-            if start == 0 || end == 0 || start > end {
-                anyhow::bail!("range must be 1-based and start <= end in '{}'", raw);
+        }
+        for file in new_files {
+            if !untracked.contains(&file) {
+                untracked.push(file);
             }
-            ranges.push((start, end));
         }
-        Ok((id, ranges))
+    }
+
+    // The IDs before the files move, while they still name these hunks
+    let diff_output = crate::diff::run_git_diff(unstage, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+
+    let mut cmd = Command::new("git");
+    if !unstage {
+        cmd.args(["add", "-A"]);
+    } else if crate::rev::resolve_commit("HEAD").is_ok() {
+        cmd.args(["reset", "-q"]);
     } else {
-        Ok((raw, Vec::new()))
+        // Nothing to reset to before the first commit
+        cmd.args(["rm", "--cached", "-r", "-q"]);
+    }
+    crate::diff::run_git_cmd(cmd.arg("--").args(paths))?;
+
+    let verb = if unstage { "unstaged" } else { "staged" };
+    let count = changed.len() + untracked.len();
+    eprintln!(
+        "{} {} file{}:",
+        verb,
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    print_file_ids(&identified, &changed);
+    for file in &untracked {
+        eprintln!("  {} (new file)", file);
+    }
+    if unstage {
+        print_remaining(true, "staged")
+    } else {
+        print_remaining(false, "unstaged")
     }
 }
 
-/// Stage specified hunks and commit them. On commit failure, unstage to restore original state.
-pub fn commit_hunks(ids: &[String], message: &str) -> Result<()> {
-    // Refuse to proceed if there are already staged changes to avoid committing unrelated work
-    let status = Command::new("git")
-        .args(["diff", "--cached", "--quiet"])
-        .status()
-        .context("failed to check staged changes")?;
-    if !status.success() {
-        anyhow::bail!("index already contains staged changes; commit or unstage them first");
+/// `track`: mark the untracked files under `paths` intent-to-add
+/// (`git add -N`), so their content shows up in `hunks` as hunks to stage,
+/// commit or discard like any other.
+pub fn track_files(paths: &[String]) -> Result<()> {
+    let mut files: Vec<String> = Vec::new();
+    for path in paths {
+        let untracked = name_only(
+            &["ls-files", "--others", "--exclude-standard", "--full-name"],
+            path,
+        )?;
+        if untracked.is_empty() {
+            crate::exit::fail!(NotFound, "no untracked files in {}", path);
+        }
+        for file in untracked {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
     }
+    crate::diff::run_git_cmd(
+        Command::new("git")
+            .args(["add", "-N", "--"])
+            .args(files.iter().map(|f| top_pathspec(f))),
+    )?;
 
     let diff_output = crate::diff::run_git_diff(false, None)?;
     let hunks = crate::diff::parse_diff(&diff_output);
     let identified = assign_ids(&hunks);
+    eprintln!(
+        "tracking {} file{}:",
+        files.len(),
+        if files.len() == 1 { "" } else { "s" }
+    );
+    print_file_ids(&identified, &files);
+    Ok(())
+}
 
-    // Build patch from all requested hunks, grouping ranges by hunk ID
-    let mut hunk_ranges: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
-    for raw_id in ids {
-        let (id, ranges) = parse_id_range(raw_id)?;
-        if let Some(entry) = hunk_ranges.iter_mut().find(|(eid, _)| eid == id) {
-            entry.1.extend(ranges);
+/// `  <file>: <ids>` for each of `files`, or `(no text hunks)` for a binary
+/// or empty file, or one whose only change is its mode.
+fn print_file_ids(identified: &[(String, &DiffHunk)], files: &[String]) {
+    for file in files {
+        let ids: Vec<&str> = identified
+            .iter()
+            .filter(|(_, hunk)| [&hunk.file, &hunk.old_file, &hunk.new_file].contains(&file))
+            .map(|(id, _)| id.as_str())
+            .collect();
+        if ids.is_empty() {
+            eprintln!("  {} (no text hunks)", file);
         } else {
-            hunk_ranges.push((id.to_string(), ranges));
+            eprintln!("  {}: {}", file, ids.join(" "));
         }
     }
+}
 
-    let mut combined_patch = String::new();
-    for (id, ranges) in &hunk_ranges {
-        let (_, hunk) = identified
-            .iter()
-            .find(|(hunk_id, _)| hunk_id == id)
-            .ok_or_else(|| anyhow::anyhow!("hunk {} not found (re-run 'hunks')", id))?;
+/// A pathspec for `path` as diffs name it, relative to the top of the
+/// worktree whichever directory the command runs in.
+fn top_pathspec(path: &str) -> String {
+    format!(":(top,literal){}", path)
+}
 
-        crate::diff::check_supported(hunk, id)?;
+/// The paths `git <args> -z -- <path>` lists.
+fn name_only(args: &[&str], path: &str) -> Result<Vec<String>> {
+    let output = crate::diff::run_git_cmd(Command::new("git").args(args).args(["-z", "--", path]))?;
+    Ok(output
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect())
+}
 
-        let patched_hunk = if ranges.is_empty() {
-            (*hunk).clone()
-        } else {
-            slice_hunk_multi(hunk, ranges, false)?
-        };
-        combined_patch.push_str(&build_patch(&patched_hunk));
-        eprintln!("{}", id);
+/// `discard --all`: discard every unstaged hunk, once the list of what will
+/// be lost is confirmed.
+pub fn discard_all(yes: bool, delete_new: bool) -> Result<()> {
+    let diff_output = crate::diff::run_git_diff(false, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified: Vec<(String, &DiffHunk)> = assign_ids(&hunks)
+        .into_iter()
+        .filter(|(_, hunk)| hunk.unsupported_metadata.is_none())
+        .collect();
+    if identified.is_empty() {
+        anyhow::bail!("no unstaged hunks");
+    }
+    let ids: Vec<String> = identified.iter().map(|(id, _)| id.clone()).collect();
+    let files = file_stats(identified.iter().map(|(_, hunk)| *hunk));
+    eprintln!("will discard: {}", ids.join(" "));
+    for line in file_stat_lines(&files) {
+        eprintln!("  {}", line);
     }
+    let plural = if ids.len() == 1 { "" } else { "s" };
+    crate::confirm::confirm(&format!("discard {} hunk{}", ids.len(), plural), yes)?;
+    apply_hunks(&ids, ApplyMode::Discard, &[], delete_new)
+}
 
-    // Stage the hunks
-    apply_patch(&combined_patch, &ApplyMode::Stage)?;
+/// Pick up to `n` hunks spread across files and change sizes.
+///
+/// Files are visited round-robin so that every file gets a turn before any file
+/// contributes a second hunk. Within a file, hunks are taken alternately from the
+/// largest and smallest end so both extremes are represented. Returns indices into
+/// `identified` in original listing order.
+fn sample_hunks(identified: &[(String, &DiffHunk)], n: usize) -> Vec<usize> {
+    let mut files: Vec<&str> = Vec::new();
+    let mut per_file: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, (_, hunk)) in identified.iter().enumerate() {
+        let entry = per_file.entry(hunk.file.as_str()).or_insert_with(|| {
+            files.push(hunk.file.as_str());
+            Vec::new()
+        });
+        entry.push(idx);
+    }
 
-    // Commit
-    let output = Command::new("git")
-        .args(["commit", "-m", message])
-        .output()
-        .context("failed to run git commit")?;
+    // Order each file's hunks largest, smallest, second largest, second smallest, ...
+    let mut queues: Vec<std::collections::VecDeque<usize>> = files
+        .iter()
+        .map(|f| {
+            let mut by_size = per_file.remove(f).unwrap_or_default();
+            by_size.sort_by_key(|&i| std::cmp::Reverse(change_size(identified[i].1)));
+            let mut order = std::collections::VecDeque::new();
+            let (mut lo, mut hi) = (0usize, by_size.len());
+            while lo < hi {
+                order.push_back(by_size[lo]);
+                lo += 1;
+                if lo < hi {
+                    hi -= 1;
+                    order.push_back(by_size[hi]);
+                }
+            }
+            order
+        })
+        .collect();
 
-    if !output.status.success() {
-        // Unstage to restore original state
-        let _ = apply_patch(&combined_patch, &ApplyMode::Unstage);
-        anyhow::bail!(
-            "git commit failed: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
+    let mut picked = Vec::new();
+    while picked.len() < n && queues.iter().any(|q| !q.is_empty()) {
+        for queue in queues.iter_mut() {
+            if picked.len() >= n {
+                break;
+            }
+            if let Some(idx) = queue.pop_front() {
+                picked.push(idx);
+            }
+        }
     }
 
-    Ok(())
+    picked.sort_unstable();
+    picked
 }
 
-pub fn undo_hunks(ids: &[String], commit: &str, lines: Option<(usize, usize)>) -> Result<()> {
-    if lines.is_some() && ids.len() != 1 {
-        anyhow::bail!("--lines requires exactly one hunk ID");
-    }
+fn print_sample_totals(identified: &[(String, &DiffHunk)], shown: usize) {
+    let files: HashSet<&str> = identified.iter().map(|(_, h)| h.file.as_str()).collect();
+    let additions: usize = identified
+        .iter()
+        .map(|(_, h)| h.lines.iter().filter(|l| l.starts_with('+')).count())
+        .sum();
+    let deletions: usize = identified
+        .iter()
+        .map(|(_, h)| h.lines.iter().filter(|l| l.starts_with('-')).count())
+        .sum();
+    println!(
+        "sampled {} of {} hunks across {} files (+{} -{} total)",
+        shown,
+        identified.len(),
+        files.len(),
+        additions,
+        deletions
+    );
+}
 
-    let diff_output = crate::diff::run_git_diff_commit(commit, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
+/// The old and new side of `hunk` to blame: for commit diffs, in the commit's
+/// parent and the commit; otherwise in HEAD and the working tree (where
+/// uncommitted lines blame as 0000000).
+fn blame_ranges<'a>(
+    hunk: &'a DiffHunk,
+    commit: Option<&str>,
+) -> (Option<BlameRange<'a>>, Option<BlameRange<'a>>) {
+    let (old_from, old_count, new_from, new_count) =
+        crate::blame::parse_hunk_header(&hunk.header).unwrap_or((1, 0, 1, 0));
+    let (old_rev, new_rev) = match commit {
+        Some(c) => (format!("{}^", c), Some(c.to_string())),
+        None => ("HEAD".to_string(), None),
+    };
+    let old = (hunk.old_file != "dev/null" && old_count > 0).then(|| BlameRange {
+        file: &hunk.old_file,
+        from: old_from,
+        count: old_count,
+        revision: Some(old_rev),
+    });
+    let new = (hunk.new_file != "dev/null" && new_count > 0).then(|| BlameRange {
+        file: &hunk.new_file,
+        from: new_from,
+        count: new_count,
+        revision: new_rev,
+    });
+    (old, new)
+}
 
-    let mut combined_patch = String::new();
-    for id in ids {
-        let (_, hunk) = identified
-            .iter()
-            .find(|(hunk_id, _)| hunk_id == id)
-            .ok_or_else(|| anyhow::anyhow!("hunk {} not found in commit {}", id, commit))?;
+/// Blame every hunk `hunks --blame` is about to print, in one pass.
+pub fn collect_blame(
+    hunks: &[&DiffHunk],
+    commit: Option<&str>,
+    ignore_revs: &[String],
+) -> Result<Blame> {
+    let ranges: Vec<BlameRange> = hunks
+        .iter()
+        .flat_map(|hunk| {
+            let (old, new) = blame_ranges(hunk, commit);
+            old.into_iter().chain(new)
+        })
+        .collect();
+    Blame::collect(&ranges, ignore_revs)
+}
 
-        crate::diff::check_supported(hunk, id)?;
+/// The blame of each of `hunk`'s lines, in order: None for `\ No newline`
+/// markers and where blame is unknown.
+pub fn blamed_lines<'b>(
+    hunk: &DiffHunk,
+    commit: Option<&str>,
+    blame: &'b Blame,
+) -> Vec<Option<&'b BlameLine>> {
+    let (old, new) = blame_ranges(hunk, commit);
+    // Old side for context and removed lines, new side for context and added
+    let old_blame = old.map(|r| blame.get(&r)).unwrap_or_default();
+    let new_blame = new.map(|r| blame.get(&r)).unwrap_or_default();
 
-        let patched_hunk = if let Some((start, end)) = lines {
-            slice_hunk(hunk, start, end, true)?
+    // Walk through lines with indices
+    let mut old_idx = 0usize;
+    let mut new_idx = 0usize;
+    let mut blamed = Vec::with_capacity(hunk.lines.len());
+    for line in &hunk.lines {
+        let side = if line.starts_with(' ') {
+            // Context line: use new side blame (exists in both)
+            old_idx += 1;
+            new_idx += 1;
+            new_blame.get(new_idx - 1)
+        } else if line.starts_with('-') {
+            old_idx += 1;
+            old_blame.get(old_idx - 1)
+        } else if line.starts_with('+') {
+            // Uncommitted added lines blame as 0000000
+            new_idx += 1;
+            new_blame.get(new_idx - 1)
         } else {
-            (*hunk).clone()
+            None
         };
-        combined_patch.push_str(&build_patch(&patched_hunk));
-        eprintln!("{}", id);
+        blamed.push(side.copied().flatten());
     }
-
-    apply_patch(&combined_patch, &ApplyMode::Discard)?;
-    Ok(())
+    blamed
 }
 
-pub fn undo_files(files: &[String], commit: &str) -> Result<()> {
-    let diff_output = crate::diff::run_git_diff_commit(commit, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-
-    let mut combined_patch = String::new();
-    let mut matched_files = HashSet::new();
-    for hunk in &hunks {
-        if files
-            .iter()
-            .any(|f| f == &hunk.file || f == &hunk.old_file || f == &hunk.new_file)
-        {
-            crate::diff::check_supported(hunk, &hunk.file)?;
-            combined_patch.push_str(&build_patch(hunk));
-            matched_files.extend(
-                files
-                    .iter()
-                    .filter(|f| *f == &hunk.file || *f == &hunk.old_file || *f == &hunk.new_file),
-            );
+fn print_blamed_lines(hunk: &DiffHunk, commit: Option<&str>, blame: &Blame, mode: BlameMode) {
+    let blamed = blamed_lines(hunk, commit, blame);
+    let author_width = blamed
+        .iter()
+        .flatten()
+        .map(|b| b.author.chars().count())
+        .max()
+        .unwrap_or(0);
+    for (line, blamed) in hunk.lines.iter().zip(&blamed) {
+        if !(line.starts_with(' ') || line.starts_with('-') || line.starts_with('+')) {
+            // Unknown line type (e.g., "\ No newline"), skip blame
+            println!("  {}", line);
+            continue;
         }
-    }
-
-    for file in files {
-        if !matched_files.contains(&file) {
-            anyhow::bail!("file {} not found in commit {}", file, commit);
+        let hash = blamed.map_or("0000000", |b| b.hash.as_str());
+        // Keep indentation to match existing preview line style
+        match (mode, blamed) {
+            (BlameMode::Full, Some(b)) => println!(
+                "  {} {} {:<aw$} {}  {}",
+                hash,
+                crate::backup::format_date(b.time),
+                b.author,
+                summary_column(&b.summary),
+                crate::color::diff_line(line),
+                aw = author_width
+            ),
+            (BlameMode::Full, None) => println!(
+                "  {} {:10} {:<aw$} {}  {}",
+                hash,
+                "",
+                "",
+                summary_column(""),
+                crate::color::diff_line(line),
+                aw = author_width
+            ),
+            (BlameMode::Short, _) => println!("  {} {}", hash, crate::color::diff_line(line)),
         }
-        eprintln!("{}", file);
     }
-
-    apply_patch(&combined_patch, &ApplyMode::Discard)?;
-    Ok(())
 }
 
-/// Fold currently staged changes into an earlier commit via autosquash rebase.
-/// If the target is HEAD, uses simple --amend instead.
-pub fn fixup(commit: &str) -> Result<()> {
-    // Verify there are staged changes
-    let status = Command::new("git")
-        .args(["diff", "--cached", "--quiet"])
-        .status()
-        .context("failed to run git diff")?;
-    if status.success() {
-        anyhow::bail!("no staged changes to fixup");
+/// A commit subject cut or padded to a fixed width, so the lines line up.
+fn summary_column(summary: &str) -> String {
+    const WIDTH: usize = 30;
+    if summary.chars().count() > WIDTH {
+        let cut: String = summary.chars().take(WIDTH - 3).collect();
+        format!("{}...", cut)
+    } else {
+        format!("{:<w$}", summary, w = WIDTH)
     }
+}
 
-    // Check no rebase/cherry-pick in progress
-    check_no_rebase_in_progress()?;
+/// The `blame` array of a `hunks --json --blame` entry: one object (or null)
+/// per line with the commit, author, date and subject.
+fn blame_json(hunk: &DiffHunk, commit: Option<&str>, blame: &Blame) -> Value {
+    Value::Array(
+        blamed_lines(hunk, commit, blame)
+            .into_iter()
+            .map(|blamed| match blamed {
+                Some(b) => json::object([
+                    ("commit", b.hash.as_str().into()),
+                    ("author", b.author.as_str().into()),
+                    ("date", crate::backup::format_date(b.time).into()),
+                    ("summary", b.summary.as_str().into()),
+                ]),
+                None => Value::Null,
+            })
+            .collect(),
+    )
+}
 
-    // Resolve the target commit SHA
-    let target_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", commit]))?;
-    let target_sha = target_sha.trim();
+/// Where a hunk shown by `show` was found.
+enum HunkSource {
+    WorkingTree,
+    Index,
+    Commit(String),
+}
 
-    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
-    let head_sha = head_sha.trim();
+impl HunkSource {
+    /// The `location` name, as in `hunks --all`.
+    fn location(&self) -> &'static str {
+        match self {
+            HunkSource::WorkingTree => "unstaged",
+            HunkSource::Index => "staged",
+            HunkSource::Commit(_) => "commit",
+        }
+    }
 
-    if target_sha == head_sha {
-        // Simple case: amend HEAD
-        let output = Command::new("git")
-            .args(["commit", "--amend", "--no-edit"])
-            .output()
-            .context("failed to amend HEAD")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit --amend failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    /// `file` as the hunks from here leave it.
+    fn new_content(&self, file: &str) -> Result<String> {
+        match self {
+            HunkSource::WorkingTree => {
+                let root = crate::diff::run_git_cmd(
+                    Command::new("git").args(["rev-parse", "--show-toplevel"]),
+                )?;
+                let path = std::path::Path::new(root.trim()).join(file);
+                std::fs::read_to_string(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))
+            }
+            HunkSource::Index => {
+                crate::diff::run_git_cmd(Command::new("git").args(["show", &format!(":{}", file)]))
+            }
+            HunkSource::Commit(sha) => crate::diff::run_git_cmd(
+                Command::new("git").args(["show", &format!("{}:{}", sha, file)]),
+            ),
         }
-    } else {
-        // Get target commit subject for fixup message
-        let subject = crate::diff::run_git_cmd(Command::new("git").args([
-            "log",
-            "-1",
-            "--format=%s",
-            target_sha,
-        ]))?;
-        let subject = subject.trim();
+    }
 
-        // Create fixup commit
-        let output = Command::new("git")
-            .args(["commit", "-m", &format!("fixup! {}", subject)])
-            .output()
-            .context("failed to create fixup commit")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    /// Which commands take the hunk from here.
+    fn describe(&self) -> String {
+        match self {
+            HunkSource::WorkingTree => {
+                "unstaged (working tree): use stage, commit, or discard".to_string()
+            }
+            HunkSource::Index => "staged (index): use unstage".to_string(),
+            HunkSource::Commit(sha) => format!("in commit {}: use undo or split", &sha[..7]),
         }
+    }
+}
 
-        // Check if target is root commit (has no parent)
-        let is_root = Command::new("git")
-            .args(["rev-parse", "--verify", &format!("{}^", target_sha)])
-            .output()
-            .map(|o| !o.status.success())
-            .unwrap_or(false);
+/// Lines of the function enclosing a hunk that its own context leaves out:
+/// the extra lines `git diff -W` shows before and after it. They may include
+/// changes of other hunks in the same function.
+struct EnclosingFunction {
+    before: Vec<String>,
+    after: Vec<String>,
+}
 
-        // Non-interactive autosquash rebase
-        let mut rebase_cmd = Command::new("git");
-        rebase_cmd.args(["rebase", "-i", "--autosquash", "--autostash"]);
-        if is_root {
-            rebase_cmd.arg("--root");
-        } else {
-            rebase_cmd.arg(format!("{}~1", target_sha));
+impl EnclosingFunction {
+    fn add_to_json(self, entry: &mut Value) {
+        if let Value::Object(fields) = entry {
+            fields.push(("function_before".to_string(), self.before.into()));
+            fields.push(("function_after".to_string(), self.after.into()));
         }
-        rebase_cmd.env("GIT_SEQUENCE_EDITOR", "true");
+    }
+}
 
-        let output = rebase_cmd.output().context("failed to run rebase")?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!(
-                "error: rebase conflict while fixing up {}",
-                &target_sha[..7.min(target_sha.len())]
-            );
-            eprintln!("resolve conflicts and run: git rebase --continue");
-            eprintln!("or abort with: git rebase --abort");
-            anyhow::bail!("rebase failed: {}", stderr);
+/// Find `hunk` inside the hunk of the `-W` diff `widened` that covers it.
+fn enclosing_function(hunk: &DiffHunk, widened: &[DiffHunk]) -> EnclosingFunction {
+    let n = hunk.lines.len();
+    for wide in widened.iter().filter(|w| w.file == hunk.file) {
+        if let Some(pos) = wide
+            .lines
+            .windows(n)
+            .position(|window| window == hunk.lines.as_slice())
+        {
+            return EnclosingFunction {
+                before: wide.lines[..pos].to_vec(),
+                after: wide.lines[pos + n..].to_vec(),
+            };
         }
     }
-
-    // Print short sha + subject of the fixed-up commit
-    let info = crate::diff::run_git_cmd(Command::new("git").args([
-        "log",
-        "-1",
-        "--format=%h %s",
-        target_sha,
-    ]));
-    if let Ok(info) = info {
-        eprintln!("fixed up {}", info.trim());
+    EnclosingFunction {
+        before: Vec::new(),
+        after: Vec::new(),
     }
-
-    Ok(())
 }
 
-/// Change the commit message of an existing commit.
-pub fn reword(commit: &str, message: &str) -> Result<()> {
-    // Check no rebase/cherry-pick in progress
-    check_no_rebase_in_progress()?;
-
-    // Resolve the target commit SHA
-    let target_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", commit]))?;
-    let target_sha = target_sha.trim();
+/// Print the hunk numbered as usual for `--lines`, with the rest of its
+/// function around it unnumbered.
+fn print_in_function(hunk: &DiffHunk, enclosing: &EnclosingFunction) {
+    let width = hunk.lines.len().to_string().len();
+    for line in &enclosing.before {
+        println!("{:>w$}:{}", "", crate::color::diff_line(line), w = width);
+    }
+    for (i, line) in hunk.lines.iter().enumerate() {
+        println!("{:>w$}:{}", i + 1, crate::color::diff_line(line), w = width);
+    }
+    for line in &enclosing.after {
+        println!("{:>w$}:{}", "", crate::color::diff_line(line), w = width);
+    }
+}
+
+pub fn show_hunk(
+    id: &str,
+    commit: Option<&str>,
+    full_file: bool,
+    with_function: bool,
+    json: bool,
+) -> Result<()> {
+    let ((id, hunk), source) = match (commit, id.strip_prefix(STAGED_ID_PREFIX)) {
+        (Some(c), _) => {
+            let sha = crate::rev::resolve_commit(c)?;
+            (find_hunk_in_commit(id, c)?, HunkSource::Commit(sha))
+        }
+        (None, Some(staged_id)) => (find_hunk_by_id(staged_id, true)?, HunkSource::Index),
+        (None, None) => match find_hunk_by_id(id, false) {
+            Ok(found) => (found, HunkSource::WorkingTree),
+            Err(_) => (find_hunk_by_id(id, true)?, HunkSource::Index),
+        },
+    };
+    let id = id.as_str();
+    let enclosing = if with_function {
+        let (staged, commit) = match &source {
+            HunkSource::WorkingTree => (false, None),
+            HunkSource::Index => (true, None),
+            HunkSource::Commit(sha) => (false, Some(sha.as_str())),
+        };
+        let widened = crate::diff::run_git_diff_function(staged, commit, Some(&hunk.file))?;
+        Some(enclosing_function(
+            &hunk,
+            &crate::diff::parse_diff(&widened),
+        ))
+    } else {
+        None
+    };
+
+    if json {
+        let commit = match &source {
+            HunkSource::Commit(sha) => Some(sha.as_str()),
+            _ => None,
+        };
+        let mut output = json::object([
+            ("id", id.into()),
+            ("location", source.location().into()),
+            ("commit", commit.into()),
+            ("file", hunk.file.as_str().into()),
+            ("header", hunk.header.as_str().into()),
+            ("lines", hunk.lines.clone().into()),
+        ]);
+        if let Some(enclosing) = enclosing {
+            enclosing.add_to_json(&mut output);
+        }
+        println!("{}", output);
+        return Ok(());
+    }
+
+    eprintln!("{} is {}", id, source.describe());
+    println!("{}", crate::color::header(&hunk.header));
+    if full_file {
+        return print_full_file(id, &hunk, &source);
+    }
+    if let Some(enclosing) = enclosing {
+        print_in_function(&hunk, &enclosing);
+        return Ok(());
+    }
+    let width = hunk.lines.len().to_string().len();
+    for (i, line) in hunk.lines.iter().enumerate() {
+        println!("{:>w$}:{}", i + 1, crate::color::diff_line(line), w = width);
+    }
+    Ok(())
+}
+
+/// Print the whole post-change file with absolute line numbers, the hunk's
+/// added lines marked `+` and its removed lines inlined as `-` without a
+/// number.
+fn print_full_file(id: &str, hunk: &DiffHunk, source: &HunkSource) -> Result<()> {
+    if hunk.new_file == "dev/null" {
+        anyhow::bail!(
+            "hunk {} deletes {}; there is no file to show",
+            id,
+            hunk.file
+        );
+    }
+    let content = source.new_content(&hunk.new_file)?;
+    let file_lines: Vec<&str> = content.lines().collect();
+    let (_, _, new_from, new_count) =
+        crate::blame::parse_hunk_header(&hunk.header).unwrap_or((1, 0, 1, 0));
+    // A hunk that only removes lines sits after line new_from
+    let start = if new_count == 0 {
+        new_from + 1
+    } else {
+        new_from
+    };
+
+    let width = file_lines.len().max(1).to_string().len();
+    let print_file_line = |n: usize, marker: char| {
+        let text = file_lines.get(n - 1).copied().unwrap_or("");
+        let line = format!("{}{}", marker, text);
+        println!("{:>w$}:{}", n, crate::color::diff_line(&line), w = width);
+    };
+
+    let mut n = 1;
+    while n < start && n <= file_lines.len() {
+        print_file_line(n, ' ');
+        n += 1;
+    }
+    for line in &hunk.lines {
+        match line.chars().next() {
+            Some('-') => println!("{:>w$}:{}", "", crate::color::diff_line(line), w = width),
+            Some(marker @ (' ' | '+')) => {
+                print_file_line(n, marker);
+                n += 1;
+            }
+            // "\ No newline at end of file"
+            _ => {}
+        }
+    }
+    while n <= file_lines.len() {
+        print_file_line(n, ' ');
+        n += 1;
+    }
+    Ok(())
+}
+
+fn find_hunk_in_commit(id: &str, commit: &str) -> Result<(String, DiffHunk)> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let diff_output = crate::diff::run_git_diff_commit(&sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let id = resolve_ref(&identified, &crate::registry::commit_location(&sha), id)?;
+    identified
+        .into_iter()
+        .find(|(hunk_id, _)| *hunk_id == id)
+        .map(|(hunk_id, hunk)| (hunk_id, hunk.clone()))
+        .ok_or_else(|| {
+            crate::exit::failure!(NotFound, "hunk {} not found in commit {}", id, commit)
+        })
+}
+
+/// Files touched by the hunks `ids` (which may carry `:range` suffixes) of
+/// `commit`, or of the working tree when `commit` is None, and how many
+/// hunks that is. With `invert`, the hunks *not* in `ids` are counted.
+pub fn hunk_files(
+    commit: Option<&str>,
+    ids: &[String],
+    invert: bool,
+) -> Result<(Vec<String>, usize)> {
+    let hunks = selected_hunks(commit, ids, invert)?;
+    let mut files: Vec<String> = Vec::new();
+    for hunk in &hunks {
+        if !files.contains(&hunk.file) {
+            files.push(hunk.file.clone());
+        }
+    }
+    Ok((files, hunks.len()))
+}
+
+/// `file_stat_lines` for the hunks `hunk_files` counts.
+pub fn hunk_stat_lines(commit: Option<&str>, ids: &[String], invert: bool) -> Result<Vec<String>> {
+    let hunks = selected_hunks(commit, ids, invert)?;
+    Ok(file_stat_lines(&file_stats(&hunks)))
+}
+
+fn selected_hunks(commit: Option<&str>, ids: &[String], invert: bool) -> Result<Vec<DiffHunk>> {
+    let diff_output = match commit {
+        Some(commit) => {
+            let sha = crate::rev::resolve_commit(commit)?;
+            crate::diff::run_git_diff_commit(&sha, None)?
+        }
+        None => crate::diff::run_git_diff(false, None)?,
+    };
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let location = match commit {
+        Some(commit) => crate::registry::commit_location(&crate::rev::resolve_commit(commit)?),
+        None => "unstaged".to_string(),
+    };
+    let mut wanted = Vec::new();
+    for raw_id in ids {
+        let raw_id = resolve_ref(&identified, &location, raw_id)?;
+        wanted.push(parse_id_range(&raw_id)?.0.to_string());
+    }
+
+    Ok(identified
+        .into_iter()
+        .filter(|(id, _)| wanted.contains(id) != invert)
+        .map(|(_, hunk)| hunk.clone())
+        .collect())
+}
+
+/// Find a hunk by ID in either staged or unstaged diff.
+fn find_hunk_by_id(id: &str, staged: bool) -> Result<(String, DiffHunk)> {
+    let diff_output = crate::diff::run_git_diff(staged, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let location = if staged { "staged" } else { "unstaged" };
+    let id = resolve_ref(&identified, location, id)?;
+
+    identified
+        .into_iter()
+        .find(|(hunk_id, _)| *hunk_id == id)
+        .map(|(hunk_id, hunk)| (hunk_id, hunk.clone()))
+        .ok_or_else(|| crate::exit::failure!(NotFound, "hunk {} not found (re-run 'hunks')", id))
+}
+
+/// Stage, unstage or discard hunks by ID. Discarding the whole hunk of an
+/// intent-to-add file (`track`) untracks the file again, keeping it unless
+/// `delete_new` is set.
+pub fn apply_hunks(
+    ids: &[String],
+    mode: ApplyMode,
+    lines: &[(usize, usize)],
+    delete_new: bool,
+) -> Result<()> {
+    if !lines.is_empty() && ids.len() != 1 {
+        crate::exit::fail!(Usage, "--lines requires exactly one hunk ID");
+    }
+
+    let staged = matches!(mode, ApplyMode::Unstage);
+    let diff_output = crate::diff::run_git_diff(staged, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+
+    let location = if staged { "staged" } else { "unstaged" };
+    let hunk_ranges = group_id_ranges(&identified, location, ids, lines)?;
+
+    let mut combined_patch = String::new();
+    let mut applied = Vec::new();
+    let mut untracked = Vec::new();
+    for (id, ranges) in &hunk_ranges {
+        // `hunks --all` marks staged IDs; only unstage takes them
+        let lookup = match id.strip_prefix(STAGED_ID_PREFIX) {
+            Some(staged_id) if staged => staged_id,
+            Some(_) => crate::exit::fail!(Usage, "hunk {} is staged; use 'unstage' for it", id),
+            None => id.as_str(),
+        };
+        let (_, hunk) = identified
+            .iter()
+            .find(|(hunk_id, _)| hunk_id == lookup)
+            .ok_or_else(|| {
+                crate::exit::failure!(NotFound, "hunk {} not found (re-run 'hunks')", id)
+            })?;
+
+        crate::diff::check_supported(hunk, id)?;
+        if crate::diff::submodule_change(hunk).is_some() {
+            if matches!(mode, ApplyMode::Discard) {
+                anyhow::bail!(
+                    "hunk {} moves submodule {}; discard it with 'git submodule update {}'",
+                    id,
+                    hunk.file,
+                    hunk.file
+                );
+            }
+            if !ranges.is_empty() {
+                anyhow::bail!(
+                    "hunk {} moves submodule {}; it has no lines to pick",
+                    id,
+                    hunk.file
+                );
+            }
+        }
+        if crate::diff::mode_change(hunk).is_some() && !ranges.is_empty() {
+            crate::exit::fail!(
+                Usage,
+                "hunk {} only changes the mode of {}; it has no lines to pick",
+                id,
+                hunk.file
+            );
+        }
+
+        let reverse = matches!(mode, ApplyMode::Unstage | ApplyMode::Discard);
+        let (patched_hunk, shown) = if ranges.is_empty() {
+            ((*hunk).clone(), id.clone())
+        } else if matches!(mode, ApplyMode::Discard) && hunk.old_file == "dev/null" {
+            (
+                slice_hunk_multi(&as_modification(hunk), ranges, reverse)?,
+                format!("{}:{}", id, format_ranges(ranges)),
+            )
+        } else {
+            (
+                slice_hunk_multi(hunk, ranges, reverse)?,
+                format!("{}:{}", id, format_ranges(ranges)),
+            )
+        };
+        // An unstaged new file is intent-to-add: reverse-applying all of it
+        // would delete the file and leave its empty index entry behind
+        if matches!(mode, ApplyMode::Discard) && ranges.is_empty() && hunk.old_file == "dev/null" {
+            untracked.push(hunk.file.clone());
+        } else {
+            combined_patch.push_str(&build_patch(&patched_hunk));
+        }
+        applied.push((shown, patched_hunk));
+    }
+
+    if !combined_patch.is_empty() {
+        apply_patch(&combined_patch, &mode)?;
+    }
+    let mut notes = Vec::new();
+    if !untracked.is_empty() {
+        crate::diff::run_git_cmd(
+            Command::new("git")
+                .args(["rm", "--cached", "-q", "--"])
+                .args(untracked.iter().map(|f| top_pathspec(f))),
+        )?;
+        let toplevel =
+            crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--show-toplevel"]))?;
+        for file in &untracked {
+            if delete_new {
+                std::fs::remove_file(std::path::Path::new(toplevel.trim()).join(file))
+                    .with_context(|| format!("failed to delete {}", file))?;
+                notes.push(format!("{}: new file deleted", file));
+            } else {
+                notes.push(format!(
+                    "{}: untracked again, file kept (--delete removes it)",
+                    file
+                ));
+            }
+        }
+    }
+    print_apply_report(&mode, &applied, &notes)
+}
+
+/// The hunk of an intent-to-add file as a change to its empty index entry
+/// rather than the file's creation, so part of it can be reverse-applied
+/// without deleting the file.
+fn as_modification(hunk: &DiffHunk) -> DiffHunk {
+    let new_side = hunk.file_header.lines().last().unwrap_or_default();
+    let old_side = new_side.replacen("+++ ", "--- ", 1).replacen("b/", "a/", 1);
+    DiffHunk {
+        old_file: hunk.new_file.clone(),
+        file_header: format!("{}\n{}", old_side, new_side),
+        ..hunk.clone()
+    }
+}
+
+/// A hunk ID and the line ranges picked from it, none for the whole hunk.
+type IdRanges = (String, Vec<(usize, usize)>);
+
+/// Resolve `ids` and group their inline `id:range` suffixes by hunk ID, so
+/// one ID named twice gives one patch; `lines` (from `--lines`, given with a
+/// single ID) add to the first.
+fn group_id_ranges(
+    identified: &[(String, &DiffHunk)],
+    location: &str,
+    ids: &[String],
+    lines: &[(usize, usize)],
+) -> Result<Vec<IdRanges>> {
+    let mut hunk_ranges: Vec<IdRanges> = Vec::new();
+    for raw_id in ids {
+        let raw_id = resolve_ref(identified, location, raw_id)?;
+        let (id, ranges) = parse_id_range(&raw_id)?;
+        if !lines.is_empty() && !ranges.is_empty() {
+            crate::exit::fail!(Usage, "use either --lines or an inline range on {}", id);
+        }
+        if let Some(entry) = hunk_ranges.iter_mut().find(|(eid, _)| eid == id) {
+            entry.1.extend(ranges);
+        } else {
+            hunk_ranges.push((id.to_string(), ranges));
+        }
+    }
+    if let Some((_, ranges)) = hunk_ranges.first_mut() {
+        ranges.extend(lines);
+    }
+    Ok(hunk_ranges)
+}
+
+/// After `stage`, `unstage` or `discard`: the IDs applied, their per-file
+/// totals, any `notes` on what happened to whole files, and the IDs still
+/// left in the diff they were taken from.
+fn print_apply_report(
+    mode: &ApplyMode,
+    applied: &[(String, DiffHunk)],
+    notes: &[String],
+) -> Result<()> {
+    let (verb, staged, location) = match mode {
+        ApplyMode::Unstage => ("unstaged", true, "staged"),
+        ApplyMode::Discard => ("discarded", false, "unstaged"),
+        _ => ("staged", false, "unstaged"),
+    };
+    let plural = |n: usize| if n == 1 { "hunk" } else { "hunks" };
+    let ids: Vec<&str> = applied.iter().map(|(id, _)| id.as_str()).collect();
+    eprintln!(
+        "{} {} {}: {}",
+        verb,
+        ids.len(),
+        plural(ids.len()),
+        ids.join(" ")
+    );
+    let files = file_stats(applied.iter().map(|(_, hunk)| hunk));
+    for line in file_stat_lines(&files).iter().chain(notes) {
+        eprintln!("  {}", line);
+    }
+    print_remaining(staged, location)
+}
+
+/// `N <location> hunks remain: <ids>`, for the report after staging.
+fn print_remaining(staged: bool, location: &str) -> Result<()> {
+    let plural = |n: usize| if n == 1 { "hunk" } else { "hunks" };
+    let diff_output = crate::diff::run_git_diff(staged, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let remaining: Vec<String> = assign_ids(&hunks).into_iter().map(|(id, _)| id).collect();
+    if remaining.is_empty() {
+        eprintln!("no {} hunks remain", location);
+    } else {
+        eprintln!(
+            "{} {} {} {}: {}",
+            remaining.len(),
+            location,
+            plural(remaining.len()),
+            if remaining.len() == 1 {
+                "remains"
+            } else {
+                "remain"
+            },
+            remaining.join(" ")
+        );
+    }
+    Ok(())
+}
+
+/// Parse an ID that may contain inline range suffixes.
+/// Supports: "id", "id:5", "id:1-11", "id:2,5-6,34" (comma-separated).
+/// Returns (id, vector of ranges). Empty vector means "whole hunk".
+pub fn parse_id_range(raw: &str) -> Result<(&str, Vec<(usize, usize)>)> {
+    if let (id, Some(range_str)) = split_range(raw) {
+        let mut ranges = Vec::new();
+        for part in range_str.split(',') {
+            let part = part.trim();
+            if part.is_empty() {
+                continue;
+            }
+            let (start, end) = if let Some((a, b)) = part.split_once('-') {
+                let start: usize = a
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid start number in '{}'", raw))?;
+                let end: usize = b
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid end number in '{}'", raw))?;
+                (start, end)
+            } else {
+                let n: usize = part
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("invalid line number in '{}'", raw))?;
+                (n, n)
+            };
+            if start == 0 || end == 0 || start > end {
+                crate::exit::fail!(Usage, "range must be 1-based and start <= end in '{}'", raw);
+            }
+            ranges.push((start, end));
+        }
+        Ok((id, ranges))
+    } else {
+        Ok((raw, Vec::new()))
+    }
+}
+
+/// The inline range suffix `parse_id_range` reads back as `ranges`.
+pub fn format_ranges(ranges: &[(usize, usize)]) -> String {
+    let parts: Vec<String> = ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect();
+    parts.join(",")
+}
+
+pub fn has_staged_changes() -> Result<bool> {
+    let status = Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .traced_status()
+        .context("failed to check staged changes")?;
+    Ok(!status.success())
+}
+
+/// Build a combined patch from working tree hunk IDs with optional inline ranges.
+fn build_worktree_patch(ids: &[String]) -> Result<String> {
+    let diff_output = crate::diff::run_git_diff(false, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+
+    // Group ranges by hunk ID
+    let mut hunk_ranges: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+    for raw_id in ids {
+        let raw_id = resolve_ref(&identified, "unstaged", raw_id)?;
+        let (id, ranges) = parse_id_range(&raw_id)?;
+        if let Some(entry) = hunk_ranges.iter_mut().find(|(eid, _)| eid == id) {
+            entry.1.extend(ranges);
+        } else {
+            hunk_ranges.push((id.to_string(), ranges));
+        }
+    }
+
+    let mut combined_patch = String::new();
+    for (id, ranges) in &hunk_ranges {
+        let (_, hunk) = identified
+            .iter()
+            .find(|(hunk_id, _)| hunk_id == id)
+            .ok_or_else(|| {
+                crate::exit::failure!(NotFound, "hunk {} not found (re-run 'hunks')", id)
+            })?;
+
+        crate::diff::check_supported(hunk, id)?;
+
+        let patched_hunk = if ranges.is_empty() {
+            (*hunk).clone()
+        } else {
+            slice_hunk_multi(hunk, ranges, false)?
+        };
+        combined_patch.push_str(&build_patch(&patched_hunk));
+        eprintln!("{}", id);
+    }
+    Ok(combined_patch)
+}
+
+/// Stage working tree hunks, honouring inline `id:range` suffixes.
+pub fn stage_hunk_specs(ids: &[String]) -> Result<()> {
+    let combined_patch = build_worktree_patch(ids)?;
+    apply_patch(&combined_patch, &ApplyMode::Stage)
+}
+
+/// Stage the changed lines inside the declaration of `name` in `file`, with
+/// its span taken from the working tree copy: added lines in it, and removed
+/// lines that sat between its lines.
+pub fn stage_symbol(name: &str, file: &str) -> Result<()> {
+    let source =
+        std::fs::read_to_string(file).with_context(|| format!("failed to read {}", file))?;
+    let (start, end) =
+        crate::symbol::span_in(file, &source, name).with_context(|| format!("in {}", file))?;
+
+    let diff_output = crate::diff::run_git_diff(false, Some(file))?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let mut combined_patch = String::new();
+    for (id, hunk) in &identified {
+        crate::diff::check_supported(hunk, id)?;
+        let mut new_line =
+            crate::blame::parse_hunk_header(&hunk.header).map_or(1, |(_, _, from, _)| from);
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        for (i, line) in hunk.lines.iter().enumerate() {
+            // A removed line sits just before the new line that follows it
+            let inside = (start..=end).contains(&new_line);
+            let changed = line.starts_with('+') || line.starts_with('-');
+            if changed && inside {
+                match ranges.last_mut() {
+                    Some((_, last)) if *last == i => *last = i + 1,
+                    _ => ranges.push((i + 1, i + 1)),
+                }
+            }
+            if line.starts_with('+') || line.starts_with(' ') {
+                new_line += 1;
+            }
+        }
+        if ranges.is_empty() {
+            continue;
+        }
+        eprintln!("{}:{}", id, format_ranges(&ranges));
+        combined_patch.push_str(&build_patch(&slice_hunk_multi(hunk, &ranges, false)?));
+    }
+    if combined_patch.is_empty() {
+        anyhow::bail!(
+            "no unstaged changes inside '{}' (lines {}-{} of {})",
+            name,
+            start,
+            end,
+            file
+        );
+    }
+    apply_patch(&combined_patch, &ApplyMode::Stage)
+}
+
+/// Stage specified hunks and commit them. On commit failure, unstage to restore original state.
+pub fn commit_hunks(ids: &[String], message: &str) -> Result<()> {
+    // Refuse to proceed if there are already staged changes to avoid committing unrelated work
+    if has_staged_changes()? {
+        crate::exit::fail!(
+            RepoState,
+            "index already contains staged changes; commit or unstage them first"
+        );
+    }
+
+    let combined_patch = build_worktree_patch(ids)?;
+
+    // Stage the hunks
+    apply_patch(&combined_patch, &ApplyMode::Stage)?;
+
+    // Commit
+    let output = git_commit()
+        .args(["-m", message])
+        .traced_output()
+        .context("failed to run git commit")?;
+
+    if !output.status.success() {
+        // Unstage to restore original state
+        let _ = apply_patch(&combined_patch, &ApplyMode::Unstage);
+        anyhow::bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    Ok(())
+}
+
+/// Commit working tree hunks as several commits, one per group in order, as
+/// `split` divides a commit. Every group's patch is built before anything is
+/// committed, and if a later commit fails, HEAD and the index go back to
+/// where they were: either every commit is made or none is.
+pub fn commit_groups(groups: &[crate::PickGroup]) -> Result<()> {
+    if has_staged_changes()? {
+        crate::exit::fail!(
+            RepoState,
+            "index already contains staged changes; commit or unstage them first"
+        );
+    }
+
+    let diff_output = crate::diff::run_git_diff(false, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let groups = resolve_group_refs(&identified, "unstaged", groups)?;
+    let mut hunk_states = initial_hunk_states(&identified);
+    check_line_ranges(&groups, &hunk_states)?;
+
+    // Line ranges stay relative to the hunks as first listed, so a later
+    // group can take the rest of a hunk an earlier one took part of
+    let mut patches = Vec::with_capacity(groups.len());
+    for group in &groups {
+        let mut patch = String::new();
+        for (id, selected) in select_group_lines(group, &hunk_states)? {
+            let state = hunk_states.get_mut(&id).expect("selected hunks exist");
+            crate::diff::check_supported(&state.hunk, &id)?;
+            let sliced = slice_hunk_with_state(&state.hunk, &state.picked, &selected)?;
+            patch.push_str(&build_patch(&sliced));
+            for (picked, selected) in state.picked.iter_mut().zip(&selected) {
+                *picked |= *selected;
+            }
+        }
+        if patch.is_empty() {
+            anyhow::bail!("no changes selected for commit");
+        }
+        patches.push(patch);
+    }
+
+    let head = crate::rev::resolve_commit("HEAD").ok();
+    for (done, (group, patch)) in groups.iter().zip(&patches).enumerate() {
+        let message = group.message_parts.join("\n\n");
+        let committed = apply_patch(patch, &ApplyMode::Stage).and_then(|_| {
+            let output = git_commit()
+                .args(["-m", &message])
+                .traced_output()
+                .context("failed to run git commit")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git commit failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+            Ok(())
+        });
+        if let Err(err) = committed {
+            roll_back_commits(head.as_deref())?;
+            if done > 0 {
+                eprintln!(
+                    "rolled back the {} commit{} already made",
+                    done,
+                    if done == 1 { "" } else { "s" }
+                );
+            }
+            return Err(err);
+        }
+        eprintln!("committed: {}", message.lines().next().unwrap_or(&message));
+    }
+    Ok(())
+}
+
+/// Put HEAD and the index back to `head` (None: no commit yet), leaving the
+/// working tree as it is.
+fn roll_back_commits(head: Option<&str>) -> Result<()> {
+    match head {
+        Some(head) => {
+            crate::diff::run_git_cmd(Command::new("git").args(["reset", "--quiet", head]))?;
+        }
+        None => {
+            crate::diff::run_git_cmd(Command::new("git").args(["update-ref", "-d", "HEAD"]))?;
+            crate::diff::run_git_cmd(Command::new("git").args(["read-tree", "--empty"]))?;
+        }
+    }
+    Ok(())
+}
+
+/// Fold working tree hunks into HEAD and/or replace its message.
+/// On amend failure, unstage the hunks to restore the original state.
+pub fn amend(ids: &[String], message: Option<&str>) -> Result<()> {
+    if ids.is_empty() && message.is_none() {
+        anyhow::bail!("nothing to amend: pass hunk IDs and/or -m");
+    }
+
+    check_no_rebase_in_progress()?;
+
+    // Anything already staged would silently end up in HEAD
+    if has_staged_changes()? {
+        crate::exit::fail!(
+            RepoState,
+            "index already contains staged changes that would be folded into HEAD; \
+             use 'fixup HEAD' to include them, or unstage them first"
+        );
+    }
+
+    let combined_patch = if ids.is_empty() {
+        String::new()
+    } else {
+        let patch = build_worktree_patch(ids)?;
+        apply_patch(&patch, &ApplyMode::Stage)?;
+        patch
+    };
+
+    let mut cmd = git_commit();
+    cmd.arg("--amend");
+    match message {
+        Some(msg) => cmd.args(["-m", msg]),
+        None => cmd.arg("--no-edit"),
+    };
+    let output = cmd.traced_output().context("failed to amend HEAD")?;
+    if !output.status.success() {
+        if !combined_patch.is_empty() {
+            let _ = apply_patch(&combined_patch, &ApplyMode::Unstage);
+        }
+        anyhow::bail!(
+            "git commit --amend failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    let info = crate::diff::run_git_cmd(Command::new("git").args(["log", "-1", "--format=%h %s"]));
+    if let Ok(info) = info {
+        eprintln!("amended {}", info.trim());
+    }
+
+    Ok(())
+}
+
+pub fn undo_hunks(
+    ids: &[String],
+    commit: &str,
+    lines: &[(usize, usize)],
+    three_way: bool,
+    json: bool,
+) -> Result<()> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let (combined_patch, files) = commit_hunks_patch(ids, &sha, commit, lines, true)?;
+    reverse_apply_to_worktree(&combined_patch, &sha, &files, three_way, json)
+}
+
+/// The patch of hunks `ids` (cut down to `lines`) of commit `sha`, sliced to
+/// be applied in reverse if `reverse`, and the files it touches. Each hunk is
+/// listed on stderr.
+fn commit_hunks_patch(
+    ids: &[String],
+    sha: &str,
+    commit: &str,
+    lines: &[(usize, usize)],
+    reverse: bool,
+) -> Result<(String, Vec<String>)> {
+    if !lines.is_empty() && ids.len() != 1 {
+        crate::exit::fail!(Usage, "--lines requires exactly one hunk ID");
+    }
+
+    let diff_output = crate::diff::run_git_diff_commit(sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+
+    let mut combined_patch = String::new();
+    let mut files: Vec<String> = Vec::new();
+    let location = crate::registry::commit_location(sha);
+    for (id, ranges) in &group_id_ranges(&identified, &location, ids, lines)? {
+        let (_, hunk) = identified
+            .iter()
+            .find(|(hunk_id, _)| hunk_id == id)
+            .ok_or_else(|| {
+                crate::exit::failure!(NotFound, "hunk {} not found in commit {}", id, commit)
+            })?;
+
+        crate::diff::check_supported(hunk, id)?;
+        if !files.contains(&hunk.file) {
+            files.push(hunk.file.clone());
+        }
+
+        let patched_hunk = if ranges.is_empty() {
+            eprintln!("{}", id);
+            (*hunk).clone()
+        } else {
+            eprintln!("{}:{}", id, format_ranges(ranges));
+            slice_hunk_multi(hunk, ranges, reverse)?
+        };
+        combined_patch.push_str(&build_patch(&patched_hunk));
+    }
+    Ok((combined_patch, files))
+}
+
+/// `restore-from`: apply hunks `ids` of `commit` forward to the working tree,
+/// to bring back a change that was since lost or reverted.
+pub fn restore_hunks(commit: &str, ids: &[String], lines: &[(usize, usize)]) -> Result<()> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let (patch, _) = commit_hunks_patch(ids, &sha, commit, lines, false)?;
+    apply_patch(&patch, &ApplyMode::Worktree).inspect_err(|_| {
+        eprintln!(
+            "hint: the hunks may already be in the working tree, or the lines around \
+             them changed since {}",
+            &sha[..7]
+        );
+    })
+}
+
+/// `revert`: reverse-apply hunks `ids` of `commit` to the index and the
+/// working tree, and commit that, with `message` or one naming what was
+/// reverted. Like `commit`, the index must have nothing else staged.
+pub fn revert_hunks(
+    ids: &[String],
+    commit: &str,
+    lines: &[(usize, usize)],
+    message: Option<&str>,
+    edit: bool,
+    trailers: &crate::trailer::Trailers,
+) -> Result<()> {
+    check_no_rebase_in_progress()?;
+    if has_staged_changes()? {
+        crate::exit::fail!(
+            RepoState,
+            "index already contains staged changes; commit or unstage them first"
+        );
+    }
+
+    let sha = crate::rev::resolve_commit(commit)?;
+    let (patch, files) = commit_hunks_patch(ids, &sha, commit, lines, true)?;
+    let message = match message {
+        Some(message) => message.to_string(),
+        None => {
+            let subject = crate::diff::run_git_cmd(Command::new("git").args([
+                "log",
+                "-1",
+                "--format=%s",
+                &sha,
+            ]))?;
+            format!(
+                "Revert part of \"{}\"\n\nThis reverts {} of commit {}.",
+                subject.trim_end(),
+                if ids.len() == 1 {
+                    "a hunk"
+                } else {
+                    "some hunks"
+                },
+                sha
+            )
+        }
+    };
+    let mut message = trailers.apply(&message)?;
+    if edit {
+        message = crate::editor::edit(&message, &hunk_stat_lines(Some(&sha), ids, false)?)?;
+    }
+
+    // The index first: if the files changed since the commit, this is where
+    // it shows, with nothing touched yet
+    apply_patch(&patch, &ApplyMode::Unstage)?;
+    if let Err(err) = apply_patch(&patch, &ApplyMode::Discard) {
+        let _ = apply_patch(&patch, &ApplyMode::Stage);
+        eprintln!(
+            "hint: commit or stash the working tree changes to {} first",
+            files.join(", ")
+        );
+        return Err(err);
+    }
+
+    let output = git_commit()
+        .args(["-m", &message])
+        .traced_output()
+        .context("failed to run git commit")?;
+    if !output.status.success() {
+        // Put the index and the working tree back as they were
+        let _ = apply_patch(&patch, &ApplyMode::Stage);
+        let _ = apply_patch(&patch, &ApplyMode::Worktree);
+        anyhow::bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// `undo-file` asks for confirmation from this many files up.
+const CONFIRM_UNDO_FILES: usize = 3;
+
+pub fn undo_files(
+    files: &[String],
+    commit: &str,
+    three_way: bool,
+    json: bool,
+    yes: bool,
+) -> Result<()> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let diff_output = crate::diff::run_git_diff_commit(&sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+
+    // The files each of `files`, a path, directory or glob, matched
+    let mut matched: Vec<Vec<&str>> = vec![Vec::new(); files.len()];
+    let mut combined_patch = String::new();
+    let mut hunk_files: Vec<String> = Vec::new();
+    for hunk in &hunks {
+        let mut selected = false;
+        for (pattern, matched) in files.iter().zip(&mut matched) {
+            if [&hunk.file, &hunk.old_file, &hunk.new_file]
+                .iter()
+                .any(|path| crate::glob::matches(pattern, path))
+            {
+                selected = true;
+                if !matched.contains(&hunk.file.as_str()) {
+                    matched.push(&hunk.file);
+                }
+            }
+        }
+        if selected {
+            crate::diff::check_supported(hunk, &hunk.file)?;
+            combined_patch.push_str(&build_patch(hunk));
+            if !hunk_files.contains(&hunk.file) {
+                hunk_files.push(hunk.file.clone());
+            }
+        }
+    }
+
+    for (pattern, matched) in files.iter().zip(&matched) {
+        if matched.is_empty() && crate::glob::is_glob(pattern) {
+            crate::exit::fail!(NotFound, "no file in commit {} matches {}", commit, pattern);
+        }
+        if matched.is_empty() {
+            crate::exit::fail!(NotFound, "file {} not found in commit {}", pattern, commit);
+        }
+        if matched[..] == [pattern.as_str()] {
+            eprintln!("{}", pattern);
+        } else {
+            eprintln!("{}: {}", pattern, matched.join(", "));
+        }
+    }
+    if hunk_files.len() >= CONFIRM_UNDO_FILES {
+        crate::confirm::confirm(
+            &format!("undo {}'s changes to {} files", &sha[..7], hunk_files.len()),
+            yes,
+        )?;
+    }
+
+    reverse_apply_to_worktree(&combined_patch, &sha, &hunk_files, three_way, json)
+}
+
+/// Reverse-apply `patch`, made of hunks of commit `sha` touching `files`, to
+/// the working tree. If the files have changed since and `three_way` is set,
+/// merge the changes in instead, failing if that leaves conflict markers.
+/// With `json`, print how each file was updated on stdout.
+fn reverse_apply_to_worktree(
+    patch: &str,
+    sha: &str,
+    files: &[String],
+    three_way: bool,
+    json: bool,
+) -> Result<()> {
+    let merged = match apply_patch(patch, &ApplyMode::Discard) {
+        Ok(()) => None,
+        Err(err) if !three_way => {
+            eprintln!("hint: pass --3way to merge the changes into the modified files");
+            return Err(err);
+        }
+        Err(_) => {
+            eprintln!("patch does not apply cleanly; falling back to a 3-way merge");
+            Some(crate::patch::reverse_apply_3way(patch, sha, files)?)
+        }
+    };
+
+    let conflicted: Vec<&crate::patch::Merged> = merged
+        .iter()
+        .flatten()
+        .filter(|m| m.conflicts > 0)
+        .collect();
+    if json {
+        let entries: Vec<Value> = files
+            .iter()
+            .map(|file| {
+                let conflicts = merged
+                    .iter()
+                    .flatten()
+                    .find(|m| &m.file == file)
+                    .map_or(0, |m| m.conflicts);
+                json::object([
+                    ("file", file.as_str().into()),
+                    ("conflicts", conflicts.into()),
+                ])
+            })
+            .collect();
+        let method = if merged.is_some() { "3way" } else { "apply" };
+        let output = json::object([("method", method.into()), ("files", Value::Array(entries))]);
+        println!("{}", output);
+    }
+    for m in &conflicted {
+        eprintln!("conflict: {} ({} conflict(s))", m.file, m.conflicts);
+    }
+    if !conflicted.is_empty() {
+        crate::exit::fail!(
+            Conflict,
+            "left conflict markers in {} file(s); resolve them in the working tree",
+            conflicted.len()
+        );
+    }
+    Ok(())
+}
+
+/// Fold currently staged changes into an earlier commit via autosquash rebase.
+/// If the target is HEAD, uses simple --amend instead.
+///
+/// With `ids`, those working tree hunks are staged first (the index must be
+/// clean, as for `commit`) and unstaged again if the fixup commit fails.
+/// Without `rebase`, only the `fixup!` commit is created (even for HEAD), to
+/// be folded in by a later autosquash.
+pub fn fixup(commit: &str, ids: &[String], rebase: bool, force_published: bool) -> Result<()> {
+    // Check no rebase/cherry-pick in progress
+    check_no_rebase_in_progress()?;
+
+    // Resolve the target commit SHA
+    let target_sha = crate::rev::resolve_commit(commit)?;
+    crate::rev::check_history_present(&target_sha)?;
+    if !force_published {
+        crate::rev::check_unpublished(&target_sha)?;
+    }
+    let target_sha = target_sha.as_str();
+
+    let staged_patch = if ids.is_empty() {
+        None
+    } else {
+        if has_staged_changes()? {
+            crate::exit::fail!(
+                RepoState,
+                "index already contains staged changes; commit or unstage them first"
+            );
+        }
+        let patch = build_worktree_patch(ids)?;
+        apply_patch(&patch, &ApplyMode::Stage)?;
+        Some(patch)
+    };
+
+    // Verify there are staged changes
+    if !has_staged_changes()? {
+        crate::exit::fail!(RepoState, "no staged changes to fixup");
+    }
+
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim();
+
+    let committed = commit_fixup(target_sha, head_sha, rebase);
+    if let Err(err) = committed {
+        if let Some(patch) = &staged_patch {
+            let _ = apply_patch(patch, &ApplyMode::Unstage);
+        }
+        return Err(err);
+    }
+
+    if !rebase {
+        eprintln!(
+            "created fixup! commit for {}; fold it in later with: \
+             git-surgeon squash {} --autosquash",
+            &target_sha[..7.min(target_sha.len())],
+            &target_sha[..7.min(target_sha.len())]
+        );
+        return Ok(());
+    }
+
+    if target_sha != head_sha {
+        // Check if target is root commit (has no parent)
+        let is_root = crate::rev::is_root(target_sha);
+
+        State::new(Operation::Fixup, head_sha, target_sha).save()?;
+
+        // Non-interactive autosquash rebase
+        let mut rebase_cmd = git_rebase()?;
+        rebase_cmd.args(["-i", "--autosquash", "--autostash"]);
+        if is_root {
+            rebase_cmd.arg("--root");
+        } else {
+            rebase_cmd.arg(format!("{}~1", target_sha));
+        }
+        rebase_cmd.env("GIT_SEQUENCE_EDITOR", "true");
+
+        let output = run_rebase(&mut rebase_cmd)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            eprintln!(
+                "error: rebase conflict while fixing up {}",
+                &target_sha[..7.min(target_sha.len())]
+            );
+            print_resume_hint();
+            crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
+        }
+        State::clear()?;
+    }
+
+    // Print short sha + subject of the fixed-up commit
+    let info = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%h %s",
+        target_sha,
+    ]));
+    if let Ok(info) = info {
+        eprintln!("fixed up {}", info.trim());
+    }
+
+    Ok(())
+}
+
+/// Commit the staged changes for a fixup of `target_sha`: amend HEAD when
+/// rebasing, or create a `fixup!` commit for the rebase to fold in.
+fn commit_fixup(target_sha: &str, head_sha: &str, rebase: bool) -> Result<()> {
+    crate::backup::create("fixup")?;
+
+    if target_sha == head_sha && rebase {
+        // Simple case: amend HEAD
+        let mut commit_cmd = git_commit();
+        commit_cmd.args(["--amend", "--no-edit"]);
+        let (_, author_date) = commit_author(head_sha)?;
+        crate::committer::configure_commit(&mut commit_cmd, head_sha, Some(&author_date))?;
+        let output = commit_cmd.traced_output().context("failed to amend HEAD")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git commit --amend failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        return Ok(());
+    }
+
+    // Get target commit subject for fixup message
+    let subject = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%s",
+        target_sha,
+    ]))?;
+    let subject = subject.trim();
+
+    // Create fixup commit
+    let output = git_commit()
+        .args(["-m", &format!("fixup! {}", subject)])
+        .traced_output()
+        .context("failed to create fixup commit")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Change the messages of existing commits: `edits` pairs each commit with
+/// its new message. HEAD alone is amended; anything else takes a single
+/// autosquash rebase from the oldest target, however many commits change.
+pub fn reword(edits: &[(String, String)], force_published: bool) -> Result<()> {
+    // Check no rebase/cherry-pick in progress
+    check_no_rebase_in_progress()?;
+
+    // Resolve every target first so nothing is rewritten if one is invalid
+    let mut targets: Vec<(String, &str, usize)> = Vec::new();
+    for (commit, message) in edits {
+        let target_sha = crate::rev::resolve_commit(commit)?;
+        crate::rev::check_history_present(&target_sha)?;
+        if !force_published {
+            crate::rev::check_unpublished(&target_sha)?;
+        }
+        if targets.iter().any(|(sha, _, _)| *sha == target_sha) {
+            anyhow::bail!("{} is given more than once", commit);
+        }
+
+        // Track distance from target to HEAD for later (used to find new SHA after rebase)
+        let distance = crate::diff::run_git_cmd(Command::new("git").args([
+            "rev-list",
+            "--count",
+            &format!("{}..HEAD", target_sha),
+        ]))?;
+        let distance: usize = distance.trim().parse().unwrap_or(0);
+        targets.push((target_sha, message, distance));
+    }
+
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim();
+
+    crate::backup::create("reword")?;
+
+    if let [(target_sha, message, _)] = targets.as_slice()
+        && target_sha == head_sha
+    {
+        // Simple case: amend HEAD with new message
+        let mut commit_cmd = git_commit();
+        commit_cmd.args(["--amend", "-m", message]);
+        let (_, author_date) = commit_author(head_sha)?;
+        crate::committer::configure_commit(&mut commit_cmd, head_sha, Some(&author_date))?;
+        let output = commit_cmd.traced_output().context("failed to amend HEAD")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git commit --amend failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    } else {
+        // One empty amend! commit per target, named by sha so that commits
+        // sharing a subject are not confused; a single rebase applies them all
+        for (target_sha, message, _) in &targets {
+            let output = git_commit()
+                .args([
+                    "--allow-empty",
+                    "-m",
+                    &format!("amend! {}\n\n{}", target_sha, message),
+                ])
+                .traced_output()
+                .context("failed to create reword commit")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git commit failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        }
+
+        let (oldest_sha, _, _) = targets
+            .iter()
+            .max_by_key(|(_, _, distance)| *distance)
+            .expect("at least one commit to reword");
+
+        // Check if target is root commit (has no parent)
+        let is_root = crate::rev::is_root(oldest_sha);
+
+        State::new(Operation::Reword, head_sha, oldest_sha).save()?;
+
+        // Non-interactive autosquash rebase
+        let mut rebase_cmd = git_rebase()?;
+        rebase_cmd.args(["-i", "--autosquash", "--autostash"]);
+        if is_root {
+            rebase_cmd.arg("--root");
+        } else {
+            rebase_cmd.arg(format!("{}~1", oldest_sha));
+        }
+        rebase_cmd.env("GIT_SEQUENCE_EDITOR", "true");
+
+        let output = run_rebase(&mut rebase_cmd)?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            eprintln!(
+                "error: rebase conflict while rewording {}",
+                &oldest_sha[..7.min(oldest_sha.len())]
+            );
+            print_resume_hint();
+            crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
+        }
+        State::clear()?;
+    }
+
+    // Print short sha + new subject of each reworded commit
+    // Use HEAD~distance to find the commit at the same position after rebase
+    for (_, _, distance) in &targets {
+        let info = crate::diff::run_git_cmd(Command::new("git").args([
+            "log",
+            "-1",
+            "--format=%h %s",
+            &format!("HEAD~{}", distance),
+        ]));
+        if let Ok(info) = info {
+            eprintln!("reworded {}", info.trim());
+        }
+    }
+
+    Ok(())
+}
+
+/// What `split` does with the changes no pick group takes.
+#[derive(Clone, Copy)]
+pub enum SplitRest<'a> {
+    /// Commit them last, with the given message or else the original one
+    Commit(Option<&'a [String]>),
+    /// Drop them (`--discard-rest`), keeping only the picked changes
+    Discard,
+}
+
+/// Split a commit into multiple commits by hunk selection.
+///
+/// With `dry_run`, every selection is validated and the resulting commits are
+/// printed without touching the repository. With `preserve_author`, each new
+/// commit keeps the original author and dates.
+pub fn split(
+    commit: &str,
+    pick_groups: &[crate::PickGroup],
+    rest: SplitRest,
+    dry_run: bool,
+    preserve_author: bool,
+    force_published: bool,
+    verify: bool,
+) -> Result<()> {
+    // Check working tree is clean
+    let status = Command::new("git")
+        .args(["status", "--porcelain"])
+        .traced_output()
+        .context("failed to check git status")?;
+    let dirty = !String::from_utf8_lossy(&status.stdout).trim().is_empty();
+    if dirty && !dry_run {
+        crate::exit::fail!(
+            RepoState,
+            "working tree is dirty; commit or stash changes before splitting"
+        );
+    }
+
+    check_no_rebase_in_progress()?;
+
+    // Resolve target commit
+    let target_sha = crate::rev::resolve_commit(commit)?;
+    if !dry_run {
+        crate::rev::check_history_present(&target_sha)?;
+    }
+    if !dry_run && !force_published {
+        crate::rev::check_unpublished(&target_sha)?;
+    }
+
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim().to_string();
+
+    let is_head = target_sha == head_sha;
+
+    // Get hunks from the target commit and validate all pick IDs exist
+    let diff_output = crate::diff::run_git_diff_commit(&target_sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let location = crate::registry::commit_location(&target_sha);
+    let pick_groups = &resolve_group_refs(&identified, &location, pick_groups)?;
+
+    // Validate all referenced IDs exist and are supported
+    for group in pick_groups {
+        for (id, _) in &group.ids {
+            let (_, hunk) = identified
+                .iter()
+                .find(|(hid, _)| hid == id)
+                .ok_or_else(|| {
+                    crate::exit::failure!(
+                        NotFound,
+                        "hunk {} not found in commit {}",
+                        id,
+                        &target_sha[..7.min(target_sha.len())]
+                    )
+                })?;
+            crate::diff::check_supported(hunk, id)?;
+        }
+    }
+
+    // Get original commit message for rest-message default
+    let original_message = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%B",
+        &target_sha,
+    ]))?;
+    let original_message = original_message.trim();
+    let rest_msg_joined;
+    let rest_msg = match rest {
+        SplitRest::Commit(Some(parts)) => {
+            rest_msg_joined = parts.join("\n\n");
+            rest_msg_joined.as_str()
+        }
+        SplitRest::Commit(None) | SplitRest::Discard => original_message,
+    };
+    let discard_rest = matches!(rest, SplitRest::Discard);
+
+    let hunk_states = initial_hunk_states(&identified);
+
+    // Pre-validate all line ranges before modifying git state
+    check_line_ranges(pick_groups, &hunk_states)?;
+
+    let mut plan = SplitPlan {
+        orig_head: head_sha,
+        target_sha,
+        is_head,
+        groups: pick_groups.to_vec(),
+        rest_msg: rest_msg.to_string(),
+        discard_rest,
+        hunk_states,
+        done: 0,
+        identity: None,
+        committer: crate::committer::mode(),
+        // Dropping the rest changes the tree on purpose
+        verify_tree: verify && !discard_rest,
+    };
+    if preserve_author {
+        plan.identity = Some(CommitIdentity::of(&plan.target_sha)?);
+    }
+
+    if dry_run {
+        print_split_plan(&plan)?;
+        if dirty {
+            eprintln!("note: working tree is dirty; split will refuse to run until it is clean");
+        }
+        return Ok(());
+    }
+
+    crate::backup::create("split")?;
+
+    // Persist the plan before touching git state so an interruption can resume
+    plan.save()?;
+    let result = start_split(&plan).and_then(|_| commit_split_groups(&mut plan));
+    finish_split(&plan, result)
+}
+
+/// Build stateful hunk tracking for split: original hunks with picked state.
+/// This keeps line ranges stable (always relative to original commit).
+#[derive(Clone)]
+struct HunkState {
+    hunk: DiffHunk,
+    picked: Vec<bool>, // which lines have been picked in previous groups
+}
+
+/// `pick_groups` with positional references replaced by the IDs they name,
+/// among the hunks of `location` (`commit:<sha>` or "unstaged").
+pub fn resolve_group_refs(
+    identified: &[(String, &DiffHunk)],
+    location: &str,
+    pick_groups: &[crate::PickGroup],
+) -> Result<Vec<crate::PickGroup>> {
+    let mut resolved = Vec::with_capacity(pick_groups.len());
+    for group in pick_groups {
+        let mut ids = Vec::with_capacity(group.ids.len());
+        for (id, range) in &group.ids {
+            ids.push((resolve_ref(identified, location, id)?, *range));
+        }
+        resolved.push(crate::PickGroup {
+            ids,
+            message_parts: group.message_parts.clone(),
+        });
+    }
+    Ok(resolved)
+}
+
+/// Check that every line range in `pick_groups` lies within its hunk.
+fn check_line_ranges(
+    pick_groups: &[crate::PickGroup],
+    hunk_states: &HashMap<String, HunkState>,
+) -> Result<()> {
+    for group in pick_groups {
+        // Group line ranges by hunk ID
+        let mut hunk_ranges: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
+        for (id, lines_range) in &group.ids {
+            if let Some(range) = lines_range {
+                hunk_ranges.entry(id.clone()).or_default().push(*range);
+            }
+        }
+
+        for (id, ranges) in &hunk_ranges {
+            let state = hunk_states
+                .get(id)
+                .ok_or_else(|| crate::exit::failure!(NotFound, "hunk {} not found", id))?;
+
+            for (start, end) in ranges {
+                if *start == 0 || *end == 0 {
+                    crate::exit::fail!(
+                        Usage,
+                        "line ranges are 1-based, got {}:{}-{}",
+                        id,
+                        start,
+                        end
+                    );
+                }
+                if *end > state.hunk.lines.len() {
+                    crate::exit::fail!(
+                        Usage,
+                        "line range {}:{}-{} exceeds hunk length ({})",
+                        id,
+                        start,
+                        end,
+                        state.hunk.lines.len()
+                    );
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `split --order`: `pick_groups` rearranged into the order their commits
+/// are made, `order` listing group numbers (1-based) and `None` for the rest.
+/// Which lines each group takes is still decided in the order the groups were
+/// given, so each group's picks are made explicit first; a listed rest
+/// becomes a group of its own with `rest_message` (else the original
+/// message), leaving nothing for the rest commit at the end.
+pub fn order_split_groups(
+    commit: &str,
+    pick_groups: &[crate::PickGroup],
+    rest_message: Option<&[String]>,
+    order: &[Option<usize>],
+) -> Result<Vec<crate::PickGroup>> {
+    for n in 1..=pick_groups.len() {
+        if order.iter().filter(|&&slot| slot == Some(n)).count() != 1 {
+            crate::exit::fail!(
+                Usage,
+                "--order must list each of the {} groups once (missing or repeated: {})",
+                pick_groups.len(),
+                n
+            );
+        }
+    }
+    if let Some(n) = order
+        .iter()
+        .flatten()
+        .find(|&&n| n == 0 || n > pick_groups.len())
+    {
+        crate::exit::fail!(
+            Usage,
+            "--order: no group {} (there are {})",
+            n,
+            pick_groups.len()
+        );
+    }
+    if order.iter().filter(|slot| slot.is_none()).count() > 1 {
+        crate::exit::fail!(Usage, "--order lists rest more than once");
+    }
+
+    let target_sha = crate::rev::resolve_commit(commit)?;
+    let diff_output = crate::diff::run_git_diff_commit(&target_sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let location = crate::registry::commit_location(&target_sha);
+    let pick_groups = resolve_group_refs(&identified, &location, pick_groups)?;
+    let mut states = initial_hunk_states(&identified);
+    check_line_ranges(&pick_groups, &states)?;
+
+    let is_change = |line: &String| line.starts_with('+') || line.starts_with('-');
+    // A whole hunk stays a bare ID, so dry runs and progress read as given
+    let explicit = |states: &HashMap<String, HunkState>,
+                    selections: Vec<(String, Vec<bool>)>|
+     -> Vec<(String, Option<(usize, usize)>)> {
+        let mut ids = Vec::new();
+        for (id, selected) in selections {
+            let lines = &states[&id].hunk.lines;
+            if lines.iter().zip(&selected).all(|(l, &s)| s == is_change(l)) {
+                ids.push((id, None));
+                continue;
+            }
+            let mut i = 0;
+            while i < selected.len() {
+                if !selected[i] {
+                    i += 1;
+                    continue;
+                }
+                let start = i;
+                while i < selected.len() && selected[i] {
+                    i += 1;
+                }
+                ids.push((id.clone(), Some((start + 1, i))));
+            }
+        }
+        ids
+    };
+
+    let mut explicit_groups = Vec::with_capacity(pick_groups.len());
+    for (n, group) in pick_groups.iter().enumerate() {
+        let selections = select_group_lines(group, &states)?;
+        if selections.is_empty() {
+            anyhow::bail!("group {}: no changes selected for commit", n + 1);
+        }
+        for (id, selected) in &selections {
+            let state = states.get_mut(id).expect("selected hunk exists");
+            for (p, s) in state.picked.iter_mut().zip(selected) {
+                *p |= *s;
+            }
+        }
+        explicit_groups.push(crate::PickGroup {
+            ids: explicit(&states, selections),
+            message_parts: group.message_parts.clone(),
+        });
+    }
+
+    let mut ordered = Vec::with_capacity(order.len());
+    for slot in order {
+        match slot {
+            Some(n) => ordered.push(explicit_groups[n - 1].clone()),
+            None => {
+                let mut ids: Vec<&String> = states.keys().collect();
+                ids.sort();
+                let remaining: Vec<(String, Vec<bool>)> = ids
+                    .into_iter()
+                    .map(|id| {
+                        let state = &states[id];
+                        let mask: Vec<bool> = state
+                            .hunk
+                            .lines
+                            .iter()
+                            .zip(&state.picked)
+                            .map(|(l, &p)| is_change(l) && !p)
+                            .collect();
+                        (id.clone(), mask)
+                    })
+                    .filter(|(_, mask)| mask.iter().any(|&s| s))
+                    .collect();
+                if remaining.is_empty() {
+                    continue;
+                }
+                let message = match rest_message {
+                    Some(parts) => parts.to_vec(),
+                    None => vec![
+                        crate::diff::run_git_cmd(Command::new("git").args([
+                            "log",
+                            "-1",
+                            "--format=%B",
+                            &target_sha,
+                        ]))?
+                        .trim()
+                        .to_string(),
+                    ],
+                };
+                ordered.push(crate::PickGroup {
+                    ids: explicit(&states, remaining),
+                    message_parts: message,
+                });
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+fn initial_hunk_states(identified: &[(String, &DiffHunk)]) -> HashMap<String, HunkState> {
+    identified
+        .iter()
+        .map(|(id, hunk)| {
+            (
+                id.clone(),
+                HunkState {
+                    hunk: (*hunk).clone(),
+                    picked: vec![false; hunk.lines.len()],
+                },
+            )
+        })
+        .collect()
+}
 
-    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
-    let head_sha = head_sha.trim();
+/// A validated split, persisted in the state file between steps.
+struct SplitPlan {
+    orig_head: String,
+    target_sha: String,
+    is_head: bool,
+    groups: Vec<crate::PickGroup>,
+    rest_msg: String,
+    /// Drop the unpicked changes instead of committing them
+    discard_rest: bool,
+    hunk_states: HashMap<String, HunkState>,
+    /// Number of pick groups already committed
+    done: usize,
+    /// Authorship metadata copied from the original commit onto each piece
+    identity: Option<CommitIdentity>,
+    /// The committer the new commits get, kept for a resumed split
+    committer: crate::committer::Mode,
+    /// Check the final tree against the original HEAD's once done
+    verify_tree: bool,
+}
 
-    // Track distance from target to HEAD for later (used to find new SHA after rebase)
-    let distance = crate::diff::run_git_cmd(Command::new("git").args([
-        "rev-list",
-        "--count",
-        &format!("{}..HEAD", target_sha),
-    ]))?;
-    let distance: usize = distance.trim().parse().unwrap_or(0);
+/// Author, author date, and committer date to give a rewritten commit, so
+/// split pieces look like the commit they came from.
+#[derive(Clone)]
+struct CommitIdentity {
+    author: String,
+    author_date: String,
+    committer_date: String,
+}
 
-    if target_sha == head_sha {
-        // Simple case: amend HEAD with new message
-        let output = Command::new("git")
-            .args(["commit", "--amend", "-m", message])
-            .output()
-            .context("failed to amend HEAD")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit --amend failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+impl CommitIdentity {
+    fn of(sha: &str) -> Result<CommitIdentity> {
+        let (author, author_date) = commit_author(sha)?;
+        let committer_date =
+            crate::diff::run_git_cmd(Command::new("git").args(["log", "-1", "--format=%cI", sha]))?;
+        Ok(CommitIdentity {
+            author,
+            author_date,
+            committer_date: committer_date.trim().to_string(),
+        })
+    }
+
+    fn apply(&self, cmd: &mut Command) {
+        cmd.args(["--author", &self.author, "--date", &self.author_date]);
+        cmd.env("GIT_COMMITTER_DATE", &self.committer_date);
+    }
+}
+
+impl SplitPlan {
+    fn save(&self) -> Result<()> {
+        let mut state = State::new(Operation::Split, &self.orig_head, &self.target_sha);
+        state.push("is-head", if self.is_head { "true" } else { "false" });
+        state.push("done", &self.done.to_string());
+        for group in &self.groups {
+            let ids: Vec<String> = group
+                .ids
+                .iter()
+                .map(|(id, range)| match range {
+                    Some((start, end)) => format!("{}:{}-{}", id, start, end),
+                    None => id.clone(),
+                })
+                .collect();
+            state.push("pick", &ids.join(" "));
+            for part in &group.message_parts {
+                state.push("message", part);
+            }
+        }
+        state.push("rest-message", &self.rest_msg);
+        if self.discard_rest {
+            state.push("discard-rest", "1");
+        }
+        if let Some(identity) = &self.identity {
+            state.push("author", &identity.author);
+            state.push("author-date", &identity.author_date);
+            state.push("committer-date", &identity.committer_date);
+        }
+        if self.committer != crate::committer::Mode::Now {
+            state.push("committer", self.committer.name());
+        }
+        if self.verify_tree {
+            state.push("verify-tree", "1");
+        }
+        // Sorted so the file is deterministic
+        let mut ids: Vec<&String> = self.hunk_states.keys().collect();
+        ids.sort();
+        for id in ids {
+            let mask: String = self.hunk_states[id]
+                .picked
+                .iter()
+                .map(|&p| if p { '1' } else { '0' })
+                .collect();
+            state.push("picked", &format!("{} {}", id, mask));
+        }
+        state.save()
+    }
+
+    fn load(state: &State) -> Result<SplitPlan> {
+        let diff_output = crate::diff::run_git_diff_commit(&state.target, None)?;
+        let hunks = crate::diff::parse_diff(&diff_output);
+        let identified = assign_ids(&hunks);
+        let mut hunk_states = initial_hunk_states(&identified);
+
+        let mut groups: Vec<crate::PickGroup> = Vec::new();
+        for (key, value) in &state.fields {
+            match key.as_str() {
+                "pick" => {
+                    let mut ids = Vec::new();
+                    for token in value.split_whitespace() {
+                        ids.extend(crate::parse_pick_id(token)?);
+                    }
+                    groups.push(crate::PickGroup {
+                        ids,
+                        message_parts: Vec::new(),
+                    });
+                }
+                "message" => match groups.last_mut() {
+                    Some(group) => group.message_parts.push(value.clone()),
+                    None => anyhow::bail!("corrupt split state: message without pick"),
+                },
+                "picked" => {
+                    let (id, mask) = value
+                        .split_once(' ')
+                        .ok_or_else(|| anyhow::anyhow!("corrupt split state: {}", value))?;
+                    let hunk_state = hunk_states
+                        .get_mut(id)
+                        .ok_or_else(|| anyhow::anyhow!("hunk {} no longer in commit", id))?;
+                    if mask.len() != hunk_state.picked.len() {
+                        anyhow::bail!("corrupt split state: mask length mismatch for {}", id);
+                    }
+                    for (slot, c) in hunk_state.picked.iter_mut().zip(mask.chars()) {
+                        *slot = c == '1';
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(SplitPlan {
+            orig_head: state.orig_head.clone(),
+            target_sha: state.target.clone(),
+            is_head: state.get("is-head") == Some("true"),
+            groups,
+            rest_msg: state.get("rest-message").unwrap_or_default().to_string(),
+            discard_rest: state.get("discard-rest").is_some(),
+            identity: match (
+                state.get("author"),
+                state.get("author-date"),
+                state.get("committer-date"),
+            ) {
+                (Some(author), Some(author_date), Some(committer_date)) => Some(CommitIdentity {
+                    author: author.to_string(),
+                    author_date: author_date.to_string(),
+                    committer_date: committer_date.to_string(),
+                }),
+                _ => None,
+            },
+            committer: state
+                .get("committer")
+                .and_then(crate::committer::Mode::parse)
+                .unwrap_or(crate::committer::Mode::Now),
+            hunk_states,
+            done: state
+                .get("done")
+                .and_then(|d| d.parse().ok())
+                .ok_or_else(|| anyhow::anyhow!("corrupt split state: missing done count"))?,
+            verify_tree: state.get("verify-tree").is_some(),
+        })
+    }
+}
+
+/// Print the commits a split would produce, simulating each group's selection
+/// so overlapping or empty picks fail exactly as they would for real.
+fn print_split_plan(plan: &SplitPlan) -> Result<()> {
+    let mut states = plan.hunk_states.clone();
+    let count_selected = |state: &HunkState, mask: &[bool]| {
+        let mut added = 0;
+        let mut removed = 0;
+        for (line, &sel) in state.hunk.lines.iter().zip(mask) {
+            if sel && line.starts_with('+') {
+                added += 1;
+            } else if sel && line.starts_with('-') {
+                removed += 1;
+            }
+        }
+        (added, removed)
+    };
+
+    for (n, group) in plan.groups.iter().enumerate() {
+        let selections = select_group_lines(group, &states)?;
+        if selections.is_empty() {
+            anyhow::bail!("group {}: no changes selected for commit", n + 1);
+        }
+        let subject = group.message_parts[0].lines().next().unwrap_or("");
+        println!("commit {}: {}", n + 1, subject);
+        for (id, selected) in &selections {
+            let state = states.get_mut(id).expect("selected hunk exists");
+            let (added, removed) = count_selected(state, selected);
+            let ranges: Vec<String> = group
+                .ids
+                .iter()
+                .filter(|(gid, _)| gid == id)
+                .filter_map(|(_, r)| {
+                    r.map(|(a, b)| {
+                        if a == b {
+                            a.to_string()
+                        } else {
+                            format!("{}-{}", a, b)
+                        }
+                    })
+                })
+                .collect();
+            let label = if ranges.is_empty() {
+                id.clone()
+            } else {
+                format!("{}:{}", id, ranges.join(","))
+            };
+            println!("  {} {} (+{} -{})", label, state.hunk.file, added, removed);
+            for (p, s) in state.picked.iter_mut().zip(selected) {
+                *p |= *s;
+            }
+        }
+        println!();
+    }
+
+    // Rest hunks in diff order: by file, then by position in the file
+    let old_start = |hunk: &DiffHunk| -> usize {
+        hunk.header
+            .split_whitespace()
+            .nth(1)
+            .and_then(|r| r.trim_start_matches('-').split(',').next()?.parse().ok())
+            .unwrap_or(0)
+    };
+    let mut ids: Vec<&String> = states.keys().collect();
+    ids.sort_by_key(|id| (&states[*id].hunk.file, old_start(&states[*id].hunk)));
+    let mut rest_lines = Vec::new();
+    for id in ids {
+        let state = &states[id];
+        let remaining: Vec<bool> = state
+            .hunk
+            .lines
+            .iter()
+            .zip(&state.picked)
+            .map(|(l, &p)| !p && (l.starts_with('+') || l.starts_with('-')))
+            .collect();
+        let (added, removed) = count_selected(state, &remaining);
+        if added + removed > 0 {
+            rest_lines.push(format!(
+                "  {} {} (+{} -{})",
+                id, state.hunk.file, added, removed
+            ));
         }
+    }
+    if rest_lines.is_empty() {
+        println!("rest: nothing left (no rest commit)");
     } else {
-        // Get original commit subject for reword marker
-        let subject = crate::diff::run_git_cmd(Command::new("git").args([
-            "log",
-            "-1",
-            "--format=%s",
-            target_sha,
-        ]))?;
-        let subject = subject.trim();
+        if plan.discard_rest {
+            println!("rest: discarded");
+        } else {
+            println!("rest: {}", plan.rest_msg.lines().next().unwrap_or(""));
+        }
+        for line in rest_lines {
+            println!("{}", line);
+        }
+    }
+    Ok(())
+}
 
-        // Create empty reword commit with new message
-        let output = Command::new("git")
-            .args([
-                "commit",
-                "--allow-empty",
-                "-m",
-                &format!("amend! {}\n\n{}", subject, message),
-            ])
-            .output()
-            .context("failed to create reword commit")?;
+/// Clear the state on success, or explain how to resume on failure.
+fn finish_split(plan: &SplitPlan, result: Result<()>) -> Result<()> {
+    match result {
+        Ok(()) => {
+            State::clear()?;
+            if plan.verify_tree {
+                verify_tree("split", &plan.orig_head)?;
+            }
+            Ok(())
+        }
+        Err(e) => {
+            eprintln!(
+                "error: split of {} interrupted after {} of {} groups",
+                &plan.target_sha[..7.min(plan.target_sha.len())],
+                plan.done,
+                plan.groups.len()
+            );
+            print_resume_hint();
+            Err(e)
+        }
+    }
+}
+
+/// Resume an interrupted split from its persisted plan.
+fn resume_split(state: &State) -> Result<()> {
+    let mut plan = SplitPlan::load(state)?;
+    crate::committer::set_mode(plan.committer);
+    let result = commit_split_groups(&mut plan);
+    finish_split(&plan, result)
+}
+
+/// Move the target commit's changes into the working tree.
+fn start_split(plan: &SplitPlan) -> Result<()> {
+    if !plan.is_head {
+        return start_rebase_at_commit(&plan.target_sha);
+    }
+
+    // HEAD: just reset
+    let output = Command::new("git")
+        .args(["reset", "HEAD~"])
+        .traced_output()
+        .context("failed to reset HEAD")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git reset failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Stage and commit a split patch. On commit failure, unstage it again so a
+/// resumed split can retry the same step from a clean index.
+fn commit_split_patch(patch: &str, message: &str, plan: &SplitPlan) -> Result<()> {
+    apply_patch(patch, &ApplyMode::Stage)?;
+
+    let mut commit_cmd = git_commit();
+    commit_cmd.args(["-m", message]);
+    if let Some(identity) = &plan.identity {
+        identity.apply(&mut commit_cmd);
+    }
+    let author_date = plan.identity.as_ref().map(|i| i.author_date.as_str());
+    crate::committer::configure_commit(&mut commit_cmd, &plan.target_sha, author_date)?;
+    let output = commit_cmd.traced_output().context("failed to commit")?;
+    if !output.status.success() {
+        let _ = apply_patch(patch, &ApplyMode::Unstage);
+        anyhow::bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    // Print only the subject line
+    let subject = message.lines().next().unwrap_or(message);
+    eprintln!("committed: {}", subject);
+    Ok(())
+}
+
+/// Work out which lines of each hunk a pick group selects, given the lines
+/// already picked by earlier groups. Returns one selection mask per hunk that
+/// contributes at least one change line.
+fn select_group_lines(
+    group: &crate::PickGroup,
+    hunk_states: &HashMap<String, HunkState>,
+) -> Result<Vec<(String, Vec<bool>)>> {
+    // Group line ranges by hunk ID so same-hunk entries produce one patch
+    let mut hunk_ranges: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+    for (id, lines_range) in &group.ids {
+        if let Some(entry) = hunk_ranges.iter_mut().find(|(eid, _)| eid == id) {
+            if let Some(range) = lines_range {
+                entry.1.push(*range);
+            }
+        } else {
+            let ranges = match lines_range {
+                Some(range) => vec![*range],
+                None => vec![],
+            };
+            hunk_ranges.push((id.clone(), ranges));
+        }
+    }
+
+    let mut selections = Vec::new();
+    for (id, ranges) in &hunk_ranges {
+        let state = hunk_states
+            .get(id)
+            .ok_or_else(|| crate::exit::failure!(NotFound, "hunk {} not found", id))?;
+
+        // Build selection mask for this group
+        let mut selected = vec![false; state.hunk.lines.len()];
+
+        if ranges.is_empty() {
+            // No line ranges: select all remaining change lines
+            for (i, line) in state.hunk.lines.iter().enumerate() {
+                if (line.starts_with('+') || line.starts_with('-')) && !state.picked[i] {
+                    selected[i] = true;
+                }
+            }
+        } else {
+            // Select lines in specified ranges
+            for (start, end) in ranges {
+                #[allow(clippy::needless_range_loop)]
+                for i in (*start - 1)..*end {
+                    if i < state.hunk.lines.len() {
+                        let line = &state.hunk.lines[i];
+                        // Only select change lines, not context
+                        if line.starts_with('+') || line.starts_with('-') {
+                            if state.picked[i] {
+                                anyhow::bail!(
+                                    "line {} in hunk {} was already picked in a previous group",
+                                    i + 1,
+                                    id
+                                );
+                            }
+                            selected[i] = true;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Skip this hunk if nothing to select
+        if selected.iter().any(|&s| s) {
+            selections.push((id.clone(), selected));
+        }
+    }
+    Ok(selections)
+}
+
+/// Commit the remaining pick groups, then the rest, then finish the rebase.
+/// Progress is saved after every commit.
+fn commit_split_groups(plan: &mut SplitPlan) -> Result<()> {
+    // Changes are in the working tree. Stage and commit each pick group using the
+    // stateful approach (line ranges always relative to original commit).
+    while plan.done < plan.groups.len() {
+        let group = &plan.groups[plan.done];
+        let mut combined_patch = String::new();
+        // Masks are only committed to the plan once the group's commit succeeds
+        let mut new_picked: Vec<(String, Vec<bool>)> = Vec::new();
+
+        for (id, selected) in select_group_lines(group, &plan.hunk_states)? {
+            let state = &plan.hunk_states[&id];
+
+            // Build patch using stateful slicing
+            let patched_hunk = slice_hunk_with_state(&state.hunk, &state.picked, &selected)?;
+            combined_patch.push_str(&build_patch(&patched_hunk));
+
+            // Selected lines become picked for next groups
+            let picked: Vec<bool> = state
+                .picked
+                .iter()
+                .zip(&selected)
+                .map(|(p, s)| *p || *s)
+                .collect();
+            new_picked.push((id, picked));
+        }
+
+        if combined_patch.is_empty() {
+            anyhow::bail!("no changes selected for commit");
+        }
+
+        commit_split_patch(&combined_patch, &group.message_parts.join("\n\n"), plan)?;
+
+        for (id, picked) in new_picked {
+            if let Some(state) = plan.hunk_states.get_mut(&id) {
+                state.picked = picked;
+            }
+        }
+        plan.done += 1;
+        plan.save()?;
+    }
+
+    // Stage and commit remaining changes (if any)
+    // Build patches for all unpicked change lines
+    let mut combined_patch = String::new();
+    let mut ids: Vec<&String> = plan.hunk_states.keys().collect();
+    ids.sort();
+
+    for id in ids {
+        let state = &plan.hunk_states[id];
+        // Check if any change lines remain unpicked
+        let remaining_selected: Vec<bool> = state
+            .hunk
+            .lines
+            .iter()
+            .zip(&state.picked)
+            .map(|(line, picked)| (line.starts_with('+') || line.starts_with('-')) && !picked)
+            .collect();
+
+        if remaining_selected.iter().any(|&s| s) {
+            let patched_hunk =
+                slice_hunk_with_state(&state.hunk, &state.picked, &remaining_selected)?;
+            combined_patch.push_str(&build_patch(&patched_hunk));
+        }
+    }
+
+    if !combined_patch.is_empty() {
+        if plan.discard_rest {
+            apply_patch(&combined_patch, &ApplyMode::Discard)?;
+            eprintln!("discarded the unpicked changes");
+        } else {
+            commit_split_patch(&combined_patch, &plan.rest_msg, plan)?;
+        }
+
+        for state in plan.hunk_states.values_mut() {
+            for (i, line) in state.hunk.lines.iter().enumerate() {
+                if line.starts_with('+') || line.starts_with('-') {
+                    state.picked[i] = true;
+                }
+            }
+        }
+        plan.save()?;
+    }
+
+    // Continue rebase if non-HEAD
+    if !plan.is_head && rebase_in_progress()? {
+        let output = run_rebase(&mut git_rebase_continue())?;
         if !output.status.success() {
-            anyhow::bail!(
-                "git commit failed: {}",
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            eprintln!("error: rebase continue failed");
+            crate::exit::fail!(Conflict, "rebase continue failed: {}", stderr);
+        }
+    }
+
+    Ok(())
+}
+
+fn rebase_in_progress() -> Result<bool> {
+    for dir_name in ["rebase-merge", "rebase-apply"] {
+        let check = Command::new("git")
+            .args(["rev-parse", "--git-path", dir_name])
+            .traced_output()
+            .context("failed to check rebase state")?;
+        let dir = String::from_utf8_lossy(&check.stdout).trim().to_string();
+        if std::path::Path::new(&dir).exists() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+pub fn check_no_rebase_in_progress() -> Result<()> {
+    if let Some(state) = State::load()? {
+        crate::exit::fail!(
+            RepoState,
+            "git-surgeon {} already in progress; run 'git-surgeon continue' or 'git-surgeon abort'",
+            state.operation.name()
+        );
+    }
+    if rebase_in_progress()? {
+        crate::exit::fail!(RepoState, "rebase already in progress");
+    }
+    Ok(())
+}
+
+fn print_resume_hint() {
+    eprintln!("resolve the problem and run: git-surgeon continue");
+    eprintln!("or abort with: git-surgeon abort");
+}
+
+/// Check that a finished rewrite left HEAD with the same tree as `orig_head`,
+/// i.e. only the way the changes are divided into commits changed. Otherwise
+/// reset back to `orig_head`, keeping uncommitted changes, and fail.
+fn verify_tree(operation: &str, orig_head: &str) -> Result<()> {
+    let tree = |rev: &str| -> Result<String> {
+        let sha = crate::diff::run_git_cmd(
+            Command::new("git").args(["rev-parse", &format!("{}^{{tree}}", rev)]),
+        )?;
+        Ok(sha.trim().to_string())
+    };
+    if tree(orig_head)? == tree("HEAD")? {
+        return Ok(());
+    }
+
+    let new_head = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let new_head = new_head.trim();
+    let stat = crate::diff::run_git_cmd(
+        Command::new("git").args(["diff", "--stat", orig_head, new_head]),
+    )?;
+    eprintln!(
+        "error: {} changed the final tree; differences from the original:",
+        operation
+    );
+    for line in stat.lines() {
+        eprintln!("  {}", line);
+    }
+
+    let output = Command::new("git")
+        .args(["reset", "--quiet", "--keep", orig_head])
+        .traced_output()
+        .context("failed to reset")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "tree verification failed and rolling back failed; restore the original with \
+             'git-surgeon restore-backup': {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    anyhow::bail!(
+        "tree verification failed; rolled back to {} (the rewritten result was {}, \
+         pass --no-verify-tree to keep such a result)",
+        &orig_head[..7.min(orig_head.len())],
+        &new_head[..7.min(new_head.len())]
+    );
+}
+
+/// Resume an interrupted git-surgeon operation.
+pub fn continue_operation() -> Result<()> {
+    let state = State::load()?
+        .ok_or_else(|| crate::exit::failure!(RepoState, "no git-surgeon operation in progress"))?;
+
+    if state.operation == Operation::Split {
+        resume_split(&state)?;
+        eprintln!("split complete");
+        return Ok(());
+    }
+
+    if state.operation == Operation::EditCommit {
+        commit_edit_leftovers(&state)?;
+    }
+
+    if rebase_in_progress()? {
+        // Keep the existing commit message of any commit being resolved
+        let output = run_rebase(&mut git_rebase_continue())?;
+        if !output.status.success() {
+            eprintln!(
+                "error: {} still has unresolved problems",
+                state.operation.name()
+            );
+            print_resume_hint();
+            crate::exit::fail!(
+                Conflict,
+                "rebase continue failed: {}",
                 String::from_utf8_lossy(&output.stderr)
             );
         }
+    }
 
-        // Check if target is root commit (has no parent)
-        let is_root = Command::new("git")
-            .args(["rev-parse", "--verify", &format!("{}^", target_sha)])
-            .output()
-            .map(|o| !o.status.success())
-            .unwrap_or(false);
+    if state.operation == Operation::Drop {
+        restore_dropped_changes(&state)?;
+    }
 
-        // Non-interactive autosquash rebase
-        let mut rebase_cmd = Command::new("git");
-        rebase_cmd.args(["rebase", "-i", "--autosquash", "--autostash"]);
-        if is_root {
-            rebase_cmd.arg("--root");
-        } else {
-            rebase_cmd.arg(format!("{}~1", target_sha));
+    State::clear()?;
+    if state.get("verify-tree").is_some()
+        && let Err(e) = verify_tree(state.operation.name(), &state.orig_head)
+    {
+        if let Some(stash) = state.get("stash") {
+            crate::autostash::pop(stash)?;
         }
-        rebase_cmd.env("GIT_SEQUENCE_EDITOR", "true");
+        return Err(e);
+    }
+    eprintln!("{} complete", state.operation.name());
+    if let Some(stash) = state.get("stash") {
+        crate::autostash::pop(stash)?;
+    }
+    Ok(())
+}
+
+/// Roll back an interrupted git-surgeon operation to where it started.
+pub fn abort_operation() -> Result<()> {
+    let state = State::load()?
+        .ok_or_else(|| crate::exit::failure!(RepoState, "no git-surgeon operation in progress"))?;
 
-        let output = rebase_cmd.output().context("failed to run rebase")?;
+    if state.operation == Operation::EditCommit {
+        remove_edit_leftovers(&state)?;
+    }
+
+    if rebase_in_progress()? {
+        let output = Command::new("git")
+            .args(["rebase", "--abort"])
+            .traced_output()
+            .context("failed to abort rebase")?;
         if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!(
-                "error: rebase conflict while rewording {}",
-                &target_sha[..7.min(target_sha.len())]
+            anyhow::bail!(
+                "git rebase --abort failed: {}",
+                String::from_utf8_lossy(&output.stderr)
             );
-            eprintln!("resolve conflicts and run: git rebase --continue");
-            eprintln!("or abort with: git rebase --abort");
-            anyhow::bail!("rebase failed: {}", stderr);
         }
     }
 
-    // Print short sha + new subject of the reworded commit
-    // Use HEAD~distance to find the commit at the same position after rebase
-    let ref_spec = if distance == 0 {
-        "HEAD".to_string()
-    } else {
-        format!("HEAD~{}", distance)
+    // fixup/reword created a helper commit before rebasing; drop it while keeping
+    // its changes staged. A HEAD split reset the commit away; restore it while
+    // keeping the (unchanged) working tree. The other operations only rebased,
+    // so the rebase abort has already restored HEAD.
+    let reset_mode = match state.operation {
+        Operation::Fixup
+        | Operation::Reword
+        | Operation::Reorder
+        | Operation::Drop
+        | Operation::EditCommit
+        | Operation::Squash => "--soft",
+        Operation::Split => "--mixed",
     };
-    let info = crate::diff::run_git_cmd(Command::new("git").args([
-        "log",
-        "-1",
-        "--format=%h %s",
-        &ref_spec,
-    ]));
-    if let Ok(info) = info {
-        eprintln!("reworded {}", info.trim());
+    let output = Command::new("git")
+        .args(["reset", "--quiet", reset_mode, &state.orig_head])
+        .traced_output()
+        .context("failed to reset")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git reset failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
     }
 
+    State::clear()?;
+    eprintln!(
+        "aborted {}, restored {}",
+        state.operation.name(),
+        &state.orig_head[..7.min(state.orig_head.len())]
+    );
+    if let Some(stash) = state.get("stash") {
+        crate::autostash::pop(stash)?;
+    }
     Ok(())
 }
 
-/// Split a commit into multiple commits by hunk selection.
-pub fn split(
+/// Where the squashed commit's message comes from.
+pub enum SquashMessage {
+    /// Message given with -m
+    Given(String),
+    /// Reuse this commit's message verbatim
+    From(String),
+    /// Concatenate the messages of all squashed commits, oldest first
+    Combined,
+    /// One of the above, finished in the editor (`--edit`)
+    Edited(Box<SquashMessage>),
+}
+
+/// Squash commits from <commit>..HEAD into a single commit.
+///
+/// `commit` may also be a range `A..B`: the commits after A up to and
+/// including B are squashed, and any commits after B are replayed on top.
+pub fn squash(
     commit: &str,
-    pick_groups: &[crate::PickGroup],
-    rest_message: Option<&[String]>,
+    message: &SquashMessage,
+    trailers: &crate::trailer::Trailers,
+    force: bool,
+    preserve_author: bool,
+    force_published: bool,
+    verify: bool,
 ) -> Result<()> {
-    // Check working tree is clean
-    let status = Command::new("git")
-        .args(["status", "--porcelain"])
-        .output()
-        .context("failed to check git status")?;
-    if !String::from_utf8_lossy(&status.stdout).trim().is_empty() {
-        anyhow::bail!("working tree is dirty; commit or stash changes before splitting");
-    }
-
     check_no_rebase_in_progress()?;
 
-    // Resolve target commit
-    let target_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", commit]))?;
-    let target_sha = target_sha.trim().to_string();
-
     let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
-    let head_sha = head_sha.trim().to_string();
-
-    let is_head = target_sha == head_sha;
-
-    // Get hunks from the target commit and validate all pick IDs exist
-    let diff_output = crate::diff::run_git_diff_commit(&target_sha, None)?;
-    let hunks = crate::diff::parse_diff(&diff_output);
-    let identified = assign_ids(&hunks);
+    let head_sha = head_sha.trim();
 
-    // Validate all referenced IDs exist and are supported
-    for group in pick_groups {
-        for (id, _) in &group.ids {
-            let (_, hunk) = identified
-                .iter()
-                .find(|(hid, _)| hid == id)
-                .ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "hunk {} not found in commit {}",
-                        id,
-                        &target_sha[..7.min(target_sha.len())]
-                    )
-                })?;
-            crate::diff::check_supported(hunk, id)?;
+    // Resolve target commit SHA
+    let target_sha = if commit.contains("..") {
+        let (base_sha, tip_sha) = crate::rev::resolve_range(commit)?;
+        if tip_sha != head_sha {
+            let oldest = oldest_in_range(&base_sha, &tip_sha)?;
+            crate::rev::check_history_present(&oldest)?;
+            if !force_published {
+                crate::rev::check_unpublished(&oldest)?;
+            }
+            return squash_range(
+                &base_sha,
+                &tip_sha,
+                message,
+                trailers,
+                force,
+                preserve_author,
+                verify,
+            );
         }
+        oldest_in_range(&base_sha, &tip_sha)?
+    } else {
+        crate::rev::resolve_commit(commit)?
+    };
+    crate::rev::check_history_present(&target_sha)?;
+    if !force_published {
+        crate::rev::check_unpublished(&target_sha)?;
     }
+    let target_sha = target_sha.as_str();
 
-    // Get original commit message for rest-message default
-    let original_message = crate::diff::run_git_cmd(Command::new("git").args([
-        "log",
-        "-1",
-        "--format=%B",
-        &target_sha,
-    ]))?;
-    let original_message = original_message.trim();
-    let rest_msg_joined;
-    let rest_msg = match rest_message {
-        Some(parts) => {
-            rest_msg_joined = parts.join("\n\n");
-            rest_msg_joined.as_str()
-        }
-        None => original_message,
+    if target_sha == head_sha {
+        anyhow::bail!("nothing to squash: target commit is HEAD");
+    }
+
+    // Extract author and date from target commit if preserving
+    let (author, author_date) = if preserve_author {
+        let (ident, date) = commit_author(target_sha)?;
+        (Some(ident), Some(date))
+    } else {
+        (None, None)
     };
 
-    // Build stateful hunk tracking: original hunks with picked state
-    // This keeps line ranges stable (always relative to original commit)
-    struct HunkState {
-        hunk: DiffHunk,
-        picked: Vec<bool>, // which lines have been picked in previous groups
+    // Verify target is ancestor of HEAD
+    let is_ancestor = Command::new("git")
+        .args(["merge-base", "--is-ancestor", target_sha, "HEAD"])
+        .traced_status()
+        .context("failed to check ancestry")?;
+    if !is_ancestor.success() {
+        anyhow::bail!(
+            "commit {} is not an ancestor of HEAD",
+            &target_sha[..7.min(target_sha.len())]
+        );
     }
 
-    let mut hunk_states: HashMap<String, HunkState> = identified
-        .iter()
-        .map(|(id, hunk)| {
-            (
-                id.clone(),
-                HunkState {
-                    hunk: (*hunk).clone(),
-                    picked: vec![false; hunk.lines.len()],
-                },
-            )
-        })
-        .collect();
+    let message = squash_message(message, trailers, target_sha, head_sha)?;
+    let message = message.as_str();
 
-    // Pre-validate all line ranges before modifying git state
-    for group in pick_groups {
-        // Group line ranges by hunk ID
-        let mut hunk_ranges: HashMap<String, Vec<(usize, usize)>> = HashMap::new();
-        for (id, lines_range) in &group.ids {
-            if let Some(range) = lines_range {
-                hunk_ranges.entry(id.clone()).or_default().push(*range);
-            }
+    // Check for merge commits in range (they will be flattened)
+    if !force {
+        let merges = Command::new("git")
+            .args(["rev-list", "--merges", &format!("{}..HEAD", target_sha)])
+            .traced_output()
+            .context("failed to check for merge commits")?;
+        if !String::from_utf8_lossy(&merges.stdout).trim().is_empty() {
+            anyhow::bail!(
+                "range contains merge commits which will be flattened; use --force to proceed"
+            );
         }
+    }
 
-        for (id, ranges) in &hunk_ranges {
-            let state = hunk_states
-                .get(id)
-                .ok_or_else(|| anyhow::anyhow!("hunk {} not found", id))?;
+    crate::backup::create("squash")?;
 
-            for (start, end) in ranges {
-                if *start == 0 || *end == 0 {
-                    anyhow::bail!("line ranges are 1-based, got {}:{}-{}", id, start, end);
-                }
-                if *end > state.hunk.lines.len() {
-                    anyhow::bail!(
-                        "line range {}:{}-{} exceeds hunk length ({})",
-                        id,
-                        start,
-                        end,
-                        state.hunk.lines.len()
-                    );
-                }
+    // Autostash if working tree is dirty (tracked files only)
+    let stash = crate::autostash::push("squash")?;
+
+    // Check if target is root commit
+    let is_root = crate::rev::is_root(target_sha);
+
+    let squashed = (|| -> Result<()> {
+        if is_root {
+            // For root commit: delete HEAD ref to create orphan state, then commit
+            // This preserves hooks and GPG signing (unlike commit-tree)
+            let output = Command::new("git")
+                .args(["update-ref", "-d", "HEAD"])
+                .traced_output()
+                .context("failed to delete HEAD ref")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git update-ref failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+
+            // Commit (git treats this as the first commit)
+            let mut commit_cmd = git_commit();
+            commit_cmd.args(["-m", message]);
+            if let Some(ref auth) = author {
+                commit_cmd.args(["--author", auth]);
+            }
+            if let Some(ref date) = author_date {
+                commit_cmd.args(["--date", date]);
+            }
+            crate::committer::configure_commit(&mut commit_cmd, head_sha, author_date.as_deref())?;
+            let output = commit_cmd.traced_output().context("failed to commit")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git commit failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+        } else {
+            // Normal case: reset to parent of target
+            let output = Command::new("git")
+                .args(["reset", "--soft", &format!("{}^", target_sha)])
+                .traced_output()
+                .context("failed to reset")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git reset failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+            }
+
+            // Commit with new message
+            let mut commit_cmd = git_commit();
+            commit_cmd.args(["-m", message]);
+            if let Some(ref auth) = author {
+                commit_cmd.args(["--author", auth]);
+            }
+            if let Some(ref date) = author_date {
+                commit_cmd.args(["--date", date]);
+            }
+            crate::committer::configure_commit(&mut commit_cmd, head_sha, author_date.as_deref())?;
+            let output = commit_cmd.traced_output().context("failed to commit")?;
+            if !output.status.success() {
+                anyhow::bail!(
+                    "git commit failed: {}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
             }
         }
+        Ok(())
+    })();
+    if let Err(e) = squashed {
+        // Nothing was rewritten yet; put HEAD back and restore the changes
+        let _ = Command::new("git")
+            .args(["update-ref", "HEAD", head_sha])
+            .traced_output();
+        if let Some(stash) = &stash {
+            crate::autostash::pop(stash)?;
+        }
+        return Err(e);
     }
 
-    if !is_head {
-        start_rebase_at_commit(&target_sha)?;
-    } else {
-        // HEAD: just reset
-        let output = Command::new("git")
-            .args(["reset", "HEAD~"])
-            .output()
-            .context("failed to reset HEAD")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git reset failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
+    // Count how many commits were squashed
+    let count = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--count",
+        &format!("{}..{}", target_sha, head_sha),
+    ]))?;
+    let count: i32 = count.trim().parse().unwrap_or(0);
+
+    if verify && let Err(e) = verify_tree("squash", head_sha) {
+        if let Some(stash) = &stash {
+            crate::autostash::pop(stash)?;
         }
+        return Err(e);
     }
 
-    // Now changes are in the working tree. Stage and commit each pick group
-    // using the stateful approach (line ranges always relative to original commit).
+    eprintln!("squashed {} commits", count + 1);
 
-    for group in pick_groups {
-        let mut combined_patch = String::new();
+    // Restore stashed changes
+    if let Some(stash) = &stash {
+        crate::autostash::pop(stash)?;
+    }
 
-        // Group line ranges by hunk ID so same-hunk entries produce one patch
-        let mut hunk_ranges: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
-        for (id, lines_range) in &group.ids {
-            if let Some(entry) = hunk_ranges.iter_mut().find(|(eid, _)| eid == id) {
-                if let Some(range) = lines_range {
-                    entry.1.push(*range);
-                }
-            } else {
-                let ranges = match lines_range {
-                    Some(range) => vec![*range],
-                    None => vec![],
-                };
-                hunk_ranges.push((id.clone(), ranges));
-            }
+    Ok(())
+}
+
+/// Subject prefixes of the commits `git rebase --autosquash` folds into the
+/// commit they name.
+const AUTOSQUASH_PREFIXES: &[&str] = &["fixup! ", "amend! ", "squash! "];
+
+/// `squash --autosquash`: fold every `fixup!`/`amend!`/`squash!` commit from
+/// `commit` (the oldest they may target, or a range `A..HEAD`) up to HEAD
+/// into its target, in one non-interactive autosquash rebase.
+pub fn autosquash(commit: &str, force_published: bool, verify: bool) -> Result<()> {
+    check_no_rebase_in_progress()?;
+
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim();
+    let (base, oldest) = if commit.contains("..") {
+        let (base_sha, tip_sha) = crate::rev::resolve_range(commit)?;
+        if tip_sha != head_sha {
+            crate::exit::fail!(Usage, "--autosquash only supports ranges ending at HEAD");
         }
+        let oldest = oldest_in_range(&base_sha, &tip_sha)?;
+        (Some(base_sha), oldest)
+    } else {
+        let sha = crate::rev::resolve_commit(commit)?;
+        let base = (!crate::rev::is_root(&sha)).then(|| format!("{}~1", sha));
+        (base, sha)
+    };
+    crate::rev::check_history_present(&oldest)?;
+    if !force_published {
+        crate::rev::check_unpublished(&oldest)?;
+    }
 
-        for (id, ranges) in &hunk_ranges {
-            let state = hunk_states
-                .get_mut(id)
-                .ok_or_else(|| anyhow::anyhow!("hunk {} not found", id))?;
+    let range_spec = match &base {
+        Some(base) => format!("{}..HEAD", base),
+        None => "HEAD".to_string(),
+    };
+    let merges =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-list", "--merges", &range_spec]))?;
+    if !merges.trim().is_empty() {
+        anyhow::bail!("range contains merge commits, which --autosquash does not support");
+    }
+    let pending = || -> Result<usize> {
+        let subjects = crate::diff::run_git_cmd(Command::new("git").args([
+            "log",
+            "--format=%s",
+            &range_spec,
+        ]))?;
+        Ok(subjects
+            .lines()
+            .filter(|s| AUTOSQUASH_PREFIXES.iter().any(|p| s.starts_with(p)))
+            .count())
+    };
+    let before = pending()?;
+    if before == 0 {
+        crate::exit::fail!(
+            NotFound,
+            "no fixup!, amend! or squash! commits in {}",
+            range_spec
+        );
+    }
 
-            // Build selection mask for this group
-            let mut selected = vec![false; state.hunk.lines.len()];
+    crate::backup::create("squash")?;
+    let mut state = State::new(Operation::Squash, head_sha, &oldest);
+    if verify {
+        state.push("verify-tree", "1");
+    }
+    state.save()?;
+
+    // squash! commits open the editor on the combined message; keep it as is
+    let mut rebase_cmd = git_rebase()?;
+    rebase_cmd
+        .args(["-i", "--autosquash", "--autostash"])
+        .arg(base.as_deref().unwrap_or("--root"))
+        .env("GIT_SEQUENCE_EDITOR", "true")
+        .env("GIT_EDITOR", "true");
+    let output = run_rebase(&mut rebase_cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        eprintln!("error: rebase conflict while folding fixup commits");
+        print_resume_hint();
+        crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
+    }
+    State::clear()?;
+    if verify {
+        verify_tree("squash", head_sha)?;
+    }
 
-            if ranges.is_empty() {
-                // No line ranges: select all remaining change lines
-                for (i, line) in state.hunk.lines.iter().enumerate() {
-                    if (line.starts_with('+') || line.starts_with('-')) && !state.picked[i] {
-                        selected[i] = true;
-                    }
-                }
-            } else {
-                // Select lines in specified ranges
-                for (start, end) in ranges {
-                    #[allow(clippy::needless_range_loop)]
-                    for i in (*start - 1)..*end {
-                        if i < state.hunk.lines.len() {
-                            let line = &state.hunk.lines[i];
-                            // Only select change lines, not context
-                            if line.starts_with('+') || line.starts_with('-') {
-                                if state.picked[i] {
-                                    anyhow::bail!(
-                                        "line {} in hunk {} was already picked in a previous group",
-                                        i + 1,
-                                        id
-                                    );
-                                }
-                                selected[i] = true;
-                            }
-                        }
-                    }
-                }
-            }
+    let left = pending()?;
+    let folded = before - left;
+    eprintln!(
+        "folded {} fixup commit{}",
+        folded,
+        if folded == 1 { "" } else { "s" }
+    );
+    if left > 0 {
+        eprintln!(
+            "{} left unfolded: the commits they name are not in {}",
+            left, range_spec
+        );
+    }
+    Ok(())
+}
 
-            // Check we're actually selecting something
-            let has_changes = selected.iter().any(|&s| s);
-            if !has_changes {
-                // Skip this hunk if nothing to select
-                continue;
-            }
+/// Squash the commits in `base..tip` (tip below HEAD) into one commit and
+/// replay the commits after `tip` on top of it.
+fn squash_range(
+    base_sha: &str,
+    tip_sha: &str,
+    message: &SquashMessage,
+    trailers: &crate::trailer::Trailers,
+    force: bool,
+    preserve_author: bool,
+    verify: bool,
+) -> Result<()> {
+    for (ancestor, descendant, what) in
+        [(base_sha, tip_sha, "range end"), (tip_sha, "HEAD", "HEAD")]
+    {
+        let is_ancestor = Command::new("git")
+            .args(["merge-base", "--is-ancestor", ancestor, descendant])
+            .traced_status()
+            .context("failed to check ancestry")?;
+        if !is_ancestor.success() {
+            anyhow::bail!(
+                "commit {} is not an ancestor of {}",
+                &ancestor[..7.min(ancestor.len())],
+                what
+            );
+        }
+    }
 
-            // Build patch using stateful slicing
-            let patched_hunk = slice_hunk_with_state(&state.hunk, &state.picked, &selected)?;
-            combined_patch.push_str(&build_patch(&patched_hunk));
+    let count = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--count",
+        &format!("{}..{}", base_sha, tip_sha),
+    ]))?;
+    let count: usize = count.trim().parse().unwrap_or(0);
+    if count < 2 {
+        anyhow::bail!("nothing to squash: range contains {} commit(s)", count);
+    }
 
-            // Mark selected lines as picked for next groups
-            for (i, sel) in selected.iter().enumerate() {
-                if *sel {
-                    state.picked[i] = true;
-                }
-            }
+    if !force {
+        let merges = crate::diff::run_git_cmd(Command::new("git").args([
+            "rev-list",
+            "--merges",
+            &format!("{}..{}", base_sha, tip_sha),
+        ]))?;
+        if !merges.trim().is_empty() {
+            anyhow::bail!(
+                "range contains merge commits which will be flattened; use --force to proceed"
+            );
         }
+    }
+    let later_merges = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--merges",
+        &format!("{}..HEAD", tip_sha),
+    ]))?;
+    if !later_merges.trim().is_empty() {
+        anyhow::bail!("commits after the range include merges, which cannot be replayed");
+    }
 
-        if combined_patch.is_empty() {
-            anyhow::bail!("no changes selected for commit");
+    let oldest = oldest_in_range(base_sha, tip_sha)?;
+    let message = squash_message(message, trailers, &oldest, tip_sha)?;
+    let author = if preserve_author {
+        Some(commit_author(&oldest)?)
+    } else {
+        None
+    };
+
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim().to_string();
+    // Branch to move back onto after replaying, or the SHA if HEAD is detached
+    let orig_ref = crate::diff::run_git_cmd(Command::new("git").args([
+        "symbolic-ref",
+        "--quiet",
+        "--short",
+        "HEAD",
+    ]))
+    .map(|r| r.trim().to_string())
+    .unwrap_or_else(|_| head_sha.clone());
+
+    crate::backup::create("squash")?;
+    let stash = crate::autostash::push("squash")?;
+    let mut state = State::new(Operation::Squash, &head_sha, tip_sha);
+    if let Some(stash) = &stash {
+        state.push("stash", stash);
+    }
+    if verify {
+        state.push("verify-tree", "1");
+    }
+    state.save()?;
+
+    // Build the squashed commit on a detached HEAD at the range end
+    let built = (|| -> Result<String> {
+        crate::diff::run_git_cmd(
+            Command::new("git").args(["checkout", "--quiet", "--detach", tip_sha]),
+        )?;
+        crate::diff::run_git_cmd(
+            Command::new("git").args(["reset", "--quiet", "--soft", base_sha]),
+        )?;
+        let mut commit_cmd = git_commit();
+        commit_cmd.args(["--quiet", "-m", &message]);
+        if let Some((ident, date)) = &author {
+            commit_cmd.args(["--author", ident, "--date", date]);
         }
-
-        apply_patch(&combined_patch, &ApplyMode::Stage)?;
-
-        // Commit
-        let message = group.message_parts.join("\n\n");
-        let output = Command::new("git")
-            .args(["commit", "-m", &message])
-            .output()
-            .context("failed to commit")?;
+        let author_date = author.as_ref().map(|(_, date)| date.as_str());
+        crate::committer::configure_commit(&mut commit_cmd, tip_sha, author_date)?;
+        let output = commit_cmd.traced_output().context("failed to commit")?;
         if !output.status.success() {
             anyhow::bail!(
                 "git commit failed: {}",
                 String::from_utf8_lossy(&output.stderr)
             );
         }
+        let sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+        Ok(sha.trim().to_string())
+    })();
+    let squashed_sha = match built {
+        Ok(sha) => sha,
+        Err(e) => {
+            // Nothing was rewritten yet; go back to where we started
+            let _ = Command::new("git")
+                .args(["checkout", "--quiet", "--force", &orig_ref])
+                .traced_output();
+            State::clear()?;
+            if let Some(stash) = &stash {
+                crate::autostash::pop(stash)?;
+            }
+            return Err(e);
+        }
+    };
 
-        // Print only the subject line
-        let subject = message.lines().next().unwrap_or(&message);
-        eprintln!("committed: {}", subject);
+    let output = run_rebase(git_rebase()?.args(["--onto", &squashed_sha, tip_sha, &orig_ref]))?;
+    if !output.status.success() {
+        eprintln!("error: rebase conflict while replaying commits after the squash");
+        print_resume_hint();
+        crate::exit::fail!(
+            Conflict,
+            "rebase failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    State::clear()?;
+    if verify && let Err(e) = verify_tree("squash", &head_sha) {
+        if let Some(stash) = &stash {
+            crate::autostash::pop(stash)?;
+        }
+        return Err(e);
     }
 
-    // Stage and commit remaining changes (if any)
-    // Build patches for all unpicked change lines
-    let mut has_remaining = false;
-    let mut combined_patch = String::new();
+    eprintln!("squashed {} commits", count);
+    if let Some(stash) = &stash {
+        crate::autostash::pop(stash)?;
+    }
+    Ok(())
+}
 
-    for (id, state) in &hunk_states {
-        // Check if any change lines remain unpicked
-        let mut remaining_selected = vec![false; state.hunk.lines.len()];
-        for (i, line) in state.hunk.lines.iter().enumerate() {
-            if (line.starts_with('+') || line.starts_with('-')) && !state.picked[i] {
-                remaining_selected[i] = true;
-                has_remaining = true;
+/// Build the squashed commit's message, trailers included, for the commits
+/// `oldest` through `tip`.
+fn squash_message(
+    source: &SquashMessage,
+    trailers: &crate::trailer::Trailers,
+    oldest: &str,
+    tip: &str,
+) -> Result<String> {
+    let full_message = |sha: &str| -> Result<String> {
+        let msg =
+            crate::diff::run_git_cmd(Command::new("git").args(["log", "-1", "--format=%B", sha]))?;
+        Ok(msg.trim_end().to_string())
+    };
+    // The squashed commits, oldest first
+    let squashed = || -> Result<Vec<String>> {
+        let later = crate::diff::run_git_cmd(Command::new("git").args([
+            "rev-list",
+            "--reverse",
+            &format!("{}..{}", oldest, tip),
+        ]))?;
+        let mut commits = vec![oldest.to_string()];
+        commits.extend(later.lines().map(str::to_string));
+        Ok(commits)
+    };
+    let message = match source {
+        SquashMessage::Given(message) => message.clone(),
+        SquashMessage::From(commit) => full_message(&crate::rev::resolve_commit(commit)?)?,
+        SquashMessage::Combined => {
+            let mut messages = Vec::new();
+            for sha in squashed()? {
+                messages.push(full_message(&sha)?);
             }
+            messages.join("\n\n")
         }
-
-        if remaining_selected.iter().any(|&s| s) {
-            let patched_hunk =
-                slice_hunk_with_state(&state.hunk, &state.picked, &remaining_selected)?;
-            combined_patch.push_str(&build_patch(&patched_hunk));
-
-            // Mark as picked (for consistency, though we're done)
-            for (i, sel) in remaining_selected.iter().enumerate() {
-                if *sel {
-                    // We'd update state.picked here but we're borrowing immutably
-                    let _ = (id, sel, i); // suppress unused warnings
-                }
+        SquashMessage::Edited(source) => {
+            let message = squash_message(source, trailers, oldest, tip)?;
+            let mut hunks = Vec::new();
+            for sha in squashed()? {
+                hunks.extend(crate::diff::parse_diff(&crate::diff::run_git_diff_commit(
+                    &sha, None,
+                )?));
             }
+            return crate::editor::edit(&message, &file_stat_lines(&file_stats(&hunks)));
         }
+    };
+    trailers.apply(&message)
+}
+
+/// The first commit after `base` on the way to `tip`.
+fn oldest_in_range(base_sha: &str, tip_sha: &str) -> Result<String> {
+    let commits = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--reverse",
+        &format!("{}..{}", base_sha, tip_sha),
+    ]))?;
+    commits
+        .lines()
+        .next()
+        .map(|l| l.to_string())
+        .ok_or_else(|| anyhow::anyhow!("no commits in range {}..{}", &base_sha[..7], &tip_sha[..7]))
+}
+
+/// Author identity (`Name <email>`) and author date of a commit. The date is
+/// ISO 8601 for unambiguous parsing.
+fn commit_author(sha: &str) -> Result<(String, String)> {
+    let ident = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%an <%ae>",
+        sha,
+    ]))?;
+    let date =
+        crate::diff::run_git_cmd(Command::new("git").args(["log", "-1", "--format=%aI", sha]))?;
+    Ok((ident.trim().to_string(), date.trim().to_string()))
+}
+
+/// Rearrange the commits in `<base>..HEAD` into the given order, oldest first.
+///
+/// Each entry of `order` is either a commit or a 1-based position in the
+/// range (1 = oldest), and every commit in the range must appear exactly once.
+pub fn reorder(range: &str, order: &[String], verify: bool) -> Result<()> {
+    check_no_rebase_in_progress()?;
+
+    let (base_sha, tip_sha) = crate::rev::resolve_range(range)?;
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim();
+    if tip_sha != head_sha {
+        crate::exit::fail!(Usage, "reorder only supports ranges ending at HEAD");
     }
 
-    if has_remaining {
-        apply_patch(&combined_patch, &ApplyMode::Stage)?;
+    let range_spec = format!("{}..HEAD", base_sha);
+    let commits: Vec<String> =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-list", "--reverse", &range_spec]))?
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+    if commits.is_empty() {
+        anyhow::bail!("no commits in range {}", range);
+    }
 
-        let output = Command::new("git")
-            .args(["commit", "-m", rest_msg])
-            .output()
-            .context("failed to commit remaining")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
+    let merges =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-list", "--merges", &range_spec]))?;
+    if !merges.trim().is_empty() {
+        anyhow::bail!("range contains merge commits, which reorder does not support");
+    }
 
-        // Print only the subject line
-        let subject = rest_msg.lines().next().unwrap_or(rest_msg);
-        eprintln!("committed: {}", subject);
+    let order = resolve_order(order, &commits)?;
+    if order == commits {
+        eprintln!("commits already in the requested order");
+        return Ok(());
     }
 
-    // Continue rebase if non-HEAD
-    if !is_head {
-        let output = Command::new("git")
-            .args(["rebase", "--continue"])
-            .output()
-            .context("failed to continue rebase")?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            eprintln!("error: rebase continue failed");
-            eprintln!("resolve conflicts and run: git rebase --continue");
-            eprintln!("or abort with: git rebase --abort");
-            anyhow::bail!("rebase continue failed: {}", stderr);
-        }
+    crate::backup::create("reorder")?;
+    let mut state = State::new(Operation::Reorder, head_sha, &base_sha);
+    if verify {
+        state.push("verify-tree", "1");
+    }
+    state.save()?;
+
+    let editor = crate::sequence::editor_command(&[], &order)?;
+    let output = run_rebase(
+        git_rebase()?
+            .args(["-i", "--autostash", &base_sha])
+            .env("GIT_SEQUENCE_EDITOR", editor),
+    )?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        eprintln!("error: rebase conflict while reordering commits");
+        print_resume_hint();
+        crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
+    }
+    State::clear()?;
+    if verify {
+        verify_tree("reorder", head_sha)?;
+    }
+
+    eprintln!("reordered {} commits", commits.len());
+    let log = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "--reverse",
+        "--format=%h %s",
+        &range_spec,
+    ]))?;
+    for line in log.lines() {
+        eprintln!("  {}", line);
     }
 
     Ok(())
 }
 
-fn check_no_rebase_in_progress() -> Result<()> {
-    for dir_name in ["rebase-merge", "rebase-apply"] {
-        let check = Command::new("git")
-            .args(["rev-parse", "--git-path", dir_name])
-            .output()
-            .context("failed to check rebase state")?;
-        let dir = String::from_utf8_lossy(&check.stdout).trim().to_string();
-        if std::path::Path::new(&dir).exists() {
-            anyhow::bail!("rebase already in progress");
+/// Stop a rebase at `commit` with its changes unstaged in the working tree,
+/// so they can be re-committed freely before `git-surgeon continue`.
+pub fn edit_commit(commit: &str) -> Result<()> {
+    check_no_rebase_in_progress()?;
+
+    let target_sha = crate::rev::resolve_commit(commit)?;
+    if crate::rev::is_root(&target_sha) {
+        anyhow::bail!("cannot edit the root commit");
+    }
+    let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let head_sha = head_sha.trim();
+
+    let is_ancestor = Command::new("git")
+        .args(["merge-base", "--is-ancestor", &target_sha, "HEAD"])
+        .traced_status()
+        .context("failed to check ancestry")?;
+    if !is_ancestor.success() {
+        anyhow::bail!("commit {} is not an ancestor of HEAD", &target_sha[..7]);
+    }
+
+    let info = crate::diff::run_git_cmd(Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%h %s",
+        &target_sha,
+    ]))?;
+
+    crate::backup::create("edit-commit")?;
+    State::new(Operation::EditCommit, head_sha, &target_sha).save()?;
+    if let Err(e) = start_rebase_at_commit(&target_sha) {
+        if rebase_in_progress()? {
+            print_resume_hint();
+        } else {
+            State::clear()?;
         }
+        return Err(e);
     }
+
+    eprintln!("stopped at {}; its changes are unstaged", info.trim());
+    eprintln!("commit them (e.g. git-surgeon commit <id> -m ...), then run: git-surgeon continue");
+    eprintln!("anything left uncommitted is committed with the original message");
     Ok(())
 }
 
-/// Squash commits from <commit>..HEAD into a single commit.
-pub fn squash(commit: &str, message: &str, force: bool, preserve_author: bool) -> Result<()> {
-    check_no_rebase_in_progress()?;
+/// Commit whatever `edit-commit` left in the working tree with the original
+/// commit's message and author, including files the commit had added.
+fn commit_edit_leftovers(state: &State) -> Result<()> {
+    if !rebase_in_progress()? {
+        return Ok(());
+    }
 
-    // Autostash if working tree is dirty (tracked files only)
+    let (toplevel, added) = added_files(&state.target)?;
+    let present: Vec<&String> = added.iter().filter(|f| toplevel.join(f).exists()).collect();
+
+    crate::diff::run_git_cmd(
+        Command::new("git")
+            .args(["add", "-u"])
+            .current_dir(&toplevel),
+    )?;
+    if !present.is_empty() {
+        crate::diff::run_git_cmd(
+            Command::new("git")
+                .args(["add", "--"])
+                .args(&present)
+                .current_dir(&toplevel),
+        )?;
+    }
+
+    if !has_staged_changes()? {
+        return Ok(());
+    }
+    let output = git_commit()
+        .args(["--quiet", "-C", &state.target])
+        .traced_output()
+        .context("failed to commit remaining changes")?;
+    if !output.status.success() {
+        print_resume_hint();
+        anyhow::bail!(
+            "git commit failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    Ok(())
+}
+
+/// Files added by `sha` (relative to the returned top-level directory).
+/// After `edit-commit` resets the commit, these are untracked.
+fn added_files(sha: &str) -> Result<(std::path::PathBuf, Vec<String>)> {
+    let toplevel =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--show-toplevel"]))?;
+    let added = crate::diff::run_git_cmd(Command::new("git").args([
+        "diff-tree",
+        "-r",
+        "--name-only",
+        "--no-commit-id",
+        "--diff-filter=A",
+        "-z",
+        sha,
+    ]))?;
+    let files = added
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| f.to_string())
+        .collect();
+    Ok((std::path::PathBuf::from(toplevel.trim()), files))
+}
+
+/// Delete untracked copies of the files `edit-commit` unstaged, so that
+/// `rebase --abort` can check the original commits out again. Files whose
+/// content was changed since are left alone.
+fn remove_edit_leftovers(state: &State) -> Result<()> {
+    let (toplevel, added) = added_files(&state.target)?;
+    for file in added {
+        let path = toplevel.join(&file);
+        if !path.exists() {
+            continue;
+        }
+        let untracked = crate::diff::run_git_cmd(
+            Command::new("git")
+                .args(["ls-files", "--others", "--", &file])
+                .current_dir(&toplevel),
+        )?;
+        if untracked.trim().is_empty() {
+            continue;
+        }
+        let current = crate::diff::run_git_cmd(
+            Command::new("git")
+                .args(["hash-object", "--", &file])
+                .current_dir(&toplevel),
+        )?;
+        let original = crate::diff::run_git_cmd(
+            Command::new("git").args(["rev-parse", &format!("{}:{}", state.target, file)]),
+        )?;
+        if current.trim() == original.trim() {
+            std::fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Remove commits from history via a non-interactive rebase. With
+/// `keep_changes`, the dropped commits' changes are left unstaged in the
+/// working tree afterwards.
+pub fn drop_commits(
+    commits: &[String],
+    keep_changes: bool,
+    yes: bool,
+    force_published: bool,
+) -> Result<()> {
     let status = Command::new("git")
         .args(["status", "--porcelain", "--untracked-files=no"])
-        .output()
+        .traced_output()
         .context("failed to check git status")?;
-    let needs_stash = !String::from_utf8_lossy(&status.stdout).trim().is_empty();
-
-    if needs_stash {
-        let output = Command::new("git")
-            .args(["stash", "push", "-m", "git-surgeon squash autostash"])
-            .output()
-            .context("failed to stash changes")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git stash failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
+    if !String::from_utf8_lossy(&status.stdout).trim().is_empty() {
+        crate::exit::fail!(
+            RepoState,
+            "working tree is dirty; commit or stash changes before dropping"
+        );
     }
 
-    // Resolve target commit SHA
-    let target_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", commit]))
-        .with_context(|| format!("could not resolve commit '{}'", commit))?;
-    let target_sha = target_sha.trim();
+    check_no_rebase_in_progress()?;
 
     let head_sha = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
     let head_sha = head_sha.trim();
 
-    if target_sha == head_sha {
-        anyhow::bail!("nothing to squash: target commit is HEAD");
+    // Resolve and order the commits by distance from HEAD, oldest first
+    let mut targets: Vec<(usize, String)> = Vec::new();
+    for commit in commits {
+        let sha = crate::rev::resolve_commit(commit)?;
+        let is_ancestor = Command::new("git")
+            .args(["merge-base", "--is-ancestor", &sha, "HEAD"])
+            .traced_status()
+            .context("failed to check ancestry")?;
+        if !is_ancestor.success() {
+            anyhow::bail!("commit {} is not an ancestor of HEAD", &sha[..7]);
+        }
+        if targets.iter().any(|(_, s)| *s == sha) {
+            anyhow::bail!("commit {} listed more than once", &sha[..7]);
+        }
+        let distance = crate::diff::run_git_cmd(Command::new("git").args([
+            "rev-list",
+            "--count",
+            &format!("{}..HEAD", sha),
+        ]))?;
+        targets.push((distance.trim().parse().unwrap_or(0), sha));
     }
+    targets.sort_by_key(|(distance, _)| std::cmp::Reverse(*distance));
+    let oldest = targets[0].1.as_str();
+    crate::rev::check_history_present(oldest)?;
+    if !force_published {
+        crate::rev::check_unpublished(oldest)?;
+    }
+    let is_root = crate::rev::is_root(oldest);
 
-    // Extract author and date from target commit if preserving
-    let (author, author_date) = if preserve_author {
-        let ident = crate::diff::run_git_cmd(Command::new("git").args([
-            "log",
-            "-1",
-            "--format=%an <%ae>",
-            target_sha,
-        ]))?
-        .trim()
-        .to_string();
-
-        let date = crate::diff::run_git_cmd(Command::new("git").args([
-            "log",
-            "-1",
-            "--format=%aI", // ISO 8601 format for unambiguous parsing
-            target_sha,
-        ]))?
-        .trim()
-        .to_string();
-
-        (Some(ident), Some(date))
+    let range_start = if is_root {
+        oldest.to_string()
     } else {
-        (None, None)
+        format!("{}~1", oldest)
     };
+    let merges = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--merges",
+        &format!("{}..HEAD", range_start),
+    ]))?;
+    if !merges.trim().is_empty() {
+        anyhow::bail!("range contains merge commits, which drop does not support");
+    }
 
-    // Verify target is ancestor of HEAD
-    let is_ancestor = Command::new("git")
-        .args(["merge-base", "--is-ancestor", target_sha, "HEAD"])
-        .status()
-        .context("failed to check ancestry")?;
-    if !is_ancestor.success() {
-        anyhow::bail!(
-            "commit {} is not an ancestor of HEAD",
-            &target_sha[..7.min(target_sha.len())]
-        );
+    let summaries: Vec<String> = targets
+        .iter()
+        .map(|(_, sha)| {
+            crate::diff::run_git_cmd(Command::new("git").args(["log", "-1", "--format=%h %s", sha]))
+                .map(|s| s.trim().to_string())
+        })
+        .collect::<Result<_>>()?;
+    // Dropped changes are gone, unless kept
+    if !keep_changes {
+        eprintln!("will drop:");
+        for summary in &summaries {
+            eprintln!("  {}", summary);
+        }
+        let count = summaries.len();
+        crate::confirm::confirm(
+            &format!("drop {} commit{}", count, if count == 1 { "" } else { "s" }),
+            yes,
+        )?;
     }
 
-    // Check for merge commits in range (they will be flattened)
-    if !force {
-        let merges = Command::new("git")
-            .args(["rev-list", "--merges", &format!("{}..HEAD", target_sha)])
-            .output()
-            .context("failed to check for merge commits")?;
-        if !String::from_utf8_lossy(&merges.stdout).trim().is_empty() {
-            anyhow::bail!(
-                "range contains merge commits which will be flattened; use --force to proceed"
-            );
+    crate::backup::create("drop")?;
+    let mut state = State::new(Operation::Drop, head_sha, oldest);
+    if keep_changes {
+        let mut patch = String::new();
+        for (_, sha) in &targets {
+            patch.push_str(&crate::diff::run_git_cmd(Command::new("git").args([
+                "diff-tree",
+                "-p",
+                "--binary",
+                "--root",
+                "--no-commit-id",
+                sha,
+            ]))?);
         }
+        state.push("kept-patch", &patch);
     }
+    state.save()?;
 
-    // Check if target is root commit
-    let is_root = Command::new("git")
-        .args(["rev-parse", "--verify", &format!("{}^", target_sha)])
-        .output()
-        .map(|o| !o.status.success())
-        .unwrap_or(false);
+    let marks: Vec<crate::sequence::Mark> = targets
+        .iter()
+        .map(|(_, sha)| crate::sequence::Mark::new(sha, crate::sequence::TodoAction::Drop))
+        .collect();
+    let editor = crate::sequence::editor_command(&marks, &[])?;
 
+    let mut rebase_cmd = git_rebase()?;
+    rebase_cmd.arg("-i");
     if is_root {
-        // For root commit: delete HEAD ref to create orphan state, then commit
-        // This preserves hooks and GPG signing (unlike commit-tree)
-        let output = Command::new("git")
-            .args(["update-ref", "-d", "HEAD"])
-            .output()
-            .context("failed to delete HEAD ref")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git update-ref failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-
-        // Commit (git treats this as the first commit)
-        let mut commit_cmd = Command::new("git");
-        commit_cmd.args(["commit", "-m", message]);
-        if let Some(ref auth) = author {
-            commit_cmd.args(["--author", auth]);
-        }
-        if let Some(ref date) = author_date {
-            commit_cmd.args(["--date", date]);
-        }
-        let output = commit_cmd.output().context("failed to commit")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
+        rebase_cmd.arg("--root");
     } else {
-        // Normal case: reset to parent of target
-        let output = Command::new("git")
-            .args(["reset", "--soft", &format!("{}^", target_sha)])
-            .output()
-            .context("failed to reset")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git reset failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
-
-        // Commit with new message
-        let mut commit_cmd = Command::new("git");
-        commit_cmd.args(["commit", "-m", message]);
-        if let Some(ref auth) = author {
-            commit_cmd.args(["--author", auth]);
-        }
-        if let Some(ref date) = author_date {
-            commit_cmd.args(["--date", date]);
-        }
-        let output = commit_cmd.output().context("failed to commit")?;
-        if !output.status.success() {
-            anyhow::bail!(
-                "git commit failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-        }
+        rebase_cmd.arg(&range_start);
     }
+    rebase_cmd.env("GIT_SEQUENCE_EDITOR", editor);
 
-    // Count how many commits were squashed
-    let count = crate::diff::run_git_cmd(Command::new("git").args([
-        "rev-list",
-        "--count",
-        &format!("{}..{}", target_sha, head_sha),
-    ]))?;
-    let count: i32 = count.trim().parse().unwrap_or(0);
+    let output = run_rebase(&mut rebase_cmd)?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        eprintln!("error: rebase conflict while dropping commits");
+        print_resume_hint();
+        crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
+    }
 
-    eprintln!("squashed {} commits", count + 1);
+    restore_dropped_changes(&state)?;
+    State::clear()?;
 
-    // Restore stashed changes
-    if needs_stash {
-        let output = Command::new("git")
-            .args(["stash", "pop"])
-            .output()
-            .context("failed to pop stash")?;
-        if !output.status.success() {
-            eprintln!(
-                "warning: stash pop failed (conflicts?), run 'git stash pop' manually: {}",
-                String::from_utf8_lossy(&output.stderr).trim()
-            );
-        }
+    for summary in &summaries {
+        eprintln!("dropped {}", summary);
     }
+    Ok(())
+}
 
+/// Apply the changes saved by `drop --keep-changes` to the working tree.
+fn restore_dropped_changes(state: &State) -> Result<()> {
+    let Some(patch) = state.get("kept-patch") else {
+        return Ok(());
+    };
+    if patch.is_empty() {
+        return Ok(());
+    }
+    if let Err(e) = apply_patch(patch, &ApplyMode::Worktree) {
+        let path = crate::state::surgeon_path("dropped.patch")?;
+        std::fs::write(&path, patch)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        eprintln!(
+            "warning: could not apply dropped changes ({}); saved them to {}",
+            e.to_string().trim(),
+            path.display()
+        );
+    }
     Ok(())
 }
 
+/// Map `--order` entries to full SHAs of commits in the range (oldest first).
+fn resolve_order(order: &[String], commits: &[String]) -> Result<Vec<String>> {
+    let mut resolved = Vec::with_capacity(order.len());
+    for entry in order {
+        // Short numbers are positions; SHAs need at least 4 hex digits
+        let sha = if entry.len() < 4 && entry.chars().all(|c| c.is_ascii_digit()) {
+            let pos: usize = entry.parse()?;
+            if pos == 0 || pos > commits.len() {
+                anyhow::bail!(
+                    "position {} out of range (range has {} commits)",
+                    pos,
+                    commits.len()
+                );
+            }
+            commits[pos - 1].clone()
+        } else {
+            let sha = crate::rev::resolve_commit(entry)?;
+            if !commits.contains(&sha) {
+                anyhow::bail!("commit {} is not in the range", entry);
+            }
+            sha
+        };
+        if resolved.contains(&sha) {
+            anyhow::bail!("commit {} listed more than once", entry);
+        }
+        resolved.push(sha);
+    }
+    if let Some(missing) = commits.iter().find(|c| !resolved.contains(c)) {
+        anyhow::bail!(
+            "--order must list every commit in the range; missing {}",
+            &missing[..7]
+        );
+    }
+    Ok(resolved)
+}
+
 fn start_rebase_at_commit(target_sha: &str) -> Result<()> {
-    let is_root = Command::new("git")
-        .args(["rev-parse", "--verify", &format!("{}^", target_sha)])
-        .output()
-        .map(|o| !o.status.success())
-        .unwrap_or(false);
-
-    // We need a custom sequence editor that marks the target commit as "edit"
-    let short_sha = &target_sha[..7.min(target_sha.len())];
-    // Use sed to change "pick <sha>" to "edit <sha>" for the target commit
-    let sed_script = format!("s/^pick {} /edit {} /", short_sha, short_sha);
-
-    let mut rebase_cmd = Command::new("git");
-    rebase_cmd.args(["rebase", "-i", "--autostash"]);
+    let is_root = crate::rev::is_root(target_sha);
+
+    // Re-invoke ourselves as the sequence editor to mark the target commit as "edit"
+    let editor = crate::sequence::editor_command(
+        &[crate::sequence::Mark::new(
+            target_sha,
+            crate::sequence::TodoAction::Edit,
+        )],
+        &[],
+    )?;
+
+    let mut rebase_cmd = git_rebase()?;
+    rebase_cmd.args(["-i", "--autostash"]);
     if is_root {
         rebase_cmd.arg("--root");
     } else {
         rebase_cmd.arg(format!("{}~1", target_sha));
     }
-    rebase_cmd.env(
-        "GIT_SEQUENCE_EDITOR",
-        format!("sed -i.bak '{}'", sed_script),
-    );
+    rebase_cmd.env("GIT_SEQUENCE_EDITOR", editor);
 
-    let output = rebase_cmd.output().context("failed to start rebase")?;
+    let output = rebase_cmd
+        .traced_output()
+        .context("failed to start rebase")?;
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("rebase failed: {}", stderr);
+        crate::exit::fail!(Conflict, "rebase failed: {}", stderr);
     }
 
     // Now we should be paused at the target commit. Reset it.
     let output = Command::new("git")
         .args(["reset", "HEAD~"])
-        .output()
+        .traced_output()
         .context("failed to reset commit")?;
     if !output.status.success() {
         anyhow::bail!(
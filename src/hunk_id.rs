@@ -1,30 +1,101 @@
+use anyhow::Result;
 use sha1::{Digest, Sha1};
 use std::collections::HashMap;
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use crate::diff::DiffHunk;
+use crate::output::Traced;
+
+/// Set from `--id-scheme` or `surgeon.idScheme` before any ID is assigned.
+static CONTENT_ONLY: AtomicBool = AtomicBool::new(false);
+
+/// Hex digits in an ID, from `surgeon.idLength`; set before any ID is
+/// assigned.
+static ID_LENGTH: AtomicUsize = AtomicUsize::new(DEFAULT_ID_LENGTH);
+
+const DEFAULT_ID_LENGTH: usize = 7;
+
+/// What a hunk's ID is hashed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdScheme {
+    /// The file path and every line of the hunk, context included
+    Full,
+    /// The file path and only the changed lines, so edits nearby that shift
+    /// the context leave the ID alone
+    Content,
+}
+
+pub fn parse_id_scheme(s: &str) -> Result<IdScheme, String> {
+    match s {
+        "full" => Ok(IdScheme::Full),
+        "content" => Ok(IdScheme::Content),
+        _ => Err("expected full or content".to_string()),
+    }
+}
+
+/// Use `flag`, or else the `surgeon.idScheme` config, for the IDs of this
+/// command.
+pub fn configure_scheme(flag: Option<IdScheme>) -> Result<()> {
+    let scheme = match flag {
+        Some(scheme) => scheme,
+        None => {
+            let output = Command::new("git")
+                .args(["config", "--get", "surgeon.idScheme"])
+                .traced_output();
+            let value = output
+                .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+                .unwrap_or_default();
+            if value.is_empty() {
+                IdScheme::Full
+            } else {
+                parse_id_scheme(&value)
+                    .map_err(|e| anyhow::anyhow!("invalid surgeon.idScheme '{}': {}", value, e))?
+            }
+        }
+    };
+    CONTENT_ONLY.store(scheme == IdScheme::Content, Ordering::Relaxed);
+
+    let length = crate::config::get_usize("surgeon.idLength")?.unwrap_or(DEFAULT_ID_LENGTH);
+    if !(4..=40).contains(&length) {
+        anyhow::bail!("invalid surgeon.idLength '{}': expected 4 to 40", length);
+    }
+    ID_LENGTH.store(length, Ordering::Relaxed);
+    Ok(())
+}
+
+fn id_length() -> usize {
+    ID_LENGTH.load(Ordering::Relaxed)
+}
 
 /// Compute raw hash for a hunk (before collision suffix).
-/// Hashes: file path + all hunk lines (context + changes).
+/// Hashes: file path + all hunk lines (context + changes), or with
+/// `content_only` the changed lines alone.
 /// Excludes @@ header line numbers so IDs survive line shifts.
-fn compute_raw_id(hunk: &DiffHunk) -> String {
+fn compute_raw_id(hunk: &DiffHunk, content_only: bool) -> String {
     let mut hasher = Sha1::new();
     hasher.update(hunk.file.as_bytes());
     for line in &hunk.lines {
+        // "\ No newline at end of file" belongs to the change
+        if content_only && !line.starts_with(['+', '-', '\\']) {
+            continue;
+        }
         hasher.update(line.as_bytes());
         hasher.update(b"\n");
     }
     let result = hasher.finalize();
-    hex::encode(&result[..4]) // 8 hex chars, truncate to 7 below
+    hex::encode(result) // truncated to the ID length below
 }
 
 /// Assign unique IDs to hunks. Duplicates get -2, -3, etc.
 pub fn assign_ids(hunks: &[DiffHunk]) -> Vec<(String, &DiffHunk)> {
+    let content_only = CONTENT_ONLY.load(Ordering::Relaxed);
     let mut seen: HashMap<String, usize> = HashMap::new();
     let mut result = Vec::new();
 
     for hunk in hunks {
-        let raw = compute_raw_id(hunk);
-        let id_prefix = &raw[..7];
+        let raw = compute_raw_id(hunk, content_only);
+        let id_prefix = &raw[..id_length()];
         let count = seen.entry(id_prefix.to_string()).or_insert(0);
         *count += 1;
         let id = if *count == 1 {
@@ -37,3 +108,192 @@ pub fn assign_ids(hunks: &[DiffHunk]) -> Vec<(String, &DiffHunk)> {
 
     result
 }
+
+/// Split a hunk reference from its inline `:range` suffix, without looking at
+/// the diff. A positional `path:N` reference keeps its `:N`; only a second
+/// colon starts the range (`src/main.rs:2:1-3`).
+pub fn split_range(raw: &str) -> (&str, Option<&str>) {
+    let Some((head, _)) = raw.split_once(':') else {
+        return (raw, None);
+    };
+    if head.starts_with('@') || looks_like_id(head) {
+        let (id, range) = raw.split_at(head.len());
+        return (id, Some(&range[1..]));
+    }
+    // A path may itself contain colons: the reference ends after the first
+    // all-digit segment
+    let mut end = 0;
+    for segment in raw.split(':') {
+        if end > 0 && !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+            let reference_end = end + segment.len();
+            return match raw.get(reference_end + 1..) {
+                Some(range) => (&raw[..reference_end], Some(range)),
+                None => (raw, None),
+            };
+        }
+        end += segment.len() + 1;
+    }
+    (raw, None)
+}
+
+/// Whether `s` has the shape of an assigned ID, optionally with the `s-`
+/// prefix `hunks --all` gives staged hunks.
+fn looks_like_id(s: &str) -> bool {
+    let s = s.strip_prefix(crate::hunk::STAGED_ID_PREFIX).unwrap_or(s);
+    let (hash, suffix) = s.split_at(s.len().min(id_length()));
+    hash.len() == id_length()
+        && hash.bytes().all(|b| b.is_ascii_hexdigit())
+        && (suffix.is_empty()
+            || suffix
+                .strip_prefix('-')
+                .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())))
+}
+
+/// Turn a reference into the ID it names in `identified`, the hunks listed
+/// from `location` (`unstaged`, `staged` or `commit`): `@N` for the Nth hunk
+/// of the listing, `path:N` for the Nth hunk of that file, and an ID whose
+/// hunk has been edited since `hunks` listed it for the hunk it became (see
+/// `registry::resolve_stale`). Any `:range` suffix is kept; other references
+/// are returned unchanged.
+pub fn resolve_ref(
+    identified: &[(String, &DiffHunk)],
+    location: &str,
+    raw: &str,
+) -> Result<String> {
+    let resolved = resolve_position(identified, raw)?;
+    let (id, range) = split_range(&resolved);
+    if !looks_like_id(id)
+        || id.starts_with(crate::hunk::STAGED_ID_PREFIX)
+        || identified.iter().any(|(hunk_id, _)| hunk_id == id)
+    {
+        return Ok(resolved);
+    }
+    Ok(
+        match crate::registry::resolve_stale(id, location, identified)? {
+            Some(current) => match range {
+                Some(range) => format!("{}:{}", current, range),
+                None => current,
+            },
+            None => resolved,
+        },
+    )
+}
+
+fn resolve_position(identified: &[(String, &DiffHunk)], raw: &str) -> Result<String> {
+    let (reference, range) = split_range(raw);
+    let with_range = |id: &str| match range {
+        Some(range) => format!("{}:{}", id, range),
+        None => id.to_string(),
+    };
+    if let Some(n) = reference.strip_prefix('@') {
+        let n = parse_position(n, raw)?;
+        let (id, _) = identified.get(n - 1).ok_or_else(|| {
+            crate::exit::failure!(
+                NotFound,
+                "no hunk {}: the listing has {} hunk(s)",
+                reference,
+                identified.len()
+            )
+        })?;
+        return Ok(with_range(id));
+    }
+    let Some((path, n)) = reference.rsplit_once(':') else {
+        return Ok(raw.to_string());
+    };
+    if looks_like_id(path) {
+        return Ok(raw.to_string());
+    }
+    let n = parse_position(n, raw)?;
+    let in_file: Vec<&String> = identified
+        .iter()
+        .filter(|(_, hunk)| hunk.file == path)
+        .map(|(id, _)| id)
+        .collect();
+    if in_file.is_empty() {
+        crate::exit::fail!(NotFound, "no hunks in {}", path);
+    }
+    let id = in_file.get(n - 1).ok_or_else(|| {
+        crate::exit::failure!(
+            NotFound,
+            "no hunk {}: {} has {} hunk(s)",
+            reference,
+            path,
+            in_file.len()
+        )
+    })?;
+    Ok(with_range(id))
+}
+
+fn parse_position(n: &str, raw: &str) -> Result<usize> {
+    match n.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => anyhow::bail!("invalid hunk position in '{}' (positions start at 1)", raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_range() {
+        assert_eq!(split_range("abc1234"), ("abc1234", None));
+        assert_eq!(split_range("abc1234:1-3"), ("abc1234", Some("1-3")));
+        assert_eq!(split_range("abc1234-2:4"), ("abc1234-2", Some("4")));
+        assert_eq!(split_range("@3:2,5"), ("@3", Some("2,5")));
+        assert_eq!(split_range("src/main.rs:2"), ("src/main.rs:2", None));
+        assert_eq!(
+            split_range("src/main.rs:2:1-3"),
+            ("src/main.rs:2", Some("1-3"))
+        );
+        assert_eq!(split_range("a:b.txt:1"), ("a:b.txt:1", None));
+    }
+
+    #[test]
+    fn test_content_only_ignores_context() {
+        let hunk = |context: &str| {
+            let diff = format!(
+                "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n\
+                 @@ -1,2 +1,2 @@\n {}\n-old\n+new\n",
+                context
+            );
+            crate::diff::parse_diff(&diff).remove(0)
+        };
+        let (before, after) = (hunk("ctx"), hunk("edited ctx"));
+        assert_ne!(
+            compute_raw_id(&before, false),
+            compute_raw_id(&after, false)
+        );
+        assert_eq!(compute_raw_id(&before, true), compute_raw_id(&after, true));
+    }
+
+    #[test]
+    fn test_resolve_position() {
+        let diff = "diff --git a/a.txt b/a.txt\n--- a/a.txt\n+++ b/a.txt\n\
+                    @@ -1 +1 @@\n-a\n+A\n@@ -9 +9 @@\n-i\n+I\n\
+                    diff --git a/b.txt b/b.txt\n--- a/b.txt\n+++ b/b.txt\n\
+                    @@ -1 +1 @@\n-b\n+B\n";
+        let hunks = crate::diff::parse_diff(diff);
+        let identified = assign_ids(&hunks);
+        let id = |i: usize| identified[i].0.clone();
+
+        assert_eq!(resolve_position(&identified, "@3").unwrap(), id(2));
+        assert_eq!(resolve_position(&identified, "a.txt:2").unwrap(), id(1));
+        assert_eq!(
+            resolve_position(&identified, "b.txt:1:1").unwrap(),
+            format!("{}:1", id(2))
+        );
+        assert_eq!(
+            resolve_position(&identified, "abc1234:2").unwrap(),
+            "abc1234:2"
+        );
+        let err = resolve_position(&identified, "@4").unwrap_err().to_string();
+        assert!(err.contains("listing has 3 hunk(s)"), "{}", err);
+        let err = resolve_position(&identified, "a.txt:3")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a.txt has 2 hunk(s)"), "{}", err);
+        assert!(resolve_position(&identified, "c.txt:1").is_err());
+        assert!(resolve_position(&identified, "@0").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+use crate::diff::DiffHunk;
+use crate::hunk_id::assign_ids;
+use crate::json::{self, Value};
+
+/// `interdiff`: list as hunks how the change `new` makes differs from the
+/// change `old` makes, whatever their parents: `old`'s change is replayed on
+/// `new`'s parent, and the result is compared with `new`. `+` lines are what
+/// only `new` has. Files `old`'s change does not apply to there are left out
+/// with a note, since comparing them would show all of `new`'s change.
+pub fn interdiff(old: &str, new: &str, full: bool, json: bool) -> Result<()> {
+    let old_sha = crate::rev::resolve_commit(old)?;
+    let new_sha = crate::rev::resolve_commit(new)?;
+    let (replayed, skipped) = replay(&old_sha, &new_sha)?;
+    for file in &skipped {
+        eprintln!(
+            "note: {}'s change to {} does not apply on {}'s parent; compare with 'git range-diff {}^! {}^!'",
+            &old_sha[..7],
+            file,
+            &new_sha[..7],
+            &old_sha[..7],
+            &new_sha[..7]
+        );
+    }
+
+    let diff_output = crate::diff::run_git_diff_trees(&replayed, &new_sha, &skipped)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    if json {
+        print_json(&old_sha, &new_sha, &identified, &skipped);
+        return Ok(());
+    }
+    if identified.is_empty() {
+        eprintln!(
+            "no differences: {} and {} make the same changes",
+            &old_sha[..7],
+            &new_sha[..7]
+        );
+        return Ok(());
+    }
+    eprintln!("interdiff {} -> {}", &old_sha[..7], &new_sha[..7]);
+    for (id, hunk) in &identified {
+        let (additions, deletions) = crate::hunk::count_changes(hunk);
+        let func = crate::hunk::function_context(hunk);
+        let func_part = if func.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", func)
+        };
+        println!(
+            "{} {}{} (+{} -{})",
+            crate::color::id(id),
+            hunk.file,
+            func_part,
+            additions,
+            deletions
+        );
+        let width = hunk.lines.len().to_string().len();
+        for (i, line) in hunk.lines.iter().enumerate() {
+            if full {
+                println!("{:>w$}:{}", i + 1, crate::color::diff_line(line), w = width);
+            } else if line.starts_with('+') || line.starts_with('-') {
+                println!("  {}", crate::color::diff_line(line));
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
+
+/// The tree of `new`'s parent with `old`'s change applied, file by file, and
+/// the files whose change did not apply.
+fn replay(old: &str, new: &str) -> Result<(String, Vec<String>)> {
+    let index = crate::state::surgeon_path(&format!("interdiff-index-{}", std::process::id()))?;
+    if let Some(dir) = index.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let scratch_git = || {
+        let mut cmd = Command::new("git");
+        cmd.env("GIT_INDEX_FILE", &index);
+        cmd
+    };
+    let result = (|| {
+        if crate::rev::is_root(new) {
+            crate::diff::run_git_cmd(scratch_git().args(["read-tree", "--empty"]))?;
+        } else {
+            crate::diff::run_git_cmd(scratch_git().args(["read-tree", &format!("{}^", new)]))?;
+        }
+        let files = crate::diff::run_git_cmd(Command::new("git").args([
+            "show",
+            "--pretty=",
+            "--no-renames",
+            "--name-only",
+            "-z",
+            old,
+        ]))?;
+        let mut skipped = Vec::new();
+        for file in files.split('\0').filter(|f| !f.is_empty()) {
+            let patch = crate::diff::run_git_cmd(
+                Command::new("git")
+                    .args(["show", "--pretty=", "--no-renames", "--binary"])
+                    .args(["--no-color", "--no-ext-diff", "--no-textconv"])
+                    .arg(old)
+                    .arg("--")
+                    .arg(format!(":(top,literal){}", file)),
+            )?;
+            if crate::patch::apply_with(scratch_git().args(["apply", "--cached"]), &patch).is_err()
+            {
+                skipped.push(file.to_string());
+            }
+        }
+        let tree = crate::diff::run_git_cmd(scratch_git().arg("write-tree"))
+            .context("failed to write the replayed tree")?;
+        Ok((tree.trim().to_string(), skipped))
+    })();
+    let _ = std::fs::remove_file(&index);
+    result
+}
+
+fn print_json(old: &str, new: &str, identified: &[(String, &DiffHunk)], skipped: &[String]) {
+    let entries: Vec<Value> = identified
+        .iter()
+        .map(|(id, hunk)| {
+            let (additions, deletions) = crate::hunk::count_changes(hunk);
+            json::object([
+                ("id", id.as_str().into()),
+                ("file", hunk.file.as_str().into()),
+                ("header", hunk.header.as_str().into()),
+                ("context", crate::hunk::function_context(hunk).into()),
+                ("additions", additions.into()),
+                ("deletions", deletions.into()),
+                ("lines", hunk.lines.clone().into()),
+            ])
+        })
+        .collect();
+    let output = json::object([
+        ("old", old.into()),
+        ("new", new.into()),
+        ("hunks", Value::Array(entries)),
+        ("skipped", skipped.to_vec().into()),
+    ]);
+    println!("{}", output);
+}
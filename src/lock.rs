@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How long mutating commands wait for the lock unless told otherwise.
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+/// Returned when the lock could not be taken in time.
+#[derive(Debug)]
+pub struct Locked {
+    pub path: PathBuf,
+    /// Contents of the lock file: pid and command of the holder
+    pub holder: String,
+}
+
+impl fmt::Display for Locked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "another git-surgeon is modifying this repository ({}); wait for it to finish, \
+             or remove {} if it is no longer running",
+            self.holder,
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for Locked {}
+
+/// Advisory lock held for the duration of a mutating command, stored in
+/// `.git/surgeon/lock`. Removed when dropped.
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Take the repository lock, waiting up to `timeout` for another holder.
+pub fn acquire(command: &str, timeout: Duration) -> Result<Lock> {
+    let path = crate::state::surgeon_path("lock")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let start = Instant::now();
+    loop {
+        if let Some(lock) = create(&path, command)? {
+            return Ok(lock);
+        }
+
+        let holder = fs::read_to_string(&path).unwrap_or_default();
+        let holder = holder.trim();
+        if holder_is_gone(holder) {
+            // Left behind by a crashed process; take it over
+            if let Some(lock) = take_over(&path, holder, command)? {
+                return Ok(lock);
+            }
+        }
+        if start.elapsed() >= timeout {
+            // A takeover marker outliving the timeout was left by a crash too
+            let marker = path.with_extension("takeover");
+            return Err(match fs::read_to_string(&marker) {
+                Ok(holder) => Locked {
+                    path: marker,
+                    holder: holder.trim().to_string(),
+                },
+                Err(_) => Locked {
+                    path,
+                    holder: holder.to_string(),
+                },
+            }
+            .into());
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Create the lock file at `path` if no one holds it. None if it exists.
+fn create(path: &Path, command: &str) -> Result<Option<Lock>> {
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(mut file) => {
+            let lock = Lock {
+                path: path.to_path_buf(),
+            };
+            writeln!(file, "pid {} {}", std::process::id(), command)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            Ok(Some(lock))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("failed to create {}", path.display())),
+    }
+}
+
+/// Replace the lock left by `stale` with our own. Only the waiter that
+/// creates the takeover marker next to it may remove the lock, and only if
+/// `stale` still holds it: one that raced another takeover finds the
+/// winner's lock there instead and goes back to waiting.
+fn take_over(path: &Path, stale: &str, command: &str) -> Result<Option<Lock>> {
+    // Removed on return, once the new lock exists
+    let Some(_marker) = create(&path.with_extension("takeover"), command)? else {
+        return Ok(None);
+    };
+    if !fs::read_to_string(path).is_ok_and(|holder| holder.trim() == stale) {
+        return Ok(None);
+    }
+    fs::remove_file(path).with_context(|| format!("failed to remove {}", path.display()))?;
+    create(path, command)
+}
+
+/// Whether the process recorded in a lock file has exited. Unknown formats
+/// and platforms are treated as still running.
+fn holder_is_gone(holder: &str) -> bool {
+    let Some(pid) = holder
+        .strip_prefix("pid ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|pid| pid.parse::<u32>().ok())
+    else {
+        return false;
+    };
+    process_is_gone(pid)
+}
+
+#[cfg(unix)]
+fn process_is_gone(pid: u32) -> bool {
+    unsafe extern "C" {
+        fn kill(pid: i32, sig: i32) -> i32;
+    }
+    /// "No such process", the same on Linux, macOS and the BSDs
+    const ESRCH: i32 = 3;
+
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // 0 would signal our own process group
+    if pid == 0 {
+        return false;
+    }
+    // SAFETY: signal 0 only checks whether the process exists
+    if unsafe { kill(pid, 0) } == 0 {
+        return false;
+    }
+    // EPERM is a live process of another user
+    std::io::Error::last_os_error().raw_os_error() == Some(ESRCH)
+}
+
+#[cfg(not(unix))]
+fn process_is_gone(_pid: u32) -> bool {
+    false
+}
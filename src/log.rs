@@ -0,0 +1,123 @@
+use anyhow::Result;
+
+use crate::diff::DiffHunk;
+use crate::json::{self, Value};
+
+/// Commits listed when no range is given.
+const DEFAULT_COUNT: usize = 20;
+
+/// A commit of the log and the hunks of its patch.
+struct Entry {
+    sha: String,
+    subject: String,
+    hunks: Vec<DiffHunk>,
+}
+
+/// `log`: list the commits of `range` (the last `DEFAULT_COUNT` of HEAD by
+/// default), newest first, each with its hunk, file and line counts and a
+/// line per file, to pick targets for fixup, split and undo.
+pub fn log(
+    range: Option<&str>,
+    file: Option<&str>,
+    grep: Option<&str>,
+    max_count: Option<usize>,
+    json: bool,
+) -> Result<()> {
+    let max_count = max_count.or(range.is_none().then_some(DEFAULT_COUNT));
+    let output = crate::diff::run_git_log_patches(range, file, grep, max_count)?;
+    let entries = parse_log(&output);
+
+    if json {
+        let commits: Vec<Value> = entries.iter().map(entry_json).collect();
+        println!("{}", Value::Array(commits));
+        return Ok(());
+    }
+    if entries.is_empty() {
+        eprintln!("no commits");
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let files = crate::hunk::file_stats(&entry.hunks);
+        let (additions, deletions) = totals(&files);
+        println!(
+            "{} {} ({} hunk{}, {} file{}, +{} -{})",
+            crate::color::id(&entry.sha[..7]),
+            entry.subject,
+            entry.hunks.len(),
+            if entry.hunks.len() == 1 { "" } else { "s" },
+            files.len(),
+            if files.len() == 1 { "" } else { "s" },
+            additions,
+            deletions
+        );
+        for line in crate::hunk::file_stat_lines(&files) {
+            println!("  {}", line);
+        }
+    }
+    Ok(())
+}
+
+/// Split `run_git_log_patches` output into its commits.
+fn parse_log(output: &str) -> Vec<Entry> {
+    let mut parts = output.split('\0').skip(1);
+    let mut entries = Vec::new();
+    while let (Some(sha), Some(rest)) = (parts.next(), parts.next()) {
+        let (subject, patch) = rest.split_once('\n').unwrap_or((rest, ""));
+        entries.push(Entry {
+            sha: sha.to_string(),
+            subject: subject.to_string(),
+            hunks: crate::diff::parse_diff(patch),
+        });
+    }
+    entries
+}
+
+fn totals(files: &[crate::hunk::FileStat]) -> (usize, usize) {
+    (
+        files.iter().map(|f| f.additions).sum(),
+        files.iter().map(|f| f.deletions).sum(),
+    )
+}
+
+fn entry_json(entry: &Entry) -> Value {
+    let files = crate::hunk::file_stats(&entry.hunks);
+    let (additions, deletions) = totals(&files);
+    let file_entries: Vec<Value> = files
+        .iter()
+        .map(|f| {
+            json::object([
+                ("file", f.file.into()),
+                ("hunks", f.hunks.into()),
+                ("additions", f.additions.into()),
+                ("deletions", f.deletions.into()),
+            ])
+        })
+        .collect();
+    json::object([
+        ("commit", entry.sha.as_str().into()),
+        ("subject", entry.subject.as_str().into()),
+        ("hunks", entry.hunks.len().into()),
+        ("additions", additions.into()),
+        ("deletions", deletions.into()),
+        ("files", Value::Array(file_entries)),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log() {
+        let output = "\0aaaaaaaaaa\0Second\n\ndiff --git a/f b/f\n--- a/f\n+++ b/f\n\
+                      @@ -1 +1 @@\n-a\n+b\n\0bbbbbbbbbb\0Merge branch\n";
+        let entries = parse_log(output);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].subject, "Second");
+        assert_eq!(entries[0].hunks.len(), 1);
+        assert_eq!(entries[1].sha, "bbbbbbbbbb");
+        assert!(entries[1].hunks.is_empty());
+    }
+}
@@ -1,12 +1,47 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, FromArgMatches, Parser};
 
+mod autostash;
+mod backup;
 mod blame;
+mod branches;
+mod color;
+mod committer;
+mod config;
+mod confirm;
+mod daemon;
 mod diff;
+mod editor;
+mod exit;
+mod find;
+mod format;
+mod glob;
 mod hunk;
 mod hunk_id;
+mod interdiff;
+mod json;
+mod lock;
+mod log;
+mod output;
 mod patch;
+mod plan;
+mod promisor;
+mod range_diff;
+mod registry;
+mod rev;
+mod sequence;
+mod signing;
 mod skill;
+mod state;
+mod suggest;
+mod symbol;
+#[cfg(feature = "tree-sitter")]
+mod syntax;
+mod template;
+mod token;
+mod trailer;
+mod workflows;
+mod yaml;
 
 #[derive(Parser)]
 #[command(name = "git-surgeon")]
@@ -14,6 +49,46 @@ mod skill;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Refuse to run unless the diff state still matches this token from `hunks --json`
+    #[arg(long, global = true, value_name = "TOKEN")]
+    if_match: Option<String>,
+    /// Fail immediately if another git-surgeon is modifying the repository
+    #[arg(long, global = true, conflicts_with = "lock_timeout")]
+    no_wait: bool,
+    /// Seconds to wait for another git-surgeon to finish before failing
+    #[arg(long, global = true, value_name = "SECS", default_value_t = lock::DEFAULT_TIMEOUT_SECS)]
+    lock_timeout: u64,
+    /// Hash hunk IDs from every line (full) or only the changed lines
+    /// (content) [default: surgeon.idScheme, else full]
+    #[arg(long, global = true, value_name = "SCHEME", value_parser = hunk_id::parse_id_scheme)]
+    id_scheme: Option<hunk_id::IdScheme>,
+    /// Context lines around each hunk, 0 for none [default: surgeon.diffContext,
+    /// else git's diff.context]
+    #[arg(short = 'U', long = "unified", global = true, value_name = "N")]
+    context: Option<usize>,
+    /// Passed to `git apply --whitespace` when applying hunks: nowarn, warn,
+    /// fix, error or error-all [default: git's apply.whitespace]
+    #[arg(long, global = true, value_name = "ACTION", value_parser = patch::parse_whitespace)]
+    whitespace: Option<&'static str>,
+    /// Kill a git command still running after SECS seconds, such as a commit
+    /// whose hook waits for input, and roll back [default: surgeon.timeout]
+    #[arg(long, global = true, value_name = "SECS")]
+    timeout: Option<u64>,
+    /// Print nothing but errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Print each git command run and how long it took; -vv also prints the
+    /// exit status and error output of those that fail
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// In a shallow clone, fetch the parent of a commit at the shallow
+    /// boundary before rewriting it, instead of refusing
+    #[arg(long, global = true)]
+    auto_deepen: bool,
+    /// After a rewrite, move other local branches that pointed at a
+    /// rewritten commit to its replacement (default: surgeon.updateRefs)
+    #[arg(long, global = true)]
+    update_refs: bool,
 }
 
 #[derive(clap::Subcommand)]
@@ -23,6 +98,9 @@ enum Commands {
         /// Show staged hunks (git diff --cached)
         #[arg(long)]
         staged: bool,
+        /// Show staged and unstaged hunks together, staged IDs prefixed with "s-"
+        #[arg(long, conflicts_with_all = ["staged", "commit"])]
+        all: bool,
         /// Filter to a specific file
         #[arg(long)]
         file: Option<String>,
@@ -32,9 +110,109 @@ enum Commands {
         /// Show full diff with line numbers (like show, but for all hunks)
         #[arg(long)]
         full: bool,
-        /// Show git blame information for each line
+        /// Show git blame information for each line: the commit, or with
+        /// `--blame=full` also its date, author and subject
+        #[arg(
+            long,
+            value_name = "MODE",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "short",
+            value_parser = blame::parse_blame_mode
+        )]
+        blame: Option<blame::BlameMode>,
+        /// With `--blame`, attribute lines changed by REV to the commits
+        /// before it, like `git blame --ignore-rev` (repeatable; added to
+        /// `blame.ignoreRevsFile`)
+        #[arg(long = "ignore-rev", value_name = "REV", requires = "blame")]
+        ignore_revs: Vec<String>,
+        /// Show only a representative sample of N hunks plus totals
+        #[arg(long, value_name = "N")]
+        sample: Option<usize>,
+        /// Output hunks and a snapshot token as JSON; with `--blame`, each hunk
+        /// also lists who last touched each line
         #[arg(long)]
-        blame: bool,
+        json: bool,
+        /// Print one line per hunk from a template: %id %file %func %adds %dels
+        /// %first_line %header %location, %n for a newline, %% for a literal %
+        #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["json", "full", "blame"])]
+        format: Option<String>,
+        /// Preview lines: none, changes (the first changed lines), or context
+        /// (the first lines of the hunk, context included)
+        #[arg(
+            long,
+            value_name = "MODE",
+            default_value = "changes",
+            value_parser = hunk::parse_preview,
+            conflicts_with_all = ["json", "full", "blame", "format"]
+        )]
+        preview: hunk::Preview,
+        /// Preview at most N lines per hunk [default: surgeon.previewLines, else 4]
+        #[arg(
+            long,
+            value_name = "N",
+            conflicts_with_all = ["json", "full", "blame", "format"]
+        )]
+        preview_lines: Option<usize>,
+        /// Print only the hunk header lines, then per-file and total counts
+        #[arg(
+            long,
+            conflicts_with_all = ["json", "full", "blame", "format", "preview", "preview_lines"]
+        )]
+        stat: bool,
+        /// Order: path-order (as in the diff), file (by path), or size (most
+        /// changed lines first)
+        #[arg(long, value_name = "ORDER", default_value = "path-order", value_parser = hunk::parse_sort)]
+        sort: hunk::Sort,
+        /// Only list hunks with at least N added plus deleted lines
+        #[arg(long, value_name = "N")]
+        min_changes: Option<usize>,
+        /// Only list hunks with at most N added plus deleted lines
+        #[arg(long, value_name = "N")]
+        max_changes: Option<usize>,
+        /// Hide hunks that only change whitespace (reindenting, blank lines)
+        #[arg(short = 'w', long)]
+        ignore_whitespace: bool,
+        /// Print each hunk whole inside its enclosing function; the function's
+        /// other lines are unnumbered, so `--lines` numbers are unchanged
+        #[arg(short = 'W', long, conflicts_with_all = ["blame", "stat", "format", "full"])]
+        function_context: bool,
+        /// Also list the unstaged hunks inside submodules with changes; their
+        /// IDs are for running git-surgeon inside the submodule
+        #[arg(long, conflicts_with_all = ["staged", "all", "commit", "json", "format", "stat"])]
+        recurse_submodules: bool,
+        /// Cluster hunks by the file and function (the `@@` context) they
+        /// change, each cluster under a `# <file>: <function>` heading
+        #[arg(long)]
+        group_by_function: bool,
+        #[command(flatten)]
+        filter: glob::PathFilter,
+        #[command(flatten)]
+        color: ColorArgs,
+    },
+    /// List changed files with their change type, hunk count, and +/- counts
+    Files {
+        /// Show staged files (git diff --cached)
+        #[arg(long, conflicts_with = "commit")]
+        staged: bool,
+        /// Show files changed by a specific commit
+        #[arg(long)]
+        commit: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Find the current hunk(s) an ID from an earlier `hunks` listing now
+    /// refers to, ranked by content similarity
+    ResolveId {
+        /// Hunk ID from the last `hunks` listing
+        id: String,
+        /// Match against staged hunks
+        #[arg(long, conflicts_with = "commit")]
+        staged: bool,
+        /// Match against the hunks of a specific commit
+        #[arg(long)]
+        commit: Option<String>,
     },
     /// Show full diff for a specific hunk
     Show {
@@ -43,30 +221,160 @@ enum Commands {
         /// Look up hunk in a specific commit
         #[arg(long)]
         commit: Option<String>,
+        /// Print the whole file after the change, with absolute line numbers
+        /// and the hunk's changes marked inline
+        #[arg(long)]
+        full_file: bool,
+        /// Also print the rest of the enclosing function, unnumbered (the
+        /// hunk's own line numbers are unchanged, as `--lines` takes them)
+        #[arg(short = 'W', long, conflicts_with = "full_file")]
+        function_context: bool,
+        /// Output the hunk and where it was found as JSON
+        #[arg(long, conflicts_with = "full_file")]
+        json: bool,
+        #[command(flatten)]
+        color: ColorArgs,
+    },
+    /// List commits with their hunk, file and line counts
+    Log {
+        /// Commits to list, as for git log (default: the last 20 of HEAD)
+        range: Option<String>,
+        /// Only commits that change FILE, counting only its hunks
+        #[arg(long)]
+        file: Option<String>,
+        /// Only commits whose message matches PATTERN (as git log --grep)
+        #[arg(long, value_name = "PATTERN")]
+        grep: Option<String>,
+        /// List at most N commits
+        #[arg(short = 'n', long, value_name = "N")]
+        max_count: Option<usize>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        color: ColorArgs,
+    },
+    /// Find the unpublished commits whose message matches a pattern, to
+    /// name the target of a rewrite
+    FindCommit {
+        /// Extended regex matched against the whole message, as in
+        /// `:/pattern` (every filter given must match)
+        #[arg(long, value_name = "PATTERN")]
+        grep: String,
+        /// Only commits whose author matches PATTERN (as git log --author)
+        #[arg(long, value_name = "PATTERN")]
+        author: Option<String>,
+        /// Only commits more recent than DATE, e.g. "2 weeks ago"
+        #[arg(long, value_name = "DATE")]
+        since: Option<String>,
+        /// Only commits that change FILE
+        #[arg(long)]
+        file: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        color: ColorArgs,
+    },
+    /// List how the changes two commits make differ, e.g. two versions of the
+    /// same commit before and after a rebase or amend
+    Interdiff {
+        /// The earlier version
+        old: String,
+        /// The later version; `+` lines are what only it has
+        new: String,
+        /// Show every line of each hunk, numbered
+        #[arg(long, conflicts_with = "json")]
+        full: bool,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        color: ColorArgs,
     },
     /// Stage hunks by ID
     Stage {
-        /// Hunk IDs to stage
+        /// Hunk IDs to stage, each optionally with an inline line range
+        /// (e.g. a1b2c3d:5-12,20)
+        #[arg(required_unless_present_any = ["whitespace_only", "symbol", "all"])]
         ids: Vec<String>,
-        /// Hunk-relative line range (e.g. 5-30) to apply only part of a hunk
-        #[arg(long, value_parser = parse_line_range)]
-        lines: Option<(usize, usize)>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to apply
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// Stage every hunk that only changes whitespace, instead of hunks by ID
+        #[arg(long, conflicts_with_all = ["ids", "lines"])]
+        whitespace_only: bool,
+        /// Stage every listed hunk, instead of hunks by ID
+        #[arg(long, conflicts_with_all = ["ids", "lines", "whitespace_only", "symbol"])]
+        all: bool,
+        /// Stage the changed lines inside the function, type or constant NAME
+        /// as declared in the working tree copy of --file
+        #[arg(
+            long,
+            value_name = "NAME",
+            requires = "file",
+            conflicts_with_all = ["ids", "lines", "whitespace_only"]
+        )]
+        symbol: Option<String>,
+        /// File that declares --symbol
+        #[arg(long, requires = "symbol")]
+        file: Option<String>,
+        /// With --all, only the hunks of the files these allow
+        #[command(flatten)]
+        filter: glob::PathFilter,
+    },
+    /// Stage whole files by path, including new, deleted and binary files
+    StageFile {
+        /// Files or directories to stage
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Mark new files intent-to-add, so their content shows up as hunks
+    Track {
+        /// Untracked files or directories to track
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+    /// Unstage whole files by path
+    UnstageFile {
+        /// Files or directories to unstage
+        #[arg(required = true)]
+        paths: Vec<String>,
     },
     /// Unstage hunks by ID
     Unstage {
-        /// Hunk IDs to unstage
+        /// Hunk IDs to unstage, each optionally with an inline line range
+        /// (e.g. a1b2c3d:5-12,20)
         ids: Vec<String>,
-        /// Hunk-relative line range (e.g. 5-30) to apply only part of a hunk
-        #[arg(long, value_parser = parse_line_range)]
-        lines: Option<(usize, usize)>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to apply
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// Unstage every staged hunk, instead of hunks by ID
+        #[arg(long, conflicts_with_all = ["ids", "lines"])]
+        all: bool,
     },
     /// Discard working tree changes for hunks
     Discard {
-        /// Hunk IDs to discard
+        /// Hunk IDs to discard, each optionally with an inline line range
+        /// (e.g. a1b2c3d:5-12,20)
         ids: Vec<String>,
-        /// Hunk-relative line range (e.g. 5-30) to apply only part of a hunk
-        #[arg(long, value_parser = parse_line_range)]
-        lines: Option<(usize, usize)>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to apply
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// Discard every unstaged hunk, instead of hunks by ID; asks for
+        /// confirmation first
+        #[arg(long, conflicts_with_all = ["ids", "lines"])]
+        all: bool,
+        /// Confirm discarding every hunk without being asked
+        #[arg(short, long, requires = "all")]
+        yes: bool,
+        /// Delete the files of discarded intent-to-add hunks (see 'track'),
+        /// rather than only untracking them
+        #[arg(long)]
+        delete: bool,
     },
     /// Undo hunks from a commit, reverse-applying them to the working tree
     Undo {
@@ -75,108 +383,965 @@ enum Commands {
         /// Commit to undo hunks from
         #[arg(long)]
         from: String,
-        /// Hunk-relative line range (e.g. 5-30) to apply only part of a hunk
-        #[arg(long, value_parser = parse_line_range)]
-        lines: Option<(usize, usize)>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to apply
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// If the files changed since the commit, merge the undo in with a
+        /// 3-way merge (default: surgeon.threeWay)
+        #[arg(long = "3way")]
+        three_way: bool,
+        /// Print each updated file and its conflict count as JSON on stdout
+        #[arg(long)]
+        json: bool,
+    },
+    /// Revert hunks of a commit in a new commit, like `git revert` for part
+    /// of a commit
+    Revert {
+        /// Hunk IDs to revert
+        ids: Vec<String>,
+        /// Commit to revert hunks of
+        #[arg(long)]
+        from: String,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to revert
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// Commit message (default: Revert part of "<subject>"; multiple -m
+        /// values are joined by blank lines)
+        #[arg(short, long, num_args = 1)]
+        message: Vec<String>,
+        /// Read the message from FILE, or from stdin if FILE is -
+        #[arg(short = 'F', long, value_name = "FILE", conflicts_with = "message")]
+        file: Option<String>,
+        /// Finish the message in $GIT_EDITOR, with the reverted files listed
+        /// below it
+        #[arg(short, long)]
+        edit: bool,
+        /// Add a Signed-off-by trailer for the committer
+        #[arg(short = 's', long)]
+        signoff: bool,
+        /// Add a trailer to the message, e.g. --trailer "Fixes=#123" (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", num_args = 1)]
+        trailer: Vec<String>,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// Apply hunks of an old commit again, forward, to the working tree
+    RestoreFrom {
+        /// Commit whose hunks to bring back
+        commit: String,
+        /// Hunk IDs to restore (as listed by `hunks --commit`)
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to restore
+        /// only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
     },
     /// Fixup an earlier commit with currently staged changes
     Fixup {
         /// Target commit to fold staged changes into
         commit: String,
+        /// Working tree hunks to stage and fold in (optionally with :START-END
+        /// range suffix); the index must have nothing else staged
+        ids: Vec<String>,
+        /// Hunk-relative line ranges (e.g. 5-30 or 2-4,9; repeatable) to fold
+        /// in only part of a hunk
+        #[arg(long, value_parser = parse_line_range, value_delimiter = ',')]
+        lines: Vec<(usize, usize)>,
+        /// Only create the fixup! commit; fold it in later with an autosquash
+        #[arg(long)]
+        no_rebase: bool,
+        /// Rewrite even if the commit is reachable from a protected ref or the upstream
+        #[arg(long)]
+        force_rewrite_published: bool,
+        /// Print the old -> new commit mapping as JSON on stdout
+        #[arg(long)]
+        json: bool,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        #[command(flatten)]
+        committer: committer::CommitterArgs,
+        #[command(flatten)]
+        sign: signing::SignArgs,
     },
     /// Change the commit message of an existing commit
     Reword {
         /// Target commit to reword
         commit: String,
-        /// New commit message (multiple -m values are joined by blank lines)
-        #[arg(short, long, required = true, num_args = 1)]
+        /// New commit message (multiple -m values are joined by blank lines);
+        /// without -m or -F the current message is kept
+        #[arg(short, long, num_args = 1)]
         message: Vec<String>,
+        /// Read the message from FILE, or from stdin if FILE is -
+        #[arg(short = 'F', long, value_name = "FILE", conflicts_with = "message")]
+        file: Option<String>,
+        /// Replace only the subject, keeping the current body and trailers
+        #[arg(long)]
+        keep_body: bool,
+        /// Finish each new message in $GIT_EDITOR, starting from the one
+        /// given (or the current one) with the commit's files listed below it
+        #[arg(short, long)]
+        edit: bool,
+        /// Add a Signed-off-by trailer for the committer
+        #[arg(short = 's', long)]
+        signoff: bool,
+        /// Add a trailer to the message, e.g. --trailer "Reviewed-by=Jane" (repeatable)
+        #[arg(
+            long,
+            visible_alias = "add-trailer",
+            value_name = "KEY=VALUE",
+            num_args = 1
+        )]
+        trailer: Vec<String>,
+        /// Rewrite even if the commit is reachable from a protected ref or the upstream
+        #[arg(long)]
+        force_rewrite_published: bool,
+        /// Print the old -> new commit mapping as JSON on stdout
+        #[arg(long)]
+        json: bool,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        #[command(flatten)]
+        committer: committer::CommitterArgs,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+        /// More commits to reword in the same rebase, each followed by its own
+        /// -m <msg> [-m <body>...] or -F <file>
+        #[arg(
+            value_name = "COMMIT -m MSG",
+            trailing_var_arg = true,
+            allow_hyphen_values = true
+        )]
+        more: Vec<String>,
     },
     /// Stage hunks and commit in one step
     Commit {
         /// Hunk IDs (optionally with :START-END range suffix)
         ids: Vec<String>,
         /// Commit message (multiple -m values are joined by blank lines, like git commit)
-        #[arg(short, long, required = true, num_args = 1)]
+        #[arg(
+            short,
+            long,
+            required_unless_present_any = ["file", "edit", "message_from", "pick"],
+            num_args = 1
+        )]
         message: Vec<String>,
+        /// Read the message from FILE, or from stdin if FILE is -
+        #[arg(short = 'F', long, value_name = "FILE", conflicts_with = "message")]
+        file: Option<String>,
+        /// Reuse this commit's message verbatim, trailers included (like git's -C)
+        #[arg(
+            short = 'C',
+            long,
+            visible_alias = "reuse-message",
+            value_name = "COMMIT",
+            conflicts_with_all = ["message", "file"]
+        )]
+        message_from: Option<String>,
+        /// Finish the message in $GIT_EDITOR, starting from -m, -F or -C (if
+        /// given) with the committed files listed below it
+        #[arg(short, long)]
+        edit: bool,
+        /// Add a Signed-off-by trailer for the committer
+        #[arg(short = 's', long)]
+        signoff: bool,
+        /// Add a trailer to the message, e.g. --trailer "Fixes=#123" (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", num_args = 1)]
+        trailer: Vec<String>,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        /// Use the message as given, ignoring surgeon.messageTemplate
+        #[arg(long)]
+        no_template: bool,
+        /// Instead of hunk IDs, commit every unstaged hunk of the files these
+        /// allow
+        #[command(flatten)]
+        filter: glob::PathFilter,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+        /// Make several commits, all or none: --pick <ids...> -m <msg>
+        /// [-m <body>...] (or -F <file>, -C <commit>) [-e] [--trailer <k=v>...]
+        /// per commit, as in split (the rest of the command line)
+        #[arg(
+            long,
+            value_name = "IDS",
+            num_args = 1..,
+            allow_hyphen_values = true,
+            conflicts_with_all = ["ids", "message", "file", "message_from", "edit", "include", "exclude"]
+        )]
+        pick: Vec<String>,
+    },
+    /// Fold hunks into HEAD and/or change its message
+    Amend {
+        /// Hunk IDs to add to HEAD (optionally with :START-END range suffix)
+        ids: Vec<String>,
+        /// New commit message (multiple -m values are joined by blank lines)
+        #[arg(short, long, num_args = 1)]
+        message: Vec<String>,
+        #[command(flatten)]
+        sign: signing::SignArgs,
     },
     /// Undo all changes to specific files from a commit
     UndoFile {
-        /// File paths to undo
+        /// File paths to undo: files, directories, or globs such as
+        /// 'src/migrations/**' or '*.lock', matched against the commit's files
         files: Vec<String>,
         /// Commit to undo files from
         #[arg(long)]
         from: String,
+        /// If the files changed since the commit, merge the undo in with a
+        /// 3-way merge (default: surgeon.threeWay)
+        #[arg(long = "3way")]
+        three_way: bool,
+        /// Print each updated file and its conflict count as JSON on stdout
+        #[arg(long)]
+        json: bool,
+        /// Confirm undoing many files at once without being asked
+        #[arg(short, long)]
+        yes: bool,
     },
     /// Split a commit into multiple commits by hunk selection
     #[command(disable_help_flag = false)]
     Split {
         /// Commit to split (e.g. HEAD, abc1234)
         commit: String,
-        /// Remaining args: --pick <ids...> -m <msg> [-m <body>...] [--rest-message <msg>...]
+        /// Validate the selections and print the resulting commits without splitting
+        #[arg(long)]
+        dry_run: bool,
+        /// Give the new commits the current user and time instead of the original
+        /// commit's author, author date, and committer date
+        #[arg(long)]
+        no_preserve_author: bool,
+        /// Rewrite even if the commit is reachable from a protected ref or the upstream
+        #[arg(long)]
+        force_rewrite_published: bool,
+        /// Check that the rewritten tip has the same tree as the original tip,
+        /// rolling back if it does not (the default)
+        #[arg(long, overrides_with = "no_verify_tree")]
+        verify_tree: bool,
+        /// Keep the result even if the rewritten tip's tree differs
+        #[arg(long, overrides_with = "verify_tree")]
+        no_verify_tree: bool,
+        /// Print the old -> new commit mapping as JSON on stdout
+        #[arg(long)]
+        json: bool,
+        /// Add a Signed-off-by trailer for the committer to every new commit
+        #[arg(short = 's', long)]
+        signoff: bool,
+        /// Skip the pre-commit and commit-msg hooks for every new commit
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        /// Use the messages as given, ignoring surgeon.messageTemplate
+        #[arg(long)]
+        no_template: bool,
+        /// Make the commits in this order of the --pick groups, e.g. 3,1,2;
+        /// `rest` places the rest commit (default: last)
+        #[arg(long, value_name = "N,...")]
+        order: Option<String>,
+        /// Drop the changes no group picks instead of committing them
+        #[arg(long)]
+        discard_rest: bool,
+        /// Make one group per surgeon.scope the commit's files map to, with
+        /// a `type(scope): subject` message, instead of --pick groups
+        #[arg(long)]
+        auto_scope: bool,
+        #[command(flatten)]
+        committer: committer::CommitterArgs,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+        /// Remaining args: --pick <ids...> -m <msg> [-m <body>...] (or -C <commit>) [-e]
+        /// [--trailer <k=v>...]
+        /// [--rest-message <msg>...]; -e finishes the group's message in $GIT_EDITOR,
+        /// and --pick --include/--exclude <glob> (or --pick-path <glob>...) picks
+        /// hunks by file instead of ID
         #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
         args: Vec<String>,
     },
+    /// Propose how to split a commit (or the working tree) into focused
+    /// commits, printed as a ready-to-run split command or a plan file
+    SuggestSplit {
+        /// Commit to split (defaults to the unstaged working tree changes,
+        /// suggested as commit commands)
+        commit: Option<String>,
+        /// What puts hunks in the same commit, comma-separated: file, dir
+        /// (the file's directory), symbol (a name one hunk declares and
+        /// another uses), blame (removed lines from the same commit)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = suggest::parse_criterion,
+            default_value = "file,symbol"
+        )]
+        by: Vec<suggest::Criterion>,
+        /// Print an apply-plan file instead of commands
+        #[arg(long)]
+        plan: bool,
+        /// With `--by blame`, look through commits changed by REV, like
+        /// `hunks --ignore-rev` (repeatable)
+        #[arg(long = "ignore-rev", value_name = "REV")]
+        ignore_revs: Vec<String>,
+    },
     /// Squash commits from <commit>..HEAD into a single commit
     Squash {
-        /// The oldest commit to include. All commits from here to HEAD are combined.
+        /// The oldest commit to include (all commits from here to HEAD are
+        /// combined), or a range A..B to squash only the commits after A up to B
         commit: String,
-        /// Commit message (required)
-        #[arg(short, long, required = true, num_args = 1)]
+        /// Commit message (defaults to combining the squashed commits' messages)
+        #[arg(short, long, num_args = 1)]
         message: Vec<String>,
+        /// Read the message from FILE, or from stdin if FILE is -
+        #[arg(
+            short = 'F',
+            long,
+            value_name = "FILE",
+            conflicts_with_all = ["message", "message_from"]
+        )]
+        file: Option<String>,
+        /// Reuse this commit's message verbatim, trailers included (like git's -C)
+        #[arg(
+            short = 'C',
+            long,
+            visible_alias = "reuse-message",
+            value_name = "COMMIT",
+            conflicts_with = "message"
+        )]
+        message_from: Option<String>,
+        /// Concatenate the messages of all squashed commits (the default without -m)
+        #[arg(long, conflicts_with_all = ["message", "message_from", "file"])]
+        combine_messages: bool,
+        /// Finish the message in $GIT_EDITOR, with the squashed files listed
+        /// below it
+        #[arg(short, long)]
+        edit: bool,
+        /// Add a Signed-off-by trailer for the committer
+        #[arg(short = 's', long)]
+        signoff: bool,
+        /// Add a trailer to the message, e.g. --trailer "Fixes=#123" (repeatable)
+        #[arg(long, value_name = "KEY=VALUE", num_args = 1)]
+        trailer: Vec<String>,
         /// Force squash even if range contains merge commits (which will be flattened)
         #[arg(long)]
         force: bool,
         /// Do not preserve the author from the oldest commit (use current user instead)
         #[arg(long)]
         no_preserve_author: bool,
+        /// Rewrite even if the commit is reachable from a protected ref or the upstream
+        #[arg(long)]
+        force_rewrite_published: bool,
+        /// Check that the rewritten tip has the same tree as the original tip,
+        /// rolling back if it does not (the default)
+        #[arg(long, overrides_with = "no_verify_tree")]
+        verify_tree: bool,
+        /// Keep the result even if the rewritten tip's tree differs
+        #[arg(long, overrides_with = "verify_tree")]
+        no_verify_tree: bool,
+        /// Print the old -> new commit mapping as JSON on stdout
+        #[arg(long)]
+        json: bool,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+        /// Instead of squashing everything, fold each fixup!, amend! and
+        /// squash! commit from <commit> (the oldest they may target) up to
+        /// HEAD into the commit it names
+        #[arg(
+            long,
+            conflicts_with_all = [
+                "message", "file", "message_from", "combine_messages", "edit",
+                "signoff", "trailer", "force", "no_preserve_author"
+            ]
+        )]
+        autosquash: bool,
+        #[command(flatten)]
+        committer: committer::CommitterArgs,
+        #[command(flatten)]
+        sign: signing::SignArgs,
     },
-    /// Install the git-surgeon skill for AI coding assistants
-    InstallSkill {
-        /// Install for Claude Code (~/.claude/skills/)
+    /// Rearrange the commits in <commit>..HEAD
+    Reorder {
+        /// Range of commits to reorder, e.g. HEAD~3..HEAD
+        range: String,
+        /// New order, oldest first: commits or 1-based positions in the range
+        #[arg(long, required = true, num_args = 1..)]
+        order: Vec<String>,
+        /// Check that the rewritten tip has the same tree as the original tip,
+        /// rolling back if it does not (the default)
+        #[arg(long, overrides_with = "no_verify_tree")]
+        verify_tree: bool,
+        /// Keep the result even if the rewritten tip's tree differs
+        #[arg(long, overrides_with = "verify_tree")]
+        no_verify_tree: bool,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// Remove commits from history
+    Drop {
+        /// Commits to drop
+        #[arg(required = true)]
+        commits: Vec<String>,
+        /// Leave the dropped commits' changes unstaged in the working tree
+        #[arg(long)]
+        keep_changes: bool,
+        /// Confirm dropping the commits without being asked
+        #[arg(short, long)]
+        yes: bool,
+        /// Rewrite even if the commit is reachable from a protected ref or the upstream
+        #[arg(long)]
+        force_rewrite_published: bool,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// Run a YAML plan of stage/commit/fixup/reword/split steps, all or nothing
+    ApplyPlan {
+        /// Plan file, or - to read from stdin
+        plan: std::path::PathBuf,
+        /// Let fixup, reword, and split steps rewrite published commits
+        #[arg(long)]
+        force_rewrite_published: bool,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// List the backup refs taken before history rewrites, newest first
+    Backups,
+    /// Reset the current branch to a backup, keeping uncommitted changes
+    RestoreBackup {
+        /// Backup name from 'backups' (defaults to the newest)
+        name: Option<String>,
+    },
+    /// Restore changes that squash stashed away but could not put back
+    RestoreAutostash {
+        /// Only list the orphaned autostashes
         #[arg(long)]
-        claude: bool,
-        /// Install for OpenCode (~/.config/opencode/skills/)
+        list: bool,
+    },
+    /// Serve hunks/show from a long-running process that keeps diffs and blame warm
+    Daemon {
+        /// Stop the daemon running for this repository
         #[arg(long)]
-        opencode: bool,
-        /// Install for Codex (~/.codex/skills/)
+        stop: bool,
+    },
+    /// Resume an interrupted history-rewriting operation
+    Continue {
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// Roll back an interrupted history-rewriting operation
+    Abort,
+    /// Stop a rebase at a commit with its changes unstaged, for re-committing
+    EditCommit {
+        /// Commit to edit
+        commit: String,
+        #[command(flatten)]
+        sign: signing::SignArgs,
+    },
+    /// Print task-oriented recipes (split a commit, absorb fixes, ...)
+    Workflows {
+        /// Show only this workflow
+        name: Option<String>,
+        /// Output as JSON
         #[arg(long)]
-        codex: bool,
+        json: bool,
+    },
+    /// Install the git-surgeon skill for AI coding assistants
+    InstallSkill {
+        #[command(flatten)]
+        platforms: skill::PlatformArgs,
+        /// Install for every platform whose config directory exists
+        #[arg(long, conflicts_with_all = skill::PLATFORM_FLAGS)]
+        all: bool,
+        /// List the platforms the skill is installed for, and by which version
+        #[arg(long, conflicts_with_all = skill::PLATFORM_FLAGS, conflicts_with = "all")]
+        list: bool,
+    },
+    /// Report whether installed skills match this version of git-surgeon
+    SkillStatus {
+        /// Reinstall the stale skills
+        #[arg(long)]
+        refresh: bool,
+    },
+    /// Remove the AI assistant skill files install-skill created
+    UninstallSkill {
+        #[command(flatten)]
+        platforms: skill::PlatformArgs,
+        /// Remove the skill from every platform that has it
+        #[arg(long, conflicts_with_all = skill::PLATFORM_FLAGS)]
+        all: bool,
     },
+    /// Give HEAD the committer of the commit a rebase just replayed, for use
+    /// as a rebase exec command
+    #[command(hide = true)]
+    InternalRestoreCommitter,
+    /// Rewrite a rebase todo file, for use as GIT_SEQUENCE_EDITOR
+    InternalEditTodo {
+        /// Set a commit's action: <sha>=pick|edit|reword|drop|squash|fixup (repeatable)
+        #[arg(long = "mark", value_name = "SHA=ACTION", value_parser = sequence::parse_mark)]
+        marks: Vec<sequence::Mark>,
+        /// Rearrange commits into this order (every commit, repeatable)
+        #[arg(long = "order", value_name = "SHA")]
+        order: Vec<String>,
+        /// Todo file path (appended by git)
+        todo: std::path::PathBuf,
+    },
+}
+
+#[derive(clap::Args)]
+struct ColorArgs {
+    /// Color the output: auto (when stdout is a terminal), always, or never
+    /// [default: surgeon.color, else auto]
+    #[arg(
+        long,
+        value_name = "WHEN",
+        value_parser = color::parse_color,
+        overrides_with = "color"
+    )]
+    color: Option<color::ColorChoice>,
+}
+
+impl ColorArgs {
+    /// Whether to color the output.
+    fn resolve(&self) -> Result<bool> {
+        let choice = match self.color {
+            Some(choice) => choice,
+            None => color::configured()?,
+        };
+        Ok(choice.resolve())
+    }
+}
+
+impl Commands {
+    /// Name of a command that modifies the index, working tree, or history,
+    /// and so must hold the repository lock. `None` for read-only commands.
+    fn mutating_name(&self) -> Option<&'static str> {
+        let name = match self {
+            Commands::Stage { .. } => "stage",
+            Commands::Unstage { .. } => "unstage",
+            Commands::StageFile { .. } => "stage-file",
+            Commands::UnstageFile { .. } => "unstage-file",
+            Commands::Track { .. } => "track",
+            Commands::Discard { .. } => "discard",
+            Commands::Undo { .. } => "undo",
+            Commands::Revert { .. } => "revert",
+            Commands::RestoreFrom { .. } => "restore-from",
+            Commands::Fixup { .. } => "fixup",
+            Commands::Reword { .. } => "reword",
+            Commands::Commit { .. } => "commit",
+            Commands::Amend { .. } => "amend",
+            Commands::UndoFile { .. } => "undo-file",
+            Commands::Split { .. } => "split",
+            Commands::Squash { .. } => "squash",
+            Commands::Reorder { .. } => "reorder",
+            Commands::Drop { .. } => "drop",
+            Commands::ApplyPlan { .. } => "apply-plan",
+            Commands::Continue { .. } => "continue",
+            Commands::Abort => "abort",
+            Commands::EditCommit { .. } => "edit-commit",
+            Commands::RestoreBackup { .. } => "restore-backup",
+            Commands::RestoreAutostash { .. } => "restore-autostash",
+            // internal-edit-todo and internal-restore-committer run inside a
+            // rebase started by a command that already holds the lock
+            Commands::Hunks { .. }
+            | Commands::Files { .. }
+            | Commands::ResolveId { .. }
+            | Commands::Show { .. }
+            | Commands::Interdiff { .. }
+            | Commands::Log { .. }
+            | Commands::FindCommit { .. }
+            | Commands::SuggestSplit { .. }
+            | Commands::Daemon { .. }
+            | Commands::Backups
+            | Commands::Workflows { .. }
+            | Commands::InstallSkill { .. }
+            | Commands::UninstallSkill { .. }
+            | Commands::SkillStatus { .. }
+            | Commands::InternalRestoreCommitter
+            | Commands::InternalEditTodo { .. } => return None,
+        };
+        Some(name)
+    }
+
+    /// Whether the command rewrites existing commits, leaving the branches on
+    /// them behind for `--update-refs` to move.
+    fn rewrites_history(&self) -> bool {
+        matches!(
+            self,
+            Commands::Amend { .. }
+                | Commands::Fixup { .. }
+                | Commands::Reword { .. }
+                | Commands::Split { .. }
+                | Commands::Squash { .. }
+                | Commands::Reorder { .. }
+                | Commands::Drop { .. }
+                | Commands::ApplyPlan { .. }
+                | Commands::EditCommit { .. }
+                | Commands::Continue { .. }
+        )
+    }
+
+    /// Signing flags of the commands that create or rewrite commits.
+    fn sign_args(&self) -> Option<&signing::SignArgs> {
+        match self {
+            Commands::Commit { sign, .. }
+            | Commands::Revert { sign, .. }
+            | Commands::Amend { sign, .. }
+            | Commands::Fixup { sign, .. }
+            | Commands::Reword { sign, .. }
+            | Commands::Split { sign, .. }
+            | Commands::Squash { sign, .. }
+            | Commands::Reorder { sign, .. }
+            | Commands::Drop { sign, .. }
+            | Commands::ApplyPlan { sign, .. }
+            | Commands::EditCommit { sign, .. }
+            | Commands::Continue { sign } => Some(sign),
+            _ => None,
+        }
+    }
 }
 
 /// A group of hunk IDs (with optional line ranges) and a commit message.
+#[derive(Clone)]
 pub struct PickGroup {
     pub ids: Vec<(String, Option<(usize, usize)>)>,
     pub message_parts: Vec<String>,
 }
 
-/// Parse the trailing args of the split command into pick groups and optional rest-message.
-fn parse_split_args(args: &[String]) -> anyhow::Result<(Vec<PickGroup>, Option<Vec<String>>)> {
+/// Read a commit message for `-F`: from a file, or from stdin for `-`.
+fn read_message_file(path: &str) -> Result<String> {
+    let text = if path == "-" {
+        std::io::read_to_string(std::io::stdin()).context("failed to read message from stdin")?
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read message file {}", path))?
+    };
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        let source = if path == "-" { "stdin" } else { path };
+        exit::fail!(Usage, "empty commit message from {}", source);
+    }
+    Ok(text.to_string())
+}
+
+/// A part of `commit`'s message (`%B` for all of it, `%b` for the body),
+/// without trailing whitespace.
+fn commit_message(commit: &str, format: &str) -> Result<String> {
+    let sha = rev::resolve_commit(commit)?;
+    let message = diff::run_git_cmd(std::process::Command::new("git").args([
+        "log",
+        "-1",
+        &format!("--format={}", format),
+        &sha,
+    ]))?;
+    Ok(message.trim_end().to_string())
+}
+
+/// The configured message template, unless `--no-template` was given.
+fn load_template(no_template: bool) -> Result<Option<template::Template>> {
+    if no_template {
+        return Ok(None);
+    }
+    template::Template::load()
+}
+
+/// stdin holds a single message, so only one `-F -` can read it.
+fn take_stdin(path: &str, taken: &mut bool) -> Result<()> {
+    if path == "-" {
+        if *taken {
+            exit::fail!(Usage, "only one message can be read from stdin");
+        }
+        *taken = true;
+    }
+    Ok(())
+}
+
+/// One commit to reword and the message given for it.
+struct RewordEdit {
+    commit: String,
+    message: Vec<String>,
+    file: Option<String>,
+}
+
+impl RewordEdit {
+    /// The new message: the given one (followed by the current body with
+    /// `keep_body`), or the current one when only adding trailers, finished
+    /// in the editor with `edit`.
+    fn new_message(
+        &self,
+        keep_body: bool,
+        edit: bool,
+        trailers: &trailer::Trailers,
+    ) -> Result<String> {
+        let unchanged = self.message.is_empty() && self.file.is_none();
+        if unchanged && trailers.is_empty() && !edit {
+            exit::fail!(
+                Usage,
+                "nothing to reword for {}: pass -m, -F, --edit, or --add-trailer",
+                self.commit
+            );
+        }
+        if unchanged && keep_body {
+            exit::fail!(Usage, "--keep-body needs a new subject from -m or -F");
+        }
+        let message = if unchanged {
+            commit_message(&self.commit, "%B")?
+        } else {
+            let message = message_text(&self.message, self.file.as_deref())?;
+            let body = if keep_body {
+                commit_message(&self.commit, "%b")?
+            } else {
+                String::new()
+            };
+            if body.is_empty() {
+                message
+            } else {
+                format!("{}\n\n{}", message, body)
+            }
+        };
+        let message = trailers.apply(&message)?;
+        if !edit {
+            return Ok(message);
+        }
+        let summary = hunk::hunk_stat_lines(Some(&self.commit), &[], true)?;
+        editor::edit(&message, &summary)
+    }
+}
+
+/// Parse the `<commit> -m <msg>...` / `<commit> -F <file>` pairs that follow
+/// reword's first commit. A commit without a message only gets the trailers.
+fn parse_reword_edits(first: RewordEdit, more: &[String]) -> Result<Vec<RewordEdit>> {
+    let mut stdin_taken = first.file.as_deref() == Some("-");
+    let mut edits = vec![first];
+    let mut i = 0;
+    while i < more.len() {
+        let arg = &more[i];
+        if arg == "-m" || arg == "--message" || arg == "-F" || arg == "--file" {
+            i += 1;
+            let Some(value) = more.get(i) else {
+                exit::fail!(Usage, "{} requires a value", arg);
+            };
+            let edit = edits.last_mut().expect("starts with the first commit");
+            if arg.starts_with("-m") || arg == "--message" {
+                if edit.file.is_some() {
+                    exit::fail!(Usage, "{}: -m and -F cannot be combined", edit.commit);
+                }
+                edit.message.push(value.clone());
+            } else {
+                if edit.file.is_some() || !edit.message.is_empty() {
+                    exit::fail!(Usage, "{}: -m and -F cannot be combined", edit.commit);
+                }
+                take_stdin(value, &mut stdin_taken)?;
+                edit.file = Some(value.clone());
+            }
+        } else if arg.starts_with('-') {
+            exit::fail!(
+                Usage,
+                "unexpected argument {}: options go before the second commit",
+                arg
+            );
+        } else {
+            edits.push(RewordEdit {
+                commit: arg.clone(),
+                message: Vec::new(),
+                file: None,
+            });
+        }
+        i += 1;
+    }
+    Ok(edits)
+}
+
+/// The message given with `-m` (parts joined by blank lines) or `-F`.
+fn message_text(parts: &[String], file: Option<&str>) -> Result<String> {
+    match file {
+        Some(path) => read_message_file(path),
+        None => Ok(parts.join("\n\n")),
+    }
+}
+
+/// Join message parts and append trailers, giving a single-part message.
+fn with_trailers(parts: &[String], trailers: &trailer::Trailers) -> anyhow::Result<Vec<String>> {
+    if trailers.is_empty() {
+        return Ok(parts.to_vec());
+    }
+    Ok(vec![trailers.apply(&parts.join("\n\n"))?])
+}
+
+/// `commit --pick ...`: make a commit of each group, with split's grammar
+/// for the groups. `trailer` and the flags given before the first --pick
+/// apply to every group.
+fn commit_in_groups(
+    pick: &[String],
+    signoff: bool,
+    trailer: &[String],
+    no_verify: bool,
+    no_template: bool,
+) -> Result<()> {
+    let args: Vec<String> = std::iter::once("--pick".to_string())
+        .chain(pick.iter().cloned())
+        .collect();
+    let parsed = parse_split_args(&args, false)?;
+    if parsed.rest_message.is_some() || !parsed.rest_trailers.is_empty() {
+        exit::fail!(
+            Usage,
+            "commit --pick groups take -m, -F, -C, -e, --trailer and --include/--exclude; \
+             the other options are split's"
+        );
+    }
+    hunk::set_no_verify(no_verify);
+    let template = load_template(no_template)?;
+
+    let mut groups = parsed.groups;
+    for (group, options) in groups.iter_mut().zip(parsed.group_options) {
+        if !options.filter.is_empty() {
+            let ids = hunk::listed_ids(false, &options.filter)?;
+            group.ids = ids.into_iter().map(|id| (id, None)).collect();
+        }
+        let ids: Vec<String> = group.ids.iter().map(|(id, _)| id.clone()).collect();
+        if let Some(template) = &template
+            && !options.reused
+        {
+            let (files, hunk_count) = hunk::hunk_files(None, &ids, false)?;
+            let message = group.message_parts.join("\n\n");
+            group.message_parts = vec![template.expand(&message, &files, hunk_count)?];
+        }
+        let trailers = trailer::Trailers::new(
+            signoff,
+            trailer.iter().cloned().chain(options.trailers).collect(),
+        )?;
+        group.message_parts = with_trailers(&group.message_parts, &trailers)?;
+        if options.edit {
+            let summary = hunk::hunk_stat_lines(None, &ids, false)?;
+            let message = editor::edit(&group.message_parts.join("\n\n"), &summary)?;
+            group.message_parts = vec![message];
+        }
+    }
+    hunk::commit_groups(&groups)
+}
+
+/// Split arguments parsed from the trailing args.
+struct SplitArgs {
+    groups: Vec<PickGroup>,
+    rest_message: Option<Vec<String>>,
+    /// What was given after each group besides its IDs and message, parallel
+    /// to `groups`
+    group_options: Vec<GroupOptions>,
+    rest_trailers: Vec<String>,
+}
+
+#[derive(Default)]
+struct GroupOptions {
+    /// `--trailer` values
+    trailers: Vec<String>,
+    /// `-e`: finish the message in the editor
+    edit: bool,
+    /// `-C`: the message is another commit's, kept as it is
+    reused: bool,
+    /// `--include`/`--exclude`: pick the commit's hunks of these files
+    /// instead of hunks by ID
+    filter: glob::PathFilter,
+}
+
+/// Options of split's group grammar that take a value.
+const GROUP_VALUE_OPTIONS: &[&str] = &[
+    "-m",
+    "--message",
+    "-F",
+    "--file",
+    "-C",
+    "--reuse-message",
+    "--message-from",
+    "--include",
+    "--exclude",
+    "--trailer",
+    "--rest-message",
+    "--rest-message-file",
+];
+
+/// Sort split's trailing args into its group grammar, in order, and the
+/// command's other options given among it. `--name=value` forms of the
+/// grammar's options are split in two.
+fn split_group_args(args: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut groups = Vec::new();
+    let mut others = Vec::new();
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        if let Some((name, value)) = arg.split_once('=')
+            && name.starts_with("--")
+            && GROUP_VALUE_OPTIONS.contains(&name)
+        {
+            groups.extend([name.to_string(), value.to_string()]);
+        } else if GROUP_VALUE_OPTIONS.contains(&arg.as_str()) {
+            groups.push(arg.clone());
+            groups.extend(args.next().cloned());
+        } else if arg == "--pick" || arg == "--pick-path" {
+            groups.push(arg.clone());
+            while let Some(id) = args.next_if(|id| !id.starts_with('-')) {
+                groups.push(id.clone());
+            }
+        } else if arg == "-e" || arg == "--edit" {
+            groups.push(arg.clone());
+        } else {
+            others.push(arg.clone());
+        }
+    }
+    (groups, others)
+}
+
+/// Parse the trailing args of the split command into pick groups and optional
+/// rest-message. The command's other options among them were moved out
+/// before clap parsed them, by `regroup`.
+fn parse_split_args(args: &[String], auto_scope: bool) -> anyhow::Result<SplitArgs> {
     let mut groups: Vec<PickGroup> = Vec::new();
+    let mut stdin_taken = false;
+    let mut group_options: Vec<GroupOptions> = Vec::new();
+    let mut rest_trailers: Vec<String> = Vec::new();
     let mut rest_messages: Vec<String> = Vec::new();
 
     // State for the group currently being built
+    let mut picking = false;
     let mut current_ids: Vec<(String, Option<(usize, usize)>)> = Vec::new();
     let mut current_msgs: Vec<String> = Vec::new();
+    let mut current_options = GroupOptions::default();
     let mut seen_rest = false;
 
     // Helper to flush the current state into a PickGroup
     fn flush_group(
         groups: &mut Vec<PickGroup>,
+        group_options: &mut Vec<GroupOptions>,
+        picking: &mut bool,
         ids: &mut Vec<(String, Option<(usize, usize)>)>,
         msgs: &mut Vec<String>,
+        options: &mut GroupOptions,
     ) -> anyhow::Result<()> {
-        if !ids.is_empty() {
-            if msgs.is_empty() {
-                anyhow::bail!("--pick group missing --message");
+        if std::mem::take(picking) {
+            if ids.is_empty() && options.filter.is_empty() {
+                exit::fail!(
+                    Usage,
+                    "--pick requires at least one hunk ID, or --include/--exclude"
+                );
+            }
+            if !ids.is_empty() && !options.filter.is_empty() {
+                exit::fail!(
+                    Usage,
+                    "--pick group takes hunk IDs or --include/--exclude, not both"
+                );
+            }
+            if msgs.is_empty() && !options.edit {
+                exit::fail!(Usage, "--pick group missing --message");
             }
             groups.push(PickGroup {
                 ids: std::mem::take(ids),
                 message_parts: std::mem::take(msgs),
             });
+            group_options.push(std::mem::take(options));
         } else if !msgs.is_empty() {
-            anyhow::bail!("--message without preceding --pick");
+            exit::fail!(Usage, "--message without preceding --pick");
         }
         Ok(())
     }
@@ -187,13 +1352,21 @@ fn parse_split_args(args: &[String]) -> anyhow::Result<(Vec<PickGroup>, Option<V
 
         if arg == "--pick" {
             if seen_rest {
-                anyhow::bail!("--pick not allowed after --rest-message");
+                exit::fail!(Usage, "--pick not allowed after --rest-message");
             }
             // Only flush if current group has messages (preserves backwards compat
             // with multiple --pick flags before --message)
-            if !current_msgs.is_empty() {
-                flush_group(&mut groups, &mut current_ids, &mut current_msgs)?;
+            if !current_msgs.is_empty() || current_options.edit {
+                flush_group(
+                    &mut groups,
+                    &mut group_options,
+                    &mut picking,
+                    &mut current_ids,
+                    &mut current_msgs,
+                    &mut current_options,
+                )?;
             }
+            picking = true;
 
             i += 1;
             // Collect IDs until we hit a flag
@@ -202,43 +1375,179 @@ fn parse_split_args(args: &[String]) -> anyhow::Result<(Vec<PickGroup>, Option<V
                 current_ids.extend(parsed);
                 i += 1;
             }
-            if current_ids.is_empty() {
-                anyhow::bail!("--pick requires at least one hunk ID");
+        } else if arg == "--pick-path" {
+            // `--pick --include <glob>...`: a group of the files matching
+            if seen_rest {
+                exit::fail!(Usage, "--pick-path not allowed after --rest-message");
+            }
+            if !current_msgs.is_empty() || current_options.edit {
+                flush_group(
+                    &mut groups,
+                    &mut group_options,
+                    &mut picking,
+                    &mut current_ids,
+                    &mut current_msgs,
+                    &mut current_options,
+                )?;
+            }
+            picking = true;
+
+            i += 1;
+            let start = i;
+            while i < args.len() && !args[i].starts_with('-') {
+                current_options.filter.include.push(args[i].clone());
+                i += 1;
+            }
+            if i == start {
+                exit::fail!(Usage, "--pick-path requires a path or glob");
             }
         } else if arg == "--message" || arg == "-m" {
             if seen_rest {
-                anyhow::bail!("--message not allowed after --rest-message");
+                exit::fail!(Usage, "--message not allowed after --rest-message");
             }
             i += 1;
             if i >= args.len() {
-                anyhow::bail!("--message requires a value");
+                exit::fail!(Usage, "--message requires a value");
+            }
+            if !picking {
+                exit::fail!(Usage, "--message without preceding --pick");
             }
-            if current_ids.is_empty() {
-                anyhow::bail!("--message without preceding --pick");
+            if current_options.reused {
+                exit::fail!(Usage, "--message cannot be combined with the group's -C");
             }
             current_msgs.push(args[i].clone());
             i += 1;
+        } else if arg == "--file" || arg == "-F" {
+            if seen_rest {
+                exit::fail!(Usage, "--file not allowed after --rest-message");
+            }
+            i += 1;
+            if i >= args.len() {
+                exit::fail!(Usage, "--file requires a value");
+            }
+            if !picking {
+                exit::fail!(Usage, "--file without preceding --pick");
+            }
+            if current_options.reused {
+                exit::fail!(Usage, "--file cannot be combined with the group's -C");
+            }
+            take_stdin(&args[i], &mut stdin_taken)?;
+            current_msgs.push(read_message_file(&args[i])?);
+            i += 1;
+        } else if arg == "-C" || arg == "--reuse-message" || arg == "--message-from" {
+            if seen_rest {
+                exit::fail!(Usage, "{} not allowed after --rest-message", arg);
+            }
+            i += 1;
+            if i >= args.len() {
+                exit::fail!(Usage, "{} requires a commit", arg);
+            }
+            if !picking {
+                exit::fail!(Usage, "{} without preceding --pick", arg);
+            }
+            if !current_msgs.is_empty() {
+                exit::fail!(
+                    Usage,
+                    "{} cannot be combined with the group's -m or -F",
+                    arg
+                );
+            }
+            current_msgs.push(commit_message(&args[i], "%B")?);
+            current_options.reused = true;
+            i += 1;
+        } else if arg == "--edit" || arg == "-e" {
+            if seen_rest || !picking {
+                exit::fail!(Usage, "--edit must follow a --pick group");
+            }
+            current_options.edit = true;
+            i += 1;
+        } else if arg == "--include" || arg == "--exclude" {
+            i += 1;
+            if i >= args.len() {
+                exit::fail!(Usage, "{} requires a value", arg);
+            }
+            if seen_rest || !picking {
+                exit::fail!(Usage, "{} must follow --pick", arg);
+            }
+            let filter = &mut current_options.filter;
+            match arg.as_str() {
+                "--include" => filter.include.push(args[i].clone()),
+                _ => filter.exclude.push(args[i].clone()),
+            }
+            i += 1;
+        } else if arg == "--rest-message-file" {
+            flush_group(
+                &mut groups,
+                &mut group_options,
+                &mut picking,
+                &mut current_ids,
+                &mut current_msgs,
+                &mut current_options,
+            )?;
+            seen_rest = true;
+
+            i += 1;
+            if i >= args.len() {
+                exit::fail!(Usage, "--rest-message-file requires a value");
+            }
+            take_stdin(&args[i], &mut stdin_taken)?;
+            rest_messages.push(read_message_file(&args[i])?);
+            i += 1;
         } else if arg == "--rest-message" {
             // Flush any pending pick group first
-            flush_group(&mut groups, &mut current_ids, &mut current_msgs)?;
+            flush_group(
+                &mut groups,
+                &mut group_options,
+                &mut picking,
+                &mut current_ids,
+                &mut current_msgs,
+                &mut current_options,
+            )?;
             seen_rest = true;
 
             i += 1;
             if i >= args.len() {
-                anyhow::bail!("--rest-message requires a value");
+                exit::fail!(Usage, "--rest-message requires a value");
             }
             rest_messages.push(args[i].clone());
             i += 1;
+        } else if arg == "--trailer" {
+            // Applies to the group being built, or to the rest commit
+            i += 1;
+            if i >= args.len() {
+                exit::fail!(Usage, "--trailer requires a value");
+            }
+            if seen_rest {
+                rest_trailers.push(args[i].clone());
+            } else if picking {
+                current_options.trailers.push(args[i].clone());
+            } else {
+                exit::fail!(
+                    Usage,
+                    "--trailer must follow a --pick group or --rest-message"
+                );
+            }
+            i += 1;
         } else {
-            anyhow::bail!("unexpected argument: {}", arg);
+            exit::fail!(Usage, "unexpected argument: {}", arg);
         }
     }
 
     // Flush the final group
-    flush_group(&mut groups, &mut current_ids, &mut current_msgs)?;
+    flush_group(
+        &mut groups,
+        &mut group_options,
+        &mut picking,
+        &mut current_ids,
+        &mut current_msgs,
+        &mut current_options,
+    )?;
 
-    if groups.is_empty() {
-        anyhow::bail!("at least one --pick ... --message pair is required");
+    if auto_scope && !groups.is_empty() {
+        exit::fail!(Usage, "--auto-scope makes the groups; it takes no --pick");
+    }
+    if groups.is_empty() && !auto_scope {
+        exit::fail!(Usage, "at least one --pick ... --message pair is required");
     }
 
     let rest_message = if rest_messages.is_empty() {
@@ -247,14 +1556,40 @@ fn parse_split_args(args: &[String]) -> anyhow::Result<(Vec<PickGroup>, Option<V
         Some(rest_messages)
     };
 
-    Ok((groups, rest_message))
+    Ok(SplitArgs {
+        groups,
+        rest_message,
+        group_options,
+        rest_trailers,
+    })
+}
+
+/// Parse split's `--order`: comma-separated group numbers, `rest` for the
+/// rest commit.
+fn parse_split_order(s: &str) -> Result<Vec<Option<usize>>> {
+    s.split(',')
+        .map(str::trim)
+        .map(|slot| {
+            if slot == "rest" {
+                return Ok(None);
+            }
+            match slot.parse::<usize>() {
+                Ok(n) => Ok(Some(n)),
+                Err(_) => Err(exit::failure!(
+                    Usage,
+                    "invalid --order entry '{}': expected a group number or rest",
+                    slot
+                )),
+            }
+        })
+        .collect()
 }
 
 ///// Parse a pick ID that may have comma-separated ranges (e.g., "id:2,5-6,34").
 /// Returns a list of (id, optional range) tuples - one per range, or one with None if no ranges.
 #[allow(clippy::type_complexity)]
 fn parse_pick_id(s: &str) -> anyhow::Result<Vec<(String, Option<(usize, usize)>)>> {
-    if let Some((id, range_str)) = s.split_once(':') {
+    if let (id, Some(range_str)) = hunk_id::split_range(s) {
         let mut results = Vec::new();
         for part in range_str.split(',') {
             let part = part.trim();
@@ -290,60 +1625,882 @@ fn parse_line_range(s: &str) -> Result<(usize, usize), String> {
     Ok((start, end))
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+/// Parse the command line. With `surgeon.json` set, commands that take
+/// `--json` get it by default, unless something given conflicts with it;
+/// the flag is whether it was added.
+fn parse_cli() -> (Cli, bool) {
+    let args: Vec<std::ffi::OsString> = std::env::args_os().collect();
+    let json_default = config::get_bool("surgeon.json").unwrap_or_else(|e| {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    });
+    if json_default == Some(true)
+        && let Some(at) = subcommand_index(&args)
+    {
+        let mut with_json = args.clone();
+        with_json.insert(at + 1, "--json".into());
+        if let Ok(cli) = try_parse(&with_json) {
+            return (cli, true);
+        }
+    }
+    (try_parse(&args).unwrap_or_else(|e| e.exit()), false)
+}
+
+/// `Cli::try_parse_from`, with each `--lines` given after one of several hunk
+/// IDs applied to that ID: `stage A --lines 1-3 B --lines 7-9`.
+fn try_parse(args: &[std::ffi::OsString]) -> Result<Cli, clap::Error> {
+    let matches = Cli::command().try_get_matches_from(args)?;
+    let mut cli = Cli::from_arg_matches(&matches).map_err(|e| e.format(&mut Cli::command()))?;
+    if let Some((regrouped, groups)) = regroup(args, &cli.command)? {
+        let matches = Cli::command().try_get_matches_from(&regrouped)?;
+        cli = Cli::from_arg_matches(&matches).map_err(|e| e.format(&mut Cli::command()))?;
+        let trailing = match &cli.command {
+            Commands::Split { args, .. } => args.as_slice(),
+            Commands::Commit { pick, .. } => pick.as_slice(),
+            _ => &[],
+        };
+        // Whatever clap left in front of the groups is no option of the command
+        if trailing.len() > groups.len() {
+            return Err(Cli::command().error(
+                clap::error::ErrorKind::UnknownArgument,
+                format!("unexpected argument '{}'", trailing[0]),
+            ));
+        }
+    }
+    if let (
+        Commands::Stage { ids, lines, .. }
+        | Commands::Unstage { ids, lines, .. }
+        | Commands::Discard { ids, lines, .. }
+        | Commands::Undo { ids, lines, .. }
+        | Commands::Revert { ids, lines, .. }
+        | Commands::RestoreFrom { ids, lines, .. }
+        | Commands::Fixup { ids, lines, .. },
+        Some((_, sub)),
+    ) = (&mut cli.command, matches.subcommand())
+    {
+        pair_lines(ids, lines, sub);
+    }
+    Ok(cli)
+}
+
+/// Split's trailing args, and commit's --pick groups, also take the command's
+/// other options, such as `-q` or `--order=rest,1` after the picks. Returns
+/// the command line with those moved in front of the groups for clap to
+/// parse, and the groups, or None if there are none among them.
+#[allow(clippy::type_complexity)]
+fn regroup(
+    args: &[std::ffi::OsString],
+    command: &Commands,
+) -> Result<Option<(Vec<std::ffi::OsString>, Vec<String>)>, clap::Error> {
+    let (lead, trailing) = match command {
+        Commands::Split { args, .. } => (None, args),
+        Commands::Commit { pick, .. } if !pick.is_empty() => (Some("--pick"), pick),
+        _ => return Ok(None),
+    };
+    let given: Vec<String> = lead
+        .map(String::from)
+        .into_iter()
+        .chain(trailing.iter().cloned())
+        .collect();
+    // The groups run to the end of the command line, unless given as
+    // `--pick=ID`, which is left to the group parser
+    let Some(start) = args.len().checked_sub(given.len()) else {
+        return Ok(None);
+    };
+    if args[start..]
+        .iter()
+        .zip(&given)
+        .any(|(arg, given)| arg.to_str() != Some(given))
+    {
+        return Ok(None);
+    }
+    let (groups, others) = split_group_args(&given);
+    if others.is_empty() && groups == given {
+        return Ok(None);
+    }
+    if lead.is_some()
+        && let Some(arg) = others.iter().find(|arg| is_split_option(arg))
+    {
+        return Err(Cli::command().error(
+            clap::error::ErrorKind::UnknownArgument,
+            format!(
+                "{} is not a commit option: commit --pick groups take -m, -F, -C, -e, \
+                 --trailer and --include/--exclude; the other options are split's",
+                arg
+            ),
+        ));
+    }
+    let regrouped = args[..start]
+        .iter()
+        .cloned()
+        .chain(others.into_iter().map(Into::into))
+        .chain(groups.iter().map(Into::into))
+        .collect();
+    Ok(Some((regrouped, groups)))
+}
+
+/// Whether `arg` is an option of split's and not of commit's.
+fn is_split_option(arg: &str) -> bool {
+    let name = arg.split('=').next().unwrap_or(arg);
+    let takes = |subcommand: &str| {
+        Cli::command()
+            .find_subcommand(subcommand)
+            .is_some_and(|command| {
+                command.get_arguments().any(|a| {
+                    a.get_long()
+                        .is_some_and(|long| name.strip_prefix("--") == Some(long))
+                        || a.get_short().is_some_and(|short| {
+                            name.strip_prefix('-') == Some(short.encode_utf8(&mut [0; 4]))
+                        })
+                })
+            })
+    };
+    takes("split") && !takes("commit")
+}
+
+/// Move each of `lines` onto the ID given last before it, as an inline range.
+/// With one ID, or a range given before any ID, `lines` are left for the
+/// command to apply to its single ID.
+fn pair_lines(ids: &mut [String], lines: &mut Vec<(usize, usize)>, matches: &clap::ArgMatches) {
+    if ids.len() < 2 || lines.is_empty() {
+        return;
+    }
+    let (Some(id_at), Some(lines_at)) = (matches.indices_of("ids"), matches.indices_of("lines"))
+    else {
+        return;
+    };
+    let id_at: Vec<usize> = id_at.collect();
+    let mut paired: Vec<Vec<(usize, usize)>> = vec![Vec::new(); ids.len()];
+    for (at, range) in lines_at.zip(lines.iter()) {
+        let Some(owner) = id_at.iter().rposition(|&i| i < at) else {
+            return;
+        };
+        paired[owner].push(*range);
+    }
+    for (id, ranges) in ids.iter_mut().zip(&paired) {
+        if ranges.is_empty() {
+            continue;
+        }
+        let separator = match hunk_id::split_range(id) {
+            (_, Some(_)) => ",",
+            (_, None) => ":",
+        };
+        id.push_str(separator);
+        id.push_str(&hunk::format_ranges(ranges));
+    }
+    lines.clear();
+}
+
+/// Where the subcommand is in `args`: the first argument that is neither a
+/// global option nor a global option's value.
+fn subcommand_index(args: &[std::ffi::OsString]) -> Option<usize> {
+    let command = Cli::command();
+    let takes_value = |arg: &str| {
+        command.get_arguments().any(|a| {
+            a.get_action().takes_values()
+                && (a
+                    .get_long()
+                    .is_some_and(|l| arg.strip_prefix("--") == Some(l))
+                    || a.get_short().is_some_and(|s| arg == format!("-{}", s)))
+        })
+    };
+    let mut i = 1;
+    while i < args.len() {
+        let arg = args[i].to_str()?;
+        if !arg.starts_with('-') {
+            return Some(i);
+        }
+        i += if takes_value(arg) { 2 } else { 1 };
+    }
+    None
+}
+
+fn main() {
+    if let Err(e) = config::load() {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(1);
+    }
+    let (cli, added_json) = parse_cli();
+    // The daemon has its own environment, so settings from this one would be
+    // lost there
+    if let Commands::Hunks { color, .. } | Commands::Show { color, .. } = &cli.command
+        && !config::from_environment()
+        && let Ok(enabled) = color.resolve()
+    {
+        // The daemon's stdout is not the terminal, so decide here
+        let when = if enabled { "always" } else { "never" };
+        let mut extra = vec![format!("--color={}", when)];
+        if added_json {
+            extra.push("--json".to_string());
+        }
+        if let Some(code) = daemon::forward(&extra) {
+            std::process::exit(code);
+        }
+    }
+    if let Err(e) = run(cli) {
+        eprintln!("Error: {:?}", e);
+        std::process::exit(exit::code(&e));
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    output::set_verbosity(cli.verbose);
+    // Dropped before the caller prints the error, so errors still show
+    let _silence = if cli.quiet {
+        Some(output::Silence::new()?)
+    } else {
+        None
+    };
+    output::set_timeout(match cli.timeout {
+        Some(secs) => secs,
+        None => config::get_usize("surgeon.timeout")?.unwrap_or(0) as u64,
+    });
+    hunk_id::configure_scheme(cli.id_scheme)?;
+    diff::configure_context(cli.context)?;
+    patch::set_whitespace(cli.whitespace);
+    rev::set_auto_deepen(cli.auto_deepen);
+
+    // Held until the command finishes; taken before the --if-match check so
+    // nothing can change the repository between the check and the command
+    let _lock = match cli.command.mutating_name() {
+        Some(name) => {
+            let timeout = if cli.no_wait { 0 } else { cli.lock_timeout };
+            Some(lock::acquire(
+                name,
+                std::time::Duration::from_secs(timeout),
+            )?)
+        }
+        None => None,
+    };
+
+    if let Some(expected) = &cli.if_match {
+        token::check(expected)?;
+    }
 
-    match cli.command {
+    let signs = match cli.command.sign_args() {
+        Some(sign) => {
+            sign.configure();
+            true
+        }
+        None => false,
+    };
+    let update_refs = cli.command.rewrites_history() && branches::enabled(cli.update_refs)?;
+    // Where history started, to report signatures dropped by the command and
+    // to move the branches on the commits it rewrote
+    let old_head = if !signs && !update_refs {
+        None
+    } else if matches!(cli.command, Commands::Continue { .. }) {
+        state::State::load()?.map(|state| state.orig_head)
+    } else {
+        rev::resolve_commit("HEAD").ok()
+    };
+
+    // A git-surgeon operation the command starts but a timeout cuts short is
+    // rolled back, leaving the repository as it was
+    let had_operation = output::timeout().is_some() && state::State::load()?.is_some();
+    if let Err(e) = dispatch(cli.command) {
+        let Some(timed_out) = output::timed_out() else {
+            return Err(e);
+        };
+        if !had_operation
+            && state::State::load()?.is_some()
+            && let Err(e) = hunk::abort_operation()
+        {
+            eprintln!("warning: failed to roll back: {:#}", e);
+        }
+        return Err(timed_out);
+    }
+
+    if let Some(old_head) = old_head {
+        if signs {
+            signing::report_dropped(&old_head)?;
+        }
+        // A rewrite stopped for editing or a conflict moves them on `continue`
+        if update_refs && state::State::load()?.is_none() {
+            branches::update_moved(&old_head)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `command` once the repository is locked and checked.
+fn dispatch(command: Commands) -> Result<()> {
+    match command {
         Commands::Hunks {
             staged,
+            all,
             file,
             commit,
             full,
             blame,
-        } => hunk::list_hunks(staged, file.as_deref(), commit.as_deref(), full, blame)?,
-        Commands::Show { id, commit } => hunk::show_hunk(&id, commit.as_deref())?,
-        Commands::Stage { ids, lines } => hunk::apply_hunks(&ids, patch::ApplyMode::Stage, lines)?,
-        Commands::Unstage { ids, lines } => {
-            hunk::apply_hunks(&ids, patch::ApplyMode::Unstage, lines)?
-        }
-        Commands::Discard { ids, lines } => {
-            hunk::apply_hunks(&ids, patch::ApplyMode::Discard, lines)?
-        }
-        Commands::Commit { ids, message } => hunk::commit_hunks(&ids, &message.join("\n\n"))?,
-        Commands::Fixup { commit } => hunk::fixup(&commit)?,
-        Commands::Reword { commit, message } => hunk::reword(&commit, &message.join("\n\n"))?,
-        Commands::Undo { ids, from, lines } => hunk::undo_hunks(&ids, &from, lines)?,
-        Commands::UndoFile { files, from } => hunk::undo_files(&files, &from)?,
-        Commands::Split { commit, args } => {
-            let (pick_groups, rest_message) = parse_split_args(&args)?;
-            hunk::split(&commit, &pick_groups, rest_message.as_deref())?;
+            ignore_revs,
+            sample,
+            json,
+            format,
+            preview,
+            preview_lines,
+            stat,
+            sort,
+            min_changes,
+            max_changes,
+            ignore_whitespace,
+            function_context,
+            recurse_submodules,
+            group_by_function,
+            filter,
+            color,
+        } => {
+            color::set_enabled(color.resolve()?);
+            hunk::list_hunks(&hunk::ListOptions {
+                staged,
+                all,
+                file: file.as_deref(),
+                commit: commit.as_deref(),
+                full,
+                blame,
+                ignore_revs: &ignore_revs,
+                sample,
+                json,
+                format: format.as_deref(),
+                preview,
+                preview_lines: match preview_lines {
+                    Some(n) => n,
+                    None => config::get_usize("surgeon.previewLines")?
+                        .unwrap_or(hunk::DEFAULT_PREVIEW_LINES),
+                },
+                stat,
+                sort,
+                min_changes,
+                max_changes,
+                ignore_whitespace,
+                function_context,
+                recurse_submodules,
+                group_by_function,
+                filter: &filter,
+            })?
+        }
+        Commands::Files {
+            staged,
+            commit,
+            json,
+        } => hunk::list_files(staged, commit.as_deref(), json)?,
+        Commands::ResolveId { id, staged, commit } => {
+            registry::resolve_id(&id, staged, commit.as_deref())?
+        }
+        Commands::Show {
+            id,
+            commit,
+            full_file,
+            function_context,
+            json,
+            color,
+        } => {
+            color::set_enabled(color.resolve()?);
+            hunk::show_hunk(&id, commit.as_deref(), full_file, function_context, json)?
         }
+        Commands::Log {
+            range,
+            file,
+            grep,
+            max_count,
+            json,
+            color,
+        } => {
+            color::set_enabled(color.resolve()?);
+            log::log(
+                range.as_deref(),
+                file.as_deref(),
+                grep.as_deref(),
+                max_count,
+                json,
+            )?
+        }
+        Commands::FindCommit {
+            grep,
+            author,
+            since,
+            file,
+            json,
+            color,
+        } => {
+            color::set_enabled(color.resolve()?);
+            let query = find::Query {
+                grep: &grep,
+                author: author.as_deref(),
+                since: since.as_deref(),
+                file: file.as_deref(),
+            };
+            find::find_commits(&query, json)?
+        }
+        Commands::Interdiff {
+            old,
+            new,
+            full,
+            json,
+            color,
+        } => {
+            color::set_enabled(color.resolve()?);
+            interdiff::interdiff(&old, &new, full, json)?
+        }
+        Commands::Stage {
+            ids,
+            lines,
+            whitespace_only,
+            all,
+            symbol,
+            file,
+            filter,
+        } => {
+            if !filter.is_empty() && !all {
+                exit::fail!(Usage, "--include and --exclude select hunks for --all");
+            }
+            registry::set_auto_resolve(true);
+            if let (Some(symbol), Some(file)) = (&symbol, &file) {
+                hunk::stage_symbol(symbol, file)?
+            } else {
+                let ids = if whitespace_only {
+                    hunk::whitespace_only_ids()?
+                } else if all {
+                    hunk::listed_ids(false, &filter)?
+                } else {
+                    ids
+                };
+                hunk::apply_hunks(&ids, patch::ApplyMode::Stage, &lines, false)?
+            }
+        }
+        Commands::StageFile { paths } => hunk::stage_files(&paths, false)?,
+        Commands::UnstageFile { paths } => hunk::stage_files(&paths, true)?,
+        Commands::Track { paths } => hunk::track_files(&paths)?,
+        Commands::Unstage { ids, lines, all } => {
+            registry::set_auto_resolve(true);
+            let ids = if all {
+                hunk::listed_ids(true, &glob::PathFilter::default())?
+            } else {
+                ids
+            };
+            hunk::apply_hunks(&ids, patch::ApplyMode::Unstage, &lines, false)?
+        }
+        Commands::Discard {
+            ids,
+            lines,
+            all,
+            yes,
+            delete,
+        } => {
+            if all {
+                hunk::discard_all(yes, delete)?
+            } else {
+                hunk::apply_hunks(&ids, patch::ApplyMode::Discard, &lines, delete)?
+            }
+        }
+        Commands::Commit {
+            ids,
+            message,
+            file,
+            message_from,
+            edit,
+            signoff,
+            trailer,
+            no_verify,
+            no_template,
+            filter,
+            sign: _,
+            pick,
+        } => {
+            registry::set_auto_resolve(true);
+            if !pick.is_empty() {
+                return commit_in_groups(&pick, signoff, &trailer, no_verify, no_template);
+            }
+            hunk::set_no_verify(no_verify);
+            let ids = match (ids.is_empty(), filter.is_empty()) {
+                (_, true) => ids,
+                (true, false) => hunk::listed_ids(false, &filter)?,
+                (false, false) => {
+                    exit::fail!(Usage, "pass hunk IDs or --include/--exclude, not both")
+                }
+            };
+            let trailers = trailer::Trailers::new(signoff, trailer)?;
+            let mut message = match &message_from {
+                Some(commit) => commit_message(commit, "%B")?,
+                None => message_text(&message, file.as_deref())?,
+            };
+            // A reused message is kept as it is
+            if let Some(template) = load_template(no_template || message_from.is_some())? {
+                let (files, hunk_count) = hunk::hunk_files(None, &ids, false)?;
+                message = template.expand(&message, &files, hunk_count)?;
+            }
+            let mut message = trailers.apply(&message)?;
+            if edit {
+                message = editor::edit(&message, &hunk::hunk_stat_lines(None, &ids, false)?)?;
+            }
+            hunk::commit_hunks(&ids, &message)?
+        }
+        Commands::Amend {
+            ids,
+            message,
+            sign: _,
+        } => {
+            let message = (!message.is_empty()).then(|| message.join("\n\n"));
+            hunk::amend(&ids, message.as_deref())?
+        }
+        Commands::Fixup {
+            commit,
+            mut ids,
+            lines,
+            no_rebase,
+            force_rewrite_published,
+            json,
+            no_verify,
+            committer,
+            sign: _,
+        } => {
+            hunk::set_no_verify(no_verify);
+            committer.configure()?;
+            let commit = rev::resolve_target(&commit)?;
+            let before = rev::resolve_commit("HEAD")?;
+            if !lines.is_empty() {
+                if ids.len() != 1 {
+                    exit::fail!(Usage, "--lines requires exactly one hunk ID");
+                }
+                ids[0] = format!("{}:{}", ids[0], hunk::format_ranges(&lines));
+            }
+            hunk::fixup(&commit, &ids, !no_rebase, force_rewrite_published)?;
+            range_diff::report(&before, json)?;
+        }
+        Commands::Reword {
+            commit,
+            message,
+            file,
+            keep_body,
+            edit,
+            signoff,
+            trailer,
+            force_rewrite_published,
+            json,
+            no_verify,
+            more,
+            committer,
+            sign: _,
+        } => {
+            hunk::set_no_verify(no_verify);
+            committer.configure()?;
+            let trailers = trailer::Trailers::new(signoff, trailer)?;
+            let first = RewordEdit {
+                commit,
+                message,
+                file,
+            };
+            let mut edits = Vec::new();
+            for mut reword in parse_reword_edits(first, &more)? {
+                // Messages name the commit as given, unless it is a search
+                if rev::is_message_pattern(&reword.commit) {
+                    reword.commit = rev::resolve_target(&reword.commit)?;
+                }
+                let message = reword.new_message(keep_body, edit, &trailers)?;
+                edits.push((reword.commit, message));
+            }
+            let before = rev::resolve_commit("HEAD")?;
+            hunk::reword(&edits, force_rewrite_published)?;
+            range_diff::report(&before, json)?;
+        }
+        Commands::Undo {
+            ids,
+            from,
+            lines,
+            three_way,
+            json,
+        } => hunk::undo_hunks(
+            &ids,
+            &rev::resolve_target(&from)?,
+            &lines,
+            patch::three_way_enabled(three_way),
+            json,
+        )?,
+        Commands::Revert {
+            ids,
+            from,
+            lines,
+            message,
+            file,
+            edit,
+            signoff,
+            trailer,
+            no_verify,
+            sign: _,
+        } => {
+            hunk::set_no_verify(no_verify);
+            let trailers = trailer::Trailers::new(signoff, trailer)?;
+            let message = if message.is_empty() && file.is_none() {
+                None
+            } else {
+                Some(message_text(&message, file.as_deref())?)
+            };
+            hunk::revert_hunks(&ids, &from, &lines, message.as_deref(), edit, &trailers)?
+        }
+        Commands::RestoreFrom { commit, ids, lines } => hunk::restore_hunks(&commit, &ids, &lines)?,
+        Commands::UndoFile {
+            files,
+            from,
+            three_way,
+            json,
+            yes,
+        } => hunk::undo_files(
+            &files,
+            &from,
+            patch::three_way_enabled(three_way),
+            json,
+            yes,
+        )?,
+        Commands::Split {
+            commit,
+            dry_run,
+            no_preserve_author,
+            force_rewrite_published,
+            verify_tree: _,
+            no_verify_tree,
+            json,
+            signoff,
+            args,
+            no_verify,
+            no_template,
+            order,
+            discard_rest,
+            auto_scope,
+            committer,
+            sign: _,
+        } => {
+            let commit = rev::resolve_target(&commit)?;
+            let mut parsed = parse_split_args(&args, auto_scope)?;
+            if auto_scope {
+                parsed.groups = suggest::scope_groups(&commit)?;
+                parsed.group_options = parsed.groups.iter().map(|_| Default::default()).collect();
+            }
+            if discard_rest && (parsed.rest_message.is_some() || !parsed.rest_trailers.is_empty()) {
+                exit::fail!(
+                    Usage,
+                    "--discard-rest drops the rest; it takes no --rest-message or trailers"
+                );
+            }
+            committer.configure()?;
+            hunk::set_no_verify(no_verify);
+            let mut groups = parsed.groups;
+            for (group, options) in groups.iter_mut().zip(&parsed.group_options) {
+                if !options.filter.is_empty() {
+                    let ids = hunk::commit_ids(&commit, &options.filter)?;
+                    group.ids = ids.into_iter().map(|id| (id, None)).collect();
+                }
+            }
+            let mut rest_message = parsed.rest_message;
+            if let Some(template) = load_template(no_template)? {
+                let mut picked = Vec::new();
+                for (group, options) in groups.iter_mut().zip(&parsed.group_options) {
+                    let ids: Vec<String> = group.ids.iter().map(|(id, _)| id.clone()).collect();
+                    if !options.reused {
+                        let (files, hunk_count) = hunk::hunk_files(Some(&commit), &ids, false)?;
+                        let message = group.message_parts.join("\n\n");
+                        group.message_parts = vec![template.expand(&message, &files, hunk_count)?];
+                    }
+                    // Hunks picked only in part still leave lines for the rest
+                    picked.extend(
+                        group
+                            .ids
+                            .iter()
+                            .filter(|(_, range)| range.is_none())
+                            .map(|(id, _)| id.clone()),
+                    );
+                }
+                if let Some(parts) = &mut rest_message {
+                    let (files, hunk_count) = hunk::hunk_files(Some(&commit), &picked, true)?;
+                    *parts = vec![template.expand(&parts.join("\n\n"), &files, hunk_count)?];
+                }
+            }
+            for (group, options) in groups.iter_mut().zip(parsed.group_options) {
+                let trailers = trailer::Trailers::new(signoff, options.trailers)?;
+                group.message_parts = with_trailers(&group.message_parts, &trailers)?;
+                if options.edit {
+                    let ids: Vec<String> = group.ids.iter().map(|(id, _)| id.clone()).collect();
+                    let summary = hunk::hunk_stat_lines(Some(&commit), &ids, false)?;
+                    let message = editor::edit(&group.message_parts.join("\n\n"), &summary)?;
+                    group.message_parts = vec![message];
+                }
+            }
+            let rest_trailers = trailer::Trailers::new(signoff, parsed.rest_trailers)?;
+            let rest_message = match rest_message {
+                Some(parts) => Some(with_trailers(&parts, &rest_trailers)?),
+                // The rest keeps the original message, which still needs them
+                None if !rest_trailers.is_empty() => {
+                    let original = commit_message(&commit, "%B")?;
+                    Some(vec![rest_trailers.apply(&original)?])
+                }
+                None => None,
+            };
+            if let Some(order) = order {
+                let order = parse_split_order(&order)?;
+                if discard_rest && order.contains(&None) {
+                    exit::fail!(Usage, "--order cannot place the rest with --discard-rest");
+                }
+                groups =
+                    hunk::order_split_groups(&commit, &groups, rest_message.as_deref(), &order)?;
+            }
+            let before = rev::resolve_commit("HEAD")?;
+            let rest = if discard_rest {
+                hunk::SplitRest::Discard
+            } else {
+                hunk::SplitRest::Commit(rest_message.as_deref())
+            };
+            hunk::split(
+                &commit,
+                &groups,
+                rest,
+                dry_run,
+                !no_preserve_author,
+                force_rewrite_published,
+                !no_verify_tree,
+            )?;
+            if !dry_run {
+                range_diff::report(&before, json)?;
+            }
+        }
+        Commands::SuggestSplit {
+            commit,
+            by,
+            plan,
+            ignore_revs,
+        } => suggest::suggest_split(commit.as_deref(), &by, plan, &ignore_revs)?,
         Commands::Squash {
             commit,
             message,
+            file,
+            message_from,
+            combine_messages: _,
+            edit,
+            signoff,
+            trailer,
             force,
             no_preserve_author,
+            force_rewrite_published,
+            verify_tree: _,
+            no_verify_tree,
+            json,
+            no_verify,
+            autosquash,
+            committer,
+            sign: _,
         } => {
-            hunk::squash(&commit, &message.join("\n\n"), force, !no_preserve_author)?;
+            hunk::set_no_verify(no_verify);
+            committer.configure()?;
+            // Range ends are checked as they are resolved
+            let commit = if commit.contains("..") {
+                commit
+            } else {
+                rev::resolve_target(&commit)?
+            };
+            let before = rev::resolve_commit("HEAD")?;
+            if autosquash {
+                hunk::autosquash(&commit, force_rewrite_published, !no_verify_tree)?;
+                range_diff::report(&before, json)?;
+                return Ok(());
+            }
+            let message = if let Some(from) = message_from {
+                hunk::SquashMessage::From(from)
+            } else if let Some(file) = file {
+                hunk::SquashMessage::Given(read_message_file(&file)?)
+            } else if message.is_empty() {
+                hunk::SquashMessage::Combined
+            } else {
+                hunk::SquashMessage::Given(message.join("\n\n"))
+            };
+            let message = if edit {
+                hunk::SquashMessage::Edited(Box::new(message))
+            } else {
+                message
+            };
+            let trailers = trailer::Trailers::new(signoff, trailer)?;
+            hunk::squash(
+                &commit,
+                &message,
+                &trailers,
+                force,
+                !no_preserve_author,
+                force_rewrite_published,
+                !no_verify_tree,
+            )?;
+            range_diff::report(&before, json)?;
+        }
+        Commands::Reorder {
+            range,
+            order,
+            verify_tree: _,
+            no_verify_tree,
+            sign: _,
+        } => hunk::reorder(&range, &order, !no_verify_tree)?,
+        Commands::Drop {
+            commits,
+            keep_changes,
+            yes,
+            force_rewrite_published,
+            sign: _,
+        } => hunk::drop_commits(&commits, keep_changes, yes, force_rewrite_published)?,
+        Commands::EditCommit { commit, sign: _ } => hunk::edit_commit(&commit)?,
+        Commands::Workflows { name, json } => {
+            workflows::print(&Cli::command(), name.as_deref(), json)?
+        }
+        Commands::ApplyPlan {
+            plan,
+            force_rewrite_published,
+            sign: _,
+        } => plan::apply_plan(&plan, force_rewrite_published)?,
+        Commands::Backups => backup::print_backups()?,
+        Commands::RestoreBackup { name } => backup::restore(name.as_deref())?,
+        Commands::RestoreAutostash { list } => autostash::restore_orphaned(list)?,
+        Commands::Daemon { stop } => {
+            if stop {
+                daemon::stop()?
+            } else {
+                daemon::serve()?
+            }
+        }
+        Commands::Continue { sign: _ } => {
+            let state = state::State::load()?;
+            hunk::continue_operation()?;
+            if let Some(state) = state
+                && matches!(
+                    state.operation,
+                    state::Operation::Fixup
+                        | state::Operation::Reword
+                        | state::Operation::Split
+                        | state::Operation::Squash
+                )
+            {
+                range_diff::report(&state.orig_head, false)?;
+            }
         }
+        Commands::Abort => hunk::abort_operation()?,
         Commands::InstallSkill {
-            claude,
-            opencode,
-            codex,
+            platforms,
+            all,
+            list,
         } => {
-            let mut platforms = Vec::new();
-            if claude {
-                platforms.push(skill::Platform::Claude);
-            }
-            if opencode {
-                platforms.push(skill::Platform::OpenCode);
+            if list {
+                skill::list_skills()?;
+            } else if all {
+                skill::install_skill_detected()?;
+            } else {
+                skill::install_skill(&platforms.selected())?;
             }
-            if codex {
-                platforms.push(skill::Platform::Codex);
+        }
+        Commands::SkillStatus { refresh } => skill::skill_status(refresh)?,
+        Commands::UninstallSkill { platforms, all } => {
+            let selected = platforms.selected();
+            if selected.is_empty() && !all {
+                exit::fail!(Usage, "at least one platform flag is required, or --all");
             }
-            skill::install_skill(&platforms)?;
+            skill::uninstall_skill(&selected)?;
+        }
+        Commands::InternalRestoreCommitter => committer::restore_from_rebase()?,
+        Commands::InternalEditTodo { marks, order, todo } => {
+            sequence::run_editor(&marks, &order, &todo)?
         }
     }
-
     Ok(())
 }
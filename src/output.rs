@@ -0,0 +1,327 @@
+//! How much a command prints: `-q` silences everything but the error a
+//! command fails with, `-v` traces each git command it runs with its timing,
+//! and `-vv` adds each traced command's exit status and error output. Also
+//! where `--timeout` kills git commands that hang, say on a hook waiting for
+//! input.
+
+use anyhow::Result;
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use std::sync::{Mutex, mpsc};
+use std::time::{Duration, Instant, SystemTime};
+
+/// 0 by default, 1 with `-v`, 2 with `-vv`.
+static VERBOSITY: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_verbosity(level: u8) {
+    VERBOSITY.store(level, Ordering::Relaxed);
+}
+
+fn verbosity() -> u8 {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+/// Running a `Command` the way `output` and `status` do, tracing it to
+/// stderr with `-v` and killing it once it outlives `--timeout`.
+pub trait Traced {
+    fn traced_output(&mut self) -> io::Result<Output>;
+    fn traced_status(&mut self) -> io::Result<ExitStatus>;
+    /// `output` with `input` written to the command's stdin.
+    fn traced_output_with_input(&mut self, input: &[u8]) -> io::Result<Output>;
+}
+
+impl Traced for Command {
+    fn traced_output(&mut self) -> io::Result<Output> {
+        traced(self, Stdin::Null, true)
+    }
+
+    fn traced_status(&mut self) -> io::Result<ExitStatus> {
+        traced(self, Stdin::Inherit, false).map(|output| output.status)
+    }
+
+    fn traced_output_with_input(&mut self, input: &[u8]) -> io::Result<Output> {
+        traced(self, Stdin::Bytes(input), false)
+    }
+}
+
+/// Seconds a git command may run before it is killed; 0 for no limit.
+static TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+
+/// The command line of the command killed for running too long.
+static TIMED_OUT: Mutex<Option<String>> = Mutex::new(None);
+
+pub fn set_timeout(secs: u64) {
+    TIMEOUT_SECS.store(secs, Ordering::Relaxed);
+    *TIMED_OUT.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+pub fn timeout() -> Option<Duration> {
+    match TIMEOUT_SECS.load(Ordering::Relaxed) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// The error for a command killed by `--timeout`, if one was.
+pub fn timed_out() -> Option<anyhow::Error> {
+    let command = TIMED_OUT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()?;
+    Some(crate::exit::failure!(
+        Timeout,
+        "'{}' timed out after {}s and was killed",
+        command,
+        TIMEOUT_SECS.load(Ordering::Relaxed)
+    ))
+}
+
+enum Stdin<'a> {
+    Null,
+    Inherit,
+    Bytes(&'a [u8]),
+}
+
+/// Run `cmd` to completion with stdout and stderr captured if `capture`.
+fn traced(cmd: &mut Command, stdin: Stdin, capture: bool) -> io::Result<Output> {
+    let start = Instant::now();
+    let output = match timeout() {
+        Some(limit) => run_with_timeout(cmd, stdin, capture, limit),
+        None => match stdin {
+            Stdin::Null => cmd.output(),
+            Stdin::Inherit => cmd.status().map(|status| Output {
+                status,
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+            Stdin::Bytes(input) => cmd.stdin(Stdio::piped()).spawn().and_then(|mut child| {
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(input)?;
+                child.wait_with_output()
+            }),
+        },
+    };
+    if verbosity() > 0 {
+        trace(cmd, start.elapsed());
+        if let Ok(output) = &output {
+            trace_result(output.status, &output.stderr);
+        }
+    }
+    output
+}
+
+/// How long to wait for a killed command's output before giving up on it,
+/// since processes it started (a hook's children) may still hold its pipes.
+const DRAIN_GRACE: Duration = Duration::from_millis(200);
+
+fn run_with_timeout(
+    cmd: &mut Command,
+    stdin: Stdin,
+    capture: bool,
+    limit: Duration,
+) -> io::Result<Output> {
+    match stdin {
+        Stdin::Null => cmd.stdin(Stdio::null()),
+        Stdin::Inherit => cmd.stdin(Stdio::inherit()),
+        Stdin::Bytes(_) => cmd.stdin(Stdio::piped()),
+    };
+    if capture {
+        cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let started = SystemTime::now();
+    let mut child = cmd.spawn()?;
+    if let (Stdin::Bytes(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        let input = input.to_vec();
+        std::thread::spawn(move || pipe.write_all(&input));
+    }
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let deadline = Instant::now() + limit;
+    let mut killed = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            killed = true;
+            break child.wait()?;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    let collect = |pipe: Option<mpsc::Receiver<Vec<u8>>>| {
+        let pipe = pipe?;
+        if killed {
+            pipe.recv_timeout(DRAIN_GRACE).ok()
+        } else {
+            pipe.recv().ok()
+        }
+    };
+    let stdout = collect(stdout).unwrap_or_default();
+    let mut stderr = collect(stderr).unwrap_or_default();
+    if killed {
+        let command = command_line(cmd);
+        remove_stale_index_lock(started);
+        stderr.extend(format!("timed out after {}s\n", limit.as_secs()).as_bytes());
+        *TIMED_OUT.lock().unwrap_or_else(|e| e.into_inner()) = Some(command);
+    }
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Read `pipe` to its end on a thread, so a full pipe cannot block the command.
+fn drain(mut pipe: impl Read + Send + 'static) -> mpsc::Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = pipe.read_to_end(&mut buf);
+        let _ = sender.send(buf);
+    });
+    receiver
+}
+
+/// A killed git leaves its `index.lock` behind, which would make every later
+/// command fail; remove it if the killed command took it.
+fn remove_stale_index_lock(since: SystemTime) {
+    let Ok(output) = Command::new("git")
+        .args(["rev-parse", "--git-path", "index.lock"])
+        .output()
+    else {
+        return;
+    };
+    let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let taken_since = std::fs::metadata(&path)
+        .and_then(|m| m.modified())
+        .is_ok_and(|modified| modified >= since);
+    if taken_since {
+        let _ = std::fs::remove_file(&path);
+    }
+}
+
+fn trace(cmd: &Command, elapsed: Duration) {
+    eprintln!(
+        "+ {} ({:.1} ms)",
+        command_line(cmd),
+        elapsed.as_secs_f64() * 1000.0
+    );
+}
+
+/// With `-vv`, a failed command's exit status and what it printed to stderr.
+fn trace_result(status: ExitStatus, stderr: &[u8]) {
+    if verbosity() < 2 || status.success() {
+        return;
+    }
+    eprintln!("  {}", status);
+    for line in String::from_utf8_lossy(stderr).lines() {
+        eprintln!("  | {}", line);
+    }
+}
+
+/// `cmd` as a shell would run it, with the environment it sets.
+fn command_line(cmd: &Command) -> String {
+    let mut parts: Vec<String> = cmd
+        .get_envs()
+        .filter_map(|(key, value)| {
+            let value = value?;
+            Some(format!(
+                "{}={}",
+                key.to_string_lossy(),
+                quote(&value.to_string_lossy())
+            ))
+        })
+        .collect();
+    if let Some(dir) = cmd.get_current_dir() {
+        parts.insert(0, format!("cd {} &&", quote(&dir.to_string_lossy())));
+    }
+    parts.push(cmd.get_program().to_string_lossy().into_owned());
+    parts.extend(cmd.get_args().map(|a| quote(&a.to_string_lossy())));
+    parts.join(" ")
+}
+
+fn quote(s: &str) -> String {
+    let plain = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@^~+,%{}".contains(c));
+    if plain {
+        s.to_string()
+    } else {
+        crate::sequence::shell_quote(s)
+    }
+}
+
+/// Keeps stdout and stderr pointed at the null device while held, for `-q`.
+pub struct Silence {
+    #[cfg(unix)]
+    _guards: (Redirect, Redirect),
+}
+
+impl Silence {
+    pub fn new() -> Result<Silence> {
+        #[cfg(unix)]
+        {
+            let null = std::fs::OpenOptions::new().write(true).open("/dev/null")?;
+            Ok(Silence {
+                _guards: (Redirect::new(1, &null)?, Redirect::new(2, &null)?),
+            })
+        }
+        #[cfg(not(unix))]
+        anyhow::bail!("--quiet is only supported on Unix")
+    }
+}
+
+#[cfg(unix)]
+pub use imp::Redirect;
+
+#[cfg(unix)]
+mod imp {
+    use anyhow::Result;
+    use std::io::Write;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    unsafe extern "C" {
+        fn dup(fd: i32) -> i32;
+        fn dup2(src: i32, dst: i32) -> i32;
+    }
+
+    /// Point `fd` at `target` until the guard is dropped.
+    pub struct Redirect {
+        fd: i32,
+        saved: OwnedFd,
+    }
+
+    impl Redirect {
+        pub fn new(fd: i32, target: &impl AsRawFd) -> Result<Redirect> {
+            // SAFETY: dup/dup2 only operate on descriptors we own or on the
+            // process's standard streams; the saved copy is owned by the guard.
+            unsafe {
+                let saved = dup(fd);
+                if saved < 0 {
+                    anyhow::bail!("failed to save file descriptor {}", fd);
+                }
+                let saved = OwnedFd::from_raw_fd(saved);
+                if dup2(target.as_raw_fd(), fd) < 0 {
+                    anyhow::bail!("failed to redirect file descriptor {}", fd);
+                }
+                Ok(Redirect { fd, saved })
+            }
+        }
+    }
+
+    impl Drop for Redirect {
+        fn drop(&mut self) {
+            let _ = std::io::stdout().flush();
+            // SAFETY: restores the descriptor saved in `new`
+            unsafe {
+                dup2(self.saved.as_raw_fd(), self.fd);
+            }
+        }
+    }
+}
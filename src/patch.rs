@@ -1,24 +1,23 @@
 use anyhow::{Context, Result};
+use std::sync::Mutex;
 
 use crate::diff::DiffHunk;
+use crate::output::Traced;
 
 pub enum ApplyMode {
     Stage,
     Unstage,
     Discard,
+    /// Apply forward to the working tree only
+    Worktree,
 }
 
-/// Slice a hunk to only include changes within the given 1-based line range.
-/// Lines outside the range have their changes neutralized:
+/// Slice a hunk keeping changes from any of the given 1-based line ranges.
+/// Lines outside them have their changes neutralized:
 /// - excluded '+' lines are dropped
 /// - excluded '-' lines become context (the deletion is kept)
 ///
 /// Context lines are always preserved for patch validity.
-pub fn slice_hunk(hunk: &DiffHunk, start: usize, end: usize, reverse: bool) -> Result<DiffHunk> {
-    slice_hunk_multi(hunk, &[(start, end)], reverse)
-}
-
-/// Slice a hunk keeping changes from any of the given 1-based line ranges.
 pub fn slice_hunk_multi(
     hunk: &DiffHunk,
     ranges: &[(usize, usize)],
@@ -58,6 +57,17 @@ pub fn slice_hunk_multi(
         .count();
 
     let (old_start, new_start) = parse_hunk_starts(&hunk.header)?;
+    // A side starts at line 0 only while it is empty
+    let old_start = if old_start == 0 && old_count > 0 {
+        1
+    } else {
+        old_start
+    };
+    let new_start = if new_start == 0 && new_count > 0 {
+        1
+    } else {
+        new_start
+    };
 
     let func_ctx = hunk
         .header
@@ -167,6 +177,17 @@ pub fn slice_hunk_with_state(
         .count();
 
     let (old_start, new_start) = parse_hunk_starts(&hunk.header)?;
+    // A side starts at line 0 only while it is empty
+    let old_start = if old_start == 0 && old_count > 0 {
+        1
+    } else {
+        old_start
+    };
+    let new_start = if new_start == 0 && new_count > 0 {
+        1
+    } else {
+        new_start
+    };
 
     let func_ctx = hunk
         .header
@@ -209,10 +230,7 @@ pub fn build_patch(hunk: &DiffHunk) -> String {
 
 /// Apply a patch using git apply.
 pub fn apply_patch(patch: &str, mode: &ApplyMode) -> Result<()> {
-    use std::io::Write;
-    use std::process::{Command, Stdio};
-
-    let mut cmd = Command::new("git");
+    let mut cmd = std::process::Command::new("git");
     cmd.arg("apply");
 
     match mode {
@@ -225,15 +243,45 @@ pub fn apply_patch(patch: &str, mode: &ApplyMode) -> Result<()> {
         ApplyMode::Discard => {
             cmd.arg("--reverse");
         }
+        ApplyMode::Worktree => {}
     }
+    apply_with(&mut cmd, patch)
+}
+
+const WHITESPACE_ACTIONS: &[&str] = &["nowarn", "warn", "fix", "error", "error-all"];
+
+/// Set from `--whitespace`: how `git apply` treats whitespace errors in the
+/// lines it adds.
+static WHITESPACE: Mutex<Option<&'static str>> = Mutex::new(None);
 
-    cmd.stdin(Stdio::piped());
-    let mut child = cmd.spawn().context("failed to run git apply")?;
-    child.stdin.as_mut().unwrap().write_all(patch.as_bytes())?;
-    let output = child.wait_with_output()?;
+pub fn parse_whitespace(s: &str) -> Result<&'static str, String> {
+    WHITESPACE_ACTIONS
+        .iter()
+        .find(|action| **action == s)
+        .copied()
+        .ok_or_else(|| "expected nowarn, warn, fix, error or error-all".to_string())
+}
+
+pub fn set_whitespace(action: Option<&'static str>) {
+    *WHITESPACE.lock().unwrap_or_else(|e| e.into_inner()) = action;
+}
+
+/// Run a prepared `git apply` command with `patch` on stdin.
+pub fn apply_with(cmd: &mut std::process::Command, patch: &str) -> Result<()> {
+    if let Some(action) = *WHITESPACE.lock().unwrap_or_else(|e| e.into_inner()) {
+        cmd.arg(format!("--whitespace={}", action));
+    }
+    // Patches cut from a -U0 diff have no context to anchor them
+    if crate::diff::context_lines() == Some(0) {
+        cmd.arg("--unidiff-zero");
+    }
+    let output = cmd
+        .traced_output_with_input(patch.as_bytes())
+        .context("failed to run git apply")?;
 
     if !output.status.success() {
-        anyhow::bail!(
+        crate::exit::fail!(
+            Conflict,
             "git apply failed: {}",
             String::from_utf8_lossy(&output.stderr)
         );
@@ -241,3 +289,112 @@ pub fn apply_patch(patch: &str, mode: &ApplyMode) -> Result<()> {
 
     Ok(())
 }
+
+/// A file updated by a 3-way merge, with the number of conflicts left as
+/// markers in it.
+pub struct Merged {
+    pub file: String,
+    pub conflicts: usize,
+}
+
+/// Whether failed applies should fall back to a 3-way merge: `--3way`, or
+/// the `surgeon.threeWay` config.
+pub fn three_way_enabled(flag: bool) -> bool {
+    flag || std::process::Command::new("git")
+        .args(["config", "--bool", "--get", "surgeon.threeWay"])
+        .traced_output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "true")
+}
+
+/// Reverse-apply `patch`, taken from commit `sha`, to the working tree by
+/// merging each of `files`: the commit's version is the base, the base with
+/// the patch reversed is the other side, and the working tree is ours. This
+/// succeeds where `git apply` fails because the file changed since the
+/// commit; overlapping edits are left as conflict markers.
+pub fn reverse_apply_3way(patch: &str, sha: &str, files: &[String]) -> Result<Vec<Merged>> {
+    use std::process::Command;
+
+    let git_path = |name: &str| -> Result<std::path::PathBuf> {
+        let path = crate::diff::run_git_cmd(Command::new("git").args([
+            "rev-parse",
+            "--git-path",
+            &format!("surgeon-3way-{}-{}", name, std::process::id()),
+        ]))?;
+        Ok(std::path::PathBuf::from(path.trim()))
+    };
+    let index = git_path("index")?;
+    let base_file = git_path("base")?;
+    let theirs_file = git_path("theirs")?;
+    let result = (|| {
+        // Reverse the patch on a scratch index holding the commit's tree
+        let scratch_git = || {
+            let mut cmd = Command::new("git");
+            cmd.env("GIT_INDEX_FILE", &index);
+            cmd
+        };
+        crate::diff::run_git_cmd(scratch_git().args(["read-tree", sha]))?;
+        apply_with(
+            scratch_git().args(["apply", "--cached", "--reverse"]),
+            patch,
+        )
+        .context("the hunks do not reverse-apply to their own commit")?;
+
+        let root =
+            crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--show-toplevel"]))?;
+        let short = &sha[..7.min(sha.len())];
+        // Collect every side first so that nothing is written unless all
+        // files can be merged
+        let mut sides = Vec::new();
+        for file in files {
+            let base = crate::diff::run_git_cmd(Command::new("git").args([
+                "cat-file",
+                "blob",
+                &format!("{}:{}", sha, file),
+            ]));
+            let theirs = crate::diff::run_git_cmd(scratch_git().args([
+                "cat-file",
+                "blob",
+                &format!(":{}", file),
+            ]));
+            let worktree = std::path::Path::new(root.trim()).join(file);
+            let (Ok(base), Ok(theirs), true) = (base, theirs, worktree.is_file()) else {
+                anyhow::bail!("cannot 3-way merge {}: it is added or deleted", file);
+            };
+            sides.push((file, base, theirs, worktree));
+        }
+
+        let mut merged = Vec::new();
+        for (file, base, theirs, worktree) in sides {
+            std::fs::write(&base_file, base)?;
+            std::fs::write(&theirs_file, theirs)?;
+
+            let output = Command::new("git")
+                .arg("merge-file")
+                .args(["-L", "working tree", "-L", short])
+                .args(["-L", &format!("undo {}", short)])
+                .arg(&worktree)
+                .arg(&base_file)
+                .arg(&theirs_file)
+                .traced_output()
+                .context("failed to run git merge-file")?;
+            // The exit code is the number of conflicts, or negative on error
+            let conflicts = match output.status.code() {
+                Some(n) if (0..128).contains(&n) => n as usize,
+                _ => anyhow::bail!(
+                    "git merge-file failed for {}: {}",
+                    file,
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            };
+            merged.push(Merged {
+                file: file.clone(),
+                conflicts,
+            });
+        }
+        Ok(merged)
+    })();
+    for path in [&index, &base_file, &theirs_file] {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
@@ -0,0 +1,656 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+use crate::hunk_id::{assign_ids, resolve_ref};
+use crate::state::State;
+use crate::yaml::{self, Yaml};
+
+/// One step of a plan file.
+enum Step {
+    Stage {
+        hunks: Vec<String>,
+    },
+    Commit {
+        hunks: Vec<String>,
+        message: String,
+    },
+    Fixup {
+        commit: String,
+        hunks: Vec<String>,
+    },
+    Reword {
+        commit: String,
+        message: String,
+    },
+    Split {
+        commit: String,
+        groups: Vec<crate::PickGroup>,
+        rest_message: Option<Vec<String>>,
+    },
+}
+
+impl Step {
+    fn kind(&self) -> &'static str {
+        match self {
+            Step::Stage { .. } => "stage",
+            Step::Commit { .. } => "commit",
+            Step::Fixup { .. } => "fixup",
+            Step::Reword { .. } => "reword",
+            Step::Split { .. } => "split",
+        }
+    }
+
+    /// Working tree hunk IDs the step consumes.
+    fn worktree_hunks(&mut self) -> &mut [String] {
+        match self {
+            Step::Stage { hunks } | Step::Commit { hunks, .. } | Step::Fixup { hunks, .. } => hunks,
+            Step::Reword { .. } | Step::Split { .. } => &mut [],
+        }
+    }
+
+    fn commit(&self) -> Option<&str> {
+        match self {
+            Step::Fixup { commit, .. }
+            | Step::Reword { commit, .. }
+            | Step::Split { commit, .. } => Some(commit),
+            Step::Stage { .. } | Step::Commit { .. } => None,
+        }
+    }
+}
+
+/// Parse a plan file into its steps.
+fn parse_plan(input: &str) -> Result<Vec<Step>> {
+    let doc = yaml::parse(input)?;
+    let steps = match &doc {
+        Yaml::List(_) => &doc,
+        Yaml::Map(entries) => {
+            if let Some((key, _)) = entries.iter().find(|(k, _)| k != "steps") {
+                anyhow::bail!("unknown top-level key '{}' (expected 'steps')", key);
+            }
+            doc.get("steps")
+                .ok_or_else(|| anyhow::anyhow!("plan has no 'steps'"))?
+        }
+        Yaml::Scalar(_) => anyhow::bail!("plan must be a list of steps"),
+    };
+    let Yaml::List(items) = steps else {
+        anyhow::bail!("'steps' must be a list");
+    };
+    if items.is_empty() {
+        anyhow::bail!("plan has no steps");
+    }
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| parse_step(item).with_context(|| format!("step {}", i + 1)))
+        .collect()
+}
+
+fn parse_step(item: &Yaml) -> Result<Step> {
+    let Yaml::Map(entries) = item else {
+        anyhow::bail!("expected a mapping like 'commit: ...'");
+    };
+    let [(kind, body)] = entries.as_slice() else {
+        anyhow::bail!("each step must have exactly one kind (stage, commit, fixup, reword, split)");
+    };
+
+    let step = match kind.as_str() {
+        "stage" => Step::Stage {
+            hunks: string_list(body, "stage")?,
+        },
+        "commit" => {
+            check_keys(body, &["hunks", "message"])?;
+            Step::Commit {
+                hunks: string_list(required(body, "hunks")?, "hunks")?,
+                message: message(required(body, "message")?)?,
+            }
+        }
+        "fixup" => match body {
+            Yaml::Scalar(commit) => Step::Fixup {
+                commit: commit.clone(),
+                hunks: Vec::new(),
+            },
+            _ => {
+                check_keys(body, &["commit", "hunks"])?;
+                Step::Fixup {
+                    commit: scalar(required(body, "commit")?, "commit")?,
+                    hunks: body
+                        .get("hunks")
+                        .map(|h| string_list(h, "hunks"))
+                        .transpose()?
+                        .unwrap_or_default(),
+                }
+            }
+        },
+        "reword" => {
+            check_keys(body, &["commit", "message"])?;
+            Step::Reword {
+                commit: scalar(required(body, "commit")?, "commit")?,
+                message: message(required(body, "message")?)?,
+            }
+        }
+        "split" => {
+            check_keys(body, &["commit", "picks", "rest-message"])?;
+            let Yaml::List(picks) = required(body, "picks")? else {
+                anyhow::bail!("'picks' must be a list");
+            };
+            let mut groups = Vec::new();
+            for pick in picks {
+                check_keys(pick, &["hunks", "message"])?;
+                let mut ids = Vec::new();
+                for raw in string_list(required(pick, "hunks")?, "hunks")? {
+                    ids.extend(crate::parse_pick_id(&raw)?);
+                }
+                groups.push(crate::PickGroup {
+                    ids,
+                    message_parts: vec![message(required(pick, "message")?)?],
+                });
+            }
+            if groups.is_empty() {
+                anyhow::bail!("'picks' must not be empty");
+            }
+            Step::Split {
+                commit: scalar(required(body, "commit")?, "commit")?,
+                groups,
+                rest_message: body
+                    .get("rest-message")
+                    .map(|m| message(m).map(|m| vec![m]))
+                    .transpose()?,
+            }
+        }
+        other => anyhow::bail!(
+            "unknown step kind '{}' (expected stage, commit, fixup, reword or split)",
+            other
+        ),
+    };
+    if step.commit().is_some_and(|c| c.is_empty()) {
+        anyhow::bail!("'commit' must not be empty");
+    }
+    Ok(step)
+}
+
+fn check_keys(body: &Yaml, allowed: &[&str]) -> Result<()> {
+    let Yaml::Map(entries) = body else {
+        anyhow::bail!("expected a mapping with keys: {}", allowed.join(", "));
+    };
+    for (key, _) in entries {
+        if !allowed.contains(&key.as_str()) {
+            anyhow::bail!("unknown key '{}' (expected: {})", key, allowed.join(", "));
+        }
+    }
+    Ok(())
+}
+
+fn required<'a>(body: &'a Yaml, key: &str) -> Result<&'a Yaml> {
+    body.get(key)
+        .ok_or_else(|| anyhow::anyhow!("missing '{}'", key))
+}
+
+fn scalar(value: &Yaml, key: &str) -> Result<String> {
+    value
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("'{}' must be a single value", key))
+}
+
+/// A list of strings, also accepting a single whitespace-separated scalar.
+fn string_list(value: &Yaml, key: &str) -> Result<Vec<String>> {
+    let list: Vec<String> = match value {
+        Yaml::Scalar(s) => s.split_whitespace().map(str::to_string).collect(),
+        Yaml::List(items) => items
+            .iter()
+            .map(|item| scalar(item, key))
+            .collect::<Result<_>>()?,
+        Yaml::Map(_) => anyhow::bail!("'{}' must be a list", key),
+    };
+    if list.is_empty() {
+        anyhow::bail!("'{}' must not be empty", key);
+    }
+    Ok(list)
+}
+
+/// A commit message: one string, or a list of paragraphs like repeated `-m`.
+fn message(value: &Yaml) -> Result<String> {
+    let text = match value {
+        Yaml::List(parts) => parts
+            .iter()
+            .map(|p| scalar(p, "message").map(|p| p.trim_end().to_string()))
+            .collect::<Result<Vec<_>>>()?
+            .join("\n\n"),
+        _ => scalar(value, "message")?.trim_end().to_string(),
+    };
+    if text.trim().is_empty() {
+        anyhow::bail!("'message' must not be empty");
+    }
+    Ok(text)
+}
+
+/// Check every step against the repository before anything is changed.
+///
+/// Positional references (`@3`, `path:2`) are replaced by the IDs they name
+/// in the starting diff, so later steps are not thrown off by earlier ones.
+///
+/// Returns, per step, the distance of its target commit from the starting
+/// HEAD along first parents.
+fn validate(steps: &mut [Step], force_published: bool) -> Result<Vec<Option<usize>>> {
+    crate::hunk::check_no_rebase_in_progress()?;
+
+    let diff_output = crate::diff::run_git_diff(false, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+
+    let mut used: HashMap<String, usize> = HashMap::new();
+    // Which lines of each unstaged hunk earlier steps take, to tell whether
+    // the tree is clean by the time a split runs
+    let mut taken: HashMap<&str, Vec<bool>> = identified
+        .iter()
+        .map(|(id, hunk)| (id.as_str(), vec![false; hunk.lines.len()]))
+        .collect();
+    let other_changes = changes_outside_hunks(&identified)?;
+    let mut index_dirty = crate::hunk::has_staged_changes()?;
+    let mut distances = Vec::with_capacity(steps.len());
+    let mut split_at: HashMap<usize, usize> = HashMap::new();
+
+    for (i, step) in steps.iter_mut().enumerate() {
+        let n = i + 1;
+        let kind = step.kind();
+        let context = || format!("step {} ({})", n, kind);
+
+        let mut step_ids: Vec<&str> = Vec::new();
+        for raw in step.worktree_hunks() {
+            *raw = resolve_ref(&identified, "unstaged", raw).with_context(context)?;
+            let (id, ranges) = crate::hunk::parse_id_range(raw).with_context(context)?;
+            let (_, hunk) = identified
+                .iter()
+                .find(|(hunk_id, _)| hunk_id == id)
+                .ok_or_else(|| {
+                    crate::exit::failure!(NotFound, "hunk {} not found (re-run 'hunks')", id)
+                })
+                .with_context(context)?;
+            crate::diff::check_supported(hunk, id).with_context(context)?;
+            if let Some(lines) = taken.get_mut(id) {
+                if ranges.is_empty() {
+                    lines.fill(true);
+                }
+                for (start, end) in ranges {
+                    for line in lines.iter_mut().take(end).skip(start.saturating_sub(1)) {
+                        *line = true;
+                    }
+                }
+            }
+            // A partially applied hunk changes its ID, so a later step could
+            // not find it again
+            if let Some(prev) = used.get(id)
+                && !step_ids.contains(&id)
+            {
+                anyhow::bail!(
+                    "{}: hunk {} is already used by step {}; list all of its lines in one step",
+                    context(),
+                    id,
+                    prev
+                );
+            }
+            used.insert(id.to_string(), n);
+            step_ids.push(id);
+        }
+
+        let distance = match step.commit() {
+            Some(commit) => {
+                let sha = crate::rev::resolve_commit(commit).with_context(context)?;
+                crate::rev::check_history_present(&sha).with_context(context)?;
+                if !force_published {
+                    crate::rev::check_unpublished(&sha).with_context(context)?;
+                }
+                let distance = first_parent_distance(&sha).with_context(context)?;
+                if let Some(prev) = split_at.get(&distance) {
+                    anyhow::bail!(
+                        "{}: {} was split by step {}; it no longer names one commit",
+                        context(),
+                        commit,
+                        prev
+                    );
+                }
+                Some((sha, distance))
+            }
+            None => None,
+        };
+
+        match step {
+            Step::Stage { .. } => index_dirty = true,
+            Step::Commit { .. } => {
+                if index_dirty {
+                    crate::exit::fail!(
+                        RepoState,
+                        "{}: the index would already contain staged changes; commit them first",
+                        context()
+                    );
+                }
+            }
+            Step::Fixup { hunks, .. } => {
+                if !index_dirty && hunks.is_empty() {
+                    anyhow::bail!("{}: nothing staged to fold in", context());
+                }
+                index_dirty = false;
+            }
+            Step::Reword { .. } | Step::Split { .. } if index_dirty => {
+                crate::exit::fail!(
+                    RepoState,
+                    "{}: the index would contain staged changes",
+                    context()
+                );
+            }
+            Step::Reword { .. } => {}
+            Step::Split { groups, .. } => {
+                let leftover = identified.iter().find(|(id, hunk)| {
+                    hunk.lines
+                        .iter()
+                        .zip(&taken[id.as_str()])
+                        .any(|(line, taken)| {
+                            !taken && (line.starts_with('+') || line.starts_with('-'))
+                        })
+                });
+                if let Some((id, _)) = leftover {
+                    crate::exit::fail!(
+                        RepoState,
+                        "{}: the working tree would still be dirty (hunk {} is not taken by an \
+                         earlier step); split needs a clean tree",
+                        context(),
+                        id
+                    );
+                }
+                if other_changes {
+                    crate::exit::fail!(
+                        RepoState,
+                        "{}: the working tree has changes no step can take (untracked or \
+                         unsupported files); split needs a clean tree",
+                        context()
+                    );
+                }
+                let (sha, distance) = distance.as_ref().expect("split has a commit");
+                let diff_output = crate::diff::run_git_diff_commit(sha, None)?;
+                let hunks = crate::diff::parse_diff(&diff_output);
+                let commit_ids = assign_ids(&hunks);
+                let location = crate::registry::commit_location(sha);
+                *groups = crate::hunk::resolve_group_refs(&commit_ids, &location, groups)
+                    .with_context(context)?;
+                for (id, _) in groups.iter().flat_map(|g| &g.ids) {
+                    if !commit_ids.iter().any(|(hunk_id, _)| hunk_id == id) {
+                        crate::exit::fail!(
+                            NotFound,
+                            "{}: hunk {} not found in commit {}",
+                            context(),
+                            id,
+                            &sha[..7]
+                        );
+                    }
+                }
+                split_at.insert(*distance, n);
+            }
+        }
+        distances.push(distance.map(|(_, d)| d));
+    }
+    Ok(distances)
+}
+
+/// Whether `git status` shows worktree changes that are not among the
+/// unstaged hunks: untracked files, or files with no hunk to take.
+fn changes_outside_hunks(identified: &[(String, &crate::diff::DiffHunk)]) -> Result<bool> {
+    let status =
+        crate::diff::run_git_cmd(Command::new("git").args(["status", "--porcelain", "-z"]))?;
+    let mut entries = status.split('\0').filter(|e| !e.is_empty());
+    while let Some(entry) = entries.next() {
+        let (xy, path) = entry.split_at(3.min(entry.len()));
+        let (x, y) = (xy.as_bytes()[0], xy.as_bytes()[1]);
+        if matches!(x, b'R' | b'C') {
+            // The entry after a rename is its source path
+            entries.next();
+        }
+        if xy.starts_with("??") {
+            return Ok(true);
+        }
+        if y != b' ' && !identified.iter().any(|(_, hunk)| hunk.file == path) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Number of first-parent steps from HEAD back to `sha`.
+fn first_parent_distance(sha: &str) -> Result<usize> {
+    let out =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-list", "--first-parent", "HEAD"]))?;
+    out.lines()
+        .position(|line| line == sha)
+        .ok_or_else(|| anyhow::anyhow!("commit {} is not on the current branch", &sha[..7]))
+}
+
+fn commit_count() -> Result<usize> {
+    let out = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-list",
+        "--count",
+        "--first-parent",
+        "HEAD",
+    ]))?;
+    Ok(out.trim().parse().unwrap_or(0))
+}
+
+/// Execute a plan file: validate every step, then run them in order. If a
+/// step fails, HEAD and the index are restored to where the plan started.
+pub fn apply_plan(path: &std::path::Path, force_published: bool) -> Result<()> {
+    let input = if path.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("failed to read plan from stdin")?
+    } else {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?
+    };
+    let mut steps =
+        parse_plan(&input).with_context(|| format!("invalid plan {}", path.display()))?;
+    let distances = validate(&mut steps, force_published)?;
+
+    let orig_head = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let orig_head = orig_head.trim().to_string();
+    let orig_index = crate::diff::run_git_cmd(Command::new("git").arg("write-tree"))?;
+    let orig_index = orig_index.trim().to_string();
+
+    // Commit references name commits in the starting history. Track how many
+    // commits have been added above each original position so they can be
+    // found again after earlier steps rewrite history.
+    let mut inserted: Vec<(usize, usize)> = Vec::new(); // (applies to distances >= .0, count)
+    let total = steps.len();
+    let mut i = 0;
+    while i < total {
+        // Consecutive rewords share one rebase
+        let end = i + steps[i..]
+            .iter()
+            .take_while(|step| matches!(step, Step::Reword { .. }))
+            .count()
+            .max(1);
+        let step = &steps[i];
+        let label = if end - i > 1 {
+            format!("steps {}-{}", i + 1, end)
+        } else {
+            format!("step {}", i + 1)
+        };
+        eprintln!("{}/{}: {}", label, total, step.kind());
+        let targets: Vec<Option<String>> = distances[i..end]
+            .iter()
+            .map(|distance| {
+                distance.map(|d| {
+                    let shift: usize = inserted
+                        .iter()
+                        .filter(|(from, _)| d >= *from)
+                        .map(|(_, n)| n)
+                        .sum();
+                    format!("HEAD~{}", d + shift)
+                })
+            })
+            .collect();
+
+        let before = commit_count()?;
+        let result = if end - i > 1 {
+            run_rewords(&steps[i..end], &targets, force_published)
+        } else {
+            run_step(step, targets[0].as_deref(), force_published)
+        };
+        if let Err(err) = result {
+            eprintln!("error: {} ({}) failed", label, step.kind());
+            return match rollback(&orig_head, &orig_index) {
+                Ok(()) => {
+                    eprintln!("rolled back to {}", &orig_head[..7]);
+                    Err(err)
+                }
+                Err(rollback_err) => Err(err.context(format!(
+                    "rollback failed ({:#}); HEAD was {}",
+                    rollback_err, orig_head
+                ))),
+            };
+        }
+        let added = commit_count()?.saturating_sub(before);
+        if added > 0 {
+            // A split replaces its commit, so only older commits move
+            let from = match step {
+                Step::Split { .. } => distances[i].map_or(0, |d| d + 1),
+                _ => 0,
+            };
+            inserted.push((from, added));
+        }
+        i = end;
+    }
+
+    eprintln!("applied plan ({} steps)", total);
+    Ok(())
+}
+
+fn run_step(step: &Step, target: Option<&str>, force_published: bool) -> Result<()> {
+    match step {
+        Step::Stage { hunks } => crate::hunk::stage_hunk_specs(hunks),
+        Step::Commit { hunks, message } => crate::hunk::commit_hunks(hunks, message),
+        Step::Fixup { hunks, .. } => {
+            if !hunks.is_empty() {
+                crate::hunk::stage_hunk_specs(hunks)?;
+            }
+            crate::hunk::fixup(
+                target.expect("fixup has a commit"),
+                &[],
+                true,
+                force_published,
+            )
+        }
+        Step::Reword { message, .. } => crate::hunk::reword(
+            &[(
+                target.expect("reword has a commit").to_string(),
+                message.clone(),
+            )],
+            force_published,
+        ),
+        Step::Split {
+            groups,
+            rest_message,
+            ..
+        } => crate::hunk::split(
+            target.expect("split has a commit"),
+            groups,
+            crate::hunk::SplitRest::Commit(rest_message.as_deref()),
+            false,
+            true,
+            force_published,
+            true,
+        ),
+    }
+}
+
+/// Run a run of reword steps as one batch reword.
+fn run_rewords(steps: &[Step], targets: &[Option<String>], force_published: bool) -> Result<()> {
+    let mut edits = Vec::with_capacity(steps.len());
+    for (step, target) in steps.iter().zip(targets) {
+        let Step::Reword { message, .. } = step else {
+            unreachable!("only reword steps are batched");
+        };
+        let target = target.clone().expect("reword has a commit");
+        edits.push((target, message.clone()));
+    }
+    crate::hunk::reword(&edits, force_published)
+}
+
+/// Undo a partially applied plan: abort any interrupted operation, then put
+/// HEAD and the index back. The working tree is never modified by plan steps
+/// once they finish, so it needs no restoring.
+fn rollback(orig_head: &str, orig_index: &str) -> Result<()> {
+    if State::load()?.is_some() {
+        crate::hunk::abort_operation()?;
+    }
+    crate::diff::run_git_cmd(Command::new("git").args(["reset", "--quiet", "--soft", orig_head]))?;
+    crate::diff::run_git_cmd(Command::new("git").args(["read-tree", orig_index]))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plan_steps() {
+        let plan = "\
+steps:
+  - stage: [a1b2c3d, e4f5678:2-3]
+  - fixup: HEAD~2
+  - commit:
+      hunks: 1111111 2222222
+      message: |
+        Add retry
+
+        Body.
+  - reword:
+      commit: HEAD~1
+      message: [Subject, Body]
+  - split:
+      commit: HEAD
+      picks:
+        - hunks: [3333333:1-4]
+          message: First
+      rest-message: Rest
+";
+        let steps = parse_plan(plan).unwrap();
+        assert_eq!(steps.len(), 5);
+        let Step::Commit { hunks, message } = &steps[2] else {
+            panic!("expected commit")
+        };
+        assert_eq!(hunks, &["1111111", "2222222"]);
+        assert_eq!(message, "Add retry\n\nBody.");
+        let Step::Reword { message, .. } = &steps[3] else {
+            panic!("expected reword")
+        };
+        assert_eq!(message, "Subject\n\nBody");
+        let Step::Split {
+            groups,
+            rest_message,
+            ..
+        } = &steps[4]
+        else {
+            panic!("expected split")
+        };
+        assert_eq!(groups[0].ids, [("3333333".to_string(), Some((1, 4)))]);
+        assert_eq!(rest_message.as_deref(), Some(&["Rest".to_string()][..]));
+    }
+
+    #[test]
+    fn test_parse_plan_rejects_unknown_keys() {
+        let err = parse_plan("steps:\n  - commit:\n      hunks: [a]\n      msg: x\n")
+            .err()
+            .expect("plan should be rejected");
+        assert!(
+            format!("{:#}", err).contains("unknown key 'msg'"),
+            "{:#}",
+            err
+        );
+        let err = parse_plan("steps:\n  - rebase: HEAD\n")
+            .err()
+            .expect("plan should be rejected");
+        assert!(
+            format!("{:#}", err).contains("step 1: unknown step kind 'rebase'"),
+            "{:#}",
+            err
+        );
+    }
+}
@@ -0,0 +1,158 @@
+//! Blob-less partial clones (`git clone --filter=blob:none`) leave old file
+//! contents on the promisor remote, and git fetches each one the moment it
+//! reads it: a diff of an old commit, or a blame walking a file's history,
+//! waits on one round trip per blob. Listing the missing blobs first and
+//! fetching them in a single batch makes that one round trip.
+
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::time::Instant;
+
+use crate::output::Traced;
+
+/// Fetch the blobs the diff of `commit` reads, before it is run.
+pub fn prefetch_commit(commit: &str, file: Option<&str>) -> Result<()> {
+    let Some(remote) = promisor_remote() else {
+        return Ok(());
+    };
+    // Only trees are read here, and a blob-less clone has every tree
+    let mut cmd = Command::new("git");
+    cmd.args(["diff-tree", "-r", "--root", "--name-only", "--no-renames"])
+        .args(["--no-commit-id", "-z", commit]);
+    if let Some(f) = file {
+        cmd.arg("--").arg(f);
+    }
+    let listed = crate::diff::run_git_cmd(&mut cmd)?;
+    let paths: Vec<&str> = listed.split('\0').filter(|p| !p.is_empty()).collect();
+    if paths.is_empty() {
+        return Ok(());
+    }
+    // Trees rather than commits: a commit that doesn't change one of `paths`
+    // would be left out, with the blob its parent side needs
+    let mut revs = vec![format!("{}^{{tree}}", commit)];
+    if !crate::rev::is_root(commit) {
+        revs.push(format!("{}^^{{tree}}", commit));
+    }
+    let missing = missing_blobs(&revs, &paths)?;
+    fetch(&remote, &missing);
+    Ok(())
+}
+
+/// Fetch every version of each file in `files` (as of a revision, None for
+/// HEAD) that blame could read, before blaming them.
+pub fn prefetch_history(files: &[(Option<&str>, &str)]) -> Result<()> {
+    let Some(remote) = promisor_remote() else {
+        return Ok(());
+    };
+    let mut missing: Vec<String> = Vec::new();
+    let mut revisions: Vec<Option<&str>> = files.iter().map(|(rev, _)| *rev).collect();
+    revisions.sort();
+    revisions.dedup();
+    for revision in revisions {
+        let paths: Vec<&str> = files
+            .iter()
+            .filter(|(rev, _)| *rev == revision)
+            .map(|(_, file)| *file)
+            .collect();
+        let rev = revision.unwrap_or("HEAD").to_string();
+        for blob in missing_blobs(&[rev], &paths)? {
+            if !missing.contains(&blob) {
+                missing.push(blob);
+            }
+        }
+    }
+    fetch(&remote, &missing);
+    Ok(())
+}
+
+/// The remote a partial clone fetches missing objects from, None in a full
+/// clone.
+fn promisor_remote() -> Option<String> {
+    let output = Command::new("git")
+        .args(["config", "--get-regexp", r"^remote\..*\.promisor$"])
+        .traced_output()
+        .ok()?;
+    let listed = String::from_utf8_lossy(&output.stdout);
+    let remote = listed.lines().find_map(|line| {
+        let (key, value) = line.split_once(' ')?;
+        if value.trim() != "true" {
+            return None;
+        }
+        key.strip_prefix("remote.")?.strip_suffix(".promisor")
+    });
+    if let Some(remote) = remote {
+        return Some(remote.to_string());
+    }
+    // Set by older git, which had no remote.<name>.promisor
+    let output = Command::new("git")
+        .args(["config", "--get", "extensions.partialClone"])
+        .traced_output()
+        .ok()?;
+    let remote = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!remote.is_empty()).then_some(remote)
+}
+
+/// The objects of `paths` (relative to the top of the worktree) in `revs`,
+/// and in their history for commits, that are not in the local repository;
+/// listing them fetches nothing.
+fn missing_blobs(revs: &[String], paths: &[&str]) -> Result<Vec<String>> {
+    let listed = crate::diff::run_git_cmd(
+        Command::new("git")
+            .args(["rev-list", "--objects", "--missing=print"])
+            .args(revs)
+            .arg("--")
+            .args(paths.iter().map(|p| format!(":(top,literal){}", p))),
+    )?;
+    Ok(listed
+        .lines()
+        .filter_map(|line| line.strip_prefix('?'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Fetch `blobs` from `remote` in one request, the way git fetches a missing
+/// object itself. A failed prefetch is only a warning: git fetches what it
+/// lacks one blob at a time as before.
+fn fetch(remote: &str, blobs: &[String]) {
+    if blobs.is_empty() {
+        return;
+    }
+    let plural = if blobs.len() == 1 { "" } else { "s" };
+    eprintln!(
+        "partial clone: fetching {} missing blob{} from {}",
+        blobs.len(),
+        plural,
+        remote
+    );
+    let started = Instant::now();
+    let input = blobs.join("\n") + "\n";
+    let result = Command::new("git")
+        .args(["-c", "fetch.negotiationAlgorithm=noop", "fetch", remote])
+        .args([
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+        ])
+        .args(["--filter=blob:none", "--stdin"])
+        .traced_output_with_input(input.as_bytes())
+        .context("failed to run git fetch")
+        .and_then(|output| {
+            if output.status.success() {
+                Ok(())
+            } else {
+                anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim())
+            }
+        });
+    match result {
+        Ok(()) => eprintln!(
+            "partial clone: fetched {} blob{} in {:.1}s",
+            blobs.len(),
+            plural,
+            started.elapsed().as_secs_f64()
+        ),
+        Err(err) => eprintln!(
+            "warning: prefetching blobs failed ({:#}); git fetches them one at a time",
+            err
+        ),
+    }
+}
@@ -0,0 +1,192 @@
+use anyhow::Result;
+use std::process::Command;
+
+use crate::json::{self, Value};
+
+/// How a commit from before a rewrite relates to the commits after it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Status {
+    Unchanged,
+    Modified,
+    Removed,
+    Added,
+}
+
+impl Status {
+    fn marker(self) -> char {
+        match self {
+            Status::Unchanged => '=',
+            Status::Modified => '!',
+            Status::Removed => '<',
+            Status::Added => '>',
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Status::Unchanged => "unchanged",
+            Status::Modified => "modified",
+            Status::Removed => "removed",
+            Status::Added => "added",
+        }
+    }
+}
+
+/// One line of `git range-diff`: an old commit, its counterpart after the
+/// rewrite, or both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub old: Option<String>,
+    pub new: Option<String>,
+    pub status: Status,
+    pub subject: String,
+}
+
+/// Parse `git range-diff --no-patch` output, e.g.
+/// `1:  2cce2a7 ! 1:  90f6c91 subject`. Unrecognized lines are skipped.
+fn parse(output: &str) -> Vec<Entry> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut rest = line.trim_start();
+            let mut next = || -> Option<&str> {
+                let token_end = rest.find(char::is_whitespace)?;
+                let token = &rest[..token_end];
+                rest = rest[token_end..].trim_start();
+                Some(token)
+            };
+            let _old_pos = next()?.strip_suffix(':')?;
+            let old = next()?;
+            let status = match next()? {
+                "=" => Status::Unchanged,
+                "!" => Status::Modified,
+                "<" => Status::Removed,
+                ">" => Status::Added,
+                _ => return None,
+            };
+            let _new_pos = next()?.strip_suffix(':')?;
+            let new = next()?;
+            let sha = |s: &str| (!s.starts_with('-')).then(|| s.to_string());
+            Some(Entry {
+                old: sha(old),
+                new: sha(new),
+                status,
+                subject: rest.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Compare the commits reachable from `old_head` with those reachable from
+/// HEAD, from where the two histories diverge.
+pub fn compute(old_head: &str) -> Result<Vec<Entry>> {
+    // The highest creation factor still pairs a small commit with its
+    // original after a fixup rewrote most of its diff
+    let output = crate::diff::run_git_cmd(Command::new("git").args([
+        "range-diff",
+        "--no-color",
+        "--no-patch",
+        "--creation-factor=100",
+        &format!("{}...HEAD", old_head),
+    ]))?;
+    let mut entries = parse(&output);
+
+    // range-diff abbreviates and shows the old subject; report full SHAs and
+    // the subject each commit has now
+    let short: Vec<&str> = entries
+        .iter()
+        .flat_map(|e| [e.old.as_deref(), e.new.as_deref()])
+        .flatten()
+        .collect();
+    if !short.is_empty() {
+        let commits = crate::diff::run_git_cmd(
+            Command::new("git")
+                .args(["show", "--no-patch", "--format=%H%x00%s"])
+                .args(&short),
+        )?;
+        let mut commits = commits.lines().filter_map(|l| l.split_once('\0'));
+        for entry in &mut entries {
+            for sha in [&mut entry.old, &mut entry.new].into_iter().flatten() {
+                if let Some((full, subject)) = commits.next() {
+                    *sha = full.to_string();
+                    entry.subject = subject.to_string();
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Report how history changed since `old_head`: a JSON object on stdout, or
+/// one `old marker new subject` line per commit on stderr.
+pub fn report(old_head: &str, as_json: bool) -> Result<()> {
+    let entries = compute(old_head)?;
+    let new_head = crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "HEAD"]))?;
+    let new_head = new_head.trim();
+
+    if as_json {
+        let commits: Vec<Value> = entries
+            .iter()
+            .map(|e| {
+                json::object([
+                    ("status", e.status.name().into()),
+                    ("old", e.old.clone().into()),
+                    ("new", e.new.clone().into()),
+                    ("subject", e.subject.as_str().into()),
+                ])
+            })
+            .collect();
+        let output = json::object([
+            ("old_head", old_head.into()),
+            ("new_head", new_head.into()),
+            ("commits", Value::Array(commits)),
+        ]);
+        println!("{}", output);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let short = |sha: &Option<String>| match sha {
+        Some(sha) => sha[..7.min(sha.len())].to_string(),
+        None => "-------".to_string(),
+    };
+    eprintln!("range-diff {}..{}:", &old_head[..7], &new_head[..7]);
+    for e in &entries {
+        eprintln!(
+            "  {} {} {} {}",
+            short(&e.old),
+            e.status.marker(),
+            short(&e.new),
+            e.subject
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_diff() {
+        let output = "\
+1:  2cce2a7 = 1:  90f6c91 keep me
+2:  ba16ccd ! 2:  2b62dfb fix: edge  case
+3:  f6109d5 < -:  ------- gone
+-:  ------- > 3:  583418f new one
+10:  aaaaaaa = 10:  bbbbbbb padded
+    @@ ignored diff line
+";
+        let entries = parse(output);
+        assert_eq!(entries.len(), 5);
+        assert_eq!(entries[0].status, Status::Unchanged);
+        assert_eq!(entries[1].subject, "fix: edge  case");
+        assert_eq!(entries[1].new.as_deref(), Some("2b62dfb"));
+        assert_eq!(entries[2].new, None);
+        assert_eq!(entries[3].old, None);
+        assert_eq!(entries[3].status, Status::Added);
+        assert_eq!(entries[4].old.as_deref(), Some("aaaaaaa"));
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::diff::DiffHunk;
+
+/// Minimum similarity for a stale ID to be resolved without asking.
+const AUTO_RESOLVE: f64 = 0.6;
+/// How many candidates a "not found" error suggests.
+const MAX_SUGGESTIONS: usize = 3;
+/// Records kept across `hunks` runs; the oldest are dropped first.
+const MAX_RECORDS: usize = 500;
+/// Changed lines shown when reporting how a hunk was edited.
+const MAX_REPORTED_LINES: usize = 6;
+
+/// Set by the commands that may act on a stale ID's likely successor (stage,
+/// unstage and commit, which are easy to undo); the others fail with it as a
+/// suggestion instead.
+static AUTO_RESOLVE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+pub fn set_auto_resolve(enabled: bool) {
+    AUTO_RESOLVE_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A hunk as a `hunks` run listed it.
+struct Record {
+    id: String,
+    /// `unstaged` or `staged`, as in `hunks --all`, or `commit:<sha>`
+    location: String,
+    file: String,
+    lines: Vec<String>,
+}
+
+/// A current hunk that may be what a stale ID referred to.
+struct Candidate<'a> {
+    id: String,
+    hunk: &'a DiffHunk,
+    /// 0.0 to 1.0
+    similarity: f64,
+}
+
+/// Remember the hunks a `hunks` run listed, with where they were listed
+/// from, so a later command given one of these IDs can find the hunk again
+/// after its content changed.
+///
+/// The registry lives in `.git/surgeon/ids` and outlasts the run: hunks
+/// listed again replace their old record, everything else is kept up to
+/// `MAX_RECORDS`, newest first. Each record is `@ <id> <location> <file>`,
+/// then the hunk's lines as they appear in the diff. Hunks listed from
+/// `commit` are recorded for that commit only.
+pub fn record(listed: &[(&str, &DiffHunk, &str)], commit: Option<&str>) -> Result<()> {
+    let mut records: Vec<Record> = listed
+        .iter()
+        .map(|(id, hunk, location)| Record {
+            id: id.to_string(),
+            location: match (*location, commit) {
+                ("commit", Some(sha)) => commit_location(sha),
+                (location, _) => location.to_string(),
+            },
+            file: hunk.file.clone(),
+            lines: hunk.lines.clone(),
+        })
+        .collect();
+    for old in load()? {
+        if !records
+            .iter()
+            .any(|r| r.id == old.id && r.location == old.location)
+        {
+            records.push(old);
+        }
+    }
+    records.truncate(MAX_RECORDS);
+
+    let mut out = String::new();
+    for record in &records {
+        out.push_str(&format!(
+            "@ {} {} {}\n",
+            record.id, record.location, record.file
+        ));
+        for line in &record.lines {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    let path = crate::state::surgeon_path("ids")?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, out).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn load() -> Result<Vec<Record>> {
+    let path = crate::state::surgeon_path("ids")?;
+    let Ok(text) = fs::read_to_string(&path) else {
+        return Ok(Vec::new());
+    };
+    let mut records: Vec<Record> = Vec::new();
+    // Hunk lines keep the \r of CRLF files, as in the diff
+    for line in text
+        .split_inclusive('\n')
+        .map(|l| l.strip_suffix('\n').unwrap_or(l))
+    {
+        if let Some(fields) = line.strip_prefix("@ ") {
+            let mut fields = fields.splitn(3, ' ');
+            if let (Some(id), Some(location), Some(file)) =
+                (fields.next(), fields.next(), fields.next())
+            {
+                records.push(Record {
+                    id: id.to_string(),
+                    location: location.to_string(),
+                    file: file.to_string(),
+                    lines: Vec::new(),
+                });
+            }
+        } else if let Some(record) = records.last_mut() {
+            record.lines.push(line.to_string());
+        }
+    }
+    Ok(records)
+}
+
+/// The location hunks of the commit `sha` are recorded under, so an ID
+/// listed from one commit is never looked for in another.
+pub fn commit_location(sha: &str) -> String {
+    format!("commit:{}", sha)
+}
+
+/// Where `location` is, for messages: `staged`, or `in <short sha>`.
+fn describe_location(location: &str) -> String {
+    match location.strip_prefix("commit:") {
+        Some(sha) => format!("in {}", &sha[..7.min(sha.len())]),
+        None => location.to_string(),
+    }
+}
+
+/// The latest record of `id` listed from `location`.
+fn lookup(id: &str, location: &str) -> Result<Option<Record>> {
+    Ok(load()?
+        .into_iter()
+        .find(|r| r.id == id && r.location == location))
+}
+
+/// Current hunks of the recorded hunk's file that resemble it, most similar
+/// first.
+fn candidates<'a>(record: &Record, identified: &[(String, &'a DiffHunk)]) -> Vec<Candidate<'a>> {
+    let mut candidates: Vec<Candidate> = identified
+        .iter()
+        .filter(|(_, hunk)| hunk.file == record.file)
+        .map(|(id, hunk)| Candidate {
+            id: id.clone(),
+            hunk,
+            similarity: similarity(&record.lines, &hunk.lines),
+        })
+        .filter(|c| c.similarity > 0.0)
+        .collect();
+    candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    candidates
+}
+
+/// Find what the unknown `id` refers to now among `identified`, the hunks
+/// listed from `location`. Resolves to the one current hunk that is clearly
+/// the same one edited, with a notice saying what changed, if auto-resolving
+/// is enabled; fails with the closest candidates otherwise. Returns None when `id` was never listed
+/// from there.
+pub fn resolve_stale(
+    id: &str,
+    location: &str,
+    identified: &[(String, &DiffHunk)],
+) -> Result<Option<String>> {
+    let Some(record) = lookup(id, location)? else {
+        return Ok(None);
+    };
+    let candidates = candidates(&record, identified);
+    let confident: Vec<&Candidate> = candidates
+        .iter()
+        .filter(|c| c.similarity >= AUTO_RESOLVE)
+        .collect();
+    if let [only] = confident[..]
+        && AUTO_RESOLVE_ENABLED.load(Ordering::Relaxed)
+    {
+        eprintln!(
+            "note: hunk {} changed since it was listed; using {} ({}% similar)",
+            id,
+            only.id,
+            percent(only.similarity)
+        );
+        for line in describe_change(&record.lines, &only.hunk.lines) {
+            eprintln!("  {}", line);
+        }
+        return Ok(Some(only.id.clone()));
+    }
+    let Some(closest) = candidates.first() else {
+        let (additions, deletions) = count_changes(&record.lines);
+        crate::exit::fail!(
+            NotFound,
+            "hunk {} not found: its change to {} (+{} -{}) is no longer {}; \
+             it may have been {} (re-run 'hunks')",
+            id,
+            record.file,
+            additions,
+            deletions,
+            describe_location(location),
+            match location {
+                "unstaged" => "staged, committed or discarded",
+                "staged" => "committed or unstaged",
+                _ => "rewritten",
+            }
+        );
+    };
+    let suggestions: Vec<String> = candidates
+        .iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|c| format!("{} ({}%)", c.id, percent(c.similarity)))
+        .collect();
+    let mut message = format!(
+        "hunk {} not found: it changed since it was listed; closest now: {}\n\
+         compared with {}:",
+        id,
+        suggestions.join(", "),
+        closest.id
+    );
+    for line in describe_change(&record.lines, &closest.hunk.lines) {
+        message.push_str("\n  ");
+        message.push_str(&line);
+    }
+    if confident.len() == 1 {
+        message.push_str(&format!(
+            "\npass {} to use it; only stage, unstage and commit take it by themselves",
+            closest.id
+        ));
+    }
+    crate::exit::fail!(NotFound, "{}", message)
+}
+
+/// `resolve-id`: print the current hunks `id` may now be, one per line as
+/// `<id> <similarity>% <file>`, most similar first.
+pub fn resolve_id(id: &str, staged: bool, commit: Option<&str>) -> Result<()> {
+    let sha = commit.map(crate::rev::resolve_commit).transpose()?;
+    let diff_output = match &sha {
+        Some(sha) => crate::diff::run_git_diff_commit(sha, None)?,
+        None => crate::diff::run_git_diff(staged, None)?,
+    };
+    let location = match &sha {
+        Some(sha) => commit_location(sha),
+        None if staged => "staged".to_string(),
+        None => "unstaged".to_string(),
+    };
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = crate::hunk_id::assign_ids(&hunks);
+
+    if let Some((_, hunk)) = identified.iter().find(|(hunk_id, _)| hunk_id == id) {
+        eprintln!("{} is unchanged", id);
+        println!("{} 100% {}", id, hunk.file);
+        return Ok(());
+    }
+    let Some(record) = lookup(id, &location)? else {
+        crate::exit::fail!(
+            NotFound,
+            "hunk {} was never listed by 'hunks' {}",
+            id,
+            match &sha {
+                Some(sha) => format!("from {}", &sha[..7]),
+                None => format!("as {}", location),
+            }
+        );
+    };
+    let candidates = candidates(&record, &identified);
+    if candidates.is_empty() {
+        crate::exit::fail!(
+            NotFound,
+            "no {} hunk in {} resembles {}",
+            if sha.is_some() {
+                "commit"
+            } else {
+                location.as_str()
+            },
+            record.file,
+            id
+        );
+    }
+    for candidate in &candidates {
+        println!(
+            "{} {}% {}",
+            candidate.id,
+            percent(candidate.similarity),
+            candidate.hunk.file
+        );
+    }
+    Ok(())
+}
+
+fn percent(similarity: f64) -> u32 {
+    (similarity * 100.0).round() as u32
+}
+
+fn count_changes(lines: &[String]) -> (usize, usize) {
+    let additions = lines.iter().filter(|l| l.starts_with('+')).count();
+    let deletions = lines.iter().filter(|l| l.starts_with('-')).count();
+    (additions, deletions)
+}
+
+/// The lines only the recorded hunk had (`was:`) and only the current one
+/// has (`now:`), in hunk order.
+fn describe_change(recorded: &[String], current: &[String]) -> Vec<String> {
+    let only_in = |a: &[String], b: &[String]| -> Vec<String> {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for line in b {
+            *counts.entry(line.as_str()).or_insert(0) += 1;
+        }
+        a.iter()
+            .filter(|line| match counts.get_mut(line.as_str()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .cloned()
+            .collect()
+    };
+    let mut report: Vec<String> = only_in(recorded, current)
+        .into_iter()
+        .map(|l| format!("was: {}", l))
+        .chain(
+            only_in(current, recorded)
+                .into_iter()
+                .map(|l| format!("now: {}", l)),
+        )
+        .collect();
+    if report.len() > MAX_REPORTED_LINES {
+        let more = report.len() - MAX_REPORTED_LINES;
+        report.truncate(MAX_REPORTED_LINES);
+        report.push(format!("... ({} more lines)", more));
+    }
+    report
+}
+
+/// How alike two hunks are. Context lines weigh less than changes, and
+/// hunks with no changed line in common are not alike at all: two hunks in
+/// a stretch of repeated lines share context without being related.
+fn similarity(a: &[String], b: &[String]) -> f64 {
+    let changed = |line: &&String| line.starts_with('+') || line.starts_with('-');
+    let (a_changed, a_context): (Vec<&String>, Vec<&String>) = a.iter().partition(changed);
+    let (b_changed, b_context): (Vec<&String>, Vec<&String>) = b.iter().partition(changed);
+    let changes = dice(&a_changed, &b_changed);
+    if changes == 0.0 {
+        return 0.0;
+    }
+    0.75 * changes + 0.25 * dice(&a_context, &b_context)
+}
+
+/// Dice coefficient of two multisets of lines: the share they have in common.
+fn dice(a: &[&String], b: &[&String]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for line in a {
+        *counts.entry(line.as_str()).or_insert(0) += 1;
+    }
+    let mut common = 0;
+    for line in b {
+        if let Some(count) = counts.get_mut(line.as_str())
+            && *count > 0
+        {
+            *count -= 1;
+            common += 1;
+        }
+    }
+    2.0 * common as f64 / (a.len() + b.len()) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_similarity() {
+        let a = lines(" ctx\n-old\n+new\n ctx2");
+        assert_eq!(similarity(&a, &a), 1.0);
+        assert_eq!(similarity(&a, &lines(" ctx\n-old\n+newer\n ctx2")), 0.625);
+        assert_eq!(similarity(&a, &lines(" ctx\n+other\n ctx2")), 0.0);
+        assert_eq!(similarity(&a, &lines("+other")), 0.0);
+    }
+
+    #[test]
+    fn test_describe_change() {
+        let recorded = lines(" ctx\n-old\n+new");
+        let current = lines(" ctx\n-old\n+newer\n+extra");
+        assert_eq!(
+            describe_change(&recorded, &current),
+            ["was: +new", "now: +newer", "now: +extra"]
+        );
+    }
+}
@@ -0,0 +1,355 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::output::Traced;
+
+/// Resolve any git revision expression (`@`, `@{u}`, `:/text`, `HEAD@{2}`,
+/// `branch~3^2`, ...) to a full commit SHA.
+///
+/// Callers should always work with the returned SHA rather than the original
+/// expression, since suffixes like `^` or `..HEAD` do not compose with every
+/// revision syntax (e.g. `:/text^` searches for "text^").
+pub fn resolve_commit(rev: &str) -> Result<String> {
+    if rev.starts_with('-') {
+        crate::exit::fail!(Usage, "invalid revision '{}'", rev);
+    }
+
+    // Resolve first, then peel: appending ^{commit} to the expression itself
+    // would break `:/text` searches, which consume the rest of the string.
+    if let Some(sha) = verify(rev)? {
+        if let Some(commit) = verify(&format!("{}^{{commit}}", sha))? {
+            return Ok(commit);
+        }
+        crate::exit::fail!(NotFound, "'{}' does not point to a commit", rev);
+    }
+
+    // Older commits than a shallow clone fetched are missing, not mistyped
+    let shallow_note = if is_shallow() {
+        "; this clone is shallow, so it may be older than the history fetched \
+         (deepen with 'git fetch --deepen=<n>')"
+    } else {
+        ""
+    };
+    let suggestions = near_miss_refs(rev);
+    if suggestions.is_empty() {
+        crate::exit::fail!(
+            NotFound,
+            "could not resolve commit '{}'{}",
+            rev,
+            shallow_note
+        );
+    }
+    crate::exit::fail!(
+        NotFound,
+        "could not resolve commit '{}' (did you mean: {}?){}",
+        rev,
+        suggestions.join(", "),
+        shallow_note
+    );
+}
+
+/// Resolve the commit a rewrite targets, like `resolve_commit` except that a
+/// `:/pattern` must match exactly one commit in the current branch. git
+/// takes the youngest match reachable from any ref, which is easily another
+/// branch's commit, or the wrong one of several with similar subjects.
+pub fn resolve_target(rev: &str) -> Result<String> {
+    let Some(pattern) = message_pattern(rev) else {
+        return resolve_commit(rev);
+    };
+    let listed = crate::diff::run_git_cmd(
+        Command::new("git")
+            .args(["log", "-E", "--format=%H %s"])
+            .arg(format!("--grep={}", pattern))
+            .arg("HEAD"),
+    )?;
+    let matches: Vec<&str> = listed.lines().collect();
+    match matches.as_slice() {
+        [] => crate::exit::fail!(
+            NotFound,
+            "no commit in the current branch matches '{}'",
+            rev
+        ),
+        [only] => Ok(only.split(' ').next().unwrap_or(only).to_string()),
+        _ => {
+            let shown: Vec<String> = matches
+                .iter()
+                .take(MAX_CANDIDATES)
+                .map(|line| match line.split_once(' ') {
+                    Some((sha, subject)) => format!("  {} {}", &sha[..7], subject),
+                    None => format!("  {}", line),
+                })
+                .collect();
+            let more = if matches.len() > MAX_CANDIDATES {
+                format!("\n  ... and {} more", matches.len() - MAX_CANDIDATES)
+            } else {
+                String::new()
+            };
+            crate::exit::fail!(
+                Usage,
+                "'{}' matches {} commits in the current branch; name one by SHA \
+                 or a longer pattern:\n{}{}",
+                rev,
+                matches.len(),
+                shown.join("\n"),
+                more
+            );
+        }
+    }
+}
+
+/// Whether `rev` is a `:/pattern` that `resolve_target` checks.
+pub fn is_message_pattern(rev: &str) -> bool {
+    message_pattern(rev).is_some()
+}
+
+/// Ambiguous `:/pattern` matches listed in the error.
+const MAX_CANDIDATES: usize = 10;
+
+/// The regex of a `:/pattern` revision, None for any other revision or for
+/// git's reserved `:/!` forms other than `:/!!` (a literal `!`).
+fn message_pattern(rev: &str) -> Option<&str> {
+    let pattern = rev.strip_prefix(":/")?;
+    match pattern.strip_prefix('!') {
+        Some(rest) => rest.starts_with('!').then_some(rest),
+        None => (!pattern.is_empty()).then_some(pattern),
+    }
+}
+
+/// Resolve a `<base>..<tip>` range to `(base, tip)` commit SHAs, each end as
+/// `resolve_target` does. An empty tip means HEAD, as in git. Symmetric `...`
+/// ranges are rejected.
+pub fn resolve_range(range: &str) -> Result<(String, String)> {
+    // Split on the last "..": `:/text` searches may themselves contain dots
+    let Some(idx) = range.rfind("..") else {
+        crate::exit::fail!(
+            Usage,
+            "expected a range like <commit>..HEAD, got '{}'",
+            range
+        );
+    };
+    let (base, tip) = (&range[..idx], &range[idx + 2..]);
+    if base.is_empty() || base.ends_with('.') {
+        crate::exit::fail!(
+            Usage,
+            "expected a range like <commit>..HEAD, got '{}'",
+            range
+        );
+    }
+    let tip = if tip.is_empty() { "HEAD" } else { tip };
+    Ok((resolve_target(base)?, resolve_target(tip)?))
+}
+
+fn verify(rev: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", rev])
+        .traced_output()
+        .context("failed to run git rev-parse")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Whether the given commit has no parent.
+pub fn is_root(sha: &str) -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", &format!("{}^", sha)])
+        .traced_output()
+        .map(|o| !o.status.success())
+        .unwrap_or(false)
+}
+
+/// Refs whose history counts as published: every `surgeon.protectedRef`
+/// config value that exists, plus the current branch's upstream.
+pub fn protected_refs() -> Vec<String> {
+    let configured = Command::new("git")
+        .args(["config", "--get-all", "surgeon.protectedRef"])
+        .traced_output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default();
+    let mut refs: Vec<String> = configured
+        .lines()
+        .map(str::trim)
+        .filter(|r| {
+            !r.is_empty()
+                && verify(&format!("{}^{{commit}}", r))
+                    .ok()
+                    .flatten()
+                    .is_some()
+        })
+        .map(str::to_string)
+        .collect();
+    let upstream = Command::new("git")
+        .args([
+            "rev-parse",
+            "--abbrev-ref",
+            "--symbolic-full-name",
+            "@{upstream}",
+        ])
+        .traced_output();
+    if let Ok(out) = upstream
+        && out.status.success()
+    {
+        let name = String::from_utf8_lossy(&out.stdout).trim().to_string();
+        if !refs.contains(&name) {
+            refs.push(name);
+        }
+    }
+    refs
+}
+
+/// Refuse to rewrite `sha` (and everything after it) when it is reachable
+/// from a protected ref, i.e. has likely been pushed.
+pub fn check_unpublished(sha: &str) -> Result<()> {
+    for name in protected_refs() {
+        let reachable = Command::new("git")
+            .args(["merge-base", "--is-ancestor", sha, &name])
+            .traced_status()
+            .context("failed to check ancestry")?;
+        if reachable.success() {
+            crate::exit::fail!(
+                Refused,
+                "commit {} is already published (reachable from {}); \
+                 pass --force-rewrite-published to rewrite it anyway",
+                &sha[..7.min(sha.len())],
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Set from `--auto-deepen`: fetch the history a rewrite needs from a shallow
+/// clone's remote, rather than refusing.
+static AUTO_DEEPEN: AtomicBool = AtomicBool::new(false);
+
+pub fn set_auto_deepen(on: bool) {
+    AUTO_DEEPEN.store(on, Ordering::Relaxed);
+}
+
+/// Refuse to rewrite `sha` (and everything after it) when a shallow clone
+/// cuts history off at it: its parent is missing, so the rebase would leave
+/// `sha` as a root commit without it. With `--auto-deepen`, fetch one more
+/// commit of history first.
+pub fn check_history_present(sha: &str) -> Result<()> {
+    if !is_shallow_boundary(sha)? {
+        return Ok(());
+    }
+    let short = &sha[..7.min(sha.len())];
+    if !AUTO_DEEPEN.load(Ordering::Relaxed) {
+        crate::exit::fail!(
+            RepoState,
+            "commit {} is at the boundary of this shallow clone, so its parent is \
+             missing; deepen first with 'git fetch --deepen=1', or pass --auto-deepen",
+            short
+        );
+    }
+    eprintln!(
+        "deepening the shallow clone to fetch the parent of {}",
+        short
+    );
+    crate::diff::run_git_cmd(Command::new("git").args(["fetch", "--quiet", "--deepen=1"]))
+        .context("failed to deepen the shallow clone")?;
+    if is_shallow_boundary(sha)? {
+        crate::exit::fail!(
+            RepoState,
+            "commit {} is still at the shallow boundary after deepening",
+            short
+        );
+    }
+    Ok(())
+}
+
+fn is_shallow() -> bool {
+    Command::new("git")
+        .args(["rev-parse", "--is-shallow-repository"])
+        .traced_output()
+        .is_ok_and(|o| String::from_utf8_lossy(&o.stdout).trim() == "true")
+}
+
+/// Whether `sha` is one of the commits a shallow clone's history stops at.
+fn is_shallow_boundary(sha: &str) -> Result<bool> {
+    let path =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-parse", "--git-path", "shallow"]))?;
+    match std::fs::read_to_string(path.trim()) {
+        Ok(shallow) => Ok(shallow.lines().any(|line| line.trim() == sha)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err).context("failed to read the shallow file"),
+    }
+}
+
+/// Local and remote-tracking ref names that look like a typo of `rev`.
+fn near_miss_refs(rev: &str) -> Vec<String> {
+    // Strip revision suffixes so "mian~2" still suggests "main"
+    let base = rev
+        .split(['~', '^', '@', ':'])
+        .next()
+        .unwrap_or(rev)
+        .to_string();
+    if base.is_empty() {
+        return Vec::new();
+    }
+
+    let refs = Command::new("git")
+        .args([
+            "for-each-ref",
+            "--format=%(refname:short)",
+            "refs/heads",
+            "refs/remotes",
+            "refs/tags",
+        ])
+        .traced_output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).into_owned())
+        .unwrap_or_default();
+
+    let mut candidates: Vec<(usize, String)> = refs
+        .lines()
+        .filter_map(|name| {
+            let dist = edit_distance(&base, name);
+            let close = dist <= 2 || (base.len() >= 3 && name.contains(base.as_str()));
+            close.then(|| (dist, name.to_string()))
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().take(3).map(|(_, n)| n).collect()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("main", "main"), 0);
+        assert_eq!(edit_distance("mian", "main"), 2);
+        assert_eq!(edit_distance("mai", "main"), 1);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_message_pattern() {
+        assert_eq!(message_pattern(":/fix parser"), Some("fix parser"));
+        assert_eq!(message_pattern(":/!!bang"), Some("!bang"));
+        assert_eq!(message_pattern(":/!-negated"), None);
+        assert_eq!(message_pattern(":/"), None);
+        assert_eq!(message_pattern("HEAD~2"), None);
+        assert_eq!(message_pattern("abc:/x"), None);
+    }
+}
@@ -0,0 +1,290 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+/// Action to assign to a commit in a rebase todo list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TodoAction {
+    Pick,
+    Edit,
+    Reword,
+    Drop,
+    Squash,
+    Fixup,
+}
+
+impl TodoAction {
+    fn keyword(&self) -> &'static str {
+        match self {
+            TodoAction::Pick => "pick",
+            TodoAction::Edit => "edit",
+            TodoAction::Reword => "reword",
+            TodoAction::Drop => "drop",
+            TodoAction::Squash => "squash",
+            TodoAction::Fixup => "fixup",
+        }
+    }
+
+    fn parse(s: &str) -> Option<TodoAction> {
+        match s {
+            "pick" | "p" => Some(TodoAction::Pick),
+            "edit" | "e" => Some(TodoAction::Edit),
+            "reword" | "r" => Some(TodoAction::Reword),
+            "drop" | "d" => Some(TodoAction::Drop),
+            "squash" | "s" => Some(TodoAction::Squash),
+            "fixup" | "f" => Some(TodoAction::Fixup),
+            _ => None,
+        }
+    }
+}
+
+/// A request to change the action of one commit in the todo list.
+#[derive(Debug, Clone)]
+pub struct Mark {
+    pub sha: String,
+    pub action: TodoAction,
+}
+
+impl Mark {
+    pub fn new(sha: &str, action: TodoAction) -> Self {
+        Mark {
+            sha: sha.to_string(),
+            action,
+        }
+    }
+
+    fn to_arg(&self) -> String {
+        format!("{}={}", self.sha, self.action.keyword())
+    }
+}
+
+/// Parse a `--mark <sha>=<action>` value.
+pub fn parse_mark(s: &str) -> Result<Mark, String> {
+    let (sha, action) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <sha>=<action>, got '{}'", s))?;
+    if sha.len() < 4 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "'{}' is not a commit SHA (at least 4 hex chars)",
+            sha
+        ));
+    }
+    let action = TodoAction::parse(action).ok_or_else(|| {
+        format!(
+            "unknown action '{}' (expected pick, edit, reword, drop, squash or fixup)",
+            action
+        )
+    })?;
+    Ok(Mark::new(sha, action))
+}
+
+/// Build a `GIT_SEQUENCE_EDITOR` value that re-invokes this binary as
+/// `internal-edit-todo`. Git appends the todo file path when running it.
+/// A non-empty `order` rearranges the commit lines before marks are applied.
+pub fn editor_command(marks: &[Mark], order: &[String]) -> Result<String> {
+    let exe = std::env::current_exe().context("could not determine git-surgeon executable path")?;
+    let mut parts = vec![
+        shell_quote(&exe.to_string_lossy()),
+        "internal-edit-todo".to_string(),
+    ];
+    for mark in marks {
+        parts.push("--mark".to_string());
+        parts.push(shell_quote(&mark.to_arg()));
+    }
+    for sha in order {
+        parts.push("--order".to_string());
+        parts.push(shell_quote(sha));
+    }
+    Ok(parts.join(" "))
+}
+
+/// Quote a string for the POSIX shell git uses to run editors (also on Windows,
+/// where Git for Windows ships sh). Single quotes keep backslashes literal.
+pub fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Whether a todo-list commit token and a mark SHA refer to the same commit.
+/// Git abbreviates SHAs in the todo list to `core.abbrev` length, which may be
+/// longer or shorter than the SHA given in the mark, so compare by prefix.
+fn sha_matches(token: &str, sha: &str) -> bool {
+    token.len() >= 4 && (sha.starts_with(token) || token.starts_with(sha))
+}
+
+/// Apply marks to a todo list. Every mark must match exactly one commit line,
+/// otherwise this fails so git aborts the rebase instead of running it unmodified.
+pub fn apply_marks(todo: &str, marks: &[Mark]) -> Result<String> {
+    let mut used = vec![false; marks.len()];
+    let mut out = String::with_capacity(todo.len());
+    for line in todo.lines() {
+        let mut parts = line.splitn(3, ' ');
+        let cmd = parts.next().unwrap_or("");
+        let token = parts.next().unwrap_or("");
+        let mark_idx = TodoAction::parse(cmd)
+            .and_then(|_| marks.iter().position(|m| sha_matches(token, &m.sha)));
+        match mark_idx {
+            Some(idx) => {
+                if used[idx] {
+                    anyhow::bail!("commit {} matches more than one todo line", marks[idx].sha);
+                }
+                used[idx] = true;
+                out.push_str(marks[idx].action.keyword());
+                out.push_str(&line[cmd.len()..]);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    if let Some(idx) = used.iter().position(|u| !u) {
+        let sha = &marks[idx].sha;
+        crate::exit::fail!(
+            NotFound,
+            "commit {} not found in rebase todo list",
+            &sha[..7.min(sha.len())]
+        );
+    }
+    Ok(out)
+}
+
+/// The commit SHA token of a todo line, if the line is a commit command.
+fn commit_token(line: &str) -> Option<&str> {
+    let mut parts = line.splitn(3, ' ');
+    let cmd = parts.next().unwrap_or("");
+    TodoAction::parse(cmd).and(parts.next())
+}
+
+/// Rearrange the commit lines of a todo list so they follow `order`. Comments
+/// and blank lines stay where they are. `order` must name every commit in the
+/// todo list exactly once.
+pub fn apply_order(todo: &str, order: &[String]) -> Result<String> {
+    let commit_lines: Vec<&str> = todo.lines().filter(|l| commit_token(l).is_some()).collect();
+    let mut used = vec![false; commit_lines.len()];
+    let mut reordered = Vec::with_capacity(order.len());
+    for sha in order {
+        let matches: Vec<usize> = commit_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| commit_token(l).is_some_and(|t| sha_matches(t, sha)))
+            .map(|(i, _)| i)
+            .collect();
+        let short = &sha[..7.min(sha.len())];
+        match matches.as_slice() {
+            [] => crate::exit::fail!(NotFound, "commit {} not found in rebase todo list", short),
+            [idx] if used[*idx] => anyhow::bail!("commit {} listed more than once", short),
+            [idx] => {
+                used[*idx] = true;
+                reordered.push(commit_lines[*idx]);
+            }
+            _ => anyhow::bail!("commit {} matches more than one todo line", short),
+        }
+    }
+    if let Some(idx) = used.iter().position(|u| !u) {
+        anyhow::bail!("commit line '{}' missing from order", commit_lines[idx]);
+    }
+
+    let mut next = reordered.into_iter();
+    let mut out = String::with_capacity(todo.len());
+    for line in todo.lines() {
+        if commit_token(line).is_some() {
+            out.push_str(next.next().unwrap_or(line));
+        } else {
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Entry point for `git-surgeon internal-edit-todo [--order <sha>...] [--mark <sha>=<action>...] <todo-file>`.
+pub fn run_editor(marks: &[Mark], order: &[String], todo_path: &Path) -> Result<()> {
+    let todo = fs::read_to_string(todo_path)
+        .with_context(|| format!("failed to read {}", todo_path.display()))?;
+    let todo = if order.is_empty() {
+        todo
+    } else {
+        apply_order(&todo, order)?
+    };
+    let rewritten = apply_marks(&todo, marks)?;
+    fs::write(todo_path, rewritten)
+        .with_context(|| format!("failed to write {}", todo_path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHA: &str = "abc1234def5678abc1234def5678abc1234def56";
+
+    #[test]
+    fn test_apply_marks_rewrites_matching_pick() {
+        let todo = "pick 1111111 first\npick abc1234 second\n\n# comment\n";
+        let result = apply_marks(todo, &[Mark::new(SHA, TodoAction::Edit)]).unwrap();
+        assert_eq!(
+            result,
+            "pick 1111111 first\nedit abc1234 second\n\n# comment\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_marks_longer_abbreviation() {
+        let todo = "pick abc1234def5 second\n";
+        let result = apply_marks(todo, &[Mark::new(SHA, TodoAction::Drop)]).unwrap();
+        assert_eq!(result, "drop abc1234def5 second\n");
+    }
+
+    #[test]
+    fn test_apply_marks_multiple() {
+        let todo = "pick 1111111 first\npick abc1234 second\n";
+        let marks = [
+            Mark::new("1111111", TodoAction::Reword),
+            Mark::new(SHA, TodoAction::Fixup),
+        ];
+        let result = apply_marks(todo, &marks).unwrap();
+        assert_eq!(result, "reword 1111111 first\nfixup abc1234 second\n");
+    }
+
+    #[test]
+    fn test_apply_marks_missing_commit_errors() {
+        let marks = [Mark::new(SHA, TodoAction::Edit)];
+        assert!(apply_marks("pick 1111111 first\n", &marks).is_err());
+    }
+
+    #[test]
+    fn test_apply_order_keeps_comments_in_place() {
+        let todo = "pick 1111111 first\npick 2222222 second\npick 3333333 third\n\n# comment\n";
+        let order = [
+            "3333333".to_string(),
+            "1111111".to_string(),
+            "2222222".to_string(),
+        ];
+        let result = apply_order(todo, &order).unwrap();
+        assert_eq!(
+            result,
+            "pick 3333333 third\npick 1111111 first\npick 2222222 second\n\n# comment\n"
+        );
+    }
+
+    #[test]
+    fn test_apply_order_requires_every_commit() {
+        let todo = "pick 1111111 first\npick 2222222 second\n";
+        assert!(apply_order(todo, &["2222222".to_string()]).is_err());
+        let dup = ["1111111".to_string(), "1111111".to_string()];
+        assert!(apply_order(todo, &dup).is_err());
+    }
+
+    #[test]
+    fn test_parse_mark() {
+        let mark = parse_mark("abc1234=reword").unwrap();
+        assert_eq!(mark.sha, "abc1234");
+        assert_eq!(mark.action, TodoAction::Reword);
+        assert!(parse_mark("abc1234").is_err());
+        assert!(parse_mark("abc1234=bogus").is_err());
+        assert!(parse_mark("HEAD=edit").is_err());
+    }
+
+    #[test]
+    fn test_shell_quote_escapes_single_quotes() {
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use std::process::Command;
+
+/// `--gpg-sign[=KEYID]` / `--no-gpg-sign`, shared by every command that
+/// creates or rewrites commits.
+#[derive(clap::Args, Clone, Default)]
+pub struct SignArgs {
+    /// Sign the commits created (with KEYID if given, else user.signingKey)
+    #[arg(
+        long,
+        value_name = "KEYID",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    pub gpg_sign: Option<String>,
+    /// Do not sign the commits created, even if commit.gpgSign is set
+    #[arg(long, conflicts_with = "gpg_sign")]
+    pub no_gpg_sign: bool,
+}
+
+impl SignArgs {
+    /// Make every git command run from here on sign (or not sign) the
+    /// commits it creates, including those replayed by rebase.
+    pub fn configure(&self) {
+        let mut config: Vec<(&str, &str)> = Vec::new();
+        if self.no_gpg_sign {
+            config.push(("commit.gpgSign", "false"));
+        } else if let Some(key) = &self.gpg_sign {
+            config.push(("commit.gpgSign", "true"));
+            if !key.is_empty() {
+                config.push(("user.signingKey", key));
+            }
+        }
+        crate::config::set_git_config(&config);
+    }
+}
+
+/// Signed commits among `range`, as (sha, subject).
+fn signed_commits(range: &str) -> Result<Vec<(String, String)>> {
+    let output =
+        crate::diff::run_git_cmd(Command::new("git").args(["rev-list", "--header", range]))?;
+    Ok(output
+        .split('\0')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let sha = lines.next()?.trim().to_string();
+            let mut signed = false;
+            let mut subject = String::new();
+            let mut in_body = false;
+            for line in lines {
+                if in_body {
+                    // Message lines are indented by four spaces
+                    subject = line.trim().to_string();
+                    break;
+                }
+                if line.is_empty() {
+                    in_body = true;
+                } else if line.starts_with("gpgsig ") || line.starts_with("gpgsig-sha256 ") {
+                    signed = true;
+                }
+            }
+            (signed && !sha.is_empty()).then_some((sha, subject))
+        })
+        .collect())
+}
+
+/// After a rewrite from `old_head`, report the signed commits that were
+/// replaced: a warning if their replacements are unsigned, else a note.
+pub fn report_dropped(old_head: &str) -> Result<()> {
+    let replaced = signed_commits(&format!("HEAD..{}", old_head))?;
+    if replaced.is_empty() {
+        return Ok(());
+    }
+    if !signed_commits(&format!("{}..HEAD", old_head))?.is_empty() {
+        eprintln!(
+            "note: {} rewritten commit(s) had signatures; the new commits are signed with your key",
+            replaced.len()
+        );
+        return Ok(());
+    }
+    eprintln!(
+        "warning: dropped the signatures of {} rewritten commit(s):",
+        replaced.len()
+    );
+    for (sha, subject) in &replaced {
+        eprintln!("  {} {}", &sha[..7.min(sha.len())], subject);
+    }
+    eprintln!("pass --gpg-sign to sign the new commits");
+    Ok(())
+}
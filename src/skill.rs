@@ -2,22 +2,134 @@ use anyhow::{Result, bail};
 use std::fs;
 use std::path::PathBuf;
 
+use crate::json;
+
 const SKILL_CONTENT: &str = include_str!("../skills/git-surgeon/SKILL.md");
 
-#[derive(Debug, Clone, Copy)]
+/// Appended to installed skills so `install-skill --list` can tell which
+/// version of git-surgeon wrote them.
+const VERSION_MARKER: &str = "<!-- git-surgeon-version: ";
+
+/// The names of the `PlatformArgs` flags, for `conflicts_with_all`.
+pub const PLATFORM_FLAGS: [&str; 8] = [
+    "claude", "opencode", "codex", "cursor", "windsurf", "zed", "gemini", "aider",
+];
+
+/// Platform flags, shared by install-skill and uninstall-skill.
+#[derive(clap::Args)]
+pub struct PlatformArgs {
+    /// Claude Code (~/.claude/skills/)
+    #[arg(long)]
+    pub claude: bool,
+    /// OpenCode (~/.config/opencode/skills/)
+    #[arg(long)]
+    pub opencode: bool,
+    /// Codex (~/.codex/skills/)
+    #[arg(long)]
+    pub codex: bool,
+    /// Cursor, as a rule (~/.cursor/rules/)
+    #[arg(long)]
+    pub cursor: bool,
+    /// Windsurf, as a rule (~/.codeium/windsurf/rules/)
+    #[arg(long)]
+    pub windsurf: bool,
+    /// Zed, as a rule (~/.config/zed/rules/)
+    #[arg(long)]
+    pub zed: bool,
+    /// Gemini CLI, as an extension (~/.gemini/extensions/)
+    #[arg(long)]
+    pub gemini: bool,
+    /// Aider, as a conventions file (~/.aider/)
+    #[arg(long)]
+    pub aider: bool,
+}
+
+impl PlatformArgs {
+    /// The platforms whose flags are set, in `Platform::ALL` order.
+    pub fn selected(&self) -> Vec<Platform> {
+        let flags = [
+            self.claude,
+            self.opencode,
+            self.codex,
+            self.cursor,
+            self.windsurf,
+            self.zed,
+            self.gemini,
+            self.aider,
+        ];
+        Platform::ALL
+            .into_iter()
+            .zip(flags)
+            .filter(|(_, set)| *set)
+            .map(|(platform, _)| platform)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Platform {
     Claude,
     OpenCode,
     Codex,
+    Cursor,
+    Windsurf,
+    Zed,
+    Gemini,
+    Aider,
 }
 
 impl Platform {
-    fn skill_dir(&self) -> PathBuf {
+    pub const ALL: [Platform; 8] = [
+        Platform::Claude,
+        Platform::OpenCode,
+        Platform::Codex,
+        Platform::Cursor,
+        Platform::Windsurf,
+        Platform::Zed,
+        Platform::Gemini,
+        Platform::Aider,
+    ];
+
+    /// The platform's own configuration directory, whose presence indicates
+    /// that the assistant is installed.
+    fn config_dir(&self) -> PathBuf {
         let home = dirs::home_dir().expect("could not determine home directory");
         match self {
-            Platform::Claude => home.join(".claude/skills/git-surgeon"),
-            Platform::OpenCode => home.join(".config/opencode/skills/git-surgeon"),
-            Platform::Codex => home.join(".codex/skills/git-surgeon"),
+            Platform::Claude => home.join(".claude"),
+            Platform::OpenCode => home.join(".config/opencode"),
+            Platform::Codex => home.join(".codex"),
+            Platform::Cursor => home.join(".cursor"),
+            Platform::Windsurf => home.join(".codeium/windsurf"),
+            Platform::Zed => home.join(".config/zed"),
+            Platform::Gemini => home.join(".gemini"),
+            Platform::Aider => home.join(".aider"),
+        }
+    }
+
+    /// The files the skill is installed as, with their contents: the skill
+    /// itself in the platform's rules or skills location, plus a manifest
+    /// where the platform needs one to pick it up.
+    fn files(&self) -> Vec<(PathBuf, String)> {
+        let config = self.config_dir();
+        match self {
+            Platform::Claude | Platform::OpenCode | Platform::Codex => vec![(
+                config.join("skills/git-surgeon/SKILL.md"),
+                self.render_skill(),
+            )],
+            Platform::Cursor => vec![(config.join("rules/git-surgeon.mdc"), self.render_skill())],
+            Platform::Windsurf => vec![(config.join("rules/git-surgeon.md"), self.render_skill())],
+            Platform::Zed => vec![(config.join("rules/git-surgeon.md"), self.render_skill())],
+            Platform::Gemini => {
+                let dir = config.join("extensions/git-surgeon");
+                vec![
+                    (dir.join("gemini-extension.json"), gemini_manifest()),
+                    (dir.join("GEMINI.md"), self.render_skill()),
+                ]
+            }
+            Platform::Aider => vec![(
+                config.join("git-surgeon/CONVENTIONS.md"),
+                self.render_skill(),
+            )],
         }
     }
 
@@ -26,22 +138,410 @@ impl Platform {
             Platform::Claude => "Claude Code",
             Platform::OpenCode => "OpenCode",
             Platform::Codex => "Codex",
+            Platform::Cursor => "Cursor",
+            Platform::Windsurf => "Windsurf",
+            Platform::Zed => "Zed",
+            Platform::Gemini => "Gemini CLI",
+            Platform::Aider => "Aider",
+        }
+    }
+
+    /// Render SKILL.md with this platform's frontmatter and invocation notes.
+    /// The bundled file is the Claude Code variant (also used by the plugin
+    /// marketplace), so Claude gets it unchanged. Zed, Gemini CLI and Aider
+    /// read plain Markdown, so they get the body alone.
+    fn render_skill(&self) -> String {
+        let source = parse_skill(SKILL_CONTENT);
+        let skill_fields = format!(
+            "name: {}\ndescription: {}\n",
+            source.name, source.description
+        );
+        let frontmatter = match self {
+            Platform::Claude => Some(skill_fields),
+            Platform::OpenCode => Some(skill_fields + "license: MIT\ncompatibility: opencode\n"),
+            Platform::Codex => Some(format!(
+                "{}metadata:\n  short-description: {}\n",
+                skill_fields,
+                first_sentence(source.description)
+            )),
+            Platform::Cursor => Some(format!(
+                "description: {}\nglobs:\nalwaysApply: false\n",
+                source.description
+            )),
+            Platform::Windsurf => Some(format!(
+                "trigger: model_decision\ndescription: {}\n",
+                source.description
+            )),
+            Platform::Zed | Platform::Gemini | Platform::Aider => None,
+        };
+        let mut out = match frontmatter {
+            Some(fields) => format!("---\n{}---\n{}", fields, source.body),
+            None => source.body.trim_start().to_string(),
+        };
+
+        let invocation = match self {
+            Platform::Claude => None,
+            Platform::OpenCode => Some(
+                "Load this skill with the `skill` tool (`skill({ name: \"git-surgeon\" })`) \
+                 before running git-surgeon commands.",
+            ),
+            Platform::Codex => {
+                Some("Mention `$git-surgeon` in a prompt to invoke this skill explicitly.")
+            }
+            Platform::Cursor => Some(
+                "Cursor attaches this rule when a request matches its description; \
+                 mention `@git-surgeon` to attach it explicitly.",
+            ),
+            Platform::Windsurf => Some(
+                "Cascade applies this rule when a request matches its description; \
+                 mention `@rules:git-surgeon` to apply it explicitly.",
+            ),
+            Platform::Zed => Some(
+                "Add this file to the Rules Library, or mention it with `@rule`, \
+                 to include it in an agent thread.",
+            ),
+            Platform::Gemini => None,
+            Platform::Aider => Some(
+                "Load this file with `--read` (or `read:` in `.aider.conf.yml`) \
+                 before asking aider to run git-surgeon commands.",
+            ),
+        };
+        if let Some(text) = invocation {
+            out.push_str(&format!("\n## Invoking this skill\n\n{}\n", text));
+        }
+        out.push_str(&format!(
+            "\n{}{} -->\n",
+            VERSION_MARKER,
+            env!("CARGO_PKG_VERSION")
+        ));
+        out
+    }
+
+    /// The installed skill file, reported by install and list.
+    fn skill_path(&self) -> PathBuf {
+        let files = self.files();
+        // The skill file comes after any manifest
+        files
+            .into_iter()
+            .last()
+            .expect("every platform installs a file")
+            .0
+    }
+}
+
+/// How an installed skill compares with what this binary would install.
+enum Status {
+    /// Identical to a fresh install.
+    Current,
+    /// Written by another git-surgeon version (`None`: one from before
+    /// versions were recorded).
+    Stale(Option<String>),
+    /// Written by this version but edited since.
+    Modified,
+}
+
+impl Platform {
+    fn flag(&self) -> &'static str {
+        let index = Platform::ALL.iter().position(|p| p == self).unwrap();
+        PLATFORM_FLAGS[index]
+    }
+
+    /// The state of the installed skill, or `None` if it is not installed.
+    fn status(&self) -> Option<Status> {
+        let installed = fs::read_to_string(self.skill_path()).ok()?;
+        let files = self.files();
+        if files
+            .iter()
+            .all(|(path, content)| fs::read_to_string(path).is_ok_and(|e| &e == content))
+        {
+            return Some(Status::Current);
+        }
+        match installed_version(&installed) {
+            Some(env!("CARGO_PKG_VERSION")) => Some(Status::Modified),
+            version => Some(Status::Stale(version.map(str::to_string))),
         }
     }
 }
 
+/// The git-surgeon version recorded in an installed skill file, if any.
+fn installed_version(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .rev()
+        .find_map(|l| l.strip_prefix(VERSION_MARKER)?.strip_suffix(" -->"))
+}
+
+/// Frontmatter fields and body of the bundled SKILL.md.
+struct SkillSource<'a> {
+    name: &'a str,
+    description: &'a str,
+    body: &'a str,
+}
+
+fn parse_skill(content: &str) -> SkillSource<'_> {
+    let rest = content.strip_prefix("---\n").unwrap_or(content);
+    let (frontmatter, body) = rest.split_once("\n---\n").unwrap_or(("", rest));
+    let field = |key: &str| {
+        frontmatter
+            .lines()
+            .find_map(|l| l.strip_prefix(key)?.strip_prefix(':'))
+            .map(str::trim)
+            .unwrap_or("")
+    };
+    SkillSource {
+        name: field("name"),
+        description: field("description"),
+        body,
+    }
+}
+
+/// The Gemini CLI extension manifest, loading GEMINI.md as context.
+fn gemini_manifest() -> String {
+    let manifest = json::object([
+        ("name", "git-surgeon".into()),
+        ("version", env!("CARGO_PKG_VERSION").into()),
+        ("contextFileName", "GEMINI.md".into()),
+    ]);
+    format!("{}\n", manifest)
+}
+
+fn first_sentence(s: &str) -> &str {
+    match s.find(". ") {
+        Some(idx) => &s[..idx + 1],
+        None => s,
+    }
+}
+
 pub fn install_skill(platforms: &[Platform]) -> Result<()> {
     if platforms.is_empty() {
-        bail!("at least one platform flag is required (--claude, --opencode, --codex)");
+        crate::exit::fail!(
+            Usage,
+            "at least one platform flag is required (--claude, --opencode, --codex, --cursor, \
+             --windsurf, --zed, --gemini, --aider, --all)"
+        );
     }
 
     for platform in platforms {
-        let dir = platform.skill_dir();
-        fs::create_dir_all(&dir)?;
-        let path = dir.join("SKILL.md");
-        fs::write(&path, SKILL_CONTENT)?;
-        println!("installed {} skill to {}", platform.name(), path.display());
+        let path = platform.skill_path();
+        let previous = match platform.status() {
+            Some(Status::Current) => {
+                println!(
+                    "{} skill already up to date at {}",
+                    platform.name(),
+                    path.display()
+                );
+                continue;
+            }
+            Some(Status::Stale(version)) => {
+                Some(version.unwrap_or_else(|| "an unknown version".to_string()))
+            }
+            _ => None,
+        };
+        for (path, content) in &platform.files() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, content)?;
+        }
+        match previous {
+            Some(version) => println!(
+                "updated {} skill at {} (was {}, now {})",
+                platform.name(),
+                path.display(),
+                version,
+                env!("CARGO_PKG_VERSION")
+            ),
+            None => println!("installed {} skill to {}", platform.name(), path.display()),
+        }
+        if let Platform::Aider = platform {
+            println!(
+                "  aider loads it with: read: {} (in ~/.aider.conf.yml)",
+                path.display()
+            );
+        }
+    }
+
+    let stale: Vec<Platform> = Platform::ALL
+        .into_iter()
+        .filter(|p| !platforms.contains(p) && matches!(p.status(), Some(Status::Stale(_))))
+        .collect();
+    if !stale.is_empty() {
+        eprintln!(
+            "note: the skill installed for {} is from another git-surgeon version; refresh with '{}'",
+            stale
+                .iter()
+                .map(|p| p.name())
+                .collect::<Vec<_>>()
+                .join(", "),
+            refresh_command(&stale)
+        );
+    }
+    Ok(())
+}
+
+fn refresh_command(platforms: &[Platform]) -> String {
+    let flags: Vec<String> = platforms
+        .iter()
+        .map(|p| format!("--{}", p.flag()))
+        .collect();
+    format!("git-surgeon install-skill {}", flags.join(" "))
+}
+
+/// `skill-status`: whether each installed skill matches what this version
+/// of git-surgeon installs, with the command that refreshes the stale ones
+/// (or, with `refresh`, refreshing them).
+pub fn skill_status(refresh: bool) -> Result<()> {
+    let mut stale = Vec::new();
+    let mut any = false;
+    for platform in Platform::ALL {
+        let Some(status) = platform.status() else {
+            continue;
+        };
+        any = true;
+        let current = env!("CARGO_PKG_VERSION");
+        let state = match status {
+            Status::Current => format!("up to date ({})", current),
+            Status::Stale(version) => {
+                stale.push(platform);
+                format!(
+                    "stale ({}, current {})",
+                    version.as_deref().unwrap_or("unknown version"),
+                    current
+                )
+            }
+            Status::Modified => format!("modified since install ({})", current),
+        };
+        println!(
+            "{}: {} at {}",
+            platform.name(),
+            state,
+            platform.skill_path().display()
+        );
+    }
+    if !any {
+        println!("no git-surgeon skills installed");
+    }
+    if refresh && !stale.is_empty() {
+        return install_skill(&stale);
+    }
+    if !stale.is_empty() {
+        eprintln!(
+            "refresh with: {} (or skill-status --refresh)",
+            refresh_command(&stale)
+        );
+    }
+    Ok(())
+}
+
+/// Install to every platform whose config directory exists, reporting the rest.
+pub fn install_skill_detected() -> Result<()> {
+    let mut detected = Vec::new();
+    for platform in Platform::ALL {
+        let dir = platform.config_dir();
+        if dir.is_dir() {
+            detected.push(platform);
+        } else {
+            println!("skipped {}: {} not found", platform.name(), dir.display());
+        }
+    }
+
+    if detected.is_empty() {
+        bail!("no supported AI assistant platforms detected");
+    }
+    install_skill(&detected)
+}
+
+/// `install-skill --list`: for every platform, whether the skill is
+/// installed, by which version of git-surgeon, and where.
+pub fn list_skills() -> Result<()> {
+    for platform in Platform::ALL {
+        let path = platform.skill_path();
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                let version = installed_version(&content).unwrap_or("unknown version");
+                println!("{}: {} ({})", platform.name(), version, path.display());
+            }
+            Err(_) => println!("{}: not installed", platform.name()),
+        }
     }
+    Ok(())
+}
 
+/// Remove the files `install_skill` created for `platforms`, and the
+/// directories it created for them if they are left empty. With no
+/// platforms, remove the skill from every platform that has it.
+pub fn uninstall_skill(platforms: &[Platform]) -> Result<()> {
+    let installed: Vec<Platform> = Platform::ALL
+        .into_iter()
+        .filter(|p| p.files().iter().any(|(path, _)| path.exists()))
+        .collect();
+    let platforms = if platforms.is_empty() {
+        if installed.is_empty() {
+            println!("no git-surgeon skills installed");
+        }
+        &installed[..]
+    } else {
+        platforms
+    };
+
+    for platform in platforms {
+        let files = platform.files();
+        if !files.iter().any(|(path, _)| path.exists()) {
+            println!("{} skill not installed", platform.name());
+            continue;
+        }
+        let config = platform.config_dir();
+        for (path, _) in &files {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            let mut dir = path.parent();
+            while let Some(d) = dir
+                && d.starts_with(&config)
+                && d != config
+                && fs::remove_dir(d).is_ok()
+            {
+                dir = d.parent();
+            }
+        }
+        println!(
+            "removed {} skill from {}",
+            platform.name(),
+            platform.skill_path().display()
+        );
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_skill_matches_bundled_file() {
+        let rendered = Platform::Claude.render_skill();
+        let (skill, marker) = rendered.split_at(SKILL_CONTENT.len());
+        assert_eq!(skill, SKILL_CONTENT);
+        assert_eq!(installed_version(marker), Some(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn test_codex_skill_has_short_description() {
+        let rendered = Platform::Codex.render_skill();
+        assert!(rendered.starts_with("---\nname: git-surgeon\n"));
+        assert!(rendered.contains("metadata:\n  short-description: Non-interactive"));
+        assert!(rendered.contains("$git-surgeon"));
+    }
+
+    #[test]
+    fn test_opencode_skill_frontmatter() {
+        let rendered = Platform::OpenCode.render_skill();
+        assert!(rendered.contains("\ncompatibility: opencode\n---\n"));
+    }
+
+    #[test]
+    fn test_first_sentence() {
+        assert_eq!(first_sentence("One. Two."), "One.");
+        assert_eq!(first_sentence("Only one"), "Only one");
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A git-surgeon operation that may be interrupted and resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Fixup,
+    Reword,
+    Split,
+    Reorder,
+    Drop,
+    EditCommit,
+    Squash,
+}
+
+impl Operation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Operation::Fixup => "fixup",
+            Operation::Reword => "reword",
+            Operation::Split => "split",
+            Operation::Reorder => "reorder",
+            Operation::Drop => "drop",
+            Operation::EditCommit => "edit-commit",
+            Operation::Squash => "squash",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Operation> {
+        match s {
+            "fixup" => Some(Operation::Fixup),
+            "reword" => Some(Operation::Reword),
+            "split" => Some(Operation::Split),
+            "reorder" => Some(Operation::Reorder),
+            "drop" => Some(Operation::Drop),
+            "edit-commit" => Some(Operation::EditCommit),
+            "squash" => Some(Operation::Squash),
+            _ => None,
+        }
+    }
+}
+
+/// Record of an in-flight operation, stored in `.git/surgeon/state`.
+///
+/// The file is line-based: `<key> <value>`, with backslashes and newlines in
+/// values escaped. Keys may repeat; `fields` keeps them in file order.
+#[derive(Debug, Clone)]
+pub struct State {
+    pub operation: Operation,
+    /// HEAD before the operation started, used by `abort`
+    pub orig_head: String,
+    /// The commit being rewritten
+    pub target: String,
+    /// Operation-specific fields
+    pub fields: Vec<(String, String)>,
+}
+
+impl State {
+    pub fn new(operation: Operation, orig_head: &str, target: &str) -> Self {
+        State {
+            operation,
+            orig_head: orig_head.to_string(),
+            target: target.to_string(),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, key: &str, value: &str) {
+        self.fields.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn serialize(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("operation {}\n", self.operation.name()));
+        out.push_str(&format!("orig-head {}\n", self.orig_head));
+        out.push_str(&format!("target {}\n", self.target));
+        for (key, value) in &self.fields {
+            out.push_str(&format!("{} {}\n", key, escape(value)));
+        }
+        out
+    }
+
+    fn deserialize(input: &str) -> Result<State> {
+        let mut operation = None;
+        let mut orig_head = None;
+        let mut target = None;
+        let mut fields = Vec::new();
+        for line in input.lines() {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "operation" => operation = Operation::parse(value),
+                "orig-head" => orig_head = Some(value.to_string()),
+                "target" => target = Some(value.to_string()),
+                _ => fields.push((key.to_string(), unescape(value))),
+            }
+        }
+        match (operation, orig_head, target) {
+            (Some(operation), Some(orig_head), Some(target)) => Ok(State {
+                operation,
+                orig_head,
+                target,
+                fields,
+            }),
+            _ => anyhow::bail!("corrupt git-surgeon state file"),
+        }
+    }
+
+    pub fn load() -> Result<Option<State>> {
+        let path = state_path()?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        State::deserialize(&content).map(Some)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = state_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, self.serialize())
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    pub fn clear() -> Result<()> {
+        let path = state_path()?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("failed to remove {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Path of a file under the per-worktree `.git/surgeon/` directory.
+pub fn surgeon_path(name: &str) -> Result<PathBuf> {
+    let out = crate::diff::run_git_cmd(Command::new("git").args([
+        "rev-parse",
+        "--git-path",
+        &format!("surgeon/{}", name),
+    ]))?;
+    Ok(PathBuf::from(out.trim()))
+}
+
+fn state_path() -> Result<PathBuf> {
+    surgeon_path("state")
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') => out.push('\n'),
+                Some(other) => out.push(other),
+                None => out.push('\\'),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_roundtrip() {
+        let mut state = State::new(Operation::Split, "aaaa", "bbbb");
+        let message = "subject\n\nbody with \\ backslash".to_string();
+        state.fields.push(("message".to_string(), message.clone()));
+        let parsed = State::deserialize(&state.serialize()).unwrap();
+        assert_eq!(parsed.operation, Operation::Split);
+        assert_eq!(parsed.orig_head, "aaaa");
+        assert_eq!(parsed.target, "bbbb");
+        assert_eq!(parsed.fields, [("message".to_string(), message)]);
+    }
+
+    #[test]
+    fn test_state_missing_fields_errors() {
+        assert!(State::deserialize("operation split\n").is_err());
+    }
+}
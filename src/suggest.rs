@@ -0,0 +1,673 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+
+use crate::diff::DiffHunk;
+use crate::hunk_id::assign_ids;
+use crate::symbol;
+
+/// Shorter names are too common to tie hunks together.
+const MIN_SYMBOL_LEN: usize = 3;
+
+/// What `suggest-split` groups hunks by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criterion {
+    File,
+    Dir,
+    Symbol,
+    Blame,
+    Scope,
+}
+
+pub fn parse_criterion(s: &str) -> Result<Criterion, String> {
+    match s {
+        "file" => Ok(Criterion::File),
+        "dir" => Ok(Criterion::Dir),
+        "symbol" => Ok(Criterion::Symbol),
+        "blame" => Ok(Criterion::Blame),
+        "scope" => Ok(Criterion::Scope),
+        _ => Err("expected file, dir, symbol, blame or scope".to_string()),
+    }
+}
+
+/// A hunk ID and its hunk.
+type Listed<'a> = (&'a str, &'a DiffHunk);
+
+/// Hunks proposed for one commit, in diff order, and why they are together.
+struct Group<'a> {
+    hunks: Vec<Listed<'a>>,
+    reasons: Vec<String>,
+    /// With `--by scope`, the scope all the hunks' files map to
+    scope: Option<String>,
+}
+
+/// A `surgeon.scope` mapping: files matching `glob` belong to `scope`.
+struct ScopeMapping {
+    glob: String,
+    scope: String,
+}
+
+/// The `surgeon.scope` mappings (`GLOB=SCOPE`, repeatable), in order.
+fn scope_mappings() -> Result<Vec<ScopeMapping>> {
+    let values = crate::config::get_all("surgeon.scope");
+    if values.is_empty() {
+        crate::exit::fail!(
+            Usage,
+            "grouping by scope needs surgeon.scope mappings, e.g. \
+             git config --add surgeon.scope 'src/api/**=api'"
+        );
+    }
+    values
+        .iter()
+        .map(|value| match value.rsplit_once('=') {
+            Some((glob, scope)) if !glob.trim().is_empty() && !scope.trim().is_empty() => {
+                Ok(ScopeMapping {
+                    glob: glob.trim().to_string(),
+                    scope: scope.trim().to_string(),
+                })
+            }
+            _ => Err(crate::exit::failure!(
+                Usage,
+                "invalid surgeon.scope '{}': expected GLOB=SCOPE",
+                value
+            )),
+        })
+        .collect()
+}
+
+/// The scope of the first mapping `file` matches.
+fn scope_of<'m>(mappings: &'m [ScopeMapping], file: &str) -> Option<&'m str> {
+    mappings
+        .iter()
+        .find(|m| crate::glob::matches(&m.glob, file))
+        .map(|m| m.scope.as_str())
+}
+
+/// The type and description of a conventional commit subject: `feat(api)!:
+/// add x` gives `feat!` and `add x`.
+fn conventional(subject: &str) -> Option<(String, String)> {
+    let (prefix, description) = subject.split_once(':')?;
+    let description = description.trim();
+    let breaking = prefix.ends_with('!');
+    let prefix = prefix.trim_end_matches('!');
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) => {
+            scope.strip_suffix(')')?;
+            kind
+        }
+        None => prefix,
+    };
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_lowercase()) || description.is_empty() {
+        return None;
+    }
+    let kind = if breaking {
+        format!("{}!", kind)
+    } else {
+        kind.to_string()
+    };
+    Some((kind, description.to_string()))
+}
+
+/// Disjoint sets of hunk indices, each remembering why it was merged.
+struct Sets {
+    parent: Vec<usize>,
+    reasons: Vec<Vec<String>>,
+}
+
+impl Sets {
+    fn new(n: usize) -> Sets {
+        Sets {
+            parent: (0..n).collect(),
+            reasons: vec![Vec::new(); n],
+        }
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        self.parent[i] = root;
+        root
+    }
+
+    /// Merge the sets of `a` and `b`; the lower index stays the root, so
+    /// groups keep the order of their first hunk.
+    fn union(&mut self, a: usize, b: usize, reason: String) {
+        let (a, b) = (self.find(a), self.find(b));
+        let (root, other) = (a.min(b), a.max(b));
+        if root != other {
+            self.parent[other] = root;
+            let moved = std::mem::take(&mut self.reasons[other]);
+            self.reasons[root].extend(moved);
+        }
+        if !self.reasons[root].contains(&reason) {
+            self.reasons[root].push(reason);
+        }
+    }
+}
+
+/// `suggest-split`: group the hunks of `commit` (or of the working tree) into
+/// proposed commits and print them as a ready-to-run `split` command (or
+/// `commit` commands), or with `plan` as an `apply-plan` file. The groups and
+/// why their hunks are together go to stderr.
+pub fn suggest_split(
+    commit: Option<&str>,
+    by: &[Criterion],
+    plan: bool,
+    ignore_revs: &[String],
+) -> Result<()> {
+    let sha = commit.map(crate::rev::resolve_commit).transpose()?;
+    let diff_output = match &sha {
+        Some(sha) => crate::diff::run_git_diff_commit(sha, None)?,
+        None => crate::diff::run_git_diff(false, None)?,
+    };
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    if identified.is_empty() {
+        match &sha {
+            Some(sha) => anyhow::bail!("commit {} has no hunks to split", &sha[..7]),
+            None => anyhow::bail!("no unstaged changes to split"),
+        }
+    }
+
+    // Hunks split and commit cannot pick (binary, submodule, ...) are left to
+    // the last commit of a split, and out of working tree suggestions
+    let (supported, unsupported): (Vec<Listed>, Vec<Listed>) = identified
+        .iter()
+        .map(|(id, hunk)| (id.as_str(), *hunk))
+        .partition(|(id, hunk)| crate::diff::check_supported(hunk, id).is_ok());
+    let mut groups = group_hunks(&supported, by, sha.as_deref(), ignore_revs)?;
+    if sha.is_some() && !unsupported.is_empty() {
+        match groups.last_mut() {
+            Some(last) if last.scope.is_none() => last.hunks.extend(unsupported.iter().copied()),
+            // They would get a scope's message; the rest keeps the commit's
+            _ => groups.push(Group {
+                hunks: unsupported.clone(),
+                reasons: Vec::new(),
+                scope: None,
+            }),
+        }
+    }
+    let convention = match &sha {
+        Some(sha) => conventional(&commit_subject(sha)?),
+        None => None,
+    };
+
+    print_groups(&groups, sha.as_deref());
+    if sha.is_none() && !unsupported.is_empty() {
+        let ids: Vec<&str> = unsupported.iter().map(|(id, _)| *id).collect();
+        eprintln!(
+            "note: not suggested (commit them with git add): {}",
+            ids.join(" ")
+        );
+    }
+    if let Some(sha) = &sha
+        && groups.len() < 2
+    {
+        eprintln!(
+            "nothing to split: all hunks of {} belong together",
+            &sha[..7]
+        );
+        return Ok(());
+    }
+
+    if plan {
+        print!(
+            "{}",
+            plan_file(&groups, sha.as_deref(), convention.as_ref())
+        );
+    } else {
+        for command in commands(&groups, sha.as_deref(), convention.as_ref()) {
+            println!("{}", command);
+        }
+    }
+    Ok(())
+}
+
+/// `split --auto-scope`: one pick group per scope the hunks of `commit` map
+/// to, with a `type(scope): subject` message from the commit's conventional
+/// subject. Hunks outside every scope are left to the rest.
+pub fn scope_groups(commit: &str) -> Result<Vec<crate::PickGroup>> {
+    let sha = crate::rev::resolve_commit(commit)?;
+    let diff_output = crate::diff::run_git_diff_commit(&sha, None)?;
+    let hunks = crate::diff::parse_diff(&diff_output);
+    let identified = assign_ids(&hunks);
+    let supported: Vec<Listed> = identified
+        .iter()
+        .map(|(id, hunk)| (id.as_str(), *hunk))
+        .filter(|(id, hunk)| crate::diff::check_supported(hunk, id).is_ok())
+        .collect();
+    let groups = group_hunks(&supported, &[Criterion::Scope], Some(&sha), &[])?;
+    let scoped: Vec<&Group> = groups.iter().filter(|g| g.scope.is_some()).collect();
+    if scoped.is_empty() {
+        crate::exit::fail!(
+            NotFound,
+            "no file of {} maps to a scope in surgeon.scope",
+            &sha[..7]
+        );
+    }
+    if groups.len() < 2 {
+        crate::exit::fail!(
+            NotFound,
+            "nothing to split: all hunks of {} are in scope {}",
+            &sha[..7],
+            scoped[0].scope.as_deref().unwrap_or_default()
+        );
+    }
+    let convention = conventional(&commit_subject(&sha)?);
+    Ok(scoped
+        .into_iter()
+        .map(|group| crate::PickGroup {
+            ids: ids(group)
+                .into_iter()
+                .map(|id| (id.to_string(), None))
+                .collect(),
+            message_parts: vec![message(group, convention.as_ref())],
+        })
+        .collect())
+}
+
+fn commit_subject(sha: &str) -> Result<String> {
+    let subject = crate::diff::run_git_cmd(std::process::Command::new("git").args([
+        "log",
+        "-1",
+        "--format=%s",
+        sha,
+    ]))?;
+    Ok(subject.trim().to_string())
+}
+
+/// Group `hunks` by each of `by`: the same file (or directory), a name one
+/// hunk declares and another uses, the commit most of their removed lines
+/// come from.
+fn group_hunks<'a>(
+    hunks: &[Listed<'a>],
+    by: &[Criterion],
+    commit: Option<&str>,
+    ignore_revs: &[String],
+) -> Result<Vec<Group<'a>>> {
+    let mut sets = Sets::new(hunks.len());
+
+    if by.contains(&Criterion::Dir) {
+        union_by_key(&mut sets, hunks, |hunk| match hunk.file.rsplit_once('/') {
+            Some((dir, _)) => format!("{}/", dir),
+            None => "./".to_string(),
+        });
+    } else if by.contains(&Criterion::File) {
+        union_by_key(&mut sets, hunks, |hunk| hunk.file.clone());
+    }
+
+    if by.contains(&Criterion::Symbol) {
+        let touched: Vec<(HashSet<String>, HashSet<String>)> = hunks
+            .iter()
+            .map(|(_, hunk)| touched_symbols(hunk))
+            .collect();
+        for (i, (declared, _)) in touched.iter().enumerate() {
+            for (j, (_, used)) in touched.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                let mut shared: Vec<&String> = declared.intersection(used).collect();
+                shared.sort();
+                if let Some(symbol) = shared.first() {
+                    sets.union(i, j, format!("share {}", symbol));
+                }
+            }
+        }
+    }
+
+    let mappings = if by.contains(&Criterion::Scope) {
+        scope_mappings()?
+    } else {
+        Vec::new()
+    };
+    if by.contains(&Criterion::Scope) {
+        union_by_key(&mut sets, hunks, |hunk| {
+            match scope_of(&mappings, &hunk.file) {
+                Some(scope) => format!("scope {}", scope),
+                None => "no scope".to_string(),
+            }
+        });
+    }
+
+    if by.contains(&Criterion::Blame) {
+        let targets = blame_targets(hunks, commit, ignore_revs)?;
+        let mut first: HashMap<&str, usize> = HashMap::new();
+        for (i, target) in targets.iter().enumerate() {
+            let Some(target) = target else { continue };
+            match first.get(target.as_str()) {
+                Some(&j) => sets.union(j, i, format!("change lines from {}", target)),
+                None => {
+                    first.insert(target, i);
+                }
+            }
+        }
+    }
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for (i, hunk) in hunks.iter().enumerate() {
+        let root = sets.find(i);
+        let index = *group_of.entry(root).or_insert_with(|| {
+            groups.push(Group {
+                hunks: Vec::new(),
+                reasons: Vec::new(),
+                scope: None,
+            });
+            groups.len() - 1
+        });
+        groups[index].hunks.push(*hunk);
+    }
+    for (root, index) in group_of {
+        groups[index].reasons = std::mem::take(&mut sets.reasons[root]);
+    }
+    if by.contains(&Criterion::Scope) {
+        for group in &mut groups {
+            let mut scopes = group
+                .hunks
+                .iter()
+                .map(|(_, h)| scope_of(&mappings, &h.file));
+            let first = scopes.next().flatten();
+            if scopes.all(|s| s == first) {
+                group.scope = first.map(str::to_string);
+            }
+            // A group of one hunk was never merged, so has no reason yet
+            if let Some(scope) = &group.scope {
+                let reason = format!("scope {}", scope);
+                if !group.reasons.contains(&reason) {
+                    group.reasons.insert(0, reason);
+                }
+            }
+        }
+        // Hunks without a scope are the rest, which keeps the commit's message
+        groups.sort_by_key(|group| group.scope.is_none());
+    }
+    Ok(groups)
+}
+
+/// Merge hunks that have the same `key`, giving it as the reason.
+fn union_by_key(sets: &mut Sets, hunks: &[Listed], key: impl Fn(&DiffHunk) -> String) {
+    let mut first: HashMap<String, usize> = HashMap::new();
+    for (i, (_, hunk)) in hunks.iter().enumerate() {
+        let key = key(hunk);
+        match first.get(&key) {
+            Some(&j) => sets.union(j, i, key),
+            None => {
+                first.insert(key, i);
+            }
+        }
+    }
+}
+
+/// The names `hunk`'s changed lines declare, and every name they mention.
+fn touched_symbols(hunk: &DiffHunk) -> (HashSet<String>, HashSet<String>) {
+    let mut declared = HashSet::new();
+    let mut used = HashSet::new();
+    for line in hunk
+        .lines
+        .iter()
+        .filter(|l| l.starts_with('+') || l.starts_with('-'))
+    {
+        declared.extend(
+            symbol::declared_names(&line[1..])
+                .into_iter()
+                .filter(|w| is_symbol(w))
+                .map(str::to_string),
+        );
+        used.extend(
+            symbol::words(&line[1..])
+                .into_iter()
+                .filter(|w| is_symbol(w))
+                .map(str::to_string),
+        );
+    }
+    (declared, used)
+}
+
+fn is_symbol(word: &str) -> bool {
+    word.len() >= MIN_SYMBOL_LEN
+        && word
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && !symbol::is_declaration_keyword(word)
+}
+
+/// For each hunk, the commit most of its removed lines were last changed in,
+/// if it removes committed lines.
+fn blame_targets(
+    hunks: &[Listed],
+    commit: Option<&str>,
+    ignore_revs: &[String],
+) -> Result<Vec<Option<String>>> {
+    let listed: Vec<&DiffHunk> = hunks.iter().map(|(_, hunk)| *hunk).collect();
+    let blame = crate::hunk::collect_blame(&listed, commit, ignore_revs)?;
+    Ok(listed
+        .iter()
+        .map(|hunk| {
+            let mut counts: Vec<(&str, usize)> = Vec::new();
+            let blamed = crate::hunk::blamed_lines(hunk, commit, &blame);
+            for (line, blamed) in hunk.lines.iter().zip(blamed) {
+                let Some(blamed) = blamed.filter(|_| line.starts_with('-')) else {
+                    continue;
+                };
+                match counts.iter_mut().find(|(hash, _)| *hash == blamed.hash) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((&blamed.hash, 1)),
+                }
+            }
+            // Earliest line wins a tie
+            counts
+                .iter()
+                .rev()
+                .max_by_key(|(_, count)| *count)
+                .map(|(hash, _)| hash.to_string())
+        })
+        .collect())
+}
+
+/// What a group's commit changes, for its placeholder message: the file, the
+/// directory all its files are in, or the files.
+fn label(group: &Group) -> String {
+    let mut files: Vec<&str> = Vec::new();
+    for (_, hunk) in &group.hunks {
+        if !files.contains(&hunk.file.as_str()) {
+            files.push(&hunk.file);
+        }
+    }
+    if let [file] = files[..] {
+        return file.to_string();
+    }
+    let dir = |file: &str| file.rsplit_once('/').map(|(dir, _)| dir.to_string());
+    let first = dir(files[0]);
+    if first.is_some() && files.iter().all(|f| dir(f) == first) {
+        return format!("{}/", first.unwrap_or_default());
+    }
+    files.join(", ")
+}
+
+/// A group's placeholder message; with a scope, `type(scope): description`
+/// after the commit's conventional subject (`chore(scope): update ...` if it
+/// has none).
+fn message(group: &Group, convention: Option<&(String, String)>) -> String {
+    match (&group.scope, convention) {
+        (Some(scope), Some((kind, description))) => {
+            let (kind, breaking) = match kind.strip_suffix('!') {
+                Some(kind) => (kind, "!"),
+                None => (kind.as_str(), ""),
+            };
+            format!("{}({}){}: {}", kind, scope, breaking, description)
+        }
+        (Some(scope), None) => format!("chore({}): update {}", scope, label(group)),
+        (None, _) => format!("Update {}", label(group)),
+    }
+}
+
+/// The groups a split picks: all but the last, which is the rest and keeps
+/// the commit's message, unless it has a scope of its own.
+fn picked<'g, 'a>(groups: &'g [Group<'a>]) -> &'g [Group<'a>] {
+    match groups.last() {
+        Some(last) if last.scope.is_none() => &groups[..groups.len() - 1],
+        _ => groups,
+    }
+}
+
+fn print_groups(groups: &[Group], commit: Option<&str>) {
+    for (i, group) in groups.iter().enumerate() {
+        let heading = if commit.is_some() && i >= picked(groups).len() && groups.len() > 1 {
+            "rest (keeps the commit's message)".to_string()
+        } else {
+            format!("commit {}", i + 1)
+        };
+        if group.reasons.is_empty() {
+            eprintln!("{}: {}", heading, label(group));
+        } else {
+            eprintln!(
+                "{}: {} ({})",
+                heading,
+                label(group),
+                group.reasons.join("; ")
+            );
+        }
+        for (id, hunk) in &group.hunks {
+            let (additions, deletions) = crate::hunk::count_changes(hunk);
+            eprintln!("  {} {} (+{} -{})", id, hunk.file, additions, deletions);
+        }
+    }
+}
+
+fn ids<'a>(group: &Group<'a>) -> Vec<&'a str> {
+    group.hunks.iter().map(|(id, _)| *id).collect()
+}
+
+/// A `split` command picking every group but the last, which keeps the
+/// original message; or one `commit` command per group.
+fn commands(
+    groups: &[Group],
+    commit: Option<&str>,
+    convention: Option<&(String, String)>,
+) -> Vec<String> {
+    let quote = crate::sequence::shell_quote;
+    match commit {
+        Some(sha) => {
+            let mut command = format!("git-surgeon split {}", &sha[..7]);
+            for group in picked(groups) {
+                command.push_str(&format!(
+                    " --pick {} -m {}",
+                    ids(group).join(" "),
+                    quote(&message(group, convention))
+                ));
+            }
+            vec![command]
+        }
+        None => groups
+            .iter()
+            .map(|group| {
+                format!(
+                    "git-surgeon commit {} -m {}",
+                    ids(group).join(" "),
+                    quote(&message(group, convention))
+                )
+            })
+            .collect(),
+    }
+}
+
+/// The same suggestion as an `apply-plan` file.
+fn plan_file(
+    groups: &[Group],
+    commit: Option<&str>,
+    convention: Option<&(String, String)>,
+) -> String {
+    // Single-quoted YAML escapes a quote by doubling it
+    let quote = |s: &str| format!("'{}'", s.replace('\'', "''"));
+    let mut out = String::from("steps:\n");
+    match commit {
+        Some(sha) => {
+            out.push_str(&format!(
+                "  - split:\n      commit: {}\n      picks:\n",
+                sha
+            ));
+            for group in picked(groups) {
+                out.push_str(&format!(
+                    "        - hunks: [{}]\n          message: {}\n",
+                    ids(group).join(", "),
+                    quote(&message(group, convention))
+                ));
+            }
+        }
+        None => {
+            for group in groups {
+                out.push_str(&format!(
+                    "  - commit:\n      hunks: [{}]\n      message: {}\n",
+                    ids(group).join(", "),
+                    quote(&message(group, convention))
+                ));
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hunk(file: &str, lines: &str) -> DiffHunk {
+        let diff = format!(
+            "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n@@ -1,1 +1,1 @@\n{1}",
+            file, lines
+        );
+        crate::diff::parse_diff(&diff).remove(0)
+    }
+
+    #[test]
+    fn test_touched_symbols() {
+        let (declared, used) = touched_symbols(&hunk(
+            "src/lib.rs",
+            " fn context_only() {}\n+pub fn parse_line(s: &str) -> Line {\n-    old(x)\n",
+        ));
+        assert_eq!(declared, HashSet::from(["parse_line".to_string()]));
+        assert!(used.contains("Line") && used.contains("old"));
+        assert!(!used.contains("context_only") && !used.contains("x"));
+    }
+
+    #[test]
+    fn test_conventional() {
+        let pair =
+            |kind: &str, description: &str| Some((kind.to_string(), description.to_string()));
+        assert_eq!(
+            conventional("feat(api): add retries"),
+            pair("feat", "add retries")
+        );
+        assert_eq!(conventional("fix!: drop v1"), pair("fix!", "drop v1"));
+        assert_eq!(conventional("Add retries"), None);
+        assert_eq!(conventional("Note: prose with a colon"), None);
+        assert_eq!(conventional("feat(api: unclosed"), None);
+    }
+
+    #[test]
+    fn test_group_hunks() {
+        let hunks = [
+            hunk("src/a.rs", "+fn helper() {}\n"),
+            hunk("src/b.rs", "+    helper();\n"),
+            hunk("src/b.rs", "+    other();\n"),
+            hunk("docs/c.md", "+text\n"),
+        ];
+        let listed: Vec<Listed> = ["1", "2", "3", "4"].into_iter().zip(&hunks).collect();
+
+        let groups = group_hunks(&listed, &[Criterion::File], None, &[]).unwrap();
+        let picked: Vec<Vec<&str>> = groups.iter().map(ids).collect();
+        assert_eq!(picked, [vec!["1"], vec!["2", "3"], vec!["4"]]);
+
+        let groups =
+            group_hunks(&listed, &[Criterion::File, Criterion::Symbol], None, &[]).unwrap();
+        let picked: Vec<Vec<&str>> = groups.iter().map(ids).collect();
+        assert_eq!(picked, [vec!["1", "2", "3"], vec!["4"]]);
+        assert_eq!(groups[0].reasons, ["src/b.rs", "share helper"]);
+        assert_eq!(label(&groups[0]), "src/");
+
+        let groups = group_hunks(&listed, &[Criterion::Dir], None, &[]).unwrap();
+        assert_eq!(groups.len(), 2);
+    }
+}
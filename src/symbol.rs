@@ -0,0 +1,315 @@
+use anyhow::Result;
+
+/// Keywords that introduce a name in the languages hunks are usually in.
+const DECLARATIONS: &[&str] = &[
+    "fn",
+    "def",
+    "function",
+    "func",
+    "class",
+    "struct",
+    "enum",
+    "trait",
+    "interface",
+    "type",
+    "const",
+    "static",
+    "let",
+    "var",
+    "mod",
+    "macro_rules",
+];
+
+/// Declaration keywords that are mostly used for locals, which `span` does
+/// not look up.
+const LOCALS: &[&str] = &["let", "var"];
+
+/// Keywords that open a block other code sits in.
+const BLOCKS: &[&str] = &[
+    "fn",
+    "def",
+    "function",
+    "func",
+    "class",
+    "struct",
+    "enum",
+    "trait",
+    "interface",
+    "impl",
+    "mod",
+];
+
+pub fn is_declaration_keyword(word: &str) -> bool {
+    DECLARATIONS.contains(&word)
+}
+
+/// The identifier-like words of `text`, in order.
+pub fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+/// The names `text` declares: each word right after a declaration keyword.
+pub fn declared_names(text: &str) -> Vec<&str> {
+    words(text)
+        .windows(2)
+        .filter(|pair| is_declaration_keyword(pair[0]) && !is_declaration_keyword(pair[1]))
+        .map(|pair| pair[1])
+        .collect()
+}
+
+/// The 1-based, inclusive line span of the declaration of `name` in
+/// `source`, the contents of `path`: from a tree-sitter parse in builds with
+/// the `tree-sitter` feature when there is a grammar for the file's
+/// language, otherwise as `span` finds it.
+pub fn span_in(path: &str, source: &str, name: &str) -> Result<(usize, usize)> {
+    #[cfg(feature = "tree-sitter")]
+    if let Some(spans) = crate::syntax::declarations(path, source, name)? {
+        return only_one(name, spans);
+    }
+    #[cfg(not(feature = "tree-sitter"))]
+    let _ = path;
+    span(source, name)
+}
+
+/// The 1-based, inclusive line span of the declaration of `name` in
+/// `source`, by scanning for keywords and brackets: from the comments and
+/// attributes right above it to its closing brace, its `;`, or for an
+/// indented block (`def f():`) its last line indented deeper than the
+/// declaration.
+pub fn span(source: &str, name: &str) -> Result<(usize, usize)> {
+    let lines: Vec<&str> = source.lines().collect();
+    let spans = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            words(&code_of(line)).windows(2).any(|pair| {
+                is_declaration_keyword(pair[0]) && !LOCALS.contains(&pair[0]) && pair[1] == name
+            })
+        })
+        .map(|(start, _)| {
+            let mut first = start;
+            while first > 0 && is_attached(lines[first - 1]) {
+                first -= 1;
+            }
+            (first + 1, end_of(&lines, start) + 1)
+        })
+        .collect();
+    only_one(name, spans)
+}
+
+fn only_one(name: &str, spans: Vec<(usize, usize)>) -> Result<(usize, usize)> {
+    match spans[..] {
+        [] => anyhow::bail!("no declaration of '{}' found", name),
+        [only] => Ok(only),
+        _ => anyhow::bail!(
+            "'{}' is declared more than once (lines {}); use hunk IDs with --lines instead",
+            name,
+            spans
+                .iter()
+                .map(|(first, _)| first.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// The declaration line of the innermost function, class, impl or similar
+/// block of `source` that contains the 1-based `line`, trimmed.
+pub fn enclosing(source: &str, line: usize) -> Option<&str> {
+    let lines: Vec<&str> = source.lines().collect();
+    let target = line.checked_sub(1)?;
+    lines
+        .iter()
+        .enumerate()
+        .take(target + 1)
+        .filter(|(_, text)| {
+            words(&code_of(text))
+                .windows(2)
+                .any(|pair| BLOCKS.contains(&pair[0]) && !BLOCKS.contains(&pair[1]))
+        })
+        .rev()
+        .find(|(i, _)| end_of(&lines, *i) >= target)
+        .map(|(_, text)| text.trim())
+}
+
+/// Comments, attributes and decorators directly above a declaration.
+fn is_attached(line: &str) -> bool {
+    let line = line.trim_start();
+    ["//", "#[", "@", "/*", "*", "#"]
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        && !line.is_empty()
+}
+
+/// The 0-based last line of the declaration starting at line `start`.
+fn end_of(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        for c in code_of(line).chars() {
+            match c {
+                '{' | '(' | '[' => {
+                    depth += 1;
+                    opened |= c == '{';
+                }
+                '}' | ')' | ']' => depth = depth.saturating_sub(1),
+                ';' if depth == 0 => return i,
+                _ => {}
+            }
+        }
+        // Checked first, as a header can hold brackets too: `def f(x={}):`
+        if depth == 0 && code_of(line).trim_end().ends_with(':') {
+            return end_of_indented(lines, i, indent(lines[start]));
+        }
+        if depth == 0 && opened {
+            return i;
+        }
+    }
+    start
+}
+
+/// The last line of the block after line `header`, indented deeper than
+/// `outer`; trailing blank lines are not part of it.
+fn end_of_indented(lines: &[&str], header: usize, outer: usize) -> usize {
+    let mut end = header;
+    for (i, line) in lines.iter().enumerate().skip(header + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent(line) <= outer {
+            break;
+        }
+        end = i;
+    }
+    end
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// `line` without string and character literals and a trailing `//` or `#`
+/// comment, so brackets in them are not counted.
+fn code_of(line: &str) -> String {
+    let mut code = String::with_capacity(line.len());
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    chars.next();
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '`' => quote = Some(c),
+                // Rust lifetimes ('a) are not literals; 'x' and '\n' are
+                '\'' if chars.clone().nth(1) == Some('\'') || chars.peek() == Some(&'\\') => {
+                    quote = Some(c)
+                }
+                '/' if chars.peek() == Some(&'/') => break,
+                '#' if code.trim().is_empty() => break,
+                c => code.push(c),
+            },
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RUST: &str = "\
+use std::fmt;
+
+/// Parses a line.
+#[inline]
+pub fn parse_line(s: &str) -> Line {
+    let brace = \"}\";
+    if s.is_empty() {
+        return Line::default();
+    }
+    Line { text: s.to_string() }
+}
+
+const LIMIT: usize = 10;
+
+fn other<'a>(x: &'a str) -> &'a str {
+    x
+}
+";
+
+    #[test]
+    fn test_span_braces() {
+        assert_eq!(span(RUST, "parse_line").unwrap(), (3, 11));
+        assert_eq!(span(RUST, "LIMIT").unwrap(), (13, 13));
+        assert_eq!(span(RUST, "other").unwrap(), (15, 17));
+        assert!(span(RUST, "missing").is_err());
+    }
+
+    #[test]
+    fn test_span_indented() {
+        let python = "\
+import os
+
+@cached
+def load(path):
+    with open(path) as f:
+        return f.read()
+
+def save(path):
+    pass
+";
+        assert_eq!(span(python, "load").unwrap(), (3, 6));
+        assert_eq!(span(python, "save").unwrap(), (8, 9));
+    }
+
+    #[test]
+    fn test_span_header_with_brackets() {
+        let python = "def f(x={}):\n    a = 1\n    return a\n";
+        assert_eq!(span(python, "f").unwrap(), (1, 3));
+    }
+
+    #[test]
+    fn test_span_ignores_comments_and_locals() {
+        let source = "\
+/// Wraps fn parse
+fn parse() {
+    let total = 0;
+}
+
+fn other() {
+    let total = 1;
+}
+";
+        assert_eq!(span(source, "parse").unwrap(), (1, 4));
+        assert!(
+            span(source, "total")
+                .unwrap_err()
+                .to_string()
+                .contains("no declaration of 'total'")
+        );
+    }
+
+    #[test]
+    fn test_enclosing() {
+        let source = "impl Parser {\n    fn one(&self) {\n        a();\n    }\n\n    fn two() {}\n}\nconst X: u8 = 1;\n";
+        assert_eq!(enclosing(source, 3), Some("fn one(&self) {"));
+        assert_eq!(enclosing(source, 5), Some("impl Parser {"));
+        assert_eq!(enclosing(source, 8), None);
+    }
+
+    #[test]
+    fn test_declared_names() {
+        assert_eq!(
+            declared_names("pub fn parse(s: &str) { let total = 0; }"),
+            ["parse", "total"]
+        );
+        assert!(declared_names("    helper();").is_empty());
+    }
+}
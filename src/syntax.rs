@@ -0,0 +1,219 @@
+//! Declaration spans from a tree-sitter parse, for `stage --symbol` in
+//! builds with the `tree-sitter` feature.
+
+use anyhow::Result;
+use std::path::Path;
+use tree_sitter::{Language, Node, Parser};
+
+/// Declaration node kinds that carry a `name` field, per language.
+struct Grammar {
+    language: Language,
+    declarations: &'static [&'static str],
+}
+
+fn grammar(path: &str) -> Option<Grammar> {
+    let extension = Path::new(path).extension()?.to_str()?;
+    let (language, declarations): (Language, &[&str]) = match extension {
+        "rs" => (
+            tree_sitter_rust::LANGUAGE.into(),
+            &[
+                "function_item",
+                "function_signature_item",
+                "struct_item",
+                "enum_item",
+                "union_item",
+                "trait_item",
+                "type_item",
+                "const_item",
+                "static_item",
+                "mod_item",
+                "macro_definition",
+            ],
+        ),
+        "py" | "pyi" => (
+            tree_sitter_python::LANGUAGE.into(),
+            &["function_definition", "class_definition"],
+        ),
+        "js" | "mjs" | "cjs" | "jsx" => (
+            tree_sitter_javascript::LANGUAGE.into(),
+            &[
+                "function_declaration",
+                "generator_function_declaration",
+                "class_declaration",
+                "method_definition",
+                "variable_declarator",
+            ],
+        ),
+        "go" => (
+            tree_sitter_go::LANGUAGE.into(),
+            &[
+                "function_declaration",
+                "method_declaration",
+                "type_spec",
+                "const_spec",
+                "var_spec",
+            ],
+        ),
+        _ => return None,
+    };
+    Some(Grammar {
+        language,
+        declarations,
+    })
+}
+
+/// Nodes a declaration is lifted to when it is their only one: its
+/// decorators, `export`, or `const`/`let`/`type` keyword.
+const WRAPPERS: &[&str] = &[
+    "decorated_definition",
+    "export_statement",
+    "lexical_declaration",
+    "variable_declaration",
+    "type_declaration",
+    "const_declaration",
+    "var_declaration",
+];
+
+/// Declarations that only count outside a function, where they are locals.
+const VALUES: &[&str] = &["variable_declarator", "const_spec", "var_spec"];
+
+/// The 1-based, inclusive spans of every declaration of `name` in `source`,
+/// each from the comments and attributes right above it to its last line.
+/// None if there is no grammar for `path`'s language.
+pub fn declarations(path: &str, source: &str, name: &str) -> Result<Option<Vec<(usize, usize)>>> {
+    let Some(grammar) = grammar(path) else {
+        return Ok(None);
+    };
+    let mut parser = Parser::new();
+    parser.set_language(&grammar.language)?;
+    let Some(tree) = parser.parse(source, None) else {
+        return Ok(None);
+    };
+
+    let mut spans = Vec::new();
+    let mut stack = vec![tree.root_node()];
+    while let Some(node) = stack.pop() {
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor));
+        if !grammar.declarations.contains(&node.kind())
+            || (VALUES.contains(&node.kind()) && in_function(node))
+        {
+            continue;
+        }
+        let mut cursor = node.walk();
+        let named = node
+            .children_by_field_name("name", &mut cursor)
+            .any(|n| n.utf8_text(source.as_bytes()) == Ok(name));
+        if named {
+            spans.push(span_of(node));
+        }
+    }
+    spans.sort_unstable();
+    Ok(Some(spans))
+}
+
+fn in_function(node: Node) -> bool {
+    std::iter::successors(node.parent(), Node::parent).any(|n| {
+        n.kind().contains("function") || n.kind().contains("method") || n.kind() == "func_literal"
+    })
+}
+
+fn span_of(mut node: Node) -> (usize, usize) {
+    while let Some(parent) = node.parent()
+        && WRAPPERS.contains(&parent.kind())
+    {
+        let mut cursor = parent.walk();
+        let siblings = parent
+            .named_children(&mut cursor)
+            .filter(|n| n.kind() == node.kind())
+            .count();
+        if siblings > 1 {
+            break;
+        }
+        node = parent;
+    }
+
+    let mut first = node.start_position().row;
+    let mut above = node.prev_named_sibling();
+    while let Some(sibling) = above
+        && (sibling.kind().contains("comment") || sibling.kind() == "attribute_item")
+        && sibling.end_position().row + 1 >= first
+    {
+        first = sibling.start_position().row;
+        above = sibling.prev_named_sibling();
+    }
+    // A node ending in a newline ends at column 0 of the next row
+    let end = node.end_position();
+    let last = if end.column == 0 && end.row > first {
+        end.row - 1
+    } else {
+        end.row
+    };
+    (first + 1, last + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_declarations() {
+        let source = "\
+/// Wraps fn parse
+#[inline]
+fn parse(s: &str) -> u8 {
+    let total = 0;
+    total
+}
+
+fn other() {
+    let total = 1;
+}
+";
+        assert_eq!(
+            declarations("a.rs", source, "parse").unwrap(),
+            Some(vec![(1, 6)])
+        );
+        assert_eq!(declarations("a.rs", source, "total").unwrap(), Some(vec![]));
+        assert_eq!(declarations("a.txt", source, "parse").unwrap(), None);
+    }
+
+    #[test]
+    fn test_other_languages() {
+        let python = "\
+import os
+
+@cached
+def f(x={}):
+    a = 1
+    return a
+
+def g():
+    pass
+";
+        assert_eq!(
+            declarations("a.py", python, "f").unwrap(),
+            Some(vec![(3, 6)])
+        );
+
+        let js = "\
+// The handler
+export const handle = (event) => {
+  const total = event.count;
+  return total;
+};
+";
+        assert_eq!(
+            declarations("a.js", js, "handle").unwrap(),
+            Some(vec![(1, 5)])
+        );
+        assert_eq!(declarations("a.js", js, "total").unwrap(), Some(vec![]));
+
+        let go = "package main\n\nvar limit = 3\n\nfunc run() {\n\tvar limit = 4\n\t_ = limit\n}\n";
+        assert_eq!(
+            declarations("a.go", go, "limit").unwrap(),
+            Some(vec![(3, 3)])
+        );
+        assert_eq!(declarations("a.go", go, "run").unwrap(), Some(vec![(5, 8)]));
+    }
+}
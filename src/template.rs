@@ -0,0 +1,190 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::Command;
+
+use crate::output::Traced;
+
+const PLACEHOLDERS: &[&str] = &["message", "subject", "body", "files", "hunk_count", "scope"];
+
+/// A commit message template from the `surgeon.messageTemplate` config: a
+/// file path, relative to the repository root unless absolute.
+pub struct Template {
+    text: String,
+}
+
+impl Template {
+    /// The configured template, or None when none is set.
+    pub fn load() -> Result<Option<Template>> {
+        let output = Command::new("git")
+            .args(["config", "--path", "--get", "surgeon.messageTemplate"])
+            .traced_output()
+            .context("failed to read surgeon.messageTemplate")?;
+        let configured = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if configured.is_empty() {
+            return Ok(None);
+        }
+        let mut path = Path::new(&configured).to_path_buf();
+        if path.is_relative() {
+            let root = crate::diff::run_git_cmd(
+                Command::new("git").args(["rev-parse", "--show-toplevel"]),
+            )?;
+            path = Path::new(root.trim()).join(path);
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read message template {}", path.display()))?;
+        Ok(Some(Template { text }))
+    }
+
+    /// Expand the placeholders for a commit of `files` made of `hunk_count`
+    /// hunks, with `message` as given by `-m`.
+    pub fn expand(&self, message: &str, files: &[String], hunk_count: usize) -> Result<String> {
+        let message = message.trim();
+        let (subject, body) = match message.split_once('\n') {
+            Some((subject, body)) => (subject, body.trim()),
+            None => (message, ""),
+        };
+
+        // Comment lines are dropped from the template, not the message
+        let text: Vec<&str> = self.text.lines().filter(|l| !l.starts_with('#')).collect();
+        let text = text.join("\n");
+        let mut out = String::new();
+        let mut rest = text.as_str();
+        while let Some(pos) = rest.find(['{', '}']) {
+            out.push_str(&rest[..pos]);
+            let brace = &rest[pos..pos + 1];
+            rest = &rest[pos + 1..];
+            if let Some(after) = rest.strip_prefix(brace) {
+                // `{{` and `}}` are literal braces
+                out.push_str(brace);
+                rest = after;
+                continue;
+            }
+            if brace == "}" {
+                anyhow::bail!(
+                    "unmatched '}}' in message template (use '}}}}' for a literal brace)"
+                );
+            }
+            let end = rest
+                .find('}')
+                .ok_or_else(|| anyhow::anyhow!("unclosed '{{' in message template"))?;
+            match &rest[..end] {
+                "message" => out.push_str(message),
+                "subject" => out.push_str(subject),
+                "body" => out.push_str(body),
+                "files" => out.push_str(&files.join(", ")),
+                "hunk_count" => out.push_str(&hunk_count.to_string()),
+                "scope" => out.push_str(&scope(files)),
+                other => anyhow::bail!(
+                    "unknown placeholder {{{}}} in message template (available: {})",
+                    other,
+                    PLACEHOLDERS
+                        .iter()
+                        .map(|p| format!("{{{}}}", p))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(cleanup(&out))
+    }
+}
+
+/// Drop trailing whitespace and the extra blank lines left behind by empty
+/// placeholders.
+fn cleanup(text: &str) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_none_or(|l| l.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// The directory that all `files` share, by its last component; for a single
+/// file at the root, its name without extension. Empty otherwise.
+fn scope(files: &[String]) -> String {
+    let mut common: Option<Vec<&str>> = None;
+    for file in files {
+        let mut dirs: Vec<&str> = file.split('/').collect();
+        dirs.pop();
+        common = Some(match common {
+            None => dirs,
+            Some(prev) => prev
+                .into_iter()
+                .zip(dirs)
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    match common.as_deref() {
+        Some([.., last]) => last.to_string(),
+        Some([]) if files.len() == 1 => Path::new(&files[0])
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_scope() {
+        assert_eq!(
+            scope(&files(&["src/parser/lex.rs", "src/parser/mod.rs"])),
+            "parser"
+        );
+        assert_eq!(scope(&files(&["src/parser/lex.rs", "src/main.rs"])), "src");
+        assert_eq!(scope(&files(&["README.md"])), "README");
+        assert_eq!(scope(&files(&["README.md", "src/main.rs"])), "");
+        assert_eq!(scope(&[]), "");
+    }
+
+    #[test]
+    fn test_expand() {
+        let template = Template {
+            text: "# comment\n{scope}: {subject}\n\n{body}\n\nFiles: {files} ({hunk_count} hunks) {{x}}\n"
+                .to_string(),
+        };
+        let files = files(&["src/parser/lex.rs", "src/parser/mod.rs"]);
+        assert_eq!(
+            template
+                .expand("Fix lexing\n\n#12 details.", &files, 3)
+                .unwrap(),
+            "parser: Fix lexing\n\n#12 details.\n\nFiles: src/parser/lex.rs, src/parser/mod.rs (3 hunks) {x}"
+        );
+        // An empty {body} leaves no run of blank lines
+        assert_eq!(
+            template.expand("Fix lexing", &files, 1).unwrap(),
+            "parser: Fix lexing\n\nFiles: src/parser/lex.rs, src/parser/mod.rs (1 hunks) {x}"
+        );
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let unknown = Template {
+            text: "{nope}".to_string(),
+        };
+        let err = unknown.expand("m", &[], 0).unwrap_err().to_string();
+        assert!(err.contains("unknown placeholder {nope}"), "{}", err);
+        let unclosed = Template {
+            text: "{message".to_string(),
+        };
+        assert!(unclosed.expand("m", &[], 0).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use sha1::{Digest, Sha1};
+use std::fmt;
+use std::process::Command;
+
+use crate::output::Traced;
+
+/// Returned when the repository no longer matches an `--if-match` token.
+#[derive(Debug)]
+pub struct StaleToken {
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for StaleToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "repository changed since listing (token {} != {}); re-run 'git-surgeon hunks --json'",
+            self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for StaleToken {}
+
+/// Token identifying the current diff state: HEAD plus the staged and
+/// unstaged diffs. Any change to what `hunks` would list changes the token.
+pub fn current() -> Result<String> {
+    let head = Command::new("git")
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .traced_output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_default();
+    let unstaged = crate::diff::run_git_diff(false, None)?;
+    let staged = crate::diff::run_git_diff(true, None)?;
+
+    let mut hasher = Sha1::new();
+    hasher.update(head.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(staged.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(unstaged.as_bytes());
+    Ok(hex::encode(hasher.finalize())[..16].to_string())
+}
+
+/// Fail with `StaleToken` unless the current state matches `expected`.
+pub fn check(expected: &str) -> Result<()> {
+    let actual = current()?;
+    if actual != expected {
+        return Err(StaleToken {
+            expected: expected.to_string(),
+            actual,
+        }
+        .into());
+    }
+    Ok(())
+}
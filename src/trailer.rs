@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+use crate::output::Traced;
+
+/// Trailers to append to a commit message, from `--trailer key=value` and
+/// `--signoff`.
+#[derive(Clone, Default)]
+pub struct Trailers {
+    pub signoff: bool,
+    pub trailers: Vec<String>,
+}
+
+impl Trailers {
+    pub fn new(signoff: bool, trailers: Vec<String>) -> Result<Trailers> {
+        for trailer in &trailers {
+            check_trailer(trailer)?;
+        }
+        Ok(Trailers { signoff, trailers })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.signoff && self.trailers.is_empty()
+    }
+
+    /// Append the trailers to `message` with `git interpret-trailers`, so the
+    /// repository's `trailer.*` configuration applies. The message needs a
+    /// final newline for a lone subject to be set apart from the trailers.
+    pub fn apply(&self, message: &str) -> Result<String> {
+        if self.is_empty() {
+            return Ok(message.to_string());
+        }
+
+        let mut cmd = Command::new("git");
+        cmd.arg("interpret-trailers");
+        for trailer in &self.trailers {
+            cmd.args(["--trailer", trailer]);
+        }
+        if self.signoff {
+            cmd.args([
+                "--trailer",
+                &format!("Signed-off-by: {}", committer_ident()?),
+            ]);
+        }
+        let output = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .traced_output_with_input(format!("{}\n", message.trim_end()).as_bytes())
+            .context("failed to run git interpret-trailers")?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git interpret-trailers failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .trim_end()
+            .to_string())
+    }
+}
+
+/// Reject trailers without a `key=value` or `key: value` shape, which
+/// interpret-trailers would otherwise turn into an empty-valued trailer.
+fn check_trailer(trailer: &str) -> Result<()> {
+    let valid = trailer.split_once(['=', ':']).is_some_and(|(key, value)| {
+        !key.trim().is_empty()
+            && !key.trim().contains(char::is_whitespace)
+            && !value.trim().is_empty()
+    });
+    if !valid {
+        anyhow::bail!("invalid trailer '{}' (expected key=value)", trailer);
+    }
+    Ok(())
+}
+
+/// `Name <email>` of the committer, as `git commit --signoff` would use.
+fn committer_ident() -> Result<String> {
+    let ident = crate::diff::run_git_cmd(Command::new("git").args(["var", "GIT_COMMITTER_IDENT"]))?;
+    let end = ident
+        .rfind('>')
+        .ok_or_else(|| anyhow::anyhow!("unexpected committer ident: {}", ident.trim()))?;
+    Ok(ident[..=end].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_trailer() {
+        assert!(check_trailer("Fixes=#123").is_ok());
+        assert!(check_trailer("Reviewed-by: A <a@b>").is_ok());
+        assert!(check_trailer("Fixes").is_err());
+        assert!(check_trailer("=value").is_err());
+        assert!(check_trailer("Fixes=").is_err());
+        assert!(check_trailer("two words=x").is_err());
+    }
+}
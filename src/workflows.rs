@@ -0,0 +1,249 @@
+use anyhow::Result;
+
+use crate::json::{self, Value};
+
+/// A task-oriented recipe: a sequence of commands that accomplish one goal.
+struct Recipe {
+    name: &'static str,
+    title: &'static str,
+    summary: &'static str,
+    steps: &'static [Step],
+}
+
+struct Step {
+    /// git-surgeon subcommand used by this step, or `None` for plain git
+    command: Option<&'static str>,
+    example: &'static str,
+    note: &'static str,
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        name: "split-commit",
+        title: "Split a commit",
+        summary: "Break one commit into several focused commits by hunk.",
+        steps: &[
+            Step {
+                command: Some("hunks"),
+                example: "git-surgeon hunks --commit <sha> --full",
+                note: "List the commit's hunks with line numbers.",
+            },
+            Step {
+                command: Some("suggest-split"),
+                example: "git-surgeon suggest-split <sha>",
+                note: "Optionally, get a proposed split command to adjust.",
+            },
+            Step {
+                command: Some("split"),
+                example: "git-surgeon split <sha> --pick <id1> -m \"first\" --pick <id2>:1-8 -m \"second\" --rest-message \"rest\"",
+                note: "Pick hunks (or line ranges) for each new commit; the rest goes last.",
+            },
+            Step {
+                command: Some("continue"),
+                example: "git-surgeon continue",
+                note: "Only needed if a hook or conflict interrupted the split.",
+            },
+        ],
+    },
+    Recipe {
+        name: "absorb-fixes",
+        title: "Absorb fixes into earlier commits",
+        summary: "Fold working tree fixes into the commits they belong to.",
+        steps: &[
+            Step {
+                command: Some("hunks"),
+                example: "git-surgeon hunks --blame",
+                note: "See which commit introduced the lines around each fix.",
+            },
+            Step {
+                command: Some("fixup"),
+                example: "git-surgeon fixup <sha> <id>... --no-rebase",
+                note: "Commit the hunks that belong to one target as a fixup! commit; repeat per target.",
+            },
+            Step {
+                command: None,
+                example: "GIT_SEQUENCE_EDITOR=true git rebase -i --autosquash <oldest-sha>~1",
+                note: "Fold every fixup! commit in with a single rebase.",
+            },
+        ],
+    },
+    Recipe {
+        name: "partial-revert",
+        title: "Revert part of a commit",
+        summary: "Reverse selected hunks or lines of an earlier commit.",
+        steps: &[
+            Step {
+                command: Some("hunks"),
+                example: "git-surgeon hunks --commit <sha>",
+                note: "Find the hunks to revert.",
+            },
+            Step {
+                command: Some("undo"),
+                example: "git-surgeon undo <id> --from <sha> --lines 2-10",
+                note: "Reverse-apply them to the working tree.",
+            },
+            Step {
+                command: Some("commit"),
+                example: "git-surgeon commit <new-id> -m \"Revert ...\"",
+                note: "Commit the reversal (list hunks again for the new IDs).",
+            },
+        ],
+    },
+    Recipe {
+        name: "stacked-prs",
+        title: "Prepare stacked PRs",
+        summary: "Shape a branch into an ordered stack of reviewable commits.",
+        steps: &[
+            Step {
+                command: Some("commit"),
+                example: "git-surgeon commit <id1> <id2> -m \"Part 1\"",
+                note: "Commit the work in reviewable slices.",
+            },
+            Step {
+                command: Some("reorder"),
+                example: "git-surgeon reorder main..HEAD --order 2 1 3",
+                note: "Put the slices in dependency order.",
+            },
+            Step {
+                command: Some("squash"),
+                example: "git-surgeon squash HEAD~1 -m \"Part 3\"",
+                note: "Combine slices that belong in the same PR.",
+            },
+            Step {
+                command: None,
+                example: "git branch part-1 HEAD~2",
+                note: "Point one branch at the top of each PR.",
+            },
+        ],
+    },
+];
+
+/// One-line description of a subcommand from the CLI definition.
+fn about(cli: &clap::Command, name: &str) -> Option<String> {
+    cli.find_subcommand(name)
+        .and_then(|c| c.get_about())
+        .map(|a| a.to_string())
+}
+
+/// Print all recipes, or just the named one, as text or JSON.
+pub fn print(cli: &clap::Command, name: Option<&str>, json: bool) -> Result<()> {
+    let recipes: Vec<&Recipe> = match name {
+        Some(n) => {
+            let recipe = RECIPES.iter().find(|r| r.name == n).ok_or_else(|| {
+                let names: Vec<&str> = RECIPES.iter().map(|r| r.name).collect();
+                anyhow::anyhow!("unknown workflow '{}' (available: {})", n, names.join(", "))
+            })?;
+            vec![recipe]
+        }
+        None => RECIPES.iter().collect(),
+    };
+
+    if json {
+        let entries: Vec<Value> = recipes
+            .iter()
+            .map(|r| {
+                let steps: Vec<Value> = r
+                    .steps
+                    .iter()
+                    .map(|s| {
+                        json::object([
+                            ("command", s.command.into()),
+                            ("about", s.command.and_then(|c| about(cli, c)).into()),
+                            ("example", s.example.into()),
+                            ("note", s.note.into()),
+                        ])
+                    })
+                    .collect();
+                json::object([
+                    ("name", r.name.into()),
+                    ("title", r.title.into()),
+                    ("summary", r.summary.into()),
+                    ("steps", Value::Array(steps)),
+                ])
+            })
+            .collect();
+        println!("{}", Value::Array(entries));
+        return Ok(());
+    }
+
+    for (i, recipe) in recipes.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!("{} ({})", recipe.title, recipe.name);
+        println!("  {}", recipe.summary);
+        for (n, step) in recipe.steps.iter().enumerate() {
+            println!();
+            println!("  {}. {}", n + 1, step.note);
+            println!("     $ {}", step.example);
+            if let Some(command) = step.command
+                && let Some(about) = about(cli, command)
+            {
+                println!("     ({}: {})", command, about);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{CommandFactory, Parser};
+
+    #[test]
+    fn test_recipe_steps_use_existing_commands() {
+        let cli = crate::Cli::command();
+        for recipe in RECIPES {
+            for step in recipe.steps {
+                if let Some(command) = step.command {
+                    assert!(
+                        about(&cli, command).is_some(),
+                        "{}: unknown command {}",
+                        recipe.name,
+                        command
+                    );
+                    assert!(
+                        step.example
+                            .starts_with(&format!("git-surgeon {}", command))
+                    );
+                }
+            }
+        }
+    }
+
+    /// Split an example on spaces, treating double-quoted text as one word.
+    fn words(example: &str) -> Vec<String> {
+        let mut words = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        for c in example.chars() {
+            match c {
+                '"' => quoted = !quoted,
+                ' ' if !quoted => {
+                    if !current.is_empty() {
+                        words.push(std::mem::take(&mut current));
+                    }
+                }
+                c => current.push(c),
+            }
+        }
+        words.push(current);
+        words
+    }
+
+    #[test]
+    fn test_recipe_examples_parse() {
+        for recipe in RECIPES {
+            for step in recipe.steps.iter().filter(|s| s.command.is_some()) {
+                let result = crate::Cli::try_parse_from(words(step.example));
+                assert!(
+                    result.is_ok(),
+                    "{}: {}",
+                    step.example,
+                    result.err().unwrap()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use anyhow::Result;
+
+/// A parsed YAML value. Only the subset needed for plan files is supported:
+/// block mappings and sequences, flow sequences (`[a, b]`), plain and quoted
+/// scalars, literal (`|`) and folded (`>`) block scalars, and `#` comments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Yaml {
+    Scalar(String),
+    List(Vec<Yaml>),
+    Map(Vec<(String, Yaml)>),
+}
+
+impl Yaml {
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Yaml::Scalar(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Yaml> {
+        match self {
+            Yaml::Map(entries) => entries.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+struct Line<'a> {
+    /// 1-based line number in the input, for error messages
+    number: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    /// All input lines, used for block scalars where blank lines matter
+    raw: Vec<&'a str>,
+    pos: usize,
+}
+
+pub fn parse(input: &str) -> Result<Yaml> {
+    let raw: Vec<&str> = input.lines().collect();
+    let lines = raw
+        .iter()
+        .enumerate()
+        .filter_map(|(i, l)| {
+            let text = l.trim_start_matches(' ');
+            if text.trim().is_empty() || text.starts_with('#') || text == "---" {
+                return None;
+            }
+            if l.starts_with('\t') {
+                // Reported below; YAML does not allow tab indentation
+                return Some(Line {
+                    number: i + 1,
+                    indent: usize::MAX,
+                    text,
+                });
+            }
+            Some(Line {
+                number: i + 1,
+                indent: l.len() - text.len(),
+                text: text.trim_end(),
+            })
+        })
+        .collect();
+    let mut parser = Parser { lines, raw, pos: 0 };
+    if let Some(line) = parser.lines.iter().find(|l| l.indent == usize::MAX) {
+        anyhow::bail!("line {}: tabs are not allowed for indentation", line.number);
+    }
+    if parser.lines.is_empty() {
+        return Ok(Yaml::Map(Vec::new()));
+    }
+    let indent = parser.lines[0].indent;
+    let value = parser.parse_block(indent)?;
+    if let Some(line) = parser.lines.get(parser.pos) {
+        anyhow::bail!("line {}: unexpected indentation", line.number);
+    }
+    Ok(value)
+}
+
+fn is_list_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+/// Split `key: value` at the first unquoted `: ` (or a trailing `:`).
+fn split_key(text: &str) -> Option<(&str, &str)> {
+    let mut quote = None;
+    for (i, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') if i == 0 => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, ':') => {
+                let rest = &text[i + 1..];
+                if rest.is_empty() || rest.starts_with(' ') {
+                    return Some((text[..i].trim(), rest.trim()));
+                }
+            }
+            (None, '#') if i > 0 && text[..i].ends_with(' ') => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+impl<'a> Parser<'a> {
+    fn parse_block(&mut self, indent: usize) -> Result<Yaml> {
+        if is_list_item(self.lines[self.pos].text) {
+            self.parse_list(indent)
+        } else {
+            self.parse_map(indent)
+        }
+    }
+
+    fn parse_list(&mut self, indent: usize) -> Result<Yaml> {
+        let mut items = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent != indent || !is_list_item(line.text) {
+                break;
+            }
+            let rest = line.text[1..].trim_start();
+            if rest.is_empty() {
+                self.pos += 1;
+                items.push(self.parse_nested(indent)?);
+            } else if split_key(rest).is_some() || is_list_item(rest) {
+                // `- key: value` starts a mapping (or `- - x` a list) whose
+                // entries are indented to where the first key starts
+                let offset = line.text.len() - rest.len();
+                let line = &mut self.lines[self.pos];
+                line.indent += offset;
+                line.text = rest;
+                let inner = line.indent;
+                items.push(self.parse_block(inner)?);
+            } else {
+                let number = line.number;
+                self.pos += 1;
+                items.push(parse_inline(rest, number)?);
+            }
+        }
+        Ok(Yaml::List(items))
+    }
+
+    fn parse_map(&mut self, indent: usize) -> Result<Yaml> {
+        let mut entries: Vec<(String, Yaml)> = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.indent < indent || (line.indent == indent && is_list_item(line.text)) {
+                break;
+            }
+            if line.indent > indent {
+                anyhow::bail!("line {}: unexpected indentation", line.number);
+            }
+            let number = line.number;
+            let (key, value) = split_key(line.text)
+                .ok_or_else(|| anyhow::anyhow!("line {}: expected 'key: value'", number))?;
+            let key = unquote(key, number)?;
+            if entries.iter().any(|(k, _)| *k == key) {
+                anyhow::bail!("line {}: duplicate key '{}'", number, key);
+            }
+            self.pos += 1;
+
+            let value = match value {
+                "" => match self.lines.get(self.pos) {
+                    // A sequence may sit at the same indentation as its key
+                    Some(next) if next.indent == indent && is_list_item(next.text) => {
+                        self.parse_list(indent)?
+                    }
+                    _ => self.parse_nested(indent)?,
+                },
+                v if v.starts_with('|') || v.starts_with('>') => {
+                    self.parse_block_scalar(v, number)?
+                }
+                v => parse_inline(v, number)?,
+            };
+            entries.push((key, value));
+        }
+        Ok(Yaml::Map(entries))
+    }
+
+    /// Parse the block indented deeper than `indent`, or an empty scalar.
+    fn parse_nested(&mut self, indent: usize) -> Result<Yaml> {
+        match self.lines.get(self.pos) {
+            Some(next) if next.indent > indent => {
+                let inner = next.indent;
+                self.parse_block(inner)
+            }
+            _ => Ok(Yaml::Scalar(String::new())),
+        }
+    }
+
+    /// Parse a `|` or `>` block scalar introduced on line `number`.
+    fn parse_block_scalar(&mut self, header: &str, number: usize) -> Result<Yaml> {
+        let folded = header.starts_with('>');
+        let chomp = &header[1..];
+        if !matches!(chomp, "" | "-" | "+") {
+            anyhow::bail!(
+                "line {}: unsupported block scalar header '{}'",
+                number,
+                header
+            );
+        }
+
+        let parent_indent = self.lines[self.pos - 1].indent;
+        let mut body: Vec<&str> = Vec::new();
+        let mut block_indent = None;
+        let mut raw_idx = number; // raw index of the line after the header
+        while raw_idx < self.raw.len() {
+            let raw = self.raw[raw_idx];
+            let text = raw.trim_start_matches(' ');
+            let indent = raw.len() - text.len();
+            if text.is_empty() {
+                body.push("");
+                raw_idx += 1;
+                continue;
+            }
+            let min = *block_indent.get_or_insert(indent);
+            if indent <= parent_indent || indent < min {
+                break;
+            }
+            body.push(&raw[min..]);
+            raw_idx += 1;
+        }
+        // Trailing blank lines belong to chomping, not to the next node
+        while self
+            .lines
+            .get(self.pos)
+            .is_some_and(|l| l.number <= raw_idx)
+        {
+            self.pos += 1;
+        }
+        while body.last() == Some(&"") {
+            body.pop();
+        }
+
+        let mut text = if folded {
+            let mut out = String::new();
+            for (i, line) in body.iter().enumerate() {
+                if line.is_empty() {
+                    out.push('\n');
+                } else {
+                    if i > 0 && !body[i - 1].is_empty() {
+                        out.push(' ');
+                    }
+                    out.push_str(line);
+                }
+            }
+            out
+        } else {
+            body.join("\n")
+        };
+        if chomp != "-" && !text.is_empty() {
+            text.push('\n');
+        }
+        Ok(Yaml::Scalar(text))
+    }
+}
+
+/// Parse a value written on the same line as its key or dash.
+fn parse_inline(text: &str, number: usize) -> Result<Yaml> {
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner
+            .trim_end()
+            .strip_suffix(']')
+            .ok_or_else(|| anyhow::anyhow!("line {}: unterminated '['", number))?;
+        let mut items = Vec::new();
+        let mut current = String::new();
+        let mut quote = None;
+        for c in inner.chars() {
+            match (quote, c) {
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                (Some(q), c) if c == q => {
+                    quote = None;
+                    current.push(c);
+                }
+                (None, ',') => items.push(std::mem::take(&mut current)),
+                _ => current.push(c),
+            }
+        }
+        if !current.trim().is_empty() || !items.is_empty() {
+            items.push(current);
+        }
+        return items
+            .iter()
+            .map(|item| unquote(item.trim(), number).map(Yaml::Scalar))
+            .collect::<Result<_>>()
+            .map(Yaml::List);
+    }
+    Ok(Yaml::Scalar(unquote(text, number)?))
+}
+
+/// Resolve quoting on a scalar and strip a trailing comment.
+fn unquote(text: &str, number: usize) -> Result<String> {
+    let Some(quote) = text.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        let plain = match text.find(" #") {
+            Some(idx) => &text[..idx],
+            None => text,
+        };
+        return Ok(plain.trim_end().to_string());
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text[1..].char_indices();
+    let mut end = None;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => match chars.next() {
+                Some((_, 'n')) => out.push('\n'),
+                Some((_, 't')) => out.push('\t'),
+                Some((_, other)) => out.push(other),
+                None => break,
+            },
+            // A doubled single quote is an escaped one
+            '\'' if quote == '\'' && text[1 + i + 1..].starts_with('\'') => {
+                out.push('\'');
+                chars.next();
+            }
+            c if c == quote => {
+                end = Some(1 + i + 1);
+                break;
+            }
+            c => out.push(c),
+        }
+    }
+    let Some(end) = end else {
+        anyhow::bail!("line {}: unterminated string", number);
+    };
+    let rest = text[end..].trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        anyhow::bail!("line {}: unexpected text after string: {}", number, rest);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar(s: &str) -> Yaml {
+        Yaml::Scalar(s.to_string())
+    }
+
+    #[test]
+    fn test_nested_maps_and_lists() {
+        let doc = "\
+steps:
+  - commit:
+      hunks: [a1b2c3d, 'e4f5678:1-3']
+      message: \"Add \\\"x\\\"\"  # comment
+  - fixup: HEAD~2
+";
+        let parsed = parse(doc).unwrap();
+        let steps = parsed.get("steps").unwrap();
+        let Yaml::List(steps) = steps else {
+            panic!("expected list")
+        };
+        assert_eq!(steps.len(), 2);
+        let commit = steps[0].get("commit").unwrap();
+        assert_eq!(
+            commit.get("hunks").unwrap(),
+            &Yaml::List(vec![scalar("a1b2c3d"), scalar("e4f5678:1-3")])
+        );
+        assert_eq!(commit.get("message").unwrap(), &scalar("Add \"x\""));
+        assert_eq!(steps[1].get("fixup").unwrap(), &scalar("HEAD~2"));
+    }
+
+    #[test]
+    fn test_block_scalars() {
+        let doc = "\
+literal: |
+  Subject
+
+  Body line
+folded: >-
+  one
+  two
+next: x
+";
+        let parsed = parse(doc).unwrap();
+        assert_eq!(
+            parsed.get("literal").unwrap(),
+            &scalar("Subject\n\nBody line\n")
+        );
+        assert_eq!(parsed.get("folded").unwrap(), &scalar("one two"));
+        assert_eq!(parsed.get("next").unwrap(), &scalar("x"));
+    }
+
+    #[test]
+    fn test_list_at_key_indentation() {
+        let doc = "picks:\n- a\n- b\nrest: c\n";
+        let parsed = parse(doc).unwrap();
+        assert_eq!(
+            parsed.get("picks").unwrap(),
+            &Yaml::List(vec![scalar("a"), scalar("b")])
+        );
+        assert_eq!(parsed.get("rest").unwrap(), &scalar("c"));
+    }
+
+    #[test]
+    fn test_errors_have_line_numbers() {
+        let err = parse("a: 1\n  b: 2\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{}", err);
+        let err = parse("a: 1\na: 2\n").unwrap_err();
+        assert!(err.to_string().contains("duplicate key"), "{}", err);
+    }
+}
@@ -0,0 +1,66 @@
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _get_hunk_ids(exe, repo, *extra_args):
+    result = run_git_agent(exe, repo, "hunks", *extra_args)
+    ids = []
+    for line in result.stdout.strip().split("\n"):
+        if line and not line.startswith("  "):
+            ids.append(line.split()[0])
+    return ids
+
+
+def test_amend_adds_hunks_to_head(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    modify_file(repo, "b.txt", "b changed\n")
+
+    ids = _get_hunk_ids(git_agent_exe, repo, "--file", "a.txt")
+    result = run_git_agent(git_agent_exe, repo, "amend", ids[0])
+    assert result.returncode == 0, result.stderr
+    assert "amended" in result.stderr
+
+    show = run_git(repo, "show", "HEAD")
+    assert "add b.txt" in show.stdout
+    assert "a changed" in show.stdout
+    # Unselected hunk stays in the working tree
+    assert "b changed" in run_git(repo, "diff").stdout
+
+
+def test_amend_message_only(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = run_git_agent(git_agent_exe, repo, "amend", "-m", "new subject", "-m", "body")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%B").stdout.strip() == "new subject\n\nbody"
+
+
+def test_amend_hunks_and_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+
+    ids = _get_hunk_ids(git_agent_exe, repo)
+    result = run_git_agent(git_agent_exe, repo, "amend", ids[0], "-m", "reworded")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "reworded"
+    assert run_git(repo, "diff").stdout == ""
+
+
+def test_amend_refuses_with_staged_changes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    run_git(repo, "add", "a.txt")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "amend", "-m", "nope")
+    assert result.returncode != 0
+    assert "staged" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
+
+
+def test_amend_requires_ids_or_message(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "amend")
+    assert result.returncode != 0
+    assert "nothing to amend" in result.stderr
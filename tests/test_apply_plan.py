@@ -0,0 +1,250 @@
+"""Tests for the apply-plan command."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _subjects(repo):
+    return run_git(repo, "log", "--format=%s").stdout.strip().splitlines()
+
+
+def _setup(repo):
+    """Two commits on top of init, plus two separate unstaged hunks in a.txt."""
+    lines = [f"a{i}" for i in range(1, 13)]
+    create_file(repo, "a.txt", "\n".join(lines) + "\n")
+    create_file(repo, "b.txt", "b\n")
+    lines[0], lines[-1] = "A1", "A12"
+    (repo / "a.txt").write_text("\n".join(lines) + "\n")
+
+
+def _write_plan(repo, text):
+    path = repo / "plan.yaml"
+    path.write_text(text)
+    # Keep the plan file itself out of the diff
+    (repo / ".git" / "info" / "exclude").write_text("plan.yaml\n")
+    return str(path)
+
+
+def test_apply_plan_commits_and_rewords(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    plan = _write_plan(repo, f"""\
+steps:
+  - commit:
+      hunks: [{ids[0]}]
+      message: first slice
+  - commit:
+      hunks: [{ids[1]}]
+      message: |
+        second slice
+
+        With a body.
+  - reword:
+      commit: HEAD~1   # add a.txt, resolved before the plan ran
+      message: "add a.txt (reworded)"
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 0, result.stderr
+    assert "step 3/3: reword" in result.stderr
+    assert "applied plan (3 steps)" in result.stderr
+
+    assert _subjects(repo) == [
+        "second slice",
+        "first slice",
+        "add b.txt",
+        "add a.txt (reworded)",
+        "init",
+    ]
+    body = run_git(repo, "log", "-1", "--format=%b").stdout.strip()
+    assert body == "With a body."
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+def test_apply_plan_batches_consecutive_rewords(git_agent_exe, repo):
+    _setup(repo)
+    run_git(repo, "checkout", "--", "a.txt")
+    plan = _write_plan(repo, """\
+steps:
+  - reword:
+      commit: HEAD~1
+      message: Add a
+  - reword:
+      commit: HEAD
+      message: Add b
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 0, result.stderr
+    assert "steps 1-2/2: reword" in result.stderr
+    assert _subjects(repo) == ["Add b", "Add a", "init"]
+    reflog = run_git(repo, "reflog", "--format=%gs").stdout
+    assert reflog.count("rebase (start)") == 1
+
+
+def test_apply_plan_stage_and_fixup(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    plan = _write_plan(repo, f"""\
+steps:
+  - stage: {ids[0]}
+  - fixup: HEAD~1
+  - fixup:
+      commit: HEAD~1
+      hunks: [{ids[1]}]
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["add b.txt", "add a.txt", "init"]
+    content = run_git(repo, "show", "HEAD~1:a.txt").stdout
+    assert content.startswith("A1\n") and content.endswith("A12\n")
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+def test_apply_plan_validates_before_changing_anything(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    before = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    plan = _write_plan(repo, f"""\
+steps:
+  - commit:
+      hunks: [{ids[0]}]
+      message: fine
+  - commit:
+      hunks: [deadbee]
+      message: missing hunk
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode != 0
+    assert "step 2 (commit)" in result.stderr
+    assert "hunk deadbee not found" in result.stderr
+    assert "step 1/" not in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == before
+
+
+def test_apply_plan_rejects_commit_after_stage(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    plan = _write_plan(repo, f"""\
+steps:
+  - stage: [{ids[0]}]
+  - commit:
+      hunks: [{ids[1]}]
+      message: would sweep up the staged hunk
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode != 0
+    assert "step 2 (commit): the index would already contain staged changes" in result.stderr
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+
+
+def test_apply_plan_rejects_malformed_plan(git_agent_exe, repo):
+    _setup(repo)
+    plan = _write_plan(repo, "steps:\n  - commit:\n      hunks: [abc1234]\n      msg: typo\n")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode != 0
+    assert "step 1" in result.stderr
+    assert "unknown key 'msg'" in result.stderr
+
+
+def test_apply_plan_rolls_back_on_failure(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    before = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    hook = repo / ".git" / "hooks" / "commit-msg"
+    hook.write_text("#!/bin/sh\ngrep -q rejected \"$1\" && exit 1\nexit 0\n")
+    hook.chmod(0o755)
+    plan = _write_plan(repo, f"""\
+steps:
+  - commit:
+      hunks: [{ids[0]}]
+      message: accepted
+  - commit:
+      hunks: [{ids[1]}]
+      message: rejected
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode != 0
+    assert "step 2 (commit) failed" in result.stderr
+    assert f"rolled back to {before[:7]}" in result.stderr
+
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == before
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+    remaining = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    assert remaining == ids
+
+
+def test_apply_plan_positional_refs_name_starting_diff(git_agent_exe, repo):
+    _setup(repo)
+    plan = _write_plan(repo, """\
+steps:
+  - commit:
+      hunks: ["@2"]
+      message: bottom
+  - commit:
+      hunks: ["a.txt:1"]
+      message: top
+""")
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:2] == ["top", "bottom"]
+    assert "+A12" in run_git(repo, "show", "HEAD~1").stdout
+    assert "+A1\n" in run_git(repo, "show", "HEAD").stdout
+
+
+def _split_plan(git_agent_exe, repo, before):
+    """A plan running `before`, then splitting a.txt's first change out of HEAD~1."""
+    commit_ids = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD~1", "--format", "%id")
+    return _write_plan(repo, f"""\
+steps:
+{before}  - split:
+      commit: HEAD~1
+      picks:
+        - hunks: [{commit_ids.stdout.split()[0]}]
+          message: first part
+""")
+
+
+def test_apply_plan_rejects_split_on_dirty_tree(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    head = run_git(repo, "rev-parse", "HEAD").stdout
+    # Only the first hunk is committed; the second would still be unstaged
+    plan = _split_plan(
+        git_agent_exe, repo, f"  - commit:\n      hunks: [{ids[0]}]\n      message: top\n"
+    )
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 5
+    assert f"step 2 (split): the working tree would still be dirty (hunk {ids[1]}" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
+
+    (repo / "new.txt").write_text("untracked\n")
+    plan = _split_plan(
+        git_agent_exe, repo, f"  - commit:\n      hunks: [{ids[0]}, {ids[1]}]\n      message: top\n"
+    )
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 5
+    assert "untracked" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
+
+
+def test_apply_plan_split_after_commits_take_every_hunk(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    plan = _split_plan(
+        git_agent_exe, repo, f"  - commit:\n      hunks: [{ids[0]}, {ids[1]}]\n      message: top\n"
+    )
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", plan)
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:3] == ["top", "add b.txt", "first part"]
@@ -0,0 +1,83 @@
+"""Tests for squash --autosquash, which folds pending fixup! commits."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _subjects(repo):
+    log = run_git(repo, "log", "--reverse", "--format=%s").stdout
+    return log.strip().split("\n")
+
+
+def _sha(repo, ref="HEAD"):
+    return run_git(repo, "rev-parse", ref).stdout.strip()
+
+
+def _fixup(repo, path, content, target):
+    (repo / path).write_text(content)
+    run_git(repo, "add", path)
+    run_git(repo, "commit", "-q", "--fixup", target)
+
+
+def test_autosquash_folds_fixups_into_their_targets(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    a_sha = _sha(repo)
+    create_file(repo, "b.txt", "b\n")
+    b_sha = _sha(repo)
+    _fixup(repo, "a.txt", "A\n", a_sha)
+    _fixup(repo, "b.txt", "B\n", b_sha)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", a_sha, "--autosquash")
+    assert result.returncode == 0, result.stderr
+    assert "folded 2 fixup commits" in result.stderr
+    assert _subjects(repo) == ["init", "add a.txt", "add b.txt"]
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "A\n"
+    assert run_git(repo, "show", "HEAD:b.txt").stdout == "B\n"
+
+
+def test_autosquash_range_and_fixup_no_rebase(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    run_git(repo, "add", "a.txt")
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", "--no-rebase")
+    assert result.returncode == 0, result.stderr
+    assert "squash" in result.stderr and "--autosquash" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~3..HEAD", "--autosquash")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["init", "add a.txt", "add b.txt"]
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "A\n"
+
+
+def test_autosquash_reports_fixups_outside_the_range(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    a_sha = _sha(repo)
+    create_file(repo, "b.txt", "b\n")
+    b_sha = _sha(repo)
+    _fixup(repo, "a.txt", "A\n", a_sha)
+    _fixup(repo, "b.txt", "B\n", b_sha)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", b_sha, "--autosquash")
+    assert result.returncode == 0, result.stderr
+    assert "folded 1 fixup commit\n" in result.stderr
+    assert "1 left unfolded" in result.stderr
+    assert _subjects(repo) == ["init", "add a.txt", "add b.txt", "fixup! add a.txt"]
+
+
+def test_autosquash_without_fixups(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    head = _sha(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD", "--autosquash")
+    assert result.returncode == 3
+    assert "no fixup!, amend! or squash! commits" in result.stderr
+    assert _sha(repo) == head
+
+
+def test_autosquash_conflicts_with_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "squash", "HEAD", "--autosquash", "-m", "x"
+    )
+    assert result.returncode == 2
@@ -0,0 +1,70 @@
+"""Tests for restoring the changes squash stashes away."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _reject_commits(repo):
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.parent.mkdir(exist_ok=True)
+    hook.write_text("#!/bin/sh\nexit 1\n")
+    hook.chmod(0o755)
+
+
+def _stashes(repo):
+    result = run_git(repo, "stash", "list", "--format=%gs")
+    return [line for line in result.stdout.splitlines() if line]
+
+
+def test_failed_squash_restores_stash(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    (repo / "a.txt").write_text("dirty\n")
+    _reject_commits(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "both")
+    assert result.returncode != 0
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
+    assert (repo / "a.txt").read_text() == "dirty\n"
+    assert _stashes(repo) == []
+
+
+def test_failed_range_squash_restores_stash(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    (repo / "c.txt").write_text("dirty\n")
+    _reject_commits(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~3..HEAD~1", "-m", "a and b")
+    assert result.returncode != 0
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
+    assert (repo / "c.txt").read_text() == "dirty\n"
+    assert _stashes(repo) == []
+
+
+def test_restore_autostash_pops_only_surgeon_stashes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("stranded\n")
+    run_git(repo, "stash", "push", "-m", "git-surgeon autostash (squash)")
+    (repo / "b.txt").write_text("mine\n")
+    run_git(repo, "stash", "push", "-m", "my own work")
+
+    result = run_git_agent(git_agent_exe, repo, "restore-autostash", "--list")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.startswith("stash@{1} ")
+    assert result.stdout.rstrip().endswith("git-surgeon autostash (squash)")
+    assert (repo / "a.txt").read_text() == "a\n"
+
+    result = run_git_agent(git_agent_exe, repo, "restore-autostash")
+    assert result.returncode == 0, result.stderr
+    assert "restored autostash" in result.stderr
+    assert (repo / "a.txt").read_text() == "stranded\n"
+    stashes = _stashes(repo)
+    assert len(stashes) == 1 and stashes[0].endswith(": my own work")
+
+    result = run_git_agent(git_agent_exe, repo, "restore-autostash")
+    assert result.returncode == 0
+    assert "no orphaned git-surgeon autostash" in result.stderr
@@ -0,0 +1,83 @@
+"""Tests for backup refs, backups, and restore-backup."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _head(repo):
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def _backup_refs(repo):
+    out = run_git(repo, "for-each-ref", "--format=%(refname) %(objectname)", "refs/surgeon/backup/")
+    return [line.split() for line in out.stdout.strip().splitlines()]
+
+
+def _setup(repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+
+def test_rewrite_creates_backup_ref(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "reworded")
+    assert result.returncode == 0, result.stderr
+
+    refs = _backup_refs(repo)
+    assert len(refs) == 1
+    name, sha = refs[0]
+    assert name.startswith("refs/surgeon/backup/") and name.endswith("-reword")
+    assert sha == before
+    assert f"backup: {name.removeprefix('refs/surgeon/backup/')} ({before[:7]})" in result.stderr
+
+
+def test_backups_lists_newest_first(git_agent_exe, repo):
+    _setup(repo)
+    run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "first rewrite")
+    run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "second rewrite")
+
+    result = run_git_agent(git_agent_exe, repo, "backups")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.strip().splitlines()
+    assert len(lines) == 2
+    assert lines[0].endswith("first rewrite")
+    assert lines[1].endswith("add b.txt")
+
+
+def test_restore_backup_resets_branch(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+    run_git_agent(git_agent_exe, repo, "drop", "HEAD~1", "--yes")
+    assert _head(repo) != before
+    (repo / "b.txt").write_text("local edit\n")
+
+    result = run_git_agent(git_agent_exe, repo, "restore-backup")
+    assert result.returncode == 0, result.stderr
+    assert f"restored {before[:7]}" in result.stderr
+    assert _head(repo) == before
+    # Uncommitted changes survive, and the pre-restore HEAD is backed up too
+    assert (repo / "b.txt").read_text() == "local edit\n"
+    assert any(name.endswith("-restore") for name, _ in _backup_refs(repo))
+
+
+def test_restore_backup_by_name(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+    run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "one")
+    first = run_git_agent(git_agent_exe, repo, "backups").stdout.split()[0]
+    run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "two")
+
+    result = run_git_agent(git_agent_exe, repo, "restore-backup", first)
+    assert result.returncode == 0, result.stderr
+    assert _head(repo) == before
+
+    result = run_git_agent(git_agent_exe, repo, "restore-backup", "nope")
+    assert result.returncode != 0
+    assert "no backup named 'nope'" in result.stderr
+
+
+def test_restore_backup_without_backups(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "restore-backup")
+    assert result.returncode != 0
+    assert "no backups to restore" in result.stderr
@@ -1,3 +1,6 @@
+import json
+from datetime import datetime, timezone
+
 from conftest import run_git_agent, run_git, create_file, modify_file
 
 
@@ -161,3 +164,48 @@ def test_blame_content_looks_like_hash(git_agent_exe, repo):
     assert len(context_lines) == 1
     # The prefix should be a real 7-char hash, not 0123456
     assert not context_lines[0].strip().startswith("0123456")
+
+
+def test_blame_multi_hunk_distinct_commits(git_agent_exe, repo):
+    """Hunks of one file are blamed together but keep their own lines' commits."""
+    create_file(repo, "multi.txt", "top\n" + "mid\n" * 20 + "bottom\n")
+    first = run_git(repo, "rev-parse", "--short=7", "HEAD").stdout.strip()
+    create_file(repo, "multi.txt", "top\n" + "mid\n" * 20 + "bottom2\n")
+    second = run_git(repo, "rev-parse", "--short=7", "HEAD").stdout.strip()
+    modify_file(repo, "multi.txt", "top!\n" + "mid\n" * 20 + "bottom3\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert f"  {first} -top" in lines
+    assert f"  {second} -bottom2" in lines
+    assert "  0000000 +bottom3" in lines
+
+
+def test_blame_full(git_agent_exe, repo):
+    """--blame=full adds the date, author and subject of each line's commit."""
+    create_file(repo, "full.txt", "old\n")
+    modify_file(repo, "full.txt", "new\n")
+    sha = run_git(repo, "rev-parse", "--short=7", "HEAD").stdout.strip()
+    stamp = int(run_git(repo, "log", "-1", "--format=%at").stdout)
+    date = datetime.fromtimestamp(stamp, timezone.utc).strftime("%Y-%m-%d")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame=full")
+    assert result.returncode == 0, result.stderr
+    removed = next(l for l in result.stdout.splitlines() if l.endswith("-old"))
+    assert removed.split()[:4] == [sha, date, "Test", "add"]
+
+
+def test_blame_json(git_agent_exe, repo):
+    """--json --blame has one blame entry per line."""
+    create_file(repo, "j.txt", "keep\nold\n")
+    modify_file(repo, "j.txt", "keep\nnew\n")
+    sha = run_git(repo, "rev-parse", "--short=7", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--json", "--blame")
+    assert result.returncode == 0, result.stderr
+    hunk = json.loads(result.stdout)["hunks"][0]
+    assert hunk["lines"] == [" keep", "-old", "+new"]
+    assert [b["commit"] for b in hunk["blame"]] == [sha, sha, "0000000"]
+    assert hunk["blame"][1]["author"] == "Test"
+    assert hunk["blame"][1]["summary"] == "add j.txt"
@@ -0,0 +1,56 @@
+"""Tests for blame.ignoreRevsFile and --ignore-rev."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _reformatted(repo):
+    """A line from one commit, then reindented by a reformat commit."""
+    create_file(repo, "f.txt", "a\nb\nc\n")
+    original = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    create_file(repo, "f.txt", "a\n  b\nc\n")
+    reformat = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    modify_file(repo, "f.txt", "a\n  b\nc!\n")
+    return original, reformat
+
+
+def _blame_of(output, line):
+    return next(l.split()[0] for l in output.splitlines() if l.endswith(line))
+
+
+def test_ignore_rev(git_agent_exe, repo):
+    original, reformat = _reformatted(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+    assert _blame_of(result.stdout, "   b") == reformat[:7]
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame", "--ignore-rev", reformat[:10])
+    assert result.returncode == 0, result.stderr
+    assert _blame_of(result.stdout, "   b") == original[:7]
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame", "--ignore-rev", "nope")
+    assert result.returncode != 0
+
+
+def test_ignore_revs_file(git_agent_exe, repo):
+    original, reformat = _reformatted(repo)
+    (repo / ".git-blame-ignore-revs").write_text(f"# reformat\n{reformat}\n")
+    run_git(repo, "config", "blame.ignoreRevsFile", ".git-blame-ignore-revs")
+    (repo / "sub").mkdir()
+
+    # Relative to the repository root, wherever the command runs
+    result = run_git_agent(git_agent_exe, repo / "sub", "hunks", "--blame")
+    assert result.returncode == 0, result.stderr
+    assert _blame_of(result.stdout, "   b") == original[:7]
+
+
+def test_missing_ignore_revs_file(git_agent_exe, repo):
+    _reformatted(repo)
+    run_git(repo, "config", "blame.ignoreRevsFile", ".git-blame-ignore-revs")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+    assert result.returncode != 0
+    assert "blame.ignoreRevsFile '.git-blame-ignore-revs' does not exist" in result.stderr
+
+    # Listing without blame is unaffected
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
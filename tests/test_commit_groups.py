@@ -0,0 +1,85 @@
+"""Tests for commit --pick groups: several commits from the working tree."""
+
+import os
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _changes(repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "1\n2\n3\n")
+    (repo / "a.txt").write_text("a2\n")
+    (repo / "b.txt").write_text("x\n1\n2\n3\ny\n")
+
+
+def _ids(git_agent_exe, repo):
+    return run_git_agent(git_agent_exe, repo, "hunks", "--format", "%id").stdout.split()
+
+
+def _subjects(repo, n):
+    return run_git(repo, "log", "--format=%s", f"-{n}").stdout.strip().split("\n")
+
+
+def test_commit_groups_make_one_commit_each(git_agent_exe, repo):
+    _changes(repo)
+    a, b = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "commit",
+        "--pick", a, "-m", "change a",
+        "--pick", f"{b}:1", "-m", "top of b", "-m", "The first line.",
+        "--trailer", "Refs=12",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo, 2) == ["top of b", "change a"]
+    assert run_git(repo, "log", "-1", "--format=%b").stdout.strip() == "The first line.\n\nRefs: 12"
+    assert run_git(repo, "show", "HEAD~1", "--format=", "--name-only").stdout.strip() == "a.txt"
+    # The rest of b.txt is left unstaged
+    assert run_git(repo, "show", "HEAD:b.txt").stdout == "x\n1\n2\n3\n"
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
+    assert (repo / "b.txt").read_text() == "x\n1\n2\n3\ny\n"
+
+
+def test_commit_groups_roll_back_when_a_later_commit_fails(git_agent_exe, repo):
+    _changes(repo)
+    a, b = _ids(git_agent_exe, repo)
+    before = run_git(repo, "rev-parse", "HEAD").stdout
+    hook = repo / ".git" / "hooks" / "commit-msg"
+    hook.write_text("#!/bin/sh\n! grep -q WIP \"$1\"\n")
+    os.chmod(hook, 0o755)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", "--pick", a, "-m", "change a", "--pick", b, "-m", "WIP b"
+    )
+    assert result.returncode != 0
+    assert "rolled back the 1 commit already made" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == before
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
+    assert (repo / "a.txt").read_text() == "a2\n"
+    assert (repo / "b.txt").read_text() == "x\n1\n2\n3\ny\n"
+
+
+def test_commit_groups_check_every_group_first(git_agent_exe, repo):
+    _changes(repo)
+    a, _ = _ids(git_agent_exe, repo)
+    before = run_git(repo, "rev-parse", "HEAD").stdout
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", "--pick", a, "-m", "change a", "--pick", "zzzzzzz", "-m", "x"
+    )
+    assert result.returncode == 3
+    assert run_git(repo, "rev-parse", "HEAD").stdout == before
+
+
+def test_commit_groups_reject_split_options(git_agent_exe, repo):
+    _changes(repo)
+    a, _ = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", "--pick", a, "-m", "x", "--dry-run")
+    assert result.returncode == 2
+    assert "the other options are split's" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "commit", a, "-m", "x", "--pick", a, "-m", "y")
+    assert result.returncode == 2
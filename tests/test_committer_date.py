@@ -0,0 +1,118 @@
+"""Tests for --committer-date-is-author-date and --preserve-committer."""
+
+import os
+import subprocess
+
+from conftest import run_git_agent, run_git
+
+
+def _commit_at(repo, name, day):
+    """Commit a new file with author date 2020-01-<day>, committed a year later."""
+    (repo / name).write_text(f"{name}\n")
+    env = dict(
+        os.environ,
+        GIT_AUTHOR_DATE=f"2020-01-{day:02d}T10:00:00+00:00",
+        GIT_COMMITTER_DATE=f"2021-01-{day:02d}T10:00:00+00:00",
+    )
+    subprocess.run(["git", "add", name], cwd=repo, check=True, env=env)
+    subprocess.run(["git", "commit", "-qm", f"add {name}"], cwd=repo, check=True, env=env)
+
+
+def _dates(repo):
+    """(subject, author date, committer date, committer), newest first."""
+    log = run_git(repo, "log", "--format=%s|%aI|%cI|%cn", "-3").stdout
+    return [tuple(line.split("|")) for line in log.strip().split("\n")]
+
+
+def _three_commits(repo):
+    for day, name in enumerate(["a.txt", "b.txt", "c.txt"], start=1):
+        _commit_at(repo, name, day)
+
+
+def test_reword_committer_date_is_author_date(git_agent_exe, repo):
+    _three_commits(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~1", "-m", "add b", "--committer-date-is-author-date"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _dates(repo) == [
+        ("add c.txt", "2020-01-03T10:00:00+00:00", "2020-01-03T10:00:00+00:00", "Test"),
+        ("add b", "2020-01-02T10:00:00+00:00", "2020-01-02T10:00:00+00:00", "Test"),
+        ("add a.txt", "2020-01-01T10:00:00+00:00", "2021-01-01T10:00:00+00:00", "Test"),
+    ]
+
+
+def test_fixup_preserve_committer_keeps_replayed_commits(git_agent_exe, repo):
+    _three_commits(repo)
+    run_git(repo, "config", "user.name", "Someone Else")
+    (repo / "a.txt").write_text("changed\n")
+    run_git(repo, "add", "a.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~2", "--preserve-committer")
+    assert result.returncode == 0, result.stderr
+    assert _dates(repo) == [
+        ("add c.txt", "2020-01-03T10:00:00+00:00", "2021-01-03T10:00:00+00:00", "Test"),
+        ("add b.txt", "2020-01-02T10:00:00+00:00", "2021-01-02T10:00:00+00:00", "Test"),
+        ("add a.txt", "2020-01-01T10:00:00+00:00", "2021-01-01T10:00:00+00:00", "Test"),
+    ]
+    assert run_git(repo, "show", "HEAD~2:a.txt").stdout == "changed\n"
+
+
+def test_squash_range_preserve_committer(git_agent_exe, repo):
+    _three_commits(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "squash", "HEAD~3..HEAD~1", "-m", "a and b", "--preserve-committer"
+    )
+    assert result.returncode == 0, result.stderr
+    # The squashed commit takes the committer of the newest commit folded in
+    assert _dates(repo)[:2] == [
+        ("add c.txt", "2020-01-03T10:00:00+00:00", "2021-01-03T10:00:00+00:00", "Test"),
+        ("a and b", "2020-01-01T10:00:00+00:00", "2021-01-02T10:00:00+00:00", "Test"),
+    ]
+
+
+def test_split_committer_date_is_author_date_in_trailing_args(git_agent_exe, repo):
+    _commit_at(repo, "a.txt", 1)
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-qm", "b and c", "--date", "2020-01-02T10:00:00+00:00")
+    _commit_at(repo, "d.txt", 3)
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD~1", "--format", "%id")
+    first = ids.stdout.split()[0]
+
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "split",
+        "HEAD~1",
+        "--pick",
+        first,
+        "-m",
+        "add b",
+        "--committer-date-is-author-date",
+    )
+    assert result.returncode == 0, result.stderr
+    assert [(s, a == c) for s, a, c, _ in _dates(repo)] == [
+        ("add d.txt", True),
+        ("b and c", True),
+        ("add b", True),
+    ]
+
+
+def test_committer_flags_conflict(git_agent_exe, repo):
+    _three_commits(repo)
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "reword",
+        "HEAD",
+        "-m",
+        "x",
+        "--committer-date-is-author-date",
+        "--preserve-committer",
+    )
+    assert result.returncode == 2
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "add c.txt"
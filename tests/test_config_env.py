@@ -0,0 +1,83 @@
+"""Tests for GIT_SURGEON_* environment settings."""
+
+import json
+import os
+import subprocess
+
+from conftest import run_git, create_file
+
+
+def _run(exe, repo, env, *args):
+    return subprocess.run(
+        [str(exe), *args], cwd=repo, capture_output=True, text=True, env=dict(os.environ, **env)
+    )
+
+
+def _change(repo):
+    create_file(repo, "a.txt", "a\nb\nc\n")
+    (repo / "a.txt").write_text("A\nB\nC\n")
+
+
+def test_env_overrides_git_config_and_file(git_agent_exe, repo):
+    _change(repo)
+    (repo / ".git-surgeon.toml").write_text("preview-lines = 3\n")
+    run_git(repo, "config", "surgeon.previewLines", "2")
+
+    result = _run(git_agent_exe, repo, {"GIT_SURGEON_PREVIEW_LINES": "1"}, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines()[2].strip().startswith("...")
+
+    result = _run(
+        git_agent_exe, repo, {"GIT_SURGEON_PREVIEW_LINES": "1"}, "hunks", "--preview-lines", "2"
+    )
+    assert result.stdout.splitlines()[2] == "  -b"
+
+
+def test_env_output_and_color(git_agent_exe, repo):
+    _change(repo)
+
+    result = _run(git_agent_exe, repo, {"GIT_SURGEON_OUTPUT": "json"}, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert json.loads(result.stdout)["hunks"][0]["file"] == "a.txt"
+
+    result = _run(git_agent_exe, repo, {"GIT_SURGEON_NO_COLOR": "1"}, "hunks", "--color=always")
+    assert "\x1b[" in result.stdout
+    result = _run(
+        git_agent_exe, repo, {"GIT_SURGEON_NO_COLOR": "1", "GIT_SURGEON_COLOR": "always"}, "hunks"
+    )
+    assert "\x1b[" not in result.stdout
+
+    result = _run(git_agent_exe, repo, {"GIT_SURGEON_OUTPUT": "yaml"}, "hunks")
+    assert result.returncode != 0
+    assert "GIT_SURGEON_OUTPUT" in result.stderr
+
+
+def test_env_protected_refs(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    run_git(repo, "branch", "published")
+    create_file(repo, "b.txt", "b\n")
+
+    env = {"GIT_SURGEON_PROTECTED_REFS": "nonexistent, published"}
+    result = _run(git_agent_exe, repo, env, "reword", "HEAD~1", "-m", "new")
+    assert result.returncode != 0
+    assert "reachable from published" in result.stderr
+
+
+def test_settings_reach_daemon_served_commands(git_agent_exe, repo):
+    from test_daemon import _start_daemon, _stop_daemon
+
+    _change(repo)
+    (repo / ".git-surgeon.toml").write_text("json = true\n")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        served = _run(git_agent_exe, repo, {}, "hunks")
+        assert served.returncode == 0, served.stderr
+        hunk = json.loads(served.stdout)["hunks"][0]
+        assert hunk["file"] == "a.txt"
+
+        # The daemon cannot see this environment, so the command runs here
+        result = _run(git_agent_exe, repo, {"GIT_SURGEON_OUTPUT": "text"}, "hunks")
+        assert result.returncode == 0, result.stderr
+        assert result.stdout.startswith(hunk["id"] + " ")
+    finally:
+        _stop_daemon(git_agent_exe, repo, proc)
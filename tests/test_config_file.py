@@ -0,0 +1,100 @@
+"""Tests for .git-surgeon.toml defaults."""
+
+import json
+import os
+import subprocess
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _change(repo):
+    create_file(repo, "a.txt", "a\nb\nc\n")
+    (repo / "a.txt").write_text("A\nB\nC\n")
+
+
+def _config(repo, text):
+    (repo / ".git-surgeon.toml").write_text(text)
+    run_git(repo, "add", ".git-surgeon.toml")
+    run_git(repo, "commit", "-qm", "config")
+
+
+def test_repo_config_sets_defaults(git_agent_exe, repo):
+    _config(repo, "# agent defaults\npreview-lines = 1\nid-length = 10\n")
+    _change(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert len(lines[0].split()[0]) == 10
+    assert lines[1] == "  -a"
+    assert lines[2].strip().startswith("...")
+
+    hunk_id = _ids(result.stdout)[0]
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+
+    # A flag, then git config, win over the file
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--staged", "--preview-lines", "2")
+    assert result.stdout.splitlines()[2] == "  -b"
+    run_git(repo, "config", "surgeon.previewLines", "3")
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--staged")
+    assert result.stdout.splitlines()[3] == "  -c"
+
+
+def test_json_by_default(git_agent_exe, repo):
+    _config(repo, "json = true\n")
+    _change(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert json.loads(result.stdout)["hunks"][0]["file"] == "a.txt"
+
+    # --stat conflicts with --json, so it keeps its text output
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--stat")
+    assert result.returncode == 0, result.stderr
+    assert "a.txt" in result.stdout and not result.stdout.startswith("{")
+
+    # Commands without --json are unaffected
+    hunk_id = json.loads(run_git_agent(git_agent_exe, repo, "hunks").stdout)["hunks"][0]["id"]
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+
+
+def test_protected_refs_from_config(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    run_git(repo, "branch", "published")
+    _config(repo, "protected-refs = ['published']\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "new")
+    assert result.returncode != 0
+    assert "reachable from published" in result.stderr
+
+
+def test_user_config_and_errors(git_agent_exe, repo, tmp_path):
+    _change(repo)
+    home = tmp_path / "home"
+    home.mkdir()
+    (home / ".git-surgeon.toml").write_text("preview-lines = 1\n")
+    env = dict(os.environ, HOME=str(home))
+
+    def run(*args):
+        return subprocess.run(
+            [str(git_agent_exe), *args], cwd=repo, capture_output=True, text=True, env=env
+        )
+
+    result = run("hunks")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines()[2].strip().startswith("...")
+
+    # The repository's file wins over the user's
+    (repo / ".git-surgeon.toml").write_text("preview-lines = 2\n")
+    assert run("hunks").stdout.splitlines()[2] == "  -b"
+
+    (repo / ".git-surgeon.toml").write_text("preview_lines = 2\n")
+    result = run("hunks")
+    assert result.returncode != 0
+    assert "unknown setting 'preview_lines'" in result.stderr
@@ -0,0 +1,49 @@
+"""Tests for -U / surgeon.diffContext."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+BASE = "".join(f"{i}\n" for i in range(1, 31))
+
+
+def _setup(repo):
+    create_file(repo, "f.txt", BASE)
+    modify_file(repo, "f.txt", BASE.replace("5\n6\n", "five\nsix\n").replace("20\n", "twenty\n"))
+
+
+def test_zero_context_hunks(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "-U0", "show", "@1")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines() == ["@@ -5,2 +5,2 @@", "1:-5", "2:-6", "3:+five", "4:+six"]
+
+
+def test_zero_context_stage_and_commit(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "-U0", "stage", "@1", "--lines", "1-3")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":f.txt").stdout == BASE.replace("5\n6\n", "five\n")
+    run_git(repo, "reset", "-q")
+
+    result = run_git_agent(git_agent_exe, repo, "-U0", "commit", "@2", "-m", "twenty")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "HEAD:f.txt").stdout == BASE.replace("20\n", "twenty\n")
+
+
+def test_wider_context_from_config(git_agent_exe, repo):
+    _setup(repo)
+    run_git(repo, "config", "surgeon.diffContext", "10")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--format=%header")
+    assert result.stdout.splitlines() == ["@@ -1,30 +1,30 @@"]
+
+    # The flag wins over the config
+    result = run_git_agent(git_agent_exe, repo, "-U1", "hunks", "--format=%header")
+    assert result.stdout.splitlines() == ["@@ -4,4 +4,4 @@", "@@ -19,3 +19,3 @@"]
+
+    run_git(repo, "config", "surgeon.diffContext", "wide")
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode != 0
+    assert "invalid surgeon.diffContext 'wide'" in result.stderr
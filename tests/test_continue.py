@@ -0,0 +1,181 @@
+"""Tests for continue/abort of interrupted operations."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _subjects(repo):
+    result = run_git(repo, "log", "--reverse", "--format=%s")
+    return [s for s in result.stdout.strip().split("\n") if s]
+
+
+def _setup_conflicting_fixup(repo):
+    """Stage a change that conflicts when folded into an earlier commit."""
+    create_file(repo, "f.txt", "one\ntwo\nthree\n")
+    target = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE\n")
+    run_git(repo, "commit", "-am", "shout three")
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE!\n")
+    run_git(repo, "add", "f.txt")
+    return target
+
+
+def test_fixup_conflict_then_continue(git_agent_exe, repo):
+    target = _setup_conflicting_fixup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", target)
+    assert result.returncode != 0
+    assert "git-surgeon continue" in result.stderr
+
+    # Resolve the fixup step, then the replayed later commit
+    modify_file(repo, "f.txt", "one\ntwo\nthree!\n")
+    run_git(repo, "add", "f.txt")
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode != 0
+    assert "git-surgeon continue" in result.stderr
+
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE!\n")
+    run_git(repo, "add", "f.txt")
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 0, result.stderr
+    assert "fixup complete" in result.stderr
+
+    assert _subjects(repo) == ["init", "add f.txt", "shout three"]
+    assert "three!" in run_git(repo, "show", "HEAD~1:f.txt").stdout
+
+
+def test_fixup_conflict_then_abort(git_agent_exe, repo):
+    target = _setup_conflicting_fixup(repo)
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", target)
+    assert result.returncode != 0
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
+    # The staged change is back in the index
+    assert "THREE!" in run_git(repo, "diff", "--cached").stdout
+
+
+def test_new_operation_refused_while_interrupted(git_agent_exe, repo):
+    target = _setup_conflicting_fixup(repo)
+    run_git_agent(git_agent_exe, repo, "fixup", target)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "nope")
+    assert result.returncode != 0
+    assert "fixup already in progress" in result.stderr
+
+
+def test_continue_without_operation_errors(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode != 0
+    assert "no git-surgeon operation in progress" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode != 0
+
+
+def test_split_hook_failure_then_abort(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    modify_file(repo, "b.txt", "b changed\n")
+    run_git(repo, "commit", "-am", "change both")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.write_text("#!/bin/sh\nexit 1\n")
+    hook.chmod(0o755)
+
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout
+    first_id = ids.split()[0]
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", first_id, "-m", "first",
+    )
+    assert result.returncode != 0
+    assert "git-surgeon abort" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+FAIL_SECOND_COMMIT_HOOK = """#!/bin/sh
+n=$(cat .git/hook-count 2>/dev/null || echo 0)
+n=$((n + 1))
+echo $n > .git/hook-count
+[ $n -eq 2 ] && exit 1
+exit 0
+"""
+
+
+def _three_file_commit(repo):
+    for name in ["a", "b", "c"]:
+        create_file(repo, f"{name}.txt", f"{name}\n")
+    for name in ["a", "b", "c"]:
+        modify_file(repo, f"{name}.txt", f"{name} changed\n")
+    run_git(repo, "commit", "-am", "change all")
+
+
+def _ids_by_file(exe, repo, commit):
+    out = run_git_agent(exe, repo, "hunks", "--commit", commit).stdout
+    return {
+        line.split()[1]: line.split()[0]
+        for line in out.split("\n")
+        if line and not line.startswith("  ")
+    }
+
+
+def _install_hook(repo):
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.write_text(FAIL_SECOND_COMMIT_HOOK)
+    hook.chmod(0o755)
+    return hook
+
+
+def test_split_resumes_remaining_groups(git_agent_exe, repo):
+    _three_file_commit(repo)
+    ids = _ids_by_file(git_agent_exe, repo, "HEAD")
+    hook = _install_hook(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids["a.txt"], "-m", "change a",
+        "--pick", ids["b.txt"], "-m", "change b",
+        "--rest-message", "change c",
+    )
+    assert result.returncode != 0
+    assert "after 1 of 2 groups" in result.stderr
+    assert (repo / ".git" / "surgeon" / "state").exists()
+
+    hook.unlink()
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 0, result.stderr
+    assert "split complete" in result.stderr
+
+    assert _subjects(repo)[-3:] == ["change a", "change b", "change c"]
+    assert "b changed" in run_git(repo, "show", "HEAD~1").stdout
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+    assert not (repo / ".git" / "surgeon" / "state").exists()
+
+
+def test_split_earlier_commit_resumes_and_finishes_rebase(git_agent_exe, repo):
+    _three_file_commit(repo)
+    target = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    create_file(repo, "later.txt", "later\n")
+    ids = _ids_by_file(git_agent_exe, repo, target)
+    hook = _install_hook(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", target,
+        "--pick", ids["a.txt"], "-m", "change a",
+        "--rest-message", "change b and c",
+    )
+    assert result.returncode != 0
+
+    hook.unlink()
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[-3:] == ["change a", "change b and c", "add later.txt"]
+    assert not (repo / ".git" / "rebase-merge").exists()
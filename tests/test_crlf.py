@@ -0,0 +1,60 @@
+"""Tests for files with CRLF line endings and --whitespace."""
+
+from conftest import run_git_agent, run_git
+
+
+def _write(repo, path, text):
+    (repo / path).write_bytes(text.encode())
+
+
+def _commit(repo, path, text):
+    _write(repo, path, text)
+    run_git(repo, "add", path)
+    run_git(repo, "commit", "-m", f"add {path}")
+
+
+def _index(repo, path):
+    return run_git(repo, "cat-file", "blob", f":{path}").stdout
+
+
+def test_crlf_in_index(git_agent_exe, repo):
+    _commit(repo, "f.txt", "a\r\nb\r\nc\r\n")
+    _write(repo, "f.txt", "a\r\nB\r\nc\r\n")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached", "--numstat").stdout.split()[:2] == ["1", "1"]
+    assert run_git(repo, "ls-files", "--eol", "f.txt").stdout.startswith("i/crlf ")
+
+    run_git(repo, "reset", "-q")
+    result = run_git_agent(git_agent_exe, repo, "discard", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert (repo / "f.txt").read_bytes() == b"a\r\nb\r\nc\r\n"
+
+
+def test_autocrlf_checkout(git_agent_exe, repo):
+    run_git(repo, "config", "core.autocrlf", "true")
+    _commit(repo, "f.txt", "a\nb\nc\n")
+    _write(repo, "f.txt", "a\r\nB\r\nc\r\n")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert _index(repo, "f.txt") == "a\nB\nc\n"
+    assert run_git(repo, "ls-files", "--eol", "f.txt").stdout.startswith("i/lf ")
+
+
+def test_whitespace_fix(git_agent_exe, repo):
+    _commit(repo, "f.txt", "a\n")
+    _write(repo, "f.txt", "a\nb  \n")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "--whitespace", "sometimes", "stage", hunk_id)
+    assert result.returncode != 0
+    assert "expected nowarn, warn, fix, error or error-all" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id, "--whitespace=fix")
+    assert result.returncode == 0, result.stderr
+    assert _index(repo, "f.txt") == "a\nb\n"
+    assert (repo / "f.txt").read_text() == "a\nb  \n"
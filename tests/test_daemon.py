@@ -0,0 +1,155 @@
+"""Tests for the daemon command and client forwarding."""
+
+import os
+import subprocess
+import time
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _start_daemon(exe, repo):
+    proc = subprocess.Popen(
+        [str(exe), "daemon"],
+        cwd=repo,
+        stderr=subprocess.PIPE,
+        text=True,
+    )
+    sock = repo / ".git" / "surgeon" / "daemon.sock"
+    for _ in range(100):
+        if sock.exists():
+            return proc
+        time.sleep(0.05)
+    proc.kill()
+    raise AssertionError("daemon did not start: " + proc.stderr.read())
+
+
+def _stop_daemon(exe, repo, proc):
+    result = run_git_agent(exe, repo, "daemon", "--stop")
+    try:
+        proc.wait(timeout=5)
+    except subprocess.TimeoutExpired:
+        proc.kill()
+        raise
+    return result, proc.stderr.read()
+
+
+def _local(exe, repo, *args):
+    result = subprocess.run(
+        [str(exe), *args],
+        cwd=repo,
+        capture_output=True,
+        text=True,
+        env={**os.environ, "GIT_SURGEON_NO_DAEMON": "1"},
+    )
+    return result
+
+
+def test_daemon_serves_read_only_commands(git_agent_exe, repo):
+    create_file(repo, "src/a.txt", "one\ntwo\n")
+    (repo / "src" / "a.txt").write_text("one\nTWO\n")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        # Run from a subdirectory: the daemon must use the client's cwd
+        served = run_git_agent(git_agent_exe, repo / "src", "hunks", "--blame")
+        local = _local(git_agent_exe, repo / "src", "hunks", "--blame")
+        assert served.returncode == 0, served.stderr
+        assert served.stdout == local.stdout
+
+        missing = run_git_agent(git_agent_exe, repo, "show", "0000000")
+        assert missing.returncode == 3
+        assert "hunk 0000000 not found" in missing.stderr
+        assert missing.stdout == ""
+
+        # Mutating commands always run locally
+        hunk_id = served.stdout.split()[0]
+        assert run_git_agent(git_agent_exe, repo, "stage", hunk_id).returncode == 0
+    finally:
+        stopped, log = _stop_daemon(git_agent_exe, repo, proc)
+
+    assert stopped.returncode == 0, stopped.stderr
+    assert "hunks -> 0" in log
+    assert "show -> 3" in log
+    assert "stage" not in log
+    assert not (repo / ".git" / "surgeon" / "daemon.sock").exists()
+
+
+def test_daemon_sees_new_changes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        assert run_git_agent(git_agent_exe, repo, "hunks").stdout.strip() == ""
+        (repo / "a.txt").write_text("ONE\n")
+        result = run_git_agent(git_agent_exe, repo, "hunks")
+        assert "+ONE" in result.stdout
+    finally:
+        _stop_daemon(git_agent_exe, repo, proc)
+
+
+def test_daemon_rereads_config_files(git_agent_exe, repo):
+    lines = [f"line{i}" for i in range(12)]
+    create_file(repo, "a.txt", "\n".join(lines) + "\n")
+    lines[0], lines[-1] = "FIRST", "LAST"
+    (repo / "a.txt").write_text("\n".join(lines) + "\n")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        before = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.split()
+        assert len(before) == 2
+        (repo / ".git-surgeon.toml").write_text("diff-context = 10\n")
+        served = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id")
+        local = _local(git_agent_exe, repo, "hunks", "--format=%id")
+        assert served.stdout == local.stdout
+        assert len(served.stdout.split()) == 1
+    finally:
+        _stop_daemon(git_agent_exe, repo, proc)
+
+
+def test_daemon_blame_follows_ignore_revs_file(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\ntwo\n")
+    (repo / "a.txt").write_text("one\nTwo\n")
+    run_git(repo, "commit", "-qam", "reformat")
+    reformat = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    (repo / "a.txt").write_text("one\n")
+    (repo / ".ignore-revs").write_text("")
+    run_git(repo, "config", "blame.ignoreRevsFile", ".ignore-revs")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        before = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+        assert reformat[:7] in before.stdout
+        (repo / ".ignore-revs").write_text(reformat + "\n")
+        served = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+        local = _local(git_agent_exe, repo, "hunks", "--blame")
+        assert served.stdout == local.stdout
+        assert reformat[:7] not in served.stdout
+    finally:
+        _stop_daemon(git_agent_exe, repo, proc)
+
+
+def test_daemon_keeps_client_color_choice(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    (repo / "a.txt").write_text("ONE\n")
+    proc = _start_daemon(git_agent_exe, repo)
+    try:
+        # Not a terminal, so auto means no color even though it is forwarded
+        assert "\x1b[" not in run_git_agent(git_agent_exe, repo, "hunks").stdout
+        result = run_git_agent(git_agent_exe, repo, "hunks", "--color=always")
+        assert "\x1b[32m+ONE\x1b[m" in result.stdout
+    finally:
+        _stop_daemon(git_agent_exe, repo, proc)
+
+
+def test_stale_socket_falls_back_to_local(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    (repo / "a.txt").write_text("ONE\n")
+    sock_dir = repo / ".git" / "surgeon"
+    sock_dir.mkdir(parents=True, exist_ok=True)
+    (sock_dir / "daemon.sock").write_text("")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert "+ONE" in result.stdout
+
+
+def test_daemon_stop_without_daemon(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "daemon", "--stop")
+    assert result.returncode != 0
+    assert "no daemon is listening" in result.stderr
@@ -45,3 +45,21 @@ def test_discard_one_of_two_hunks(git_agent_exe, repo):
     # Should still have changes (the other hunk)
     diff = run_git(repo, "diff")
     assert diff.stdout.strip() != ""
+
+
+def test_discard_all_requires_confirmation(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+
+    result = run_git_agent(git_agent_exe, repo, "discard", "--all")
+    assert result.returncode == 2
+    assert "will discard: " in result.stderr
+    assert "  a.txt | 1 hunk  +1 -1" in result.stderr
+    assert "refusing to discard 2 hunks without --yes" in result.stderr
+    assert (repo / "a.txt").read_text() == "A\n"
+
+    result = run_git_agent(git_agent_exe, repo, "discard", "--all", "--yes")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff").stdout == ""
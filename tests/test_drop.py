@@ -0,0 +1,83 @@
+"""Tests for the drop command."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _subjects(repo):
+    return run_git(repo, "log", "--format=%s").stdout.strip().splitlines()
+
+
+def test_drop_single_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD~1", "--yes")
+    assert result.returncode == 0, result.stderr
+    assert "dropped" in result.stderr and "add b.txt" in result.stderr
+    assert _subjects(repo) == ["add c.txt", "add a.txt", "init"]
+    assert not (repo / "b.txt").exists()
+
+
+def test_drop_multiple_commits(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD", "HEAD~2", "--yes")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["add b.txt", "init"]
+
+
+def test_drop_keep_changes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    (repo / "a.txt").write_text("one\ntwo\n")
+    run_git(repo, "commit", "-am", "add two")
+    (repo / "a.txt").write_text("one\ntwo\nthree\n")
+    run_git(repo, "commit", "-am", "add three")
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD~1", "HEAD", "--keep-changes")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["add a.txt", "init"]
+    assert (repo / "a.txt").read_text() == "one\ntwo\nthree\n"
+    # Changes are unstaged
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+    assert run_git(repo, "diff", "--quiet").returncode != 0
+
+
+def test_drop_refuses_dirty_tree(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "changed\n")
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD", "--yes")
+    assert result.returncode != 0
+    assert "dirty" in result.stderr
+
+
+def test_drop_conflict_then_abort(git_agent_exe, repo):
+    create_file(repo, "f.txt", "one\n")
+    (repo / "f.txt").write_text("two\n")
+    run_git(repo, "commit", "-am", "second")
+    (repo / "f.txt").write_text("three\n")
+    run_git(repo, "commit", "-am", "third")
+    before = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD~1", "--yes")
+    assert result.returncode != 0
+    assert "git-surgeon continue" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == before
+
+
+def test_drop_requires_confirmation(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    head = run_git(repo, "rev-parse", "HEAD").stdout
+
+    result = run_git_agent(git_agent_exe, repo, "drop", "HEAD~1")
+    assert result.returncode == 2
+    assert "will drop:" in result.stderr and "add a.txt" in result.stderr
+    assert "refusing to drop 1 commit without --yes" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
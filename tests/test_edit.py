@@ -0,0 +1,137 @@
+"""Tests for --edit, which finishes commit messages in the editor."""
+
+import os
+import subprocess
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _get_hunk_ids(exe, repo, *extra_args):
+    result = run_git_agent(exe, repo, "hunks", *extra_args)
+    ids = []
+    for line in result.stdout.strip().split("\n"):
+        if line and not line.startswith("  "):
+            ids.append(line.split()[0])
+    return ids
+
+
+def _editor(repo, script):
+    """An editor running `script`, after it saves what it was given for `_seen`.
+    It lives in .git, to keep the working tree clean."""
+    editor = repo / ".git" / "editor.sh"
+    editor.write_text(f'#!/bin/sh\ncp "$1" "{repo / ".git" / "seen.txt"}"\n{script}\n')
+    editor.chmod(0o755)
+    return editor
+
+
+def _seen(repo):
+    return (repo / ".git" / "seen.txt").read_text()
+
+
+def _run(exe, repo, editor, *args):
+    return subprocess.run(
+        [str(exe), *args],
+        cwd=repo,
+        capture_output=True,
+        text=True,
+        env=dict(os.environ, GIT_EDITOR=str(editor)),
+    )
+
+
+PREPEND_SUBJECT = """printf 'Edited subject\\n\\n' | cat - "$1" > "$1.new" && mv "$1.new" "$1\""""
+
+
+def _message(repo, ref="HEAD"):
+    return run_git(repo, "log", "-1", "--format=%B", ref).stdout.strip()
+
+
+def test_commit_edit_without_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    editor = _editor(repo, PREPEND_SUBJECT)
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = _run(git_agent_exe, repo, editor, "commit", ids[0], "-e")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "Edited subject"
+    assert "#   a.txt | 1 hunk  +1 -1" in _seen(repo)
+
+
+def test_commit_edit_starts_from_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    editor = _editor(repo, PREPEND_SUBJECT)
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = _run(
+        git_agent_exe, repo, editor, "commit", ids[0], "-m", "Draft", "--edit", "-s"
+    )
+    assert result.returncode == 0, result.stderr
+    message = _message(repo)
+    assert message.startswith("Edited subject\n\nDraft\n\nSigned-off-by: ")
+
+
+def test_commit_edit_empty_message_aborts(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    editor = _editor(repo, ': > "$1"')
+    head = run_git(repo, "rev-parse", "HEAD").stdout
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = _run(git_agent_exe, repo, editor, "commit", ids[0], "-m", "Draft", "-e")
+    assert result.returncode == 2
+    assert "empty commit message" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
+    assert run_git(repo, "diff", "--cached").stdout == ""
+
+
+def test_reword_edit_starts_from_current_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    editor = _editor(repo, PREPEND_SUBJECT)
+
+    result = _run(git_agent_exe, repo, editor, "reword", "HEAD", "-e")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "Edited subject\n\nadd a.txt"
+    assert _seen(repo).startswith("add a.txt\n")
+    assert "#   a.txt | 1 hunk  +1 -0" in _seen(repo)
+
+
+def test_squash_edit_starts_from_combined_messages(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    first = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    create_file(repo, "b.txt", "b\n")
+    editor = _editor(repo, PREPEND_SUBJECT)
+
+    result = _run(git_agent_exe, repo, editor, "squash", first, "--edit")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "Edited subject\n\nadd a.txt\n\nadd b.txt"
+    assert "#   a.txt | 1 hunk  +1 -0" in _seen(repo)
+    assert "#   b.txt | 1 hunk  +1 -0" in _seen(repo)
+
+
+def test_split_edit_group(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    modify_file(repo, "b.txt", "b\n")
+    run_git(repo, "add", "-A")
+    run_git(repo, "commit", "-qm", "both")
+    editor = _editor(repo, PREPEND_SUBJECT)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = _run(
+        git_agent_exe,
+        repo,
+        editor,
+        "split",
+        "HEAD",
+        "--pick",
+        ids[0],
+        "-e",
+        "--rest-message",
+        "rest",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == "Edited subject"
+    assert _message(repo) == "rest"
+    assert "#   a.txt | 1 hunk  +1 -1" in _seen(repo)
+    assert "b.txt" not in _seen(repo)
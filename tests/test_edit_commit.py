@@ -0,0 +1,70 @@
+"""Tests for the edit-commit command."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _subjects(repo):
+    return run_git(repo, "log", "--format=%s").stdout.strip().splitlines()
+
+
+def _setup(repo):
+    lines = [f"a{i}" for i in range(1, 13)]
+    create_file(repo, "a.txt", "\n".join(lines) + "\n")
+    lines[0], lines[-1] = "A1", "A12"
+    (repo / "a.txt").write_text("\n".join(lines) + "\n")
+    (repo / "b.txt").write_text("new file\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "mixed change")
+    create_file(repo, "c.txt", "c\n")
+
+
+def test_edit_commit_stops_with_changes_unstaged(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "edit-commit", "HEAD~1")
+    assert result.returncode == 0, result.stderr
+    assert "stopped at" in result.stderr and "mixed change" in result.stderr
+
+    hunks = run_git_agent(git_agent_exe, repo, "hunks")
+    assert len(_get_hunk_ids(hunks.stdout)) == 2
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+
+
+def test_edit_commit_recommit_and_continue(git_agent_exe, repo):
+    _setup(repo)
+    run_git_agent(git_agent_exe, repo, "edit-commit", "HEAD~1")
+
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "first part")
+    assert result.returncode == 0, result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 0, result.stderr
+    assert "edit-commit complete" in result.stderr
+
+    # Leftovers (including the untracked new file) are committed with the original message
+    assert _subjects(repo) == ["add c.txt", "mixed change", "first part", "add a.txt", "init"]
+    shown = run_git(repo, "show", "--stat", "--format=", "HEAD~1").stdout
+    assert "b.txt" in shown
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+def test_edit_commit_abort_restores(git_agent_exe, repo):
+    _setup(repo)
+    before = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    run_git_agent(git_agent_exe, repo, "edit-commit", "HEAD~1")
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == before
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+def test_edit_commit_refuses_root(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "edit-commit", "HEAD")
+    assert result.returncode != 0
+    assert "root commit" in result.stderr
@@ -0,0 +1,52 @@
+import os
+import subprocess
+
+from conftest import run_git, create_file
+
+
+def _rebase_with_editor(exe, repo, upstream, *marks):
+    args = " ".join(f"--mark {m}" for m in marks)
+    env = dict(os.environ, GIT_SEQUENCE_EDITOR=f"'{exe}' internal-edit-todo {args}")
+    return subprocess.run(
+        ["git", "rebase", "-i", upstream],
+        cwd=repo,
+        capture_output=True,
+        text=True,
+        env=env,
+    )
+
+
+def _subjects(repo):
+    result = run_git(repo, "log", "--reverse", "--format=%s")
+    return [s for s in result.stdout.strip().split("\n") if s]
+
+
+def test_edit_todo_drops_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+    sha = run_git(repo, "rev-parse", "HEAD~1").stdout.strip()
+
+    result = _rebase_with_editor(git_agent_exe, repo, "HEAD~2", f"{sha}=drop")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["init", "add a.txt", "add c.txt"]
+
+
+def test_edit_todo_fixup_marks(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    sha = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = _rebase_with_editor(git_agent_exe, repo, "HEAD~2", f"{sha[:10]}=fixup")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["init", "add a.txt"]
+    assert (repo / "b.txt").exists()
+
+
+def test_edit_todo_unknown_commit_aborts_rebase(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = _rebase_with_editor(git_agent_exe, repo, "HEAD~1", "deadbeef=drop")
+    assert result.returncode != 0
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == head
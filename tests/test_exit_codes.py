@@ -0,0 +1,79 @@
+"""Tests for exit codes by failure class."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _hunk_id(git_agent_exe, repo):
+    output = run_git_agent(git_agent_exe, repo, "hunks").stdout
+    return output.split()[0]
+
+
+def test_usage_and_not_found(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+
+    assert run_git_agent(git_agent_exe, repo, "stage", "--bogus").returncode == 2
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--message", "x")
+    assert result.returncode == 2, result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "0000000")
+    assert result.returncode == 3
+    assert "not found" in result.stderr
+    result = run_git_agent(git_agent_exe, repo, "reword", "no-such-commit", "-m", "x")
+    assert result.returncode == 3
+    assert "could not resolve commit" in result.stderr
+
+
+def test_apply_conflict(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    hunk_id = _hunk_id(git_agent_exe, repo)
+    run_git(repo, "commit", "-qam", "change")
+    (repo / "a.txt").write_text("something else\n")
+
+    result = run_git_agent(git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD")
+    assert result.returncode == 4, result.stderr
+
+
+def test_repo_state_and_refused(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", "x", "-m", "x")
+    assert result.returncode == 5
+    assert "dirty" in result.stderr
+
+    run_git(repo, "checkout", "a.txt")
+    run_git(repo, "config", "surgeon.protectedRef", "HEAD")
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "new")
+    assert result.returncode == 6
+    assert "published" in result.stderr
+
+
+def test_line_range_past_hunk_end_is_usage(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    run_git(repo, "commit", "-qam", "change")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD", "--format", "%id")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", f"{hunk_id.stdout.strip()}:1-99", "-m", "x"
+    )
+    assert result.returncode == 2
+    assert "exceeds hunk length" in result.stderr
+
+
+def test_continue_with_unresolved_conflict(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("b\n")
+    run_git(repo, "commit", "-qam", "two")
+    (repo / "a.txt").write_text("c\n")
+    run_git(repo, "commit", "-qam", "three")
+    (repo / "a.txt").write_text("d\n")
+    run_git(repo, "add", "a.txt")
+
+    assert run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1").returncode == 4
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 4
+    assert "unresolved problems" in result.stderr
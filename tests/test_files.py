@@ -0,0 +1,47 @@
+"""Tests for the files command."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def test_files_lists_change_types_and_counts(git_agent_exe, repo):
+    create_file(repo, "keep.txt", "one\n" + "mid\n" * 10 + "two\n")
+    create_file(repo, "old.txt", "moved\n")
+    create_file(repo, "gone.txt", "bye\n")
+    modify_file(repo, "keep.txt", "ONE\n" + "mid\n" * 10 + "TWO\n")
+    run_git(repo, "mv", "old.txt", "new.txt")
+    run_git(repo, "rm", "-q", "gone.txt")
+    (repo / "added.txt").write_text("a\nb\n")
+    run_git(repo, "add", "keep.txt", "added.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "files", "--staged")
+    assert result.returncode == 0, result.stderr
+    assert sorted(result.stdout.splitlines()) == [
+        "added      added.txt 1 hunk (+2 -0)",
+        "deleted    gone.txt 1 hunk (+0 -1)",
+        "modified   keep.txt 2 hunks (+2 -2)",
+        "renamed    new.txt (from old.txt) 0 hunks (+0 -0)",
+    ]
+
+
+def test_files_for_commit_as_json(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "A\n")
+    run_git(repo, "commit", "-qam", "shout")
+
+    result = run_git_agent(git_agent_exe, repo, "files", "--commit", "HEAD", "--json")
+    assert result.returncode == 0, result.stderr
+    assert json.loads(result.stdout) == {
+        "files": [
+            {
+                "file": "a.txt",
+                "status": "modified",
+                "old_file": None,
+                "hunks": 1,
+                "additions": 1,
+                "deletions": 1,
+            }
+        ]
+    }
+    assert run_git_agent(git_agent_exe, repo, "files").stdout == ""
@@ -0,0 +1,63 @@
+"""Tests for the find-commit command."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _setup(repo):
+    """A published 'fix parser' commit, then three unpublished ones."""
+    create_file(repo, "parser.txt", "a\n")
+    run_git(repo, "commit", "-q", "--allow-empty", "-m", "fix parser typo")
+    run_git(repo, "branch", "published")
+    run_git(repo, "branch", "--set-upstream-to", "published")
+    (repo / "parser.txt").write_text("b\n")
+    run_git(repo, "commit", "-qam", "fix parser crash")
+    create_file(repo, "lexer.txt", "x\n")
+    (repo / "lexer.txt").write_text("y\n")
+    run_git(repo, "commit", "-qam", "Fix lexer", "--author", "Other <other@example.com>")
+
+
+def test_find_commit_lists_unpublished_matches(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "find-commit", "--grep", "[Ff]ix")
+    assert result.returncode == 0, result.stderr
+    subjects = [line.split(" ", 1)[1] for line in result.stdout.strip().split("\n")]
+    # The published "fix parser typo" is left out
+    assert subjects == ["Fix lexer", "fix parser crash"]
+
+
+def test_find_commit_json_with_author_and_file(git_agent_exe, repo):
+    _setup(repo)
+    lexer_sha = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = run_git_agent(
+        git_agent_exe, repo, "find-commit", "--grep", "ix", "--author", "Other", "--json"
+    )
+    assert result.returncode == 0, result.stderr
+    commits = json.loads(result.stdout)
+    assert commits == [
+        {
+            "commit": lexer_sha,
+            "subject": "Fix lexer",
+            "author": "Other <other@example.com>",
+            "date": commits[0]["date"],
+        }
+    ]
+
+    result = run_git_agent(
+        git_agent_exe, repo, "find-commit", "--grep", "fix", "--file", "parser.txt"
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip().endswith("fix parser crash")
+    assert len(result.stdout.strip().split("\n")) == 1
+
+
+def test_find_commit_without_match_is_not_found(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "find-commit", "--grep", "typo")
+    assert result.returncode == 3
+    assert "no unpublished commit matches 'typo'" in result.stderr
+    assert result.stdout == ""
@@ -124,3 +124,91 @@ def test_fixup_root_commit(git_agent_exe, repo):
     new_root_sha = run_git(repo, "log", "--reverse", "--format=%H").stdout.strip().split("\n")[0]
     show = run_git(repo, "show", "--stat", new_root_sha)
     assert "root_extra.txt" in show.stdout
+
+
+def test_fixup_with_hunk_ids(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--file", "a.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", *ids)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "A\n"
+    assert _commit_subjects(repo) == ["init", "add a.txt", "add b.txt"]
+    # The other hunk is left alone in the working tree
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == "M b.txt"
+
+
+def test_fixup_with_hunk_lines(git_agent_exe, repo):
+    create_file(repo, "a.txt", "1\n2\n3\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "one\n2\nthree\n")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+    show = run_git_agent(git_agent_exe, repo, "show", ids[0]).stdout
+    # Lines 1-2 of the hunk are "-1" and "+one"
+    assert "1:-1" in show and "2:+one" in show
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", ids[0], "--lines", "1-2")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "one\n2\n3\n"
+    assert (repo / "a.txt").read_text() == "one\n2\nthree\n"
+
+
+def test_fixup_with_hunk_ids_refuses_staged_changes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+    run_git(repo, "add", "b.txt")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+    old_sha = _commit_sha(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", *ids)
+    assert result.returncode != 0
+    assert "index already contains staged changes" in result.stderr
+    assert _commit_sha(repo) == old_sha
+
+
+def test_fixup_with_hunk_ids_unstages_on_failure(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.parent.mkdir(parents=True, exist_ok=True)
+    hook.write_text("#!/bin/sh\nexit 1\n")
+    hook.chmod(0o755)
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", *ids)
+    assert result.returncode != 0
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
+    assert (repo / "a.txt").read_text() == "A\n"
+
+
+def test_fixup_no_rebase_only_creates_fixup_commits(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+    b_sha = _commit_sha(repo)
+    a_ids = _get_hunk_ids(git_agent_exe, repo, "--file", "a.txt")
+    b_ids = _get_hunk_ids(git_agent_exe, repo, "--file", "b.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", *a_ids, "--no-rebase")
+    assert result.returncode == 0, result.stderr
+    assert "created fixup! commit" in result.stderr
+    result = run_git_agent(git_agent_exe, repo, "fixup", b_sha, *b_ids, "--no-rebase")
+    assert result.returncode == 0, result.stderr
+
+    # Nothing was rewritten, even for the HEAD target
+    assert _commit_subjects(repo) == [
+        "init", "add a.txt", "add b.txt", "fixup! add a.txt", "fixup! add b.txt",
+    ]
+    assert _commit_sha(repo, "HEAD~2") == b_sha
+
+    run_git(repo, "-c", "sequence.editor=true", "rebase", "-i", "--autosquash", "HEAD~4")
+    assert _commit_subjects(repo) == ["init", "add a.txt", "add b.txt"]
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "A\n"
+    assert run_git(repo, "show", "HEAD:b.txt").stdout == "B\n"
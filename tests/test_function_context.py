@@ -0,0 +1,57 @@
+"""Tests for showing hunks inside their enclosing function (-W)."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+BODY = "".join(f"    x{i} = {i}\n" for i in range(1, 11))
+BEFORE = "def first():\n" + BODY + "    return x10\n\n\ndef second():\n    pass\n"
+AFTER = BEFORE.replace("return x10", "return x10 + 1")
+
+
+def _setup(repo):
+    create_file(repo, "m.py", BEFORE)
+    modify_file(repo, "m.py", AFTER)
+
+
+def test_show_function_context(git_agent_exe, repo):
+    _setup(repo)
+    [hunk_id] = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.split()
+
+    plain = run_git_agent(git_agent_exe, repo, "show", hunk_id).stdout.splitlines()
+    result = run_git_agent(git_agent_exe, repo, "show", hunk_id, "-W")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+
+    # The function's own start is included, without a number
+    assert " : def first():" in lines
+    assert " :     x1 = 1" in lines
+    # The hunk keeps its numbering
+    assert lines[-len(plain) + 1:] == plain[1:]
+
+
+def test_lines_numbers_unchanged_by_function_context(git_agent_exe, repo):
+    _setup(repo)
+    [hunk_id] = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id").stdout.split()
+    shown = run_git_agent(git_agent_exe, repo, "show", hunk_id, "-W").stdout.splitlines()
+    added = next(line for line in shown if "+    return x10 + 1" in line)
+    removed = next(line for line in shown if "-    return x10" in line)
+    start, end = sorted(int(line.split(":")[0]) for line in (added, removed))
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id, "--lines", f"{start}-{end}")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":m.py").stdout == AFTER
+
+
+def test_hunks_function_context_json(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "-W", "--json")
+    assert result.returncode == 0, result.stderr
+    [hunk] = json.loads(result.stdout)["hunks"]
+    assert hunk["function_before"][0] == " def first():"
+    assert hunk["function_after"] == []
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "-W")
+    assert " : def first():" in result.stdout
@@ -0,0 +1,55 @@
+"""Tests for hunks --group-by-function."""
+
+import json
+
+from conftest import run_git_agent, create_file
+
+
+def _source(one_edits=(), two_edits=()):
+    def body(name, edits):
+        lines = [f"    {name}{i} = {i}" for i in range(20)]
+        for i in edits:
+            lines[i] += "0"
+        return f"def {name}():\n" + "\n".join(lines) + "\n"
+
+    return body("one", one_edits) + "\n" + body("two", two_edits)
+
+
+def test_hunks_cluster_under_their_function(git_agent_exe, repo):
+    create_file(repo, "a.py", _source())
+    (repo / "a.py").write_text(_source(one_edits=(1, 17), two_edits=(10,)))
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--group-by-function")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    headings = [line for line in lines if line.startswith("# ")]
+    # The first hunk's @@ context is the line above it, not def one()
+    assert headings == ["# a.py: def one(): (2 hunks)", "# a.py: def two(): (1 hunk)"]
+    assert lines.index(headings[1]) > 4
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--group-by-function", "--json")
+    assert result.returncode == 0, result.stderr
+    data = json.loads(result.stdout)
+    ids = [hunk["id"] for hunk in data["hunks"]]
+    assert data["groups"] == [
+        {"file": "a.py", "function": "def one():", "hunks": ids[:2]},
+        {"file": "a.py", "function": "def two():", "hunks": ids[2:]},
+    ]
+
+
+def test_sort_keeps_clusters_together(git_agent_exe, repo):
+    create_file(repo, "a.py", _source())
+    create_file(repo, "b.txt", "x\n")
+    (repo / "a.py").write_text(_source(one_edits=(1, 2, 3, 17), two_edits=(10,)))
+    (repo / "b.txt").write_text("y\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "hunks", "--group-by-function", "--sort", "size", "--stat"
+    )
+    assert result.returncode == 0, result.stderr
+    headings = [line for line in result.stdout.splitlines() if line.startswith("# ")]
+    assert headings == [
+        "# a.py: def one(): (2 hunks)",
+        "# a.py: def two(): (1 hunk)",
+        "# b.txt: (top level) (1 hunk)",
+    ]
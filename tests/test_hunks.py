@@ -118,3 +118,181 @@ def test_full_flag_with_commit(git_agent_exe, repo):
     assert "commit.txt" in result.stdout
     # Should have line numbers
     assert "1:" in result.stdout
+
+
+def test_sample_limits_hunks_and_prints_totals(git_agent_exe, repo):
+    for name in ["a", "b", "c"]:
+        content = "top\n" + "mid\n" * 20 + "bottom\n"
+        create_file(repo, f"{name}.txt", content)
+        modify_file(repo, f"{name}.txt", "top changed\n" + "mid\n" * 20 + "bottom changed\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--sample", "3")
+    assert result.returncode == 0
+    header_lines = [
+        l for l in result.stdout.strip().split("\n")
+        if l and not l.startswith("  ") and not l.startswith("sampled")
+    ]
+    assert len(header_lines) == 3
+    # Spread across files: one hunk from each
+    assert {l.split()[1] for l in header_lines} == {"a.txt", "b.txt", "c.txt"}
+    assert "sampled 3 of 6 hunks across 3 files (+6 -6 total)" in result.stdout
+
+
+def test_format_prints_one_line_per_hunk(git_agent_exe, repo):
+    create_file(repo, "a.py", "def f():\n" + "    x = 1\n" * 3 + "\n" * 10 + "last\n")
+    modify_file(repo, "a.py", "def f():\n" + "    x = 1\n" * 3 + "\n" * 10 + "LAST\n")
+    create_file(repo, "b.txt", "one\n")
+    modify_file(repo, "b.txt", "one\ntwo\n")
+
+    ids = [l.split()[0] for l in run_git_agent(git_agent_exe, repo, "hunks").stdout.splitlines()
+           if l and not l.startswith("  ")]
+    result = run_git_agent(
+        git_agent_exe, repo, "hunks", "--format", "%id\t%file\t%func\t%adds/%dels\t%first_line"
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines() == [
+        f"{ids[0]}\ta.py\tdef f():\t1/1\t-last",
+        f"{ids[1]}\tb.txt\t\t1/0\t+two",
+    ]
+
+
+def test_format_rejects_unknown_field(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--format", "%id %size")
+    assert result.returncode != 0
+    assert "unknown format field %size" in result.stderr
+    assert "%first_line" in result.stderr
+
+
+def test_preview_lines_limits_preview(git_agent_exe, repo):
+    create_file(repo, "a.txt", "".join(f"line {i}\n" for i in range(6)))
+    modify_file(repo, "a.txt", "".join(f"LINE {i}\n" for i in range(6)))
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--preview-lines", "2")
+    assert result.returncode == 0, result.stderr
+    preview = [l for l in result.stdout.splitlines() if l.startswith("  ")]
+    assert preview == ["  -line 0", "  -line 1", "  ... (+10 more lines)"]
+
+
+def test_preview_none_prints_headers_only(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "b\n")
+    create_file(repo, "b.txt", "c\n")
+    modify_file(repo, "b.txt", "d\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--preview=none")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert len(lines) == 2
+    assert lines[0].endswith("a.txt (+1 -1)")
+
+
+def test_preview_context_includes_context_lines(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\ntwo\nthree\nfour\n")
+    modify_file(repo, "a.txt", "one\ntwo\nTHREE\nfour\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--preview=context", "--preview-lines", "3")
+    assert result.returncode == 0, result.stderr
+    preview = [l for l in result.stdout.splitlines() if l.startswith("  ")]
+    assert preview == ["   one", "   two", "  -three", "  ... (+2 more lines)"]
+
+
+def test_color_always_highlights_ids_and_changes(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "two\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--color=always")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0].startswith("\x1b[1m") and "\x1b[m a.txt (+1 -1)" in lines[0]
+    assert lines[1:3] == ["  \x1b[31m-one\x1b[m", "  \x1b[32m+two\x1b[m"]
+
+
+def test_color_auto_is_off_when_piped(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "two\n")
+
+    for args in [(), ("--color=auto",), ("--color=always", "--color=never")]:
+        result = run_git_agent(git_agent_exe, repo, "hunks", *args)
+        assert result.returncode == 0, result.stderr
+        assert "\x1b[" not in result.stdout
+
+
+def test_stat_prints_headers_and_summary(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n" + "mid\n" * 10 + "two\n")
+    modify_file(repo, "a.txt", "ONE\n" + "mid\n" * 10 + "TWO\nthree\n")
+    create_file(repo, "long_name.txt", "x\n")
+    modify_file(repo, "long_name.txt", "x\ny\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--stat")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert [(l.split()[1], l.rsplit(" ", 2)[1:]) for l in lines[:3]] == [
+        ("a.txt", ["(+1", "-1)"]),
+        ("a.txt", ["(+2", "-1)"]),
+        ("long_name.txt", ["(+1", "-0)"]),
+    ]
+    assert lines[3:] == [
+        "",
+        " a.txt         | 2 hunks +3 -2",
+        " long_name.txt | 1 hunk  +1 -0",
+        " 3 hunks in 2 files, +4 -2",
+    ]
+
+
+def _headers(output):
+    return [l for l in output.splitlines() if l and not l.startswith("  ")]
+
+
+def test_size_filters_and_sort(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "ONE\n")
+    create_file(repo, "b.txt", "x\n")
+    modify_file(repo, "b.txt", "x\n" + "new\n" * 5)
+    all_ids = {l.split()[1]: l.split()[0] for l in _headers(run_git_agent(git_agent_exe, repo, "hunks").stdout)}
+
+    # IDs stay the same when other hunks are filtered out
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--max-changes", "2")
+    assert [l.split()[:2] for l in _headers(result.stdout)] == [[all_ids["a.txt"], "a.txt"]]
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--min-changes", "3", "--format", "%file")
+    assert result.stdout.splitlines() == ["b.txt"]
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--sort=size", "--format", "%file %adds")
+    assert result.stdout.splitlines() == ["b.txt 5", "a.txt 1"]
+    for order in ["file", "path-order"]:
+        result = run_git_agent(git_agent_exe, repo, "hunks", f"--sort={order}", "--format", "%file")
+        assert result.stdout.splitlines() == ["a.txt", "b.txt"]
+
+
+def test_all_lists_staged_and_unstaged(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    create_file(repo, "b.txt", "two\n")
+    modify_file(repo, "a.txt", "ONE\n")
+    run_git(repo, "add", "a.txt")
+    modify_file(repo, "b.txt", "TWO\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--all")
+    assert result.returncode == 0, result.stderr
+    headers = _headers(result.stdout)
+    assert len(headers) == 2
+    staged_id = headers[0].split()[0]
+    assert staged_id.startswith("s-")
+    assert headers[0].endswith("a.txt (+1 -1) [staged]")
+    assert headers[1].endswith("b.txt (+1 -1) [unstaged]")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--all", "--format", "%location %file")
+    assert result.stdout.splitlines() == ["staged a.txt", "unstaged b.txt"]
+
+    # The prefixed ID works with show and unstage, but not stage
+    result = run_git_agent(git_agent_exe, repo, "show", staged_id)
+    assert "+ONE" in result.stdout
+    result = run_git_agent(git_agent_exe, repo, "stage", staged_id)
+    assert result.returncode != 0
+    assert "use 'unstage'" in result.stderr
+    result = run_git_agent(git_agent_exe, repo, "unstage", staged_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached").stdout == ""
+
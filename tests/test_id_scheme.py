@@ -0,0 +1,54 @@
+"""Tests for content-only hunk IDs."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+BASE = "".join(f"line{i}\n" for i in range(1, 21))
+
+
+def _ids(exe, repo, *args):
+    result = run_git_agent(exe, repo, *args, "hunks", "--format=%id")
+    assert result.returncode == 0, result.stderr
+    return result.stdout.split()
+
+
+def _edit(repo, *changes):
+    lines = BASE.splitlines()
+    for n, text in changes:
+        lines[n - 1] = text
+    modify_file(repo, "a.txt", "\n".join(lines) + "\n")
+
+
+def test_content_ids_survive_context_edits(git_agent_exe, repo):
+    create_file(repo, "a.txt", BASE)
+    _edit(repo, (14, "changed14"))
+    [full] = _ids(git_agent_exe, repo)
+    [content] = _ids(git_agent_exe, repo, "--id-scheme", "content")
+
+    # Commit an edit to line 11, in the hunk's context, and keep line 14's
+    _edit(repo, (11, "changed11"))
+    run_git(repo, "commit", "-qam", "edit line 11")
+    _edit(repo, (11, "changed11"), (14, "changed14"))
+
+    assert _ids(git_agent_exe, repo) != [full]
+    assert _ids(git_agent_exe, repo, "--id-scheme", "content") == [content]
+
+
+def test_config_selects_scheme_for_every_command(git_agent_exe, repo):
+    create_file(repo, "a.txt", BASE)
+    _edit(repo, (14, "changed14"))
+    content = _ids(git_agent_exe, repo, "--id-scheme", "content")
+    run_git(repo, "config", "surgeon.idScheme", "content")
+    assert _ids(git_agent_exe, repo) == content
+
+    result = run_git_agent(git_agent_exe, repo, "stage", content[0])
+    assert result.returncode == 0, result.stderr
+    assert "+changed14" in run_git(repo, "diff", "--cached").stdout
+
+
+def test_invalid_config(git_agent_exe, repo):
+    create_file(repo, "a.txt", BASE)
+    run_git(repo, "config", "surgeon.idScheme", "fuzzy")
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode != 0
+    assert "invalid surgeon.idScheme 'fuzzy'" in result.stderr
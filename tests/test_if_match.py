@@ -0,0 +1,66 @@
+"""Tests for hunks --json snapshot tokens and --if-match."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _listing(exe, repo, *args):
+    result = run_git_agent(exe, repo, "hunks", "--json", *args)
+    assert result.returncode == 0, result.stderr
+    return json.loads(result.stdout)
+
+
+def test_hunks_json_lists_hunks_and_token(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\ntwo\n")
+    modify_file(repo, "a.txt", "one\nTWO\n")
+
+    data = _listing(git_agent_exe, repo)
+    assert len(data["token"]) == 16
+    [hunk] = data["hunks"]
+    assert hunk["file"] == "a.txt"
+    assert hunk["additions"] == 1
+    assert hunk["deletions"] == 1
+    assert "+TWO" in hunk["lines"]
+
+
+def test_hunks_json_empty_diff(git_agent_exe, repo):
+    data = _listing(git_agent_exe, repo)
+    assert data["hunks"] == []
+
+
+def test_if_match_allows_unchanged_state(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "changed\n")
+    data = _listing(git_agent_exe, repo)
+    hunk_id = data["hunks"][0]["id"]
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id, "--if-match", data["token"])
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode != 0
+
+
+def test_if_match_refuses_after_change(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    create_file(repo, "b.txt", "two\n")
+    modify_file(repo, "a.txt", "changed\n")
+    data = _listing(git_agent_exe, repo)
+    hunk_id = data["hunks"][0]["id"]
+
+    modify_file(repo, "b.txt", "also changed\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id, "--if-match", data["token"])
+    assert result.returncode == 5
+    assert "repository changed since listing" in result.stderr
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+
+
+def test_token_changes_when_staging(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "changed\n")
+    before = _listing(git_agent_exe, repo)
+
+    run_git(repo, "add", "a.txt")
+    after = _listing(git_agent_exe, repo, "--staged")
+    assert before["token"] != after["token"]
+    assert after["hunks"][0]["id"] == before["hunks"][0]["id"]
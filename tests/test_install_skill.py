@@ -0,0 +1,187 @@
+import json
+import os
+import subprocess
+
+
+def _install(exe, home, *args):
+    env = dict(os.environ, HOME=str(home))
+    return subprocess.run(
+        [str(exe), "install-skill", *args],
+        capture_output=True,
+        text=True,
+        env=env,
+    )
+
+
+def test_install_skill_single_platform(git_agent_exe, tmp_path):
+    result = _install(git_agent_exe, tmp_path, "--claude")
+    assert result.returncode == 0, result.stderr
+    skill = tmp_path / ".claude" / "skills" / "git-surgeon" / "SKILL.md"
+    assert "git-surgeon" in skill.read_text()
+
+
+def test_install_skill_all_detects_platforms(git_agent_exe, tmp_path):
+    (tmp_path / ".codex").mkdir()
+
+    result = _install(git_agent_exe, tmp_path, "--all")
+    assert result.returncode == 0, result.stderr
+    assert (tmp_path / ".codex" / "skills" / "git-surgeon" / "SKILL.md").exists()
+    assert not (tmp_path / ".claude").exists()
+    assert "skipped Claude Code" in result.stdout
+    assert "skipped OpenCode" in result.stdout
+
+
+def test_install_skill_all_nothing_detected(git_agent_exe, tmp_path):
+    result = _install(git_agent_exe, tmp_path, "--all")
+    assert result.returncode != 0
+    assert "no supported" in result.stderr
+
+
+def test_install_skill_is_idempotent(git_agent_exe, tmp_path):
+    _install(git_agent_exe, tmp_path, "--claude")
+    result = _install(git_agent_exe, tmp_path, "--claude")
+    assert result.returncode == 0
+    assert "already up to date" in result.stdout
+
+
+def test_install_skill_renders_platform_frontmatter(git_agent_exe, tmp_path):
+    result = _install(git_agent_exe, tmp_path, "--claude", "--codex")
+    assert result.returncode == 0, result.stderr
+
+    claude = (tmp_path / ".claude" / "skills" / "git-surgeon" / "SKILL.md").read_text()
+    codex = (tmp_path / ".codex" / "skills" / "git-surgeon" / "SKILL.md").read_text()
+    assert "short-description" not in claude
+    assert "metadata:\n  short-description:" in codex
+    assert "$git-surgeon" in codex
+
+
+def test_install_skill_rule_platforms(git_agent_exe, tmp_path):
+    result = _install(git_agent_exe, tmp_path, "--cursor", "--windsurf", "--zed")
+    assert result.returncode == 0, result.stderr
+
+    cursor = (tmp_path / ".cursor" / "rules" / "git-surgeon.mdc").read_text()
+    assert cursor.startswith("---\ndescription: Non-interactive")
+    assert "\nalwaysApply: false\n---\n" in cursor
+    windsurf = (tmp_path / ".codeium" / "windsurf" / "rules" / "git-surgeon.md").read_text()
+    assert windsurf.startswith("---\ntrigger: model_decision\n")
+    zed = (tmp_path / ".config" / "zed" / "rules" / "git-surgeon.md").read_text()
+    assert zed.startswith("# git-surgeon\n")
+
+
+def test_install_skill_gemini_extension_and_aider(git_agent_exe, tmp_path):
+    result = _install(git_agent_exe, tmp_path, "--gemini", "--aider")
+    assert result.returncode == 0, result.stderr
+
+    extension = tmp_path / ".gemini" / "extensions" / "git-surgeon"
+    manifest = json.loads((extension / "gemini-extension.json").read_text())
+    assert manifest["name"] == "git-surgeon"
+    assert manifest["contextFileName"] == "GEMINI.md"
+    assert (extension / "GEMINI.md").read_text().startswith("# git-surgeon\n")
+
+    conventions = tmp_path / ".aider" / "git-surgeon" / "CONVENTIONS.md"
+    assert conventions.exists()
+    assert f"read: {conventions}" in result.stdout
+
+    result = _install(git_agent_exe, tmp_path, "--gemini")
+    assert "already up to date" in result.stdout
+
+
+def _uninstall(exe, home, *args):
+    env = dict(os.environ, HOME=str(home))
+    return subprocess.run(
+        [str(exe), "uninstall-skill", *args],
+        capture_output=True,
+        text=True,
+        env=env,
+    )
+
+
+def test_install_skill_list_shows_versions(git_agent_exe, tmp_path):
+    _install(git_agent_exe, tmp_path, "--claude", "--gemini")
+    # An install from before versions were recorded
+    old = tmp_path / ".cursor" / "rules" / "git-surgeon.mdc"
+    old.parent.mkdir(parents=True)
+    old.write_text("---\ndescription: old\n---\n")
+
+    result = _install(git_agent_exe, tmp_path, "--list")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    claude = next(line for line in lines if line.startswith("Claude Code: "))
+    assert claude.split()[2][0].isdigit()
+    assert ".claude/skills/git-surgeon/SKILL.md" in claude
+    assert any(line.startswith("Gemini CLI: ") and "GEMINI.md" in line for line in lines)
+    assert any(line.startswith("Cursor: unknown version") for line in lines)
+    assert "Codex: not installed" in lines
+
+
+def test_uninstall_skill_removes_created_files(git_agent_exe, tmp_path):
+    (tmp_path / ".claude").mkdir()
+    (tmp_path / ".claude" / "settings.json").write_text("{}")
+    _install(git_agent_exe, tmp_path, "--claude", "--gemini")
+
+    result = _uninstall(git_agent_exe, tmp_path, "--claude")
+    assert result.returncode == 0, result.stderr
+    assert "removed Claude Code skill" in result.stdout
+    assert not (tmp_path / ".claude" / "skills").exists()
+    assert (tmp_path / ".claude" / "settings.json").exists()
+    assert (tmp_path / ".gemini" / "extensions" / "git-surgeon").exists()
+
+    result = _uninstall(git_agent_exe, tmp_path, "--all")
+    assert result.returncode == 0, result.stderr
+    assert "removed Gemini CLI skill" in result.stdout
+    assert not (tmp_path / ".gemini" / "extensions").exists()
+    assert (tmp_path / ".gemini").is_dir()
+
+    result = _uninstall(git_agent_exe, tmp_path, "--codex")
+    assert "Codex skill not installed" in result.stdout
+    assert _uninstall(git_agent_exe, tmp_path).returncode != 0
+
+
+def _skill_status(exe, home, *args):
+    env = dict(os.environ, HOME=str(home))
+    return subprocess.run(
+        [str(exe), "skill-status", *args],
+        capture_output=True,
+        text=True,
+        env=env,
+    )
+
+
+def _make_stale(path):
+    content = path.read_text()
+    marker = content.rindex("<!-- git-surgeon-version: ")
+    path.write_text(content[:marker] + "<!-- git-surgeon-version: 0.0.1 -->\n")
+
+
+def test_skill_status_reports_stale_and_modified(git_agent_exe, tmp_path):
+    _install(git_agent_exe, tmp_path, "--claude", "--codex", "--zed")
+    _make_stale(tmp_path / ".codex" / "skills" / "git-surgeon" / "SKILL.md")
+    zed = tmp_path / ".config" / "zed" / "rules" / "git-surgeon.md"
+    zed.write_text("# my notes\n" + zed.read_text())
+
+    result = _skill_status(git_agent_exe, tmp_path)
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0].startswith("Claude Code: up to date (")
+    assert lines[1].startswith("Codex: stale (0.0.1, current ")
+    assert lines[2].startswith("Zed: modified since install (")
+    assert "git-surgeon install-skill --codex" in result.stderr
+
+    result = _skill_status(git_agent_exe, tmp_path, "--refresh")
+    assert result.returncode == 0, result.stderr
+    assert "updated Codex skill" in result.stdout
+    assert "was 0.0.1" in result.stdout
+    result = _skill_status(git_agent_exe, tmp_path)
+    assert "Codex: up to date" in result.stdout
+    assert "Zed: modified" in result.stdout
+
+
+def test_install_skill_notes_other_stale_installs(git_agent_exe, tmp_path):
+    _install(git_agent_exe, tmp_path, "--claude", "--cursor")
+    _make_stale(tmp_path / ".cursor" / "rules" / "git-surgeon.mdc")
+
+    result = _install(git_agent_exe, tmp_path, "--claude")
+    assert result.returncode == 0, result.stderr
+    assert "already up to date" in result.stdout
+    assert "Cursor" in result.stderr
+    assert "install-skill --cursor" in result.stderr
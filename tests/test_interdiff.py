@@ -0,0 +1,77 @@
+"""Tests for the interdiff command."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file
+
+BASE = "".join(f"line{i}\n" for i in range(1, 21))
+
+
+def _commit_all(repo, message):
+    run_git(repo, "commit", "-qam", message)
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def _two_versions(repo, second_edit):
+    """v1 changes line 3 of a.txt on top of the base; the upstream then moves
+    on (changing line 18), and v2 redoes the change there with `second_edit`."""
+    create_file(repo, "a.txt", BASE)
+    base = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    (repo / "a.txt").write_text(BASE.replace("line3\n", "LINE3\n"))
+    v1 = _commit_all(repo, "change line 3")
+
+    run_git(repo, "checkout", "-q", "-b", "upstream", base)
+    (repo / "a.txt").write_text(BASE.replace("line18\n", "LINE18\n"))
+    _commit_all(repo, "upstream")
+    text = (repo / "a.txt").read_text().replace("line3\n", "LINE3\n")
+    (repo / "a.txt").write_text(second_edit(text))
+    v2 = _commit_all(repo, "change line 3")
+    return v1, v2
+
+
+def test_interdiff_ignores_base_changes(git_agent_exe, repo):
+    v1, v2 = _two_versions(repo, lambda text: text)
+
+    result = run_git_agent(git_agent_exe, repo, "interdiff", v1, v2)
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == ""
+    assert "no differences" in result.stderr
+
+
+def test_interdiff_lists_what_changed_between_versions(git_agent_exe, repo):
+    v1, v2 = _two_versions(repo, lambda text: text.replace("line5\n", "line5 fixed\n"))
+
+    result = run_git_agent(git_agent_exe, repo, "interdiff", v1, v2)
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0].split()[1] == "a.txt" and lines[0].endswith(" (+1 -1)")
+    assert lines[1:3] == ["  -line5", "  +line5 fixed"]
+
+    result = run_git_agent(git_agent_exe, repo, "interdiff", v1, v2, "--json")
+    data = json.loads(result.stdout)
+    assert [h["file"] for h in data["hunks"]] == ["a.txt"]
+    assert data["skipped"] == []
+
+
+def test_interdiff_skips_files_that_do_not_apply(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    base = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+    v1 = _commit_all(repo, "v1")
+
+    run_git(repo, "checkout", "-q", "-b", "other", base)
+    (repo / "a.txt").write_text("conflicting\n")
+    _commit_all(repo, "upstream")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B2\n")
+    v2 = _commit_all(repo, "v2")
+
+    result = run_git_agent(git_agent_exe, repo, "interdiff", v1, v2, "--json")
+    assert result.returncode == 0, result.stderr
+    data = json.loads(result.stdout)
+    assert data["skipped"] == ["a.txt"]
+    assert [h["file"] for h in data["hunks"]] == ["b.txt"]
+    assert data["hunks"][0]["lines"] == ["-B", "+B2"]
+    assert "does not apply" in result.stderr
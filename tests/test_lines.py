@@ -48,3 +48,85 @@ def test_lines_rejects_invalid_range(git_agent_exe, repo):
     """Invalid range format is rejected."""
     result = run_git_agent(git_agent_exe, repo, "undo", "abc", "--from", "HEAD", "--lines", "5-3")
     assert result.returncode != 0
+
+
+def test_inline_ranges_on_stage_unstage_discard(git_agent_exe, repo):
+    """Each ID takes its own inline range in one stage/unstage/discard."""
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "a\na1\na2\na3\n")
+    modify_file(repo, "b.txt", "b\nb1\nb2\nb3\n")
+    a_id, b_id = _get_hunk_ids(git_agent_exe, repo)
+
+    # Hunk lines: context "a", then the three additions at lines 2-4
+    result = run_git_agent(git_agent_exe, repo, "stage", f"{a_id}:2", f"{b_id}:3-4")
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.splitlines()[0] == f"staged 2 hunks: {a_id}:2 {b_id}:3-4"
+    assert run_git(repo, "show", ":a.txt").stdout == "a\na1\n"
+    assert run_git(repo, "show", ":b.txt").stdout == "b\nb2\nb3\n"
+
+    staged_b = _get_hunk_ids(git_agent_exe, repo, "--staged")[1]
+    result = run_git_agent(git_agent_exe, repo, "unstage", f"{staged_b}:2", f"{staged_b}:3")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":b.txt").stdout == "b\n"
+
+    a_id = _get_hunk_ids(git_agent_exe, repo)[0]
+    # The unstaged hunk is now " a", " a1", "+a2", "+a3"
+    result = run_git_agent(git_agent_exe, repo, "discard", f"{a_id}:3")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "a.txt").read_text() == "a\na1\na3\n"
+
+
+def test_inline_range_conflicts_with_lines(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a\na1\n")
+    hunk_id = _get_hunk_ids(git_agent_exe, repo)[0]
+
+    result = run_git_agent(git_agent_exe, repo, "stage", f"{hunk_id}:2", "--lines", "2")
+    assert result.returncode == 2
+    assert "either --lines or an inline range" in result.stderr
+
+
+def test_lines_takes_several_ranges(git_agent_exe, repo):
+    """--lines takes comma-separated ranges and can be repeated."""
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a\na1\na2\na3\na4\n")
+    hunk_id = _get_hunk_ids(git_agent_exe, repo)[0]
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id, "--lines", "2,4-5")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":a.txt").stdout == "a\na1\na3\na4\n"
+
+    run_git(repo, "commit", "-qm", "partial")
+    run_git(repo, "checkout", "a.txt")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout.split()[0]
+    # The commit's hunk is " a", "+a1", "+a3", "+a4"
+    result = run_git_agent(
+        git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD", "--lines", "2", "--lines", "4"
+    )
+    assert result.returncode == 0, result.stderr
+    assert (repo / "a.txt").read_text() == "a\na3\n"
+
+
+def test_lines_pair_with_the_id_before_them(git_agent_exe, repo):
+    """Each --lines applies to the hunk ID given just before it."""
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+    modify_file(repo, "a.txt", "a\na1\na2\n")
+    modify_file(repo, "b.txt", "b\nb1\nb2\n")
+    modify_file(repo, "c.txt", "c\nc1\n")
+    a_id, b_id, c_id = _get_hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "stage", a_id, "--lines", "2", b_id, "--lines", "3", c_id
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.splitlines()[0] == f"staged 3 hunks: {a_id}:2 {b_id}:3 {c_id}"
+    assert run_git(repo, "show", ":a.txt").stdout == "a\na1\n"
+    assert run_git(repo, "show", ":b.txt").stdout == "b\nb2\n"
+    assert run_git(repo, "show", ":c.txt").stdout == "c\nc1\n"
+
+    # Ranges given before any ID still need a single ID
+    result = run_git_agent(git_agent_exe, repo, "discard", "--lines", "2", a_id, b_id)
+    assert result.returncode == 2
@@ -0,0 +1,123 @@
+"""Tests for the advisory repository lock."""
+
+import os
+import subprocess
+import time
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _hold_lock(repo, pid):
+    lock = repo / ".git" / "surgeon" / "lock"
+    lock.parent.mkdir(parents=True, exist_ok=True)
+    lock.write_text(f"pid {pid} stage\n")
+    return lock
+
+
+def _setup(exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    (repo / "a.txt").write_text("ONE\n")
+    return _get_hunk_ids(run_git_agent(exe, repo, "hunks").stdout)
+
+
+def test_lock_no_wait_fails_fast(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    lock = _hold_lock(repo, os.getpid())
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--no-wait")
+    assert result.returncode == 7
+    assert "another git-surgeon is modifying this repository" in result.stderr
+    assert f"pid {os.getpid()} stage" in result.stderr
+    assert lock.exists()
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+
+
+def test_lock_timeout_waits_then_fails(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    _hold_lock(repo, os.getpid())
+
+    start = time.monotonic()
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--lock-timeout", "1")
+    assert result.returncode == 7
+    assert time.monotonic() - start >= 1
+
+
+def test_lock_read_only_commands_ignore_lock(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    _hold_lock(repo, os.getpid())
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--no-wait")
+    assert result.returncode == 0, result.stderr
+    assert _get_hunk_ids(result.stdout) == ids
+
+
+def test_lock_stale_holder_is_taken_over(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    # A pid far above any default pid_max, so the holder is certainly gone
+    lock = _hold_lock(repo, 99999999)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--no-wait")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 1
+    assert not lock.exists()
+
+
+def test_lock_takeover_by_many_waiters(git_agent_exe, repo):
+    _setup(git_agent_exe, repo)
+    _hold_lock(repo, 99999999)
+    (repo / "b.txt").write_text("b\n")
+
+    procs = [
+        subprocess.Popen(
+            [git_agent_exe, "stage-file", "b.txt"],
+            cwd=repo,
+            stdout=subprocess.PIPE,
+            stderr=subprocess.PIPE,
+        )
+        for _ in range(8)
+    ]
+    codes = []
+    for proc in procs:
+        _, stderr = proc.communicate(timeout=30)
+        # None times out on the lock; the later ones find nothing to stage
+        assert proc.returncode != 7, stderr
+        codes.append(proc.returncode)
+    assert codes.count(0) == 1
+    assert sorted(p.name for p in (repo / ".git" / "surgeon").iterdir() if "lock" in p.name) == []
+
+
+def test_lock_takeover_marker_left_by_crash_is_reported(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    lock = _hold_lock(repo, 99999999)
+    marker = lock.with_suffix(".takeover")
+    marker.write_text("pid 99999998 stage\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--no-wait")
+    assert result.returncode == 7
+    assert ".git/surgeon/lock.takeover" in result.stderr
+    marker.unlink()
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--no-wait")
+    assert result.returncode == 0, result.stderr
+
+
+def test_lock_held_by_other_users_process_is_kept(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+    # init is alive; unless run as root, kill -0 fails on it with EPERM
+    lock = _hold_lock(repo, 1)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--no-wait")
+    assert result.returncode != 0
+    assert "pid 1 stage" in result.stderr
+    assert lock.read_text() == "pid 1 stage\n"
+
+
+def test_lock_released_after_command(git_agent_exe, repo):
+    ids = _setup(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "change")
+    assert result.returncode == 0, result.stderr
+    assert not (repo / ".git" / "surgeon" / "lock").exists()
@@ -0,0 +1,47 @@
+"""Tests for the log command."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _history(repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("b\nc\n")
+    run_git(repo, "add", "b.txt")
+    run_git(repo, "commit", "-qam", "touch both")
+    (repo / "b.txt").write_text("B\nc\n")
+    run_git(repo, "commit", "-qam", "fix b")
+
+
+def test_log_lists_counts_per_commit(git_agent_exe, repo):
+    _history(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "log", "HEAD~2..HEAD")
+    assert result.returncode == 0, result.stderr
+    blocks = result.stdout.split("\n\n")
+    assert len(blocks) == 2
+    assert blocks[0].splitlines()[0].endswith(" fix b (1 hunk, 1 file, +1 -1)")
+    lines = blocks[1].splitlines()
+    assert lines[0].endswith(" touch both (2 hunks, 2 files, +3 -1)")
+    assert [line.split()[0] for line in lines[1:]] == ["a.txt", "b.txt"]
+
+
+def test_log_file_and_grep(git_agent_exe, repo):
+    _history(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "log", "--file", "a.txt", "--json")
+    assert result.returncode == 0, result.stderr
+    data = json.loads(result.stdout)
+    assert [c["subject"] for c in data][:1] == ["touch both"]
+    assert data[0]["files"] == [
+        {"file": "a.txt", "hunks": 1, "additions": 1, "deletions": 1}
+    ]
+    assert data[0]["commit"] == run_git(repo, "rev-parse", "HEAD~").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "log", "--grep", "fix", "--json")
+    assert [c["subject"] for c in json.loads(result.stdout)] == ["fix b"]
+
+    result = run_git_agent(git_agent_exe, repo, "log", "-n", "1", "--json")
+    assert len(json.loads(result.stdout)) == 1
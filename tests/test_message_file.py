@@ -0,0 +1,94 @@
+"""Tests for reading commit messages with -F <file> and -F -."""
+
+import subprocess
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _run_with_stdin(exe, repo, stdin, *args):
+    return subprocess.run(
+        [str(exe), *args], cwd=repo, input=stdin, capture_output=True, text=True
+    )
+
+
+def _message(repo, rev="HEAD"):
+    return run_git(repo, "log", "-1", "--format=%B", rev).stdout.strip()
+
+
+MESSAGE = 'Add pagination\n\nAdds "page" and `per_page` params.\n\n- with $quoting\n'
+
+
+def test_commit_message_from_file(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+    msg_file = repo / ".git" / "MSG"
+    msg_file.write_text(MESSAGE)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-F", str(msg_file))
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == MESSAGE.strip()
+
+
+def test_reword_message_from_stdin(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = _run_with_stdin(git_agent_exe, repo, MESSAGE, "reword", "HEAD~1", "-F", "-")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == MESSAGE.strip()
+
+
+def test_squash_message_from_stdin(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = _run_with_stdin(git_agent_exe, repo, MESSAGE, "squash", "HEAD~1", "-F", "-")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == MESSAGE.strip()
+
+
+def test_empty_message_file_rejected(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = _run_with_stdin(git_agent_exe, repo, "\n  \n", "reword", "HEAD", "-F", "-")
+    assert result.returncode != 0
+    assert "empty commit message from stdin" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-F", "missing.txt")
+    assert result.returncode != 0
+    assert "failed to read message file missing.txt" in result.stderr
+
+
+def test_split_message_files(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    rest_file = repo / ".git" / "REST"
+    rest_file.write_text("Add c\n\nThe rest.\n")
+
+    result = _run_with_stdin(
+        git_agent_exe, repo, MESSAGE,
+        "split", "HEAD", "--pick", ids[0], "-F", "-", "--rest-message-file", str(rest_file),
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == MESSAGE.strip()
+    assert _message(repo) == "Add c\n\nThe rest."
+
+
+def test_split_stdin_only_once(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = _run_with_stdin(
+        git_agent_exe, repo, MESSAGE,
+        "split", "HEAD", "--pick", "abc", "-F", "-", "--rest-message-file", "-",
+    )
+    assert result.returncode != 0
+    assert "only one message can be read from stdin" in result.stderr
@@ -0,0 +1,68 @@
+"""Tests for targeting commits by :/pattern in rewriting commands."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _history(repo):
+    create_file(repo, "parser.txt", "parse\n")
+    (repo / "parser.txt").write_text("parse\nfixed crash\n")
+    run_git(repo, "commit", "-qam", "fix parser crash")
+    create_file(repo, "lexer.txt", "lex\n")
+    (repo / "parser.txt").write_text("parse\nfixed crash\nfixed leak\n")
+    run_git(repo, "commit", "-qam", "fix parser leak")
+
+
+def _subjects(repo):
+    return run_git(repo, "log", "--format=%s").stdout.strip().split("\n")
+
+
+def test_reword_by_unique_pattern(git_agent_exe, repo):
+    _history(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", ":/lexer", "-m", "add the lexer")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:3] == ["fix parser leak", "add the lexer", "fix parser crash"]
+
+
+def test_ambiguous_pattern_lists_candidates(git_agent_exe, repo):
+    _history(repo)
+    before = run_git(repo, "rev-parse", "HEAD").stdout
+
+    result = run_git_agent(git_agent_exe, repo, "reword", ":/fix parser", "-m", "x")
+    assert result.returncode == 2
+    assert "matches 2 commits" in result.stderr
+    assert "fix parser leak" in result.stderr
+    assert "fix parser crash" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == before
+
+
+def test_pattern_only_matching_another_branch_is_not_found(git_agent_exe, repo):
+    _history(repo)
+    run_git(repo, "checkout", "-qb", "other")
+    create_file(repo, "other.txt", "o\n")
+    run_git(repo, "checkout", "-q", "-")
+    (repo / "parser.txt").write_text("changed\n")
+    run_git(repo, "add", "parser.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", ":/add other")
+    assert result.returncode == 3
+    assert "no commit in the current branch matches" in result.stderr
+
+
+def test_squash_range_by_patterns(git_agent_exe, repo):
+    _history(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "squash", ":/add parser..:/add lexer", "-m", "crash fix and lexer"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:3] == ["fix parser leak", "crash fix and lexer", "add parser.txt"]
+
+
+def test_undo_from_pattern(git_agent_exe, repo):
+    _history(repo)
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--commit", ":/leak", "--format", "%id")
+
+    result = run_git_agent(git_agent_exe, repo, "undo", ids.stdout.split()[0], "--from", ":/leak")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "parser.txt").read_text() == "parse\nfixed crash\n"
@@ -0,0 +1,77 @@
+"""Tests for hunks of files whose only change is their mode."""
+
+import os
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _ids(exe, repo, *args):
+    return run_git_agent(exe, repo, "hunks", "--format", "%id", *args).stdout.split()
+
+
+def _chmod_x(repo, name):
+    path = repo / name
+    path.chmod(path.stat().st_mode | 0o111)
+
+
+def test_mode_change_is_listed(git_agent_exe, repo):
+    create_file(repo, "run.sh", "echo hi\n")
+    _chmod_x(repo, "run.sh")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert "run.sh mode 100644 -> 100755" in result.stdout
+
+    ids = _ids(git_agent_exe, repo)
+    result = run_git_agent(git_agent_exe, repo, "show", ids[0])
+    assert "old mode 100644\nnew mode 100755" in result.stdout
+
+
+def test_stage_and_unstage_mode_change(git_agent_exe, repo):
+    create_file(repo, "run.sh", "echo hi\n")
+    _chmod_x(repo, "run.sh")
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0])
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "ls-files", "-s", "run.sh").stdout.startswith("100755")
+
+    result = run_git_agent(git_agent_exe, repo, "unstage", "--all")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "ls-files", "-s", "run.sh").stdout.startswith("100644")
+    assert os.access(repo / "run.sh", os.X_OK)
+
+
+def test_discard_mode_change(git_agent_exe, repo):
+    create_file(repo, "run.sh", "echo hi\n")
+    _chmod_x(repo, "run.sh")
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "discard", ids[0])
+    assert result.returncode == 0, result.stderr
+    assert not os.access(repo / "run.sh", os.X_OK)
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+
+def test_commit_mode_change_alone(git_agent_exe, repo):
+    create_file(repo, "run.sh", "echo hi\n")
+    _chmod_x(repo, "run.sh")
+    (repo / "run.sh").write_text("echo hello\n")
+    create_file(repo, "tool.sh", "echo tool\n")
+    _chmod_x(repo, "tool.sh")
+    ids = _ids(git_agent_exe, repo, "--file", "tool.sh")
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "make tool executable")
+    assert result.returncode == 0, result.stderr
+    summary = run_git(repo, "show", "--summary", "--format=", "HEAD").stdout
+    assert summary.strip() == "mode change 100644 => 100755 tool.sh"
+
+
+def test_mode_change_has_no_lines_to_pick(git_agent_exe, repo):
+    create_file(repo, "run.sh", "echo hi\n")
+    _chmod_x(repo, "run.sh")
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", f"{ids[0]}:1")
+    assert result.returncode == 2
+    assert "only changes the mode of run.sh" in result.stderr
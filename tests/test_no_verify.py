@@ -0,0 +1,88 @@
+"""Tests for --no-verify skipping commit hooks."""
+
+import os
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _install_hook(repo, name, body):
+    hook = repo / ".git" / "hooks" / name
+    hook.parent.mkdir(parents=True, exist_ok=True)
+    hook.write_text("#!/bin/sh\n" + body)
+    os.chmod(hook, 0o755)
+
+
+def _log_hook_runs(repo):
+    """A pre-commit hook that records each run in .git/hook-runs."""
+    _install_hook(repo, "pre-commit", 'echo run >> "$(git rev-parse --git-dir)/hook-runs"\n')
+    return repo / ".git" / "hook-runs"
+
+
+def test_commit_no_verify_skips_failing_hook(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    _install_hook(repo, "pre-commit", "exit 1\n")
+    (repo / "a.txt").write_text("A\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "change")
+    assert result.returncode != 0
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "change", "--no-verify")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "change"
+
+
+def test_split_no_verify_runs_no_hooks(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    runs = _log_hook_runs(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b", "--no-verify"
+    )
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "rev-list", "--count", "HEAD").stdout.strip() == "4"
+    assert not runs.exists()
+
+
+def test_split_runs_hooks_by_default(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    runs = _log_hook_runs(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b")
+    assert result.returncode == 0, result.stderr
+    assert runs.read_text().count("run") == 2
+
+
+def test_rewrites_no_verify_skip_failing_hooks(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    _install_hook(repo, "pre-commit", "exit 1\n")
+    _install_hook(repo, "commit-msg", "exit 1\n")
+    _install_hook(repo, "pre-rebase", "exit 1\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "add a!", "-n")
+    assert result.returncode == 0, result.stderr
+
+    (repo / "a.txt").write_text("A\n")
+    run_git(repo, "add", "a.txt")
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~1", "--no-verify")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "HEAD~1:a.txt").stdout == "A\n"
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "both", "--no-verify")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "both"
@@ -0,0 +1,58 @@
+"""Tests for prefetching blobs in blob-less partial clones."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _missing(clone):
+    listed = run_git(clone, "rev-list", "--objects", "--missing=print", "--all").stdout
+    return [line for line in listed.splitlines() if line.startswith("?")]
+
+
+def _partial_clone(repo):
+    """A blob-less clone of `repo`, whose files each have three versions. It
+    lives in .git, to keep the origin's working tree clean."""
+    for n in range(3):
+        create_file(repo, "a.txt", "".join(f"a{i}\n" for i in range(n + 1)))
+        create_file(repo, "b.txt", "".join(f"b{i}\n" for i in range(n + 1)))
+    run_git(repo, "config", "uploadpack.allowFilter", "true")
+    run_git(repo, "config", "uploadpack.allowAnySHA1InWant", "true")
+    clone = repo / ".git" / "clone"
+    run_git(repo, "clone", "-q", "--filter=blob:none", f"file://{repo}", str(clone))
+    run_git(clone, "config", "user.email", "test@test.com")
+    run_git(clone, "config", "user.name", "Test")
+    return clone
+
+
+def test_commit_diff_prefetches_in_one_batch(git_agent_exe, repo):
+    clone = _partial_clone(repo)
+    assert _missing(clone)
+
+    result = run_git_agent(git_agent_exe, clone, "hunks", "--commit", "HEAD~2")
+    assert result.returncode == 0, result.stderr
+    assert "b.txt (+1 -0)" in result.stdout
+    assert "partial clone: fetching 2 missing blobs from origin" in result.stderr
+    assert "partial clone: fetched 2 blobs" in result.stderr
+
+    result = run_git_agent(git_agent_exe, clone, "hunks", "--commit", "HEAD~2")
+    assert "partial clone" not in result.stderr
+
+
+def test_blame_prefetches_file_history(git_agent_exe, repo):
+    clone = _partial_clone(repo)
+    (clone / "a.txt").write_text("a0\na1\nchanged\n")
+
+    result = run_git_agent(git_agent_exe, clone, "hunks", "--blame")
+    assert result.returncode == 0, result.stderr
+    assert "partial clone: fetching 2 missing blobs from origin" in result.stderr
+    missing = _missing(clone)
+    # Only b.txt's old versions are still missing
+    assert len(missing) == 2
+
+
+def test_full_clone_fetches_nothing(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--blame")
+    assert result.returncode == 0, result.stderr
+    assert "partial clone" not in result.stderr
@@ -0,0 +1,135 @@
+"""Tests for --include and --exclude, which pick hunks by file."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _listed_files(exe, repo, *args):
+    result = run_git_agent(exe, repo, "hunks", "--format", "%file", *args)
+    assert result.returncode == 0, result.stderr
+    return result.stdout.split()
+
+
+def _change_three_files(repo):
+    create_file(repo, "src/a.rs", "a\n")
+    create_file(repo, "src/b.rs", "b\n")
+    create_file(repo, "Cargo.lock", "lock\n")
+    (repo / "src" / "a.rs").write_text("A\n")
+    (repo / "src" / "b.rs").write_text("B\n")
+    (repo / "Cargo.lock").write_text("LOCK\n")
+
+
+def test_hunks_include_and_exclude(git_agent_exe, repo):
+    _change_three_files(repo)
+
+    assert _listed_files(git_agent_exe, repo, "--exclude", "*.lock") == ["src/a.rs", "src/b.rs"]
+    assert _listed_files(git_agent_exe, repo, "--include", "src", "--exclude", "src/b.rs") == [
+        "src/a.rs"
+    ]
+
+
+def test_stage_all_exclude(git_agent_exe, repo):
+    _change_three_files(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "--all", "--exclude", "*.lock")
+    assert result.returncode == 0, result.stderr
+    staged = run_git(repo, "diff", "--cached", "--name-only").stdout.split()
+    assert staged == ["src/a.rs", "src/b.rs"]
+
+
+def test_stage_filter_needs_all(git_agent_exe, repo):
+    _change_three_files(repo)
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--format", "%id").stdout.split()
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--include", "src")
+    assert result.returncode == 2
+    assert "--all" in result.stderr
+
+
+def test_commit_exclude(git_agent_exe, repo):
+    _change_three_files(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", "--exclude", "*.lock", "-m", "Change sources"
+    )
+    assert result.returncode == 0, result.stderr
+    committed = run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout.split()
+    assert committed == ["src/a.rs", "src/b.rs"]
+    assert run_git(repo, "diff", "--name-only").stdout.split() == ["Cargo.lock"]
+
+
+def test_commit_filter_with_ids_is_refused(git_agent_exe, repo):
+    _change_three_files(repo)
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--format", "%id").stdout.split()
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", ids[0], "--include", "src", "-m", "message"
+    )
+    assert result.returncode == 2
+    assert "not both" in result.stderr
+
+
+def test_commit_filter_without_match(git_agent_exe, repo):
+    _change_three_files(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", "--include", "*.py", "-m", "message")
+    assert result.returncode == 3
+    assert "--include/--exclude" in result.stderr
+
+
+def test_split_pick_by_file(git_agent_exe, repo):
+    _change_three_files(repo)
+    run_git(repo, "commit", "-qam", "everything")
+
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "split",
+        "HEAD",
+        "--pick",
+        "--include",
+        "*.lock",
+        "-m",
+        "Update lockfile",
+        "--rest-message",
+        "Change sources",
+    )
+    assert result.returncode == 0, result.stderr
+    first = run_git(repo, "show", "--name-only", "--format=%s", "HEAD~1").stdout.split("\n")
+    assert first[:3] == ["Update lockfile", "", "Cargo.lock"]
+    rest = run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout.split()
+    assert rest == ["src/a.rs", "src/b.rs"]
+
+
+def test_split_pick_path_groups(git_agent_exe, repo):
+    _change_three_files(repo)
+    run_git(repo, "commit", "-qam", "everything")
+    b_id = run_git_agent(
+        git_agent_exe, repo, "hunks", "--commit", "HEAD", "--format", "%id", "--include", "src/b.rs"
+    ).stdout.split()[0]
+
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "split",
+        "HEAD",
+        "--pick", b_id, "-m", "Change b",
+        "--pick-path", "src/**", "-m", "Change sources",
+        "--pick-path", "*.lock", "-m", "Update lockfile",
+    )
+    assert result.returncode == 0, result.stderr
+    log = run_git(repo, "log", "-3", "--reverse", "--name-only", "--format=%s").stdout
+    log = [line for line in log.split("\n") if line]
+    assert log == [
+        "Change b", "src/b.rs", "Change sources", "src/a.rs", "Update lockfile", "Cargo.lock",
+    ]
+
+
+def test_split_pick_path_requires_a_glob(git_agent_exe, repo):
+    _change_three_files(repo)
+    run_git(repo, "commit", "-qam", "everything")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick-path", "-m", "nothing"
+    )
+    assert result.returncode == 2
+    assert "--pick-path requires" in result.stderr
@@ -0,0 +1,97 @@
+"""Tests for @N and path:N hunk references."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _setup(repo):
+    """Two hunks in a.txt, one in b.txt."""
+    create_file(repo, "a.txt", "first\n" + "mid\n" * 20 + "last\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "FIRST\n" + "mid\n" * 20 + "LAST\n")
+    modify_file(repo, "b.txt", "B\n")
+
+
+def _ids(exe, repo):
+    result = run_git_agent(exe, repo, "hunks", "--format=%id")
+    return result.stdout.split()
+
+
+def test_stage_by_ordinal(git_agent_exe, repo):
+    _setup(repo)
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "@3")
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.splitlines()[0] == f"staged 1 hunk: {ids[2]}"
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout.split() == ["b.txt"]
+
+
+def test_stage_by_file_position(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "a.txt:2")
+    assert result.returncode == 0, result.stderr
+    staged = run_git(repo, "diff", "--cached").stdout
+    assert "+LAST" in staged
+    assert "+FIRST" not in staged
+
+
+def test_file_position_with_line_range(git_agent_exe, repo):
+    create_file(repo, "c.txt", "one\ntwo\n")
+    modify_file(repo, "c.txt", "ONE\nTWO\n")
+
+    # Lines 2 and 4 of the hunk: -two and +TWO
+    result = run_git_agent(git_agent_exe, repo, "commit", "c.txt:1:2,4", "-m", "two")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "HEAD:c.txt").stdout == "one\nTWO\n"
+
+
+def test_show_reports_resolved_id(git_agent_exe, repo):
+    _setup(repo)
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "show", "@2", "--json")
+    assert result.returncode == 0, result.stderr
+    assert json.loads(result.stdout)["id"] == ids[1]
+
+
+def test_undo_by_position_in_commit(git_agent_exe, repo):
+    _setup(repo)
+    run_git(repo, "commit", "-am", "both")
+
+    result = run_git_agent(git_agent_exe, repo, "undo", "b.txt:1", "--from", "HEAD")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "b.txt").read_text() == "b\n"
+    assert run_git(repo, "diff", "--name-only").stdout.split() == ["b.txt"]
+
+
+def test_out_of_range(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "@4")
+    assert result.returncode != 0
+    assert "the listing has 3 hunk(s)" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "b.txt:2")
+    assert result.returncode != 0
+    assert "b.txt has 1 hunk(s)" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "nope.txt:1")
+    assert result.returncode != 0
+    assert "no hunks in nope.txt" in result.stderr
+
+
+def test_split_by_position(git_agent_exe, repo):
+    _setup(repo)
+    run_git(repo, "commit", "-am", "all")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", "@3", "-m", "b only",
+        "--rest-message", "a only",
+    )
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", "--name-only", "--format=", "HEAD~1").stdout.split() == ["b.txt"]
+    assert run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout.split() == ["a.txt"]
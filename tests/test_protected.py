@@ -0,0 +1,89 @@
+"""Tests for refusing to rewrite published commits."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _head(repo):
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def _setup(repo):
+    """Three commits; 'published' points at the middle one."""
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    run_git(repo, "branch", "published")
+    create_file(repo, "c.txt", "c\n")
+    run_git(repo, "config", "surgeon.protectedRef", "published")
+
+
+def test_reword_published_commit_refused(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "new")
+    assert result.returncode != 0
+    assert "is already published (reachable from published)" in result.stderr
+    assert "--force-rewrite-published" in result.stderr
+    assert _head(repo) == before
+
+
+def test_unpublished_commit_allowed(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "new subject")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "new subject"
+
+
+def test_force_rewrite_published(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~1", "-m", "new", "--force-rewrite-published"
+    )
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--skip=1", "--format=%s").stdout.strip() == "new"
+
+
+def test_upstream_is_protected(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    run_git(repo, "branch", "base")
+    create_file(repo, "b.txt", "b\n")
+    run_git(repo, "branch", "--set-upstream-to", "base")
+
+    for args in (["drop", "HEAD~1"], ["squash", "HEAD~1", "-m", "both"]):
+        result = run_git_agent(git_agent_exe, repo, *args)
+        assert result.returncode != 0, args
+        assert "reachable from base" in result.stderr
+
+    # Only HEAD is unpushed, so it can still be rewritten
+    (repo / "b.txt").write_text("B\n")
+    run_git(repo, "add", "b.txt")
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD")
+    assert result.returncode == 0, result.stderr
+
+
+def test_split_and_interior_squash_refused(git_agent_exe, repo):
+    _setup(repo)
+    create_file(repo, "d.txt", "d\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD~2").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~2", "--pick", ids[0], "-m", "part"
+    )
+    assert result.returncode != 0
+    assert "is already published" in result.stderr
+
+    # A dry run changes nothing, so it is allowed
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~2", "--dry-run", "--pick", ids[0], "-m", "part"
+    )
+    assert "is already published" not in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~3..HEAD~1", "-m", "x")
+    assert result.returncode != 0
+    assert "is already published" in result.stderr
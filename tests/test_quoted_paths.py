@@ -0,0 +1,28 @@
+"""Tests for paths git quotes in diff headers."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def test_unusual_paths(git_agent_exe, repo):
+    names = ["ä.txt", "sp ace.txt", 'q"t.txt']
+    for name in names:
+        create_file(repo, name, "1\n")
+        modify_file(repo, name, "1\n2\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id %file")
+    assert result.returncode == 0, result.stderr
+    listed = dict(line.split(" ", 1) for line in result.stdout.splitlines())
+    assert sorted(listed.values()) == sorted(names)
+
+    ids = {name: hunk_id for hunk_id, name in listed.items()}
+    result = run_git_agent(git_agent_exe, repo, "show", ids["ä.txt"], "--full-file")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines()[-1] == "2:+2"
+
+    result = run_git_agent(git_agent_exe, repo, "stage", *ids.values())
+    assert result.returncode == 0, result.stderr
+    staged = run_git(repo, "diff", "--cached", "--name-only", "-z")
+    assert sorted(staged.stdout.split("\0")[:-1]) == sorted(names)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--staged", "--file", "ä.txt", "--format=%file")
+    assert result.stdout == "ä.txt\n"
@@ -0,0 +1,95 @@
+"""Tests for the old -> new commit report after rewrites."""
+
+import json
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _sha(repo, rev):
+    return run_git(repo, "rev-parse", rev).stdout.strip()
+
+
+def _setup(repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+
+
+def test_reword_reports_range_diff(git_agent_exe, repo):
+    _setup(repo)
+    old_b = _sha(repo, "HEAD~1")
+    old_c = _sha(repo, "HEAD")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "add b, reworded")
+    assert result.returncode == 0, result.stderr
+    new_b = _sha(repo, "HEAD~1")
+    new_c = _sha(repo, "HEAD")
+    assert f"range-diff {old_c[:7]}..{new_c[:7]}:" in result.stderr
+    assert f"  {old_b[:7]} ! {new_b[:7]} add b, reworded" in result.stderr
+    assert f"  {old_c[:7]} = {new_c[:7]} add c.txt" in result.stderr
+
+
+def test_fixup_json_output(git_agent_exe, repo):
+    _setup(repo)
+    old_head = _sha(repo, "HEAD")
+    old_a = _sha(repo, "HEAD~2")
+    (repo / "a.txt").write_text("A\n")
+    run_git(repo, "add", "a.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", "HEAD~2", "--json")
+    assert result.returncode == 0, result.stderr
+    report = json.loads(result.stdout)
+    assert report["old_head"] == old_head
+    assert report["new_head"] == _sha(repo, "HEAD")
+    commits = report["commits"]
+    assert [c["subject"] for c in commits] == ["add a.txt", "add b.txt", "add c.txt"]
+    assert [c["status"] for c in commits] == ["modified", "unchanged", "unchanged"]
+    assert commits[0]["old"] == old_a
+    assert commits[0]["new"] == _sha(repo, "HEAD~2")
+
+
+def test_split_reports_added_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b", "--json"
+    )
+    assert result.returncode == 0, result.stderr
+    commits = json.loads(result.stdout)["commits"]
+    assert [(c["status"], c["subject"]) for c in commits] == [
+        ("modified", "add b"),
+        ("added", "add b and c"),
+    ]
+    assert commits[1]["old"] is None
+
+
+def test_squash_reports_removed_commits(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "b and c", "--json")
+    assert result.returncode == 0, result.stderr
+    commits = json.loads(result.stdout)["commits"]
+    assert [(c["status"], c["subject"]) for c in commits] == [
+        ("removed", "add b.txt"),
+        ("modified", "b and c"),
+    ]
+    assert commits[0]["new"] is None
+
+
+def test_split_dry_run_has_no_report(git_agent_exe, repo):
+    _setup(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--dry-run", "--pick", ids[0], "-m", "x"
+    )
+    assert "range-diff" not in result.stderr
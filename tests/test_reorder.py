@@ -0,0 +1,82 @@
+"""Tests for the reorder command."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _subjects(repo, n):
+    log = run_git(repo, "log", "--reverse", "--format=%s", f"HEAD~{n}..HEAD")
+    return log.stdout.strip().splitlines()
+
+
+def _shas(repo, n):
+    log = run_git(repo, "log", "--reverse", "--format=%H", f"HEAD~{n}..HEAD")
+    return log.stdout.strip().splitlines()
+
+
+def test_reorder_by_sha(git_agent_exe, repo):
+    for name in ["a", "b", "c"]:
+        create_file(repo, f"{name}.txt", f"{name}\n")
+    a, b, c = _shas(repo, 3)
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~3..HEAD", "--order", c, a, b)
+    assert result.returncode == 0, result.stderr
+    assert "reordered 3 commits" in result.stderr
+    assert _subjects(repo, 3) == ["add c.txt", "add a.txt", "add b.txt"]
+
+
+def test_reorder_by_position(git_agent_exe, repo):
+    for name in ["a", "b", "c"]:
+        create_file(repo, f"{name}.txt", f"{name}\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~3..", "--order", "2", "1", "3")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo, 3) == ["add b.txt", "add a.txt", "add c.txt"]
+
+
+def test_reorder_preserves_dirty_worktree(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "a changed\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~2..HEAD", "--order", "2", "1")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo, 2) == ["add b.txt", "add a.txt"]
+    assert (repo / "a.txt").read_text() == "a changed\n"
+
+
+def test_reorder_requires_every_commit(git_agent_exe, repo):
+    for name in ["a", "b", "c"]:
+        create_file(repo, f"{name}.txt", f"{name}\n")
+    before = _shas(repo, 3)
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~3..HEAD", "--order", "3", "1")
+    assert result.returncode != 0
+    assert "missing" in result.stderr
+    assert _shas(repo, 3) == before
+
+
+def test_reorder_rejects_commit_outside_range(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~1..HEAD", "--order", "HEAD~1")
+    assert result.returncode != 0
+    assert "not in the range" in result.stderr
+
+
+def test_reorder_conflict_then_abort(git_agent_exe, repo):
+    create_file(repo, "f.txt", "one\n")
+    (repo / "f.txt").write_text("two\n")
+    run_git(repo, "commit", "-am", "second")
+    (repo / "f.txt").write_text("three\n")
+    run_git(repo, "commit", "-am", "third")
+    before = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~2..HEAD", "--order", "2", "1")
+    assert result.returncode != 0
+    assert "git-surgeon continue" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "abort")
+    assert result.returncode == 0, result.stderr
+    assert "aborted reorder" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == before
@@ -0,0 +1,57 @@
+"""Tests for reusing recorded conflict resolutions (rerere) during rewrites."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _subjects(repo):
+    result = run_git(repo, "log", "--reverse", "--format=%s")
+    return [s for s in result.stdout.strip().split("\n") if s]
+
+
+def _setup_conflicting_fixup(repo):
+    """Stage a change that conflicts when folded into an earlier commit."""
+    create_file(repo, "f.txt", "one\ntwo\nthree\n")
+    target = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE\n")
+    run_git(repo, "commit", "-am", "shout three")
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE!\n")
+    run_git(repo, "add", "f.txt")
+    return target
+
+
+def test_fixup_reuses_recorded_resolutions(git_agent_exe, repo):
+    target = _setup_conflicting_fixup(repo)
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+    # First time round, resolve both conflicts by hand
+    result = run_git_agent(git_agent_exe, repo, "fixup", target)
+    assert result.returncode != 0
+    modify_file(repo, "f.txt", "one\ntwo\nthree!\n")
+    run_git(repo, "add", "f.txt")
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode != 0
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE!\n")
+    run_git(repo, "add", "f.txt")
+    result = run_git_agent(git_agent_exe, repo, "continue")
+    assert result.returncode == 0, result.stderr
+    resolved = run_git(repo, "rev-parse", "HEAD^{tree}").stdout.strip()
+
+    # Redo the same fixup from the original history: rerere replays both
+    run_git(repo, "reset", "--hard", head)
+    modify_file(repo, "f.txt", "one\ntwo\nTHREE!\n")
+    run_git(repo, "add", "f.txt")
+    result = run_git_agent(git_agent_exe, repo, "fixup", target)
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.count("resolved f.txt using a recorded resolution (rerere)") == 2
+    assert _subjects(repo) == ["init", "add f.txt", "shout three"]
+    assert run_git(repo, "rev-parse", "HEAD^{tree}").stdout.strip() == resolved
+    assert run_git(repo, "show", "HEAD~1:f.txt").stdout == "one\ntwo\nthree!\n"
+
+
+def test_unrecorded_conflict_still_stops(git_agent_exe, repo):
+    target = _setup_conflicting_fixup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "fixup", target)
+    assert result.returncode != 0
+    assert "recorded resolution" not in result.stderr
+    assert "git-surgeon continue" in result.stderr
@@ -0,0 +1,133 @@
+"""Tests for finding hunks again after their content changed."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _setup(repo):
+    """Two hunks in a.txt, listed once."""
+    create_file(repo, "a.txt", "first\n" + "mid\n" * 20 + "last\n")
+    modify_file(repo, "a.txt", "FIRST\n" + "mid\n" * 20 + "LAST\n")
+
+
+def _ids(exe, repo):
+    result = run_git_agent(exe, repo, "hunks", "--format=%id")
+    return result.stdout.split()
+
+
+def test_edited_hunk_is_resolved_with_notice(git_agent_exe, repo):
+    _setup(repo)
+    old = _ids(git_agent_exe, repo)[0]
+    modify_file(repo, "a.txt", "FIRST!\n" + "mid\n" * 20 + "LAST\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage", old)
+    assert result.returncode == 0, result.stderr
+    assert f"hunk {old} changed since it was listed" in result.stderr
+    assert "was: +FIRST\n" in result.stderr
+    assert "now: +FIRST!\n" in result.stderr
+    staged = run_git(repo, "diff", "--cached").stdout
+    assert "+FIRST!" in staged
+    assert "+LAST" not in staged
+
+
+def test_rewritten_hunk_suggests_closest(git_agent_exe, repo):
+    _setup(repo)
+    old = _ids(git_agent_exe, repo)[0]
+    new_top = "totally\ndifferent\nlines\nhere\nnow\n"
+    modify_file(repo, "a.txt", new_top + "mid\n" * 20 + "LAST\n")
+    current = run_git_agent(git_agent_exe, repo, "resolve-id", old)
+    assert current.returncode == 0, current.stderr
+    closest = current.stdout.split()[0]
+
+    result = run_git_agent(git_agent_exe, repo, "stage", old)
+    assert result.returncode != 0
+    assert "it changed since it was listed; closest now: " + closest in result.stderr
+    assert run_git(repo, "diff", "--cached").stdout == ""
+
+
+def test_resolve_id_lists_candidates(git_agent_exe, repo):
+    _setup(repo)
+    old_first, old_last = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "resolve-id", old_last)
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == f"{old_last} 100% a.txt\n"
+
+    modify_file(repo, "a.txt", "FIRST!\n" + "mid\n" * 20 + "LAST\n")
+    result = run_git_agent(git_agent_exe, repo, "resolve-id", old_first)
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0].endswith("% a.txt")
+    assert lines[0].split()[0] not in (old_first, old_last)
+
+    result = run_git_agent(git_agent_exe, repo, "resolve-id", "0000000")
+    assert result.returncode != 0
+    assert "was never listed by 'hunks' as unstaged" in result.stderr
+
+
+def test_registry_keeps_earlier_listings(git_agent_exe, repo):
+    _setup(repo)
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "b.txt", "B\n")
+    old = _ids(git_agent_exe, repo)[0]
+    # Listing only b.txt does not forget a.txt's hunks
+    run_git_agent(git_agent_exe, repo, "hunks", "--file", "b.txt")
+    modify_file(repo, "a.txt", "FIRST!\n" + "mid\n" * 20 + "LAST\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage", old)
+    assert result.returncode == 0, result.stderr
+    assert "+FIRST!" in run_git(repo, "diff", "--cached").stdout
+
+
+def test_gone_hunk_reports_its_change(git_agent_exe, repo):
+    _setup(repo)
+    old = _ids(git_agent_exe, repo)[0]
+    modify_file(repo, "a.txt", "first\n" + "mid\n" * 20 + "LAST\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage", old)
+    assert result.returncode != 0
+    assert f"hunk {old} not found: its change to a.txt (+1 -1) is no longer unstaged" in result.stderr
+
+
+def test_ids_only_resolve_within_their_location(git_agent_exe, repo):
+    _setup(repo)
+    run_git(repo, "commit", "-am", "both")
+    old = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD", "--format=%id").stdout.split()[0]
+    modify_file(repo, "a.txt", "FIRST!\n" + "mid\n" * 20 + "LAST\n")
+
+    # A commit's hunk is not mistaken for a similar working tree one
+    result = run_git_agent(git_agent_exe, repo, "stage", old)
+    assert result.returncode != 0
+    assert "changed since it was listed" not in result.stderr
+    assert run_git(repo, "diff", "--cached").stdout == ""
+
+
+def test_commit_ids_only_resolve_within_their_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\ntwo\nthree\n")
+    modify_file(repo, "a.txt", "one\ntwo\nthree\nfour\n")
+    run_git(repo, "commit", "-qam", "add four")
+    modify_file(repo, "a.txt", "one\ntwo\nthree\nfour\nfive\n")
+    run_git(repo, "commit", "-qam", "add five")
+    listed = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD~1", "--format=%id")
+    old = listed.stdout.split()[0]
+
+    result = run_git_agent(git_agent_exe, repo, "undo", old, "--from", "HEAD")
+    assert result.returncode == 3
+    assert "using" not in result.stderr
+    assert (repo / "a.txt").read_text() == "one\ntwo\nthree\nfour\nfive\n"
+
+    result = run_git_agent(git_agent_exe, repo, "resolve-id", old, "--commit", "HEAD")
+    assert result.returncode == 3
+    assert "was never listed by 'hunks' from" in result.stderr
+
+
+def test_destructive_commands_only_suggest(git_agent_exe, repo):
+    _setup(repo)
+    old = _ids(git_agent_exe, repo)[0]
+    modify_file(repo, "a.txt", "FIRST!\n" + "mid\n" * 20 + "LAST\n")
+    current = run_git_agent(git_agent_exe, repo, "resolve-id", old).stdout.split()[0]
+
+    result = run_git_agent(git_agent_exe, repo, "discard", old)
+    assert result.returncode == 3
+    assert f"closest now: {current}" in result.stderr
+    assert f"pass {current} to use it" in result.stderr
+    assert (repo / "a.txt").read_text().startswith("FIRST!\n")
@@ -0,0 +1,62 @@
+"""Tests for the restore-from command."""
+
+from conftest import run_git_agent, run_git, create_file
+
+CONTENT = "top\n" + "ctx\n" * 20 + "bottom\n"
+
+
+def _get_hunk_ids(exe, repo, *extra_args):
+    result = run_git_agent(exe, repo, "hunks", *extra_args)
+    ids = []
+    for line in result.stdout.strip().split("\n"):
+        if line and not line.startswith("  "):
+            ids.append(line.split()[0])
+    return ids
+
+
+def _lost_change(repo):
+    """Commit a two-hunk change, then revert it; return the change's SHA."""
+    create_file(repo, "f.txt", CONTENT)
+    (repo / "f.txt").write_text(CONTENT.replace("top", "TOP").replace("bottom", "BOTTOM"))
+    run_git(repo, "commit", "-qam", "shout")
+    sha = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    run_git(repo, "revert", "--no-edit", "HEAD")
+    return sha
+
+
+def test_restore_from_brings_back_a_hunk(git_agent_exe, repo):
+    sha = _lost_change(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", sha)
+
+    result = run_git_agent(git_agent_exe, repo, "restore-from", sha, ids[1])
+    assert result.returncode == 0, result.stderr
+    assert ids[1] in result.stderr
+    assert (repo / "f.txt").read_text() == CONTENT.replace("bottom", "BOTTOM")
+    assert run_git(repo, "diff", "--cached").stdout == ""
+
+
+def test_restore_from_part_of_a_hunk(git_agent_exe, repo):
+    create_file(repo, "f.txt", "a\nb\n")
+    (repo / "f.txt").write_text("A\nB\n")
+    run_git(repo, "commit", "-qam", "upper")
+    sha = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    run_git(repo, "revert", "--no-edit", "HEAD")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", sha)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "restore-from", sha, ids[0], "--lines", "2,4"
+    )
+    assert result.returncode == 0, result.stderr
+    assert (repo / "f.txt").read_text() == "a\nB\n"
+
+
+def test_restore_from_fails_when_already_applied(git_agent_exe, repo):
+    create_file(repo, "f.txt", CONTENT)
+    (repo / "f.txt").write_text(CONTENT.replace("top", "TOP"))
+    run_git(repo, "commit", "-qam", "shout")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(git_agent_exe, repo, "restore-from", "HEAD", ids[0])
+    assert result.returncode != 0
+    assert "already be in the working tree" in result.stderr
+    assert (repo / "f.txt").read_text() == CONTENT.replace("top", "TOP")
@@ -0,0 +1,76 @@
+"""Tests for -C / --reuse-message, which take another commit's message."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+MESSAGE = "Add retries\n\nRetry failed requests twice.\n\nFixes: #12"
+
+
+def _hunk_ids(exe, repo, *args):
+    result = run_git_agent(exe, repo, "hunks", "--format", "%id", *args)
+    return result.stdout.split()
+
+
+def _message(repo, ref="HEAD"):
+    return run_git(repo, "log", "-1", "--format=%B", ref).stdout.strip()
+
+
+def _good_message_commit(repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "A\n")
+    run_git(repo, "commit", "-qam", MESSAGE)
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def test_commit_reuse_message(git_agent_exe, repo):
+    sha = _good_message_commit(repo)
+    modify_file(repo, "a.txt", "AA\n")
+    ids = _hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-C", sha[:7])
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == MESSAGE
+
+
+def test_commit_reuse_message_skips_the_template(git_agent_exe, repo):
+    sha = _good_message_commit(repo)
+    (repo / ".git" / "template.txt").write_text("{subject}\n\nTemplated\n")
+    run_git(repo, "config", "surgeon.messageTemplate", str(repo / ".git" / "template.txt"))
+    modify_file(repo, "a.txt", "AA\n")
+    ids = _hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "--reuse-message", sha)
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == MESSAGE
+
+
+def test_squash_reuse_message(git_agent_exe, repo):
+    sha = _good_message_commit(repo)
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "--reuse-message", sha)
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == MESSAGE
+
+
+def test_split_group_reuse_message(git_agent_exe, repo):
+    sha = _good_message_commit(repo)
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "b.txt", "B\n")
+    modify_file(repo, "c.txt", "c\n")
+    run_git(repo, "add", "-A")
+    run_git(repo, "commit", "-qm", "both")
+    ids = _hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-C", sha,
+        "--rest-message", "add c",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == MESSAGE
+    assert _message(repo) == "add c"
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~1", "--pick", ids[0], "-m", "x", "-C", sha,
+    )
+    assert result.returncode == 2
@@ -0,0 +1,85 @@
+"""Tests for the revert command."""
+
+from conftest import run_git_agent, run_git, create_file
+
+CONTENT = "top\n" + "ctx\n" * 20 + "bottom\n"
+
+
+def _get_hunk_ids(exe, repo, *extra_args):
+    result = run_git_agent(exe, repo, "hunks", *extra_args)
+    ids = []
+    for line in result.stdout.strip().split("\n"):
+        if line and not line.startswith("  "):
+            ids.append(line.split()[0])
+    return ids
+
+
+def _two_hunk_commit(repo):
+    create_file(repo, "f.txt", CONTENT)
+    (repo / "f.txt").write_text(CONTENT.replace("top", "TOP").replace("bottom", "BOTTOM"))
+    run_git(repo, "commit", "-qam", "shout")
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def test_revert_commits_the_reverted_hunk(git_agent_exe, repo):
+    sha = _two_hunk_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", sha)
+
+    result = run_git_agent(git_agent_exe, repo, "revert", ids[0], "--from", sha)
+    assert result.returncode == 0, result.stderr
+
+    message = run_git(repo, "log", "-1", "--format=%B").stdout.strip()
+    assert message == f'Revert part of "shout"\n\nThis reverts a hunk of commit {sha}.'
+    expected = CONTENT.replace("bottom", "BOTTOM")
+    assert run_git(repo, "show", "HEAD:f.txt").stdout == expected
+    assert (repo / "f.txt").read_text() == expected
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+
+def test_revert_with_message_and_lines(git_agent_exe, repo):
+    create_file(repo, "f.txt", "a\nb\n")
+    (repo / "f.txt").write_text("A\nB\n")
+    run_git(repo, "commit", "-qam", "upper")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe,
+        repo,
+        "revert",
+        ids[0],
+        "--from",
+        "HEAD",
+        "--lines",
+        "1,3",
+        "-m",
+        "Keep a lower",
+    )
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "Keep a lower"
+    assert (repo / "f.txt").read_text() == "a\nB\n"
+
+
+def test_revert_refuses_staged_changes(git_agent_exe, repo):
+    sha = _two_hunk_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", sha)
+    create_file(repo, "other.txt", "x\n")
+    (repo / "other.txt").write_text("y\n")
+    run_git(repo, "add", "other.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "revert", ids[0], "--from", sha)
+    assert result.returncode != 0
+    assert "staged changes" in result.stderr
+    assert (repo / "f.txt").read_text().startswith("TOP\n")
+
+
+def test_revert_leaves_nothing_behind_when_the_worktree_conflicts(git_agent_exe, repo):
+    sha = _two_hunk_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", sha)
+    edited = CONTENT.replace("top", "TOP!").replace("bottom", "BOTTOM")
+    (repo / "f.txt").write_text(edited)
+
+    result = run_git_agent(git_agent_exe, repo, "revert", ids[0], "--from", sha)
+    assert result.returncode != 0
+    assert run_git(repo, "rev-parse", "HEAD").stdout.strip() == sha
+    assert run_git(repo, "diff", "--cached").stdout == ""
+    assert (repo / "f.txt").read_text() == edited
@@ -0,0 +1,57 @@
+"""Commit arguments accept full git revision syntax."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _subjects(repo):
+    result = run_git(repo, "log", "--reverse", "--format=%s")
+    return [s for s in result.stdout.strip().split("\n") if s]
+
+
+def test_hunks_commit_at_shorthand(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "@")
+    assert result.returncode == 0, result.stderr
+    assert "a.txt" in result.stdout
+
+
+def test_hunks_blame_with_message_search(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "one\ntwo\n")
+    run_git(repo, "commit", "-am", "append two")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--commit", ":/append two", "--blame")
+    assert result.returncode == 0, result.stderr
+    assert "a.txt" in result.stdout
+    assert "+two" in result.stdout
+
+
+def test_reword_message_search(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", ":/add a.txt", "-m", "first file")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo) == ["init", "first file", "add b.txt"]
+
+
+def test_undo_from_reflog_syntax(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    modify_file(repo, "a.txt", "a changed\n")
+    run_git(repo, "commit", "-am", "change a")
+
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD@{0}").stdout.split()
+    result = run_git_agent(git_agent_exe, repo, "undo", ids[0], "--from", "HEAD@{0}")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "a.txt").read_text() == "a\n"
+
+
+def test_unknown_revision_suggests_near_miss(git_agent_exe, repo):
+    run_git(repo, "branch", "feature")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "featrue")
+    assert result.returncode != 0
+    assert "could not resolve commit 'featrue'" in result.stderr
+    assert "feature" in result.stderr
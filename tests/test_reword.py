@@ -92,3 +92,90 @@ def test_reword_invalid_commit(git_agent_exe, repo):
         git_agent_exe, repo, "reword", "nonexistent", "-m", "message"
     )
     assert result.returncode != 0
+
+
+def _commit_with_body(repo):
+    (repo / "f.txt").write_text("content\n")
+    run_git(repo, "add", "f.txt")
+    run_git(repo, "commit", "-m", "add f", "-m", "Long explanation.", "-m", "Refs: #7")
+    create_file(repo, "g.txt", "g\n")
+
+
+def test_reword_add_trailer_keeps_message(git_agent_exe, repo):
+    _commit_with_body(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~1", "--add-trailer", "Reviewed-by=Jane <j@x.com>"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_message(repo, "HEAD~1") == (
+        "add f\n\nLong explanation.\n\nRefs: #7\nReviewed-by: Jane <j@x.com>"
+    )
+    assert _commit_subjects(repo)[-1] == "add g.txt"
+
+
+def test_reword_keep_body_replaces_subject(git_agent_exe, repo):
+    _commit_with_body(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "Add f", "--keep-body")
+    assert result.returncode == 0, result.stderr
+    assert _commit_message(repo, "HEAD~1") == "Add f\n\nLong explanation.\n\nRefs: #7"
+
+
+def test_reword_needs_message_or_trailer(git_agent_exe, repo):
+    create_file(repo, "f.txt", "content\n")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD")
+    assert result.returncode != 0
+    assert "nothing to reword" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "--keep-body", "-s")
+    assert result.returncode != 0
+    assert "--keep-body needs a new subject" in result.stderr
+
+
+def _rebase_count(repo):
+    return run_git(repo, "reflog", "--format=%gs").stdout.count("rebase (start)")
+
+
+def test_reword_several_commits_in_one_rebase(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword",
+        "HEAD~2", "-m", "Add a",
+        "HEAD", "-m", "Add c", "-m", "With a body.",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo) == ["init", "Add a", "add b.txt", "Add c"]
+    assert _commit_message(repo) == "Add c\n\nWith a body."
+    assert _rebase_count(repo) == 1
+    assert "reworded" in result.stderr and "Add a" in result.stderr
+
+
+def test_reword_several_commits_trailer_only(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "--add-trailer", "Reviewed-by=Jane", "HEAD~1", "HEAD"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_message(repo, "HEAD~1") == "add a.txt\n\nReviewed-by: Jane"
+    assert _commit_message(repo) == "add b.txt\n\nReviewed-by: Jane"
+
+
+def test_reword_several_commits_rejects_duplicates(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    old_sha = _commit_sha(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "x", "@", "-m", "y")
+    assert result.returncode != 0
+    assert "given more than once" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD", "-m", "x", "HEAD~1")
+    assert result.returncode != 0
+    assert "nothing to reword for HEAD~1" in result.stderr
+    assert _commit_sha(repo) == old_sha
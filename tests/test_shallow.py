@@ -0,0 +1,57 @@
+"""Tests for rewriting history in shallow clones."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _shallow_clone(repo):
+    """A clone of `repo` with only its last two commits, with no upstream. It
+    lives in .git, to keep the origin's working tree clean."""
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+    clone = repo / ".git" / "clone"
+    run_git(repo, "clone", "-q", "--depth", "2", f"file://{repo}", str(clone))
+    run_git(clone, "config", "user.email", "test@test.com")
+    run_git(clone, "config", "user.name", "Test")
+    # Not published: these tests rewrite it
+    run_git(clone, "branch", "--unset-upstream")
+    return clone
+
+
+def test_rewrite_at_shallow_boundary_is_refused(git_agent_exe, repo):
+    clone = _shallow_clone(repo)
+    head = run_git(clone, "rev-parse", "HEAD").stdout
+
+    result = run_git_agent(git_agent_exe, clone, "reword", "HEAD~1", "-m", "new")
+    assert result.returncode == 5
+    assert "boundary of this shallow clone" in result.stderr
+    assert "--auto-deepen" in result.stderr
+    assert run_git(clone, "rev-parse", "HEAD").stdout == head
+
+
+def test_auto_deepen_fetches_the_parent(git_agent_exe, repo):
+    clone = _shallow_clone(repo)
+
+    result = run_git_agent(
+        git_agent_exe, clone, "reword", "HEAD~1", "-m", "new", "--auto-deepen"
+    )
+    assert result.returncode == 0, result.stderr
+    assert "deepening the shallow clone" in result.stderr
+    log = run_git(clone, "log", "--format=%s").stdout.splitlines()
+    assert log == ["add c.txt", "new", "add a.txt"]
+
+
+def test_commit_behind_the_boundary_mentions_shallow(git_agent_exe, repo):
+    clone = _shallow_clone(repo)
+
+    result = run_git_agent(git_agent_exe, clone, "squash", "HEAD~3")
+    assert result.returncode == 3
+    assert "this clone is shallow" in result.stderr
+
+
+def test_rewrite_within_shallow_history(git_agent_exe, repo):
+    clone = _shallow_clone(repo)
+
+    result = run_git_agent(git_agent_exe, clone, "reword", "HEAD", "-m", "new")
+    assert result.returncode == 0, result.stderr
+    assert run_git(clone, "log", "-1", "--format=%s").stdout.strip() == "new"
@@ -1,3 +1,5 @@
+import json
+
 from conftest import run_git_agent, run_git, create_file, modify_file
 
 
@@ -38,3 +40,74 @@ def test_show_line_numbers_sequential(git_agent_exe, repo):
 def test_show_invalid_id(git_agent_exe, repo):
     result = run_git_agent(git_agent_exe, repo, "show", "invalid")
     assert result.returncode != 0
+
+
+def test_show_color_always(git_agent_exe, repo):
+    create_file(repo, "show.txt", "before\n")
+    modify_file(repo, "show.txt", "after\n")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "show", ids[0], "--color=always")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0] == "\x1b[36m@@ -1 +1 @@\x1b[m"
+    assert lines[1:] == ["1:\x1b[31m-before\x1b[m", "2:\x1b[32m+after\x1b[m"]
+
+
+def test_show_full_file_annotates_hunk_in_place(git_agent_exe, repo):
+    create_file(repo, "f.txt", "".join(f"line {i}\n" for i in range(1, 11)))
+    modify_file(repo, "f.txt", "".join(f"line {i}\n" for i in range(1, 11)).replace("line 5\n", "five\nand a half\n"))
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "show", ids[0], "--full-file")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    assert lines[0].startswith("@@ ")
+    assert lines[1:] == [
+        " 1: line 1",
+        " 2: line 2",
+        " 3: line 3",
+        " 4: line 4",
+        "  :-line 5",
+        " 5:+five",
+        " 6:+and a half",
+        " 7: line 6",
+        " 8: line 7",
+        " 9: line 8",
+        "10: line 9",
+        "11: line 10",
+    ]
+
+
+def test_show_full_file_pure_deletion_from_commit(git_agent_exe, repo):
+    create_file(repo, "f.txt", "a\nb\nc\n")
+    modify_file(repo, "f.txt", "a\nc\n")
+    run_git(repo, "commit", "-qam", "drop b")
+    modify_file(repo, "f.txt", "changed later\n")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(git_agent_exe, repo, "show", ids[0], "--commit", "HEAD", "--full-file")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines()[1:] == ["1: a", " :-b", "2: c"]
+
+
+def test_show_reports_where_hunk_was_found(git_agent_exe, repo):
+    create_file(repo, "a.txt", "one\n")
+    modify_file(repo, "a.txt", "ONE\n")
+    [unstaged] = _get_hunk_ids(git_agent_exe, repo)
+    result = run_git_agent(git_agent_exe, repo, "show", unstaged)
+    assert f"{unstaged} is unstaged (working tree)" in result.stderr
+
+    run_git(repo, "add", "a.txt")
+    result = run_git_agent(git_agent_exe, repo, "show", unstaged)
+    assert f"{unstaged} is staged (index)" in result.stderr
+    data = json.loads(run_git_agent(git_agent_exe, repo, "show", unstaged, "--json").stdout)
+    assert data["location"] == "staged"
+    assert data["commit"] is None
+    assert "+ONE" in data["lines"]
+
+    run_git(repo, "commit", "-qm", "shout")
+    head = run_git(repo, "rev-parse", "HEAD").stdout.strip()
+    result = run_git_agent(git_agent_exe, repo, "show", unstaged, "--commit", "HEAD", "--json")
+    data = json.loads(result.stdout)
+    assert (data["location"], data["commit"], data["file"]) == ("commit", head, "a.txt")
@@ -0,0 +1,102 @@
+"""Tests for --gpg-sign / --no-gpg-sign and dropped-signature reports.
+
+Signing uses an SSH key so no GPG agent is needed.
+"""
+
+import subprocess
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _setup_signing(repo):
+    # Inside .git so the key files stay out of the working tree
+    key = repo / ".git" / "signing-key"
+    subprocess.run(
+        ["ssh-keygen", "-q", "-t", "ed25519", "-N", "", "-f", str(key)], check=True
+    )
+    run_git(repo, "config", "gpg.format", "ssh")
+    run_git(repo, "config", "user.signingKey", f"{key}.pub")
+    return key
+
+
+def _is_signed(repo, rev="HEAD"):
+    return "\ngpgsig " in run_git(repo, "cat-file", "commit", rev).stdout
+
+
+def _signed_history(repo):
+    (repo / "a.txt").write_text("a\n")
+    run_git(repo, "add", "a.txt")
+    run_git(repo, "commit", "-S", "-m", "add a")
+    (repo / "b.txt").write_text("b\n")
+    run_git(repo, "add", "b.txt")
+    run_git(repo, "commit", "-S", "-m", "add b")
+
+
+def test_rewrite_warns_about_dropped_signatures(git_agent_exe, repo):
+    _setup_signing(repo)
+    _signed_history(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "add a!")
+    assert result.returncode == 0, result.stderr
+    assert "warning: dropped the signatures of 2 rewritten commit(s):" in result.stderr
+    assert "pass --gpg-sign" in result.stderr
+    assert not _is_signed(repo, "HEAD")
+
+
+def test_gpg_sign_signs_rewritten_commits(git_agent_exe, repo):
+    _setup_signing(repo)
+    _signed_history(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~1", "-m", "add a!", "--gpg-sign")
+    assert result.returncode == 0, result.stderr
+    assert "dropped the signatures" not in result.stderr
+    assert "the new commits are signed with your key" in result.stderr
+    assert _is_signed(repo, "HEAD")
+    assert _is_signed(repo, "HEAD~1")
+
+
+def test_gpg_sign_with_key_on_commit(git_agent_exe, repo):
+    key = _setup_signing(repo)
+    run_git(repo, "config", "--unset", "user.signingKey")
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", ids[0], "-m", "signed", f"--gpg-sign={key}.pub"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _is_signed(repo)
+
+
+def test_no_gpg_sign_overrides_config(git_agent_exe, repo):
+    _setup_signing(repo)
+    run_git(repo, "config", "commit.gpgSign", "true")
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "both", "--no-gpg-sign")
+    assert result.returncode == 0, result.stderr
+    assert not _is_signed(repo)
+    assert "dropped the signatures of 2" in result.stderr
+
+
+def test_split_accepts_signing_flag_in_trailing_args(git_agent_exe, repo):
+    _setup_signing(repo)
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b", "--gpg-sign"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _is_signed(repo, "HEAD")
+    assert _is_signed(repo, "HEAD~1")
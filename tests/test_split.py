@@ -237,6 +237,28 @@ def test_split_earlier_commit(git_agent_exe, repo):
     assert "modify both regions" not in subjects
 
 
+def test_split_earlier_commit_with_long_abbrev(git_agent_exe, repo):
+    """The rebase todo may abbreviate SHAs beyond 7 chars (core.abbrev)."""
+    run_git(repo, "config", "core.abbrev", "12")
+    content = "top\n" + "ctx\n" * 20 + "bottom\n"
+    create_file(repo, "f.txt", content)
+
+    modify_file(repo, "f.txt", "top modified\n" + "ctx\n" * 20 + "bottom modified\n")
+    run_git(repo, "add", "f.txt")
+    run_git(repo, "commit", "-m", "modify both regions")
+    target_sha = _commit_sha(repo)
+    create_file(repo, "later.txt", "later\n")
+
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", target_sha)
+    result = run_git_agent(
+        git_agent_exe, repo, "split", target_sha,
+        "--pick", ids[0], "-m", "modify top",
+        "--rest-message", "modify bottom",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-3:] == ["modify top", "modify bottom", "add later.txt"]
+
+
 def test_split_with_line_ranges(git_agent_exe, repo):
     """Split with inline id:range syntax for partial hunk selection."""
     # Create a file with a single hunk that has multiple changes
@@ -659,7 +681,7 @@ def test_split_multi_pick_groups_with_line_ranges(git_agent_exe, repo):
         show = run_git(repo, "show", sha)
         return [l[1:] for l in show.stdout.split("\n") if l.startswith("+") and not l.startswith("+++")]
 
-    log = run_git(repo, "log", "--all", "--format=%H %s")
+    log = run_git(repo, "log", "--format=%H %s")
     for line in log.stdout.strip().split("\n"):
         if "add logging" in line:
             lines = added_lines(line.split()[0])
@@ -941,3 +963,258 @@ def test_split_pick_after_rest_message_fails(git_agent_exe, repo):
     )
     assert result.returncode != 0
     assert "not allowed after --rest-message" in result.stderr
+
+
+def _two_region_commit(repo):
+    content = "top\n" + "ctx\n" * 20 + "bottom\n"
+    create_file(repo, "f.txt", content)
+    modify_file(repo, "f.txt", "top modified\n" + "ctx\n" * 20 + "bottom modified\n")
+    run_git(repo, "add", "f.txt")
+    run_git(repo, "commit", "-m", "modify both regions")
+
+
+def test_split_dry_run_prints_plan(git_agent_exe, repo):
+    _two_region_commit(repo)
+    before = _commit_sha(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo,
+        "split", "HEAD", "--dry-run",
+        "--pick", f"{ids[0]}:2", "-m", "modify top", "-m", "Body.",
+        "--rest-message", "modify bottom",
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines() == [
+        "commit 1: modify top",
+        f"  {ids[0]}:2 f.txt (+1 -0)",
+        "",
+        "rest: modify bottom",
+        f"  {ids[0]} f.txt (+0 -1)",
+        f"  {ids[1]} f.txt (+1 -1)",
+    ]
+    # Nothing changed
+    assert _commit_sha(repo) == before
+    assert run_git(repo, "status", "--porcelain").stdout.strip() == ""
+
+
+def test_split_dry_run_whole_hunks_no_rest(git_agent_exe, repo):
+    _two_region_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo,
+        "split", "HEAD",
+        "--pick", ids[0], "-m", "top",
+        "--pick", ids[1], "-m", "bottom",
+        "--dry-run",
+    )
+    assert result.returncode == 0, result.stderr
+    assert f"  {ids[0]} f.txt (+1 -1)" in result.stdout
+    assert "commit 2: bottom" in result.stdout
+    assert "rest: nothing left (no rest commit)" in result.stdout
+
+
+def test_split_dry_run_rejects_overlap(git_agent_exe, repo):
+    _two_region_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo,
+        "split", "HEAD", "--dry-run",
+        "--pick", f"{ids[0]}:1-2", "-m", "first",
+        "--pick", f"{ids[0]}:2", "-m", "second",
+    )
+    assert result.returncode != 0
+    assert "already picked" in result.stderr
+
+
+def test_split_dry_run_allows_dirty_tree(git_agent_exe, repo):
+    _two_region_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+    modify_file(repo, "f.txt", "dirty\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--dry-run", "--pick", ids[0], "-m", "top",
+    )
+    assert result.returncode == 0, result.stderr
+    assert "working tree is dirty" in result.stderr
+
+
+def _foreign_two_region_commit(repo):
+    content = "top\n" + "ctx\n" * 20 + "bottom\n"
+    create_file(repo, "f.txt", content)
+    modify_file(repo, "f.txt", "top modified\n" + "ctx\n" * 20 + "bottom modified\n")
+    run_git(repo, "add", "f.txt")
+    run_git(
+        repo, "-c", "user.name=Other", "-c", "user.email=other@example.com",
+        "commit", "-m", "foreign change", "--date", "2020-01-02T03:04:05+00:00",
+    )
+
+
+def test_split_preserves_author_and_dates(git_agent_exe, repo):
+    _foreign_two_region_commit(repo)
+    original = run_git(repo, "log", "-1", "--format=%an|%ae|%aI|%cI").stdout.strip()
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "top", "--rest-message", "bottom",
+    )
+    assert result.returncode == 0, result.stderr
+    for ref in ["HEAD", "HEAD~1"]:
+        info = run_git(repo, "log", "-1", "--format=%an|%ae|%aI|%cI", ref).stdout.strip()
+        assert info == original
+
+
+def test_split_no_preserve_author(git_agent_exe, repo):
+    _foreign_two_region_commit(repo)
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "top", "--no-preserve-author",
+    )
+    assert result.returncode == 0, result.stderr
+    author = run_git(repo, "log", "-1", "--format=%an", "HEAD~1").stdout.strip()
+    assert author == "Test"
+
+
+def _three_file_commit(git_agent_exe, repo):
+    create_file(repo, "base.txt", "base\n")
+    for name in ("a.txt", "b.txt", "c.txt"):
+        modify_file(repo, name, f"{name}\n")
+    run_git(repo, "add", "-A")
+    run_git(repo, "commit", "-qm", "three files")
+    return _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+
+def test_split_order_commits_groups_in_given_order(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+    tree = _commit_sha(repo, "HEAD^{tree}")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add a",
+        "--pick", ids[1], "-m", "add b",
+        "--pick", ids[2], "-m", "add c",
+        "--order", "3,1,2",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-3:] == ["add c", "add a", "add b"]
+    assert run_git(repo, "show", "--name-only", "--format=", "HEAD~2").stdout == "c.txt\n"
+    assert _commit_sha(repo, "HEAD^{tree}") == tree
+
+
+def test_split_order_keeps_picks_of_a_shared_hunk(git_agent_exe, repo):
+    create_file(repo, "f.txt", "keep\n")
+    modify_file(repo, "f.txt", "keep\none\ntwo\n")
+    run_git(repo, "commit", "-qam", "two lines")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD")
+
+    # The first group takes line "one"; the second the rest of the hunk,
+    # and is committed first
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", f"{ids[0]}:2", "-m", "add one",
+        "--pick", ids[0], "-m", "add two",
+        "--order", "2,1",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-2:] == ["add two", "add one"]
+    assert run_git(repo, "show", "HEAD~1:f.txt").stdout == "keep\ntwo\n"
+    assert run_git(repo, "show", "HEAD:f.txt").stdout == "keep\none\ntwo\n"
+
+
+def test_split_order_places_rest(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add a",
+        "--rest-message", "add b and c",
+        "--order", "rest,1",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-2:] == ["add b and c", "add a"]
+    assert run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout == "a.txt\n"
+
+
+def test_split_order_and_trailer_take_equals_form(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add a", "--trailer=Refs=one",
+        "--rest-message", "add b and c",
+        "--order=rest,1",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-2:] == ["add b and c", "add a"]
+    assert run_git(repo, "log", "-1", "--format=%b").stdout.strip() == "Refs: one"
+
+
+def test_split_order_must_list_every_group(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+    head = _commit_sha(repo)
+
+    for order in ("2", "1,1", "1,2,3", "two,1"):
+        result = run_git_agent(
+            git_agent_exe, repo, "split", "HEAD",
+            "--pick", ids[0], "-m", "add a",
+            "--pick", ids[1], "-m", "add b",
+            "--order", order,
+        )
+        assert result.returncode == 2, (order, result.stderr)
+    assert _commit_sha(repo) == head
+
+
+def test_split_discard_rest_keeps_only_the_picks(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add a", "--discard-rest",
+    )
+    assert result.returncode == 0, result.stderr
+    assert "discarded the unpicked changes" in result.stderr
+    assert _commit_subjects(repo)[-2:] == ["add base.txt", "add a"]
+    assert run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout == "a.txt\n"
+    assert not (repo / "b.txt").exists()
+    assert not (repo / "c.txt").exists()
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+
+def test_split_discard_rest_below_head(git_agent_exe, repo):
+    create_file(repo, "f.txt", "top\n" + "ctx\n" * 20 + "bottom\n")
+    modify_file(repo, "f.txt", "TOP\n" + "ctx\n" * 20 + "BOTTOM\n")
+    run_git(repo, "commit", "-qam", "shout")
+    create_file(repo, "later.txt", "later\n")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--commit", "HEAD~1")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~1",
+        "--pick", ids[0], "-m", "shout top", "--discard-rest",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _commit_subjects(repo)[-2:] == ["shout top", "add later.txt"]
+    assert (repo / "f.txt").read_text() == "TOP\n" + "ctx\n" * 20 + "bottom\n"
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+
+def test_split_discard_rest_dry_run_and_conflicts(git_agent_exe, repo):
+    ids = _three_file_commit(git_agent_exe, repo)
+    head = _commit_sha(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--dry-run",
+        "--pick", ids[0], "-m", "add a", "--discard-rest",
+    )
+    assert result.returncode == 0, result.stderr
+    assert "rest: discarded" in result.stdout
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add a",
+        "--rest-message", "rest", "--discard-rest",
+    )
+    assert result.returncode == 2
+    assert _commit_sha(repo) == head
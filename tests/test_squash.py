@@ -253,3 +253,99 @@ def test_squash_no_preserve_author(exe, repo):
     # Check that the author is the current user
     author = run_git(repo, "log", "-1", "--format=%an <%ae>").stdout.strip()
     assert author == "Current User <current@example.com>"
+
+
+def _commit(repo, name, content=None):
+    write_file(repo, f"{name}.txt", content or name)
+    run_git(repo, "add", f"{name}.txt")
+    run_git(repo, "commit", "-m", f"commit {name}")
+
+
+def test_squash_interior_range(exe, repo):
+    """Squash A..B in the middle of the branch and replay later commits."""
+    for name in ["a", "b", "c", "d"]:
+        _commit(repo, name)
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~3..HEAD~1", "-m", "b and c")
+    assert result.returncode == 0, result.stderr
+    assert "squashed 2 commits" in result.stderr
+
+    log = run_git(repo, "log", "--format=%s").stdout.strip().split("\n")
+    assert log == ["commit d", "b and c", "commit a", "init"]
+    branch = run_git(repo, "symbolic-ref", "--short", "HEAD").stdout.strip()
+    assert branch == "main"
+    for name in ["a", "b", "c", "d"]:
+        assert (repo / f"{name}.txt").exists()
+
+
+def test_squash_interior_range_preserves_dirty_tree(exe, repo):
+    for name in ["a", "b", "c"]:
+        _commit(repo, name)
+    write_file(repo, "a.txt", "dirty")
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~3..HEAD~1", "-m", "a and b")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "a.txt").read_text() == "dirty"
+    log = run_git(repo, "log", "--format=%s").stdout.strip().split("\n")
+    assert log == ["commit c", "a and b", "init"]
+
+
+def test_squash_range_ending_at_head(exe, repo):
+    for name in ["a", "b", "c"]:
+        _commit(repo, name)
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~2..HEAD", "-m", "b and c")
+    assert result.returncode == 0, result.stderr
+    log = run_git(repo, "log", "--format=%s").stdout.strip().split("\n")
+    assert log == ["b and c", "commit a", "init"]
+
+
+def test_squash_interior_range_single_commit_errors(exe, repo):
+    for name in ["a", "b"]:
+        _commit(repo, name)
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~2..HEAD~1", "-m", "x")
+    assert result.returncode != 0
+    assert "nothing to squash" in result.stderr
+
+
+def test_squash_without_message_combines_messages(exe, repo):
+    _commit(repo, "a")
+    write_file(repo, "b.txt", "b")
+    run_git(repo, "add", "b.txt")
+    run_git(repo, "commit", "-m", "commit b", "-m", "Body of b.")
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~1")
+    assert result.returncode == 0, result.stderr
+    message = run_git(repo, "log", "-1", "--format=%B").stdout.strip()
+    assert message == "commit a\n\ncommit b\n\nBody of b."
+
+
+def test_squash_combine_messages_interior_range(exe, repo):
+    for name in ["a", "b", "c"]:
+        _commit(repo, name)
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~3..HEAD~1", "--combine-messages")
+    assert result.returncode == 0, result.stderr
+    message = run_git(repo, "log", "-1", "--format=%B", "HEAD~1").stdout.strip()
+    assert message == "commit a\n\ncommit b"
+
+
+def test_squash_message_from(exe, repo):
+    write_file(repo, "a.txt", "a")
+    run_git(repo, "add", "a.txt")
+    run_git(repo, "commit", "-m", "Add feature", "-m", "Detailed body.")
+    _commit(repo, "b")
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~1", "--message-from", "HEAD~1")
+    assert result.returncode == 0, result.stderr
+    message = run_git(repo, "log", "-1", "--format=%B").stdout.strip()
+    assert message == "Add feature\n\nDetailed body."
+
+
+def test_squash_message_from_conflicts_with_message(exe, repo):
+    for name in ["a", "b"]:
+        _commit(repo, name)
+
+    result = run_git_agent(exe, repo, "squash", "HEAD~1", "-m", "x", "--message-from", "HEAD")
+    assert result.returncode != 0
@@ -65,3 +65,40 @@ def test_stage_hunks_different_files(git_agent_exe, repo):
 def test_stage_invalid_id(git_agent_exe, repo):
     result = run_git_agent(git_agent_exe, repo, "stage", "invalid")
     assert result.returncode != 0
+
+
+def test_stage_reports_result(git_agent_exe, repo):
+    create_file(repo, "f1.txt", "f1\n")
+    create_file(repo, "f2.txt", "f2\n")
+    modify_file(repo, "f1.txt", "f1 changed\nmore\n")
+    modify_file(repo, "f2.txt", "f2 changed\n")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0])
+    assert result.returncode == 0, result.stderr
+    lines = result.stderr.splitlines()
+    assert lines[0] == f"staged 1 hunk: {ids[0]}"
+    assert lines[1] == "  f1.txt | 1 hunk  +2 -1"
+    assert lines[2] == f"1 unstaged hunk remains: {ids[1]}"
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[1])
+    assert result.stderr.splitlines()[-1] == "no unstaged hunks remain"
+
+
+def test_stage_all(git_agent_exe, repo):
+    create_file(repo, "f1.txt", "f1\n")
+    create_file(repo, "f2.txt", "f2\n")
+    modify_file(repo, "f1.txt", "f1 changed\n")
+    modify_file(repo, "f2.txt", "f2 changed\n")
+    ids = _get_hunk_ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "--all")
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.splitlines()[0] == f"staged 2 hunks: {' '.join(ids)}"
+    assert run_git(repo, "diff").stdout == ""
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "--all")
+    assert result.returncode != 0
+    assert "no unstaged hunks" in result.stderr
+    result = run_git_agent(git_agent_exe, repo, "stage", "--all", ids[0])
+    assert result.returncode == 2
@@ -0,0 +1,64 @@
+"""Tests for the stage-file and unstage-file commands."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _staged(repo):
+    return run_git(repo, "diff", "--cached", "--name-status").stdout.splitlines()
+
+
+def test_stage_file_new_deleted_and_binary(git_agent_exe, repo):
+    create_file(repo, "gone.txt", "bye\n")
+    create_file(repo, "img.bin", "old")
+    (repo / "gone.txt").unlink()
+    (repo / "img.bin").write_bytes(b"\x00\x01\x02")
+    (repo / "new.txt").write_text("hello\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "stage-file", "gone.txt", "img.bin", "new.txt"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _staged(repo) == ["D\tgone.txt", "M\timg.bin", "A\tnew.txt"]
+    assert "staged 3 files:" in result.stderr
+    assert "  new.txt (new file)" in result.stderr
+    assert "  img.bin (no text hunks)" in result.stderr
+    assert "no unstaged hunks remain" in result.stderr
+
+
+def test_stage_file_reports_hunk_ids(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+    ids = run_git_agent(git_agent_exe, repo, "hunks", "--format", "%id").stdout.split()
+
+    result = run_git_agent(git_agent_exe, repo, "stage-file", "a.txt")
+    assert result.returncode == 0, result.stderr
+    assert f"  a.txt: {ids[0]}" in result.stderr
+    assert f"1 unstaged hunk remains: {ids[1]}" in result.stderr
+    assert _staged(repo) == ["M\ta.txt"]
+
+
+def test_stage_file_without_changes_changes_nothing(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+
+    result = run_git_agent(git_agent_exe, repo, "stage-file", "a.txt", "b.txt")
+    assert result.returncode == 3
+    assert "no unstaged changes to b.txt" in result.stderr
+    assert _staged(repo) == []
+
+
+def test_unstage_file(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "new.txt").write_text("hello\n")
+    run_git(repo, "add", "-A")
+
+    result = run_git_agent(git_agent_exe, repo, "unstage-file", "a.txt", "new.txt")
+    assert result.returncode == 0, result.stderr
+    assert _staged(repo) == []
+    assert "unstaged 2 files:" in result.stderr
+    assert (repo / "new.txt").exists()
+    assert (repo / "a.txt").read_text() == "A\n"
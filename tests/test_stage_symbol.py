@@ -0,0 +1,59 @@
+"""Tests for stage --symbol."""
+
+from conftest import run_git_agent, run_git, create_file
+
+BASE = """\
+fn first() {
+    let a = 1;
+    a
+}
+fn second() {
+    let b = 2;
+    b
+}
+"""
+
+CHANGED = """\
+fn first() {
+    let a = 10;
+    a
+}
+fn second() {
+    let b = 20;
+    b + 1
+}
+"""
+
+
+def test_stage_symbol_stages_only_its_lines(git_agent_exe, repo):
+    create_file(repo, "src/lib.rs", BASE)
+    (repo / "src" / "lib.rs").write_text(CHANGED)
+    # Both functions' changes are in one hunk
+    hunks = run_git_agent(git_agent_exe, repo, "hunks").stdout
+    assert len([l for l in hunks.splitlines() if l and not l.startswith(" ")]) == 1
+
+    result = run_git_agent(
+        git_agent_exe, repo, "stage", "--symbol", "second", "--file", "src/lib.rs"
+    )
+    assert result.returncode == 0, result.stderr
+    staged = run_git(repo, "show", ":src/lib.rs").stdout
+    assert staged == BASE.replace("b = 2;\n    b\n", "b = 20;\n    b + 1\n")
+    assert (repo / "src" / "lib.rs").read_text() == CHANGED
+
+
+def test_stage_symbol_errors(git_agent_exe, repo):
+    create_file(repo, "src/lib.rs", BASE)
+    (repo / "src" / "lib.rs").write_text(CHANGED.replace("let a = 10;", "let a = 1;"))
+
+    result = run_git_agent(
+        git_agent_exe, repo, "stage", "--symbol", "missing", "--file", "src/lib.rs"
+    )
+    assert result.returncode != 0
+    assert "no declaration of 'missing' found" in result.stderr
+
+    result = run_git_agent(
+        git_agent_exe, repo, "stage", "--symbol", "first", "--file", "src/lib.rs"
+    )
+    assert result.returncode != 0
+    assert "no unstaged changes inside 'first' (lines 1-4 of src/lib.rs)" in result.stderr
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
@@ -0,0 +1,74 @@
+"""Tests for submodule pointer changes."""
+
+import subprocess
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _with_submodule(repo):
+    """Add a submodule at `sub`, then commit inside it so its pointer moves."""
+    # Inside .git so the outer repository doesn't see it
+    inner = repo / ".git" / "inner"
+    subprocess.run(["git", "init", "-q", str(inner)], check=True)
+    for key, value in [("user.email", "test@test.com"), ("user.name", "Test")]:
+        run_git(inner, "config", key, value)
+    create_file(inner, "f.txt", "one\n")
+    result = run_git(repo, "-c", "protocol.file.allow=always", "submodule", "add", str(inner), "sub")
+    assert result.returncode == 0, result.stderr
+    run_git(repo, "commit", "-m", "add sub")
+    sub = repo / "sub"
+    for key, value in [("user.email", "test@test.com"), ("user.name", "Test")]:
+        run_git(sub, "config", key, value)
+    create_file(sub, "g.txt", "two\n")
+    return sub
+
+
+def test_pointer_change_is_a_hunk(git_agent_exe, repo):
+    sub = _with_submodule(repo)
+    # A dirty work tree inside the submodule doesn't change its ID
+    modify_file(sub, "f.txt", "changed\n")
+    # Submodule logs in `git diff` must not hide the hunk
+    run_git(repo, "config", "diff.submodule", "log")
+    new = run_git(sub, "rev-parse", "--short=7", "HEAD").stdout.strip()
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    header = result.stdout.splitlines()[0]
+    hunk_id = header.split()[0]
+    assert header.endswith(f" -> {new}")
+    assert header.split()[1:3] == ["sub", "Subproject"]
+
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == "sub\n"
+
+    staged_id = run_git_agent(git_agent_exe, repo, "hunks", "--staged", "--format=%id").stdout.strip()
+    result = run_git_agent(git_agent_exe, repo, "unstage", staged_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
+
+    result = run_git_agent(git_agent_exe, repo, "discard", hunk_id)
+    assert result.returncode != 0
+    assert "git submodule update sub" in result.stderr
+
+
+def test_recurse_submodules(git_agent_exe, repo):
+    sub = _with_submodule(repo)
+    run_git(repo, "add", "sub")
+    run_git(repo, "commit", "-m", "bump sub")
+    modify_file(sub, "f.txt", "changed\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.stdout == ""
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--recurse-submodules")
+    assert result.returncode == 0, result.stderr
+    lines = result.stdout.splitlines()
+    hunk_id = lines[0].split()[0]
+    assert lines[0] == f"{hunk_id} sub/f.txt (+1 -1) [submodule]"
+    assert "cd sub && git-surgeon stage" in result.stderr
+
+    # The ID is the submodule's own
+    result = run_git_agent(git_agent_exe, sub, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(sub, "show", ":f.txt").stdout == "changed\n"
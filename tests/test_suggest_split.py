@@ -0,0 +1,121 @@
+"""Tests for the suggest-split command."""
+
+import shlex
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _subjects(repo):
+    return run_git(repo, "log", "--format=%s").stdout.strip().splitlines()
+
+
+def _mixed_commit(repo):
+    """One commit touching docs and two source files that share a helper."""
+    create_file(repo, "src/a.rs", "fn main() {}\n")
+    create_file(repo, "src/b.rs", "fn b() {}\n")
+    create_file(repo, "docs/c.md", "x\n")
+    (repo / "src" / "a.rs").write_text("fn main() {}\nfn helper() {}\n")
+    (repo / "src" / "b.rs").write_text("fn b() { helper(); }\n")
+    (repo / "docs" / "c.md").write_text("y\n")
+    run_git(repo, "commit", "-qam", "mixed")
+
+
+def test_suggested_split_command_runs(git_agent_exe, repo):
+    _mixed_commit(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split", "HEAD")
+    assert result.returncode == 0, result.stderr
+    assert "share helper" in result.stderr
+    [command] = result.stdout.splitlines()
+    args = shlex.split(command)
+    assert args[:2] == ["git-surgeon", "split"]
+    assert args.count("--pick") == 1
+    assert "Update docs/c.md" in args
+
+    result = run_git_agent(git_agent_exe, repo, *args[1:])
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:2] == ["mixed", "Update docs/c.md"]
+    files = run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout.split()
+    assert files == ["src/a.rs", "src/b.rs"]
+
+
+def test_plan_file_applies(git_agent_exe, repo):
+    _mixed_commit(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split", "HEAD", "--by", "file", "--plan")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.startswith("steps:\n  - split:\n")
+    (repo / ".git" / "plan.yaml").write_text(result.stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "apply-plan", ".git/plan.yaml")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:3] == ["mixed", "Update src/a.rs", "Update docs/c.md"]
+
+
+def test_working_tree_and_nothing_to_split(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split")
+    assert result.returncode == 0, result.stderr
+    commands = [shlex.split(line) for line in result.stdout.splitlines()]
+    assert [c[-1] for c in commands] == ["Update a.txt", "Update b.txt"]
+    for command in commands:
+        assert command[1] == "commit"
+        result = run_git_agent(git_agent_exe, repo, *command[1:])
+        assert result.returncode == 0, result.stderr
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split", "HEAD")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == ""
+    assert "nothing to split" in result.stderr
+
+
+def _scoped_commit(repo):
+    create_file(repo, "src/api/a.rs", "a\n")
+    create_file(repo, "docs/b.md", "b\n")
+    create_file(repo, "Makefile", "all:\n")
+    (repo / "src" / "api" / "a.rs").write_text("A\n")
+    (repo / "docs" / "b.md").write_text("B\n")
+    (repo / "Makefile").write_text("all: build\n")
+    run_git(repo, "commit", "-qam", "feat: add retries")
+    run_git(repo, "config", "--add", "surgeon.scope", "src/api/**=api")
+    run_git(repo, "config", "--add", "surgeon.scope", "docs=docs")
+
+
+def test_suggest_split_by_scope(git_agent_exe, repo):
+    _scoped_commit(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split", "HEAD", "--by", "scope")
+    assert result.returncode == 0, result.stderr
+    assert "commit 1: docs/b.md (scope docs)" in result.stderr
+    assert "rest (keeps the commit's message): Makefile" in result.stderr
+    args = shlex.split(result.stdout.strip())
+    assert args.count("--pick") == 2
+    assert "feat(api): add retries" in args
+    assert "feat(docs): add retries" in args
+
+
+def test_split_auto_scope(git_agent_exe, repo):
+    _scoped_commit(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--auto-scope")
+    assert result.returncode == 0, result.stderr
+    assert _subjects(repo)[:3] == [
+        "feat: add retries", "feat(api): add retries", "feat(docs): add retries",
+    ]
+    files = run_git(repo, "show", "--name-only", "--format=", "HEAD").stdout.split()
+    assert files == ["Makefile"]
+
+
+def test_scope_needs_mappings(git_agent_exe, repo):
+    _mixed_commit(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "suggest-split", "HEAD", "--by", "scope")
+    assert result.returncode == 2
+    assert "surgeon.scope" in result.stderr
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--auto-scope")
+    assert result.returncode == 2
@@ -0,0 +1,95 @@
+"""Tests for commit message templates (surgeon.messageTemplate)."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+TEMPLATE = "# {scope}: short summary\n{scope}: {subject}\n\n{body}\n\nTouches {files} ({hunk_count} hunks)\n"
+
+
+def _set_template(repo, text=TEMPLATE):
+    (repo / ".git" / "template.txt").write_text(text)
+    run_git(repo, "config", "surgeon.messageTemplate", ".git/template.txt")
+
+
+def _message(repo, rev="HEAD"):
+    return run_git(repo, "log", "-1", "--format=%B", rev).stdout.strip()
+
+
+def test_commit_expands_template(git_agent_exe, repo):
+    (repo / "src").mkdir()
+    create_file(repo, "src/a.py", "a\n")
+    create_file(repo, "src/b.py", "b\n")
+    (repo / "src" / "a.py").write_text("A\n")
+    (repo / "src" / "b.py").write_text("B\n")
+    _set_template(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", *ids, "-m", "Capitalize", "-m", "Both files.",
+        "--trailer", "Fixes=#1",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == (
+        "src: Capitalize\n\nBoth files.\n\nTouches src/a.py, src/b.py (2 hunks)\n\nFixes: #1"
+    )
+
+
+def test_no_template_keeps_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    _set_template(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "plain", "--no-template")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "plain"
+
+
+def test_split_expands_template_per_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    _set_template(repo, "{scope}: {message} [{hunk_count}]\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add b",
+        "--rest-message", "add c",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == "b: add b [1]"
+    assert _message(repo) == "c: add c [1]"
+
+
+def test_split_without_rest_message_keeps_original(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    _set_template(repo, "{scope}: {message}\n")
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == "b: add b"
+    assert _message(repo) == "add b and c"
+
+
+def test_unknown_placeholder_is_an_error(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    _set_template(repo, "{ticket}: {message}\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "change")
+    assert result.returncode != 0
+    assert "unknown placeholder {ticket}" in result.stderr
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
@@ -0,0 +1,40 @@
+"""Tests for files with a textconv diff driver."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _upper_driver(repo):
+    (repo / ".gitattributes").write_text("*.up diff=upper\n")
+    run_git(repo, "config", "diff.upper.textconv", "tr a-z A-Z <")
+
+
+def test_text_file_stages_raw_hunks(git_agent_exe, repo):
+    _upper_driver(repo)
+    create_file(repo, "a.up", "one\ntwo\n")
+    modify_file(repo, "a.up", "one\nthree\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    raw, view = result.stdout.split("\n\n")[:2]
+    assert raw.splitlines()[1:] == ["  -two", "  +three"]
+    assert view.splitlines() == ["(view) a.up (+1 -1)", "  -TWO", "  +THREE"]
+    assert "use the IDs of its raw hunks" in result.stderr
+
+    hunk_id = raw.split()[0]
+    result = run_git_agent(git_agent_exe, repo, "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":a.up").stdout == "one\nthree\n"
+
+
+def test_binary_file_is_view_only(git_agent_exe, repo):
+    _upper_driver(repo)
+    create_file(repo, "b.up", "x\0y\n")
+    modify_file(repo, "b.up", "x\0z\n")
+
+    result = run_git_agent(git_agent_exe, repo, "hunks")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines()[0] == "(view) b.up (+1 -1)"
+    assert "stage it whole with 'git add'" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--format=%id")
+    assert result.stdout == ""
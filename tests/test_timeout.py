@@ -0,0 +1,52 @@
+"""Tests for --timeout."""
+
+import os
+import stat
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _hanging_hook(repo):
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.parent.mkdir(exist_ok=True)
+    hook.write_text("#!/bin/sh\nsleep 30\n")
+    hook.chmod(hook.stat().st_mode | stat.S_IEXEC)
+
+
+def test_timeout_kills_hanging_commit(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    _hanging_hook(repo)
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks").stdout.split()[0]
+    head = run_git(repo, "rev-parse", "HEAD").stdout
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", hunk_id, "-m", "change", "--timeout", "1"
+    )
+    assert result.returncode == 8
+    assert "timed out after 1s" in result.stderr
+    assert "git commit" in result.stderr
+    # The hunk is unstaged again and git's lock is gone
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == ""
+    assert not os.path.exists(repo / ".git" / "index.lock")
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
+
+
+def test_timeout_rolls_back_operation(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+    run_git(repo, "commit", "-qam", "both")
+    head = run_git(repo, "rev-parse", "HEAD").stdout
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout.split()[0]
+    _hanging_hook(repo)
+
+    run_git(repo, "config", "surgeon.timeout", "1")
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", hunk_id, "-m", "first"
+    )
+    assert result.returncode == 8
+    assert "aborted split" in result.stderr
+    assert run_git(repo, "rev-parse", "HEAD").stdout == head
+    assert run_git(repo, "status", "--porcelain").stdout == ""
@@ -0,0 +1,69 @@
+"""Tests for track (intent-to-add) and discarding intent-to-add hunks."""
+
+from conftest import run_git_agent, run_git
+
+
+def _ids(exe, repo):
+    return run_git_agent(exe, repo, "hunks", "--format", "%id").stdout.split()
+
+
+def _status(repo):
+    return run_git(repo, "status", "--porcelain", "-uall").stdout.splitlines()
+
+
+def test_track_makes_new_files_hunks(git_agent_exe, repo):
+    (repo / "sub").mkdir()
+    (repo / "sub" / "a.txt").write_text("a\nb\n")
+    (repo / "sub" / "b.txt").write_text("c\n")
+
+    result = run_git_agent(git_agent_exe, repo, "track", "sub")
+    assert result.returncode == 0, result.stderr
+    ids = _ids(git_agent_exe, repo)
+    assert len(ids) == 2
+    assert "tracking 2 files:" in result.stderr
+    assert f"  sub/a.txt: {ids[0]}" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "stage", ids[0], "--lines", "1")
+    assert result.returncode == 0, result.stderr
+    assert run_git(repo, "show", ":sub/a.txt").stdout == "a\n"
+
+
+def test_track_without_untracked_files(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "track", "missing.txt")
+    assert result.returncode == 3
+    assert "no untracked files in missing.txt" in result.stderr
+
+
+def test_discard_untracks_intent_to_add_file(git_agent_exe, repo):
+    (repo / "new.txt").write_text("hello\n")
+    run_git_agent(git_agent_exe, repo, "track", "new.txt")
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "discard", ids[0])
+    assert result.returncode == 0, result.stderr
+    assert "new.txt: untracked again, file kept" in result.stderr
+    assert _status(repo) == ["?? new.txt"]
+    assert (repo / "new.txt").read_text() == "hello\n"
+
+
+def test_discard_delete_removes_intent_to_add_file(git_agent_exe, repo):
+    (repo / "new.txt").write_text("hello\n")
+    (repo / "other.txt").write_text("x\n")
+    run_git_agent(git_agent_exe, repo, "track", "new.txt", "other.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "discard", "--all", "--yes", "--delete")
+    assert result.returncode == 0, result.stderr
+    assert _status(repo) == []
+    assert not (repo / "new.txt").exists()
+    assert not (repo / "other.txt").exists()
+
+
+def test_discard_lines_of_intent_to_add_file_keeps_it_tracked(git_agent_exe, repo):
+    (repo / "new.txt").write_text("a\nb\n")
+    run_git_agent(git_agent_exe, repo, "track", "new.txt")
+    ids = _ids(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "discard", ids[0], "--lines", "2")
+    assert result.returncode == 0, result.stderr
+    assert (repo / "new.txt").read_text() == "a\n"
+    assert _status(repo) == [" A new.txt"]
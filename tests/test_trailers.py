@@ -0,0 +1,95 @@
+"""Tests for --signoff and --trailer on commit-creating commands."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _message(repo, rev="HEAD"):
+    return run_git(repo, "log", "-1", "--format=%B", rev).stdout.strip()
+
+
+def test_commit_with_trailers_and_signoff(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "commit", ids[0], "-m", "change a",
+        "--trailer", "Fixes=#12", "--trailer", "Reviewed-by: R <r@example.com>", "--signoff",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == (
+        "change a\n\n"
+        "Fixes: #12\n"
+        "Reviewed-by: R <r@example.com>\n"
+        "Signed-off-by: Test <test@test.com>"
+    )
+
+
+def test_invalid_trailer_rejected(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks").stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "commit", ids[0], "-m", "x", "--trailer", "Fixes")
+    assert result.returncode != 0
+    assert "invalid trailer 'Fixes'" in result.stderr
+    assert run_git(repo, "diff", "--cached", "--quiet").returncode == 0
+
+
+def test_reword_and_squash_trailers(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~1", "-m", "add a", "--trailer", "Issue=7"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == "add a\n\nIssue: 7"
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "add both", "-s")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "add both\n\nSigned-off-by: Test <test@test.com>"
+
+
+def test_split_trailers_per_group(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD",
+        "--pick", ids[0], "-m", "add b", "--trailer", "Part=1",
+        "--rest-message", "add c", "--trailer", "Part=2",
+        "--signoff",
+    )
+    assert result.returncode == 0, result.stderr
+    assert _message(repo, "HEAD~1") == "add b\n\nPart: 1\nSigned-off-by: Test <test@test.com>"
+    assert _message(repo) == "add c\n\nPart: 2\nSigned-off-by: Test <test@test.com>"
+
+
+def test_split_signoff_keeps_original_rest_message(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b", "-s")
+    assert result.returncode == 0, result.stderr
+    assert _message(repo) == "add b and c\n\nSigned-off-by: Test <test@test.com>"
+
+
+def test_split_trailer_without_group_rejected(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--trailer", "X=1", "--pick", "abc", "-m", "m")
+    assert result.returncode != 0
+    assert "--trailer must follow a --pick group or --rest-message" in result.stderr
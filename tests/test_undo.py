@@ -89,3 +89,47 @@ def test_undo_hunk_context_mismatch(repo, git_agent_exe):
     # Undo should fail because context no longer matches
     result = run_git_agent(git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD~1")
     assert result.returncode != 0
+
+
+def _drifted_history(git_agent_exe, repo):
+    """A commit changing line 5, then a later edit to line 3 in its context."""
+    lines = [f"line{i}" for i in range(1, 11)]
+    (repo / "file.txt").write_text("\n".join(lines) + "\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add file")
+    lines[4] = "changed5"
+    modify_file(repo, "file.txt", "\n".join(lines) + "\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "modify line5")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout.split()[0]
+    lines[2] = "changed3"
+    modify_file(repo, "file.txt", "\n".join(lines) + "\n")
+    return hunk_id, lines
+
+
+def test_undo_3way_merges_drifted_hunk(repo, git_agent_exe):
+    hunk_id, lines = _drifted_history(git_agent_exe, repo)
+
+    result = run_git_agent(git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD")
+    assert result.returncode != 0
+    assert "--3way" in result.stderr
+
+    result = run_git_agent(git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD", "--3way", "--json")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.strip() == '{"method":"3way","files":[{"file":"file.txt","conflicts":0}]}'
+    lines[4] = "line5"
+    assert (repo / "file.txt").read_text() == "\n".join(lines) + "\n"
+
+
+def test_undo_3way_reports_conflicts(repo, git_agent_exe):
+    hunk_id, lines = _drifted_history(git_agent_exe, repo)
+    lines[4] = "edited5"
+    (repo / "file.txt").write_text("\n".join(lines) + "\n")
+    run_git(repo, "config", "surgeon.threeWay", "true")
+
+    result = run_git_agent(git_agent_exe, repo, "undo", hunk_id, "--from", "HEAD", "--json")
+    assert result.returncode != 0
+    assert '"conflicts":1' in result.stdout
+    assert "left conflict markers in 1 file(s)" in result.stderr
+    content = (repo / "file.txt").read_text()
+    assert "<<<<<<< working tree" in content and "edited5" in content
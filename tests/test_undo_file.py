@@ -85,3 +85,80 @@ def test_undo_file_deletion_lists_correct_path(repo, git_agent_exe):
     assert result.returncode == 0
     assert "gone.txt" in result.stdout
     assert "dev/null" not in result.stdout
+
+
+def test_undo_file_3way(repo, git_agent_exe):
+    lines = [f"line{i}" for i in range(1, 11)]
+    (repo / "file.txt").write_text("\n".join(lines) + "\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add file")
+    lines[4] = "changed5"
+    (repo / "file.txt").write_text("\n".join(lines) + "\n")
+    run_git(repo, "commit", "-am", "modify line5")
+    lines[2] = "changed3"
+    (repo / "file.txt").write_text("\n".join(lines) + "\n")
+
+    result = run_git_agent(git_agent_exe, repo, "undo-file", "file.txt", "--from", "HEAD", "--3way")
+    assert result.returncode == 0, result.stderr
+    lines[4] = "line5"
+    assert (repo / "file.txt").read_text() == "\n".join(lines) + "\n"
+
+
+def test_undo_file_many_files_requires_confirmation(git_agent_exe, repo):
+    for name in ("a.txt", "b.txt", "c.txt"):
+        (repo / name).write_text(name + "\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-qm", "three files")
+
+    args = ["undo-file", "a.txt", "b.txt", "c.txt", "--from", "HEAD"]
+    result = run_git_agent(git_agent_exe, repo, *args)
+    assert result.returncode == 2
+    assert "without --yes" in result.stderr
+    assert (repo / "a.txt").exists()
+
+    result = run_git_agent(git_agent_exe, repo, *args, "-y")
+    assert result.returncode == 0, result.stderr
+    assert not (repo / "a.txt").exists()
+
+
+def _commit_generated(repo):
+    (repo / "src" / "migrations").mkdir(parents=True)
+    (repo / "src" / "migrations" / "01.sql").write_text("one\n")
+    (repo / "src" / "migrations" / "02.sql").write_text("two\n")
+    (repo / "src" / "main.rs").write_text("fn main() {}\n")
+    (repo / "Cargo.lock").write_text("lock\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add migrations")
+
+
+def test_undo_file_directory(repo, git_agent_exe):
+    _commit_generated(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "undo-file", "src/migrations", "--from", "HEAD")
+    assert result.returncode == 0, result.stderr
+    assert "src/migrations: src/migrations/01.sql, src/migrations/02.sql" in result.stderr
+    assert not (repo / "src" / "migrations" / "01.sql").exists()
+    assert not (repo / "src" / "migrations" / "02.sql").exists()
+    assert (repo / "src" / "main.rs").exists()
+
+
+def test_undo_file_globs(repo, git_agent_exe):
+    _commit_generated(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "undo-file", "src/**/0?.sql", "*.lock", "--from", "HEAD", "--yes"
+    )
+    assert result.returncode == 0, result.stderr
+    assert "*.lock: Cargo.lock" in result.stderr
+    assert not (repo / "Cargo.lock").exists()
+    assert not (repo / "src" / "migrations" / "02.sql").exists()
+    assert (repo / "src" / "main.rs").exists()
+
+
+def test_undo_file_glob_without_match(repo, git_agent_exe):
+    _commit_generated(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "undo-file", "*.py", "--from", "HEAD")
+    assert result.returncode == 3
+    assert "no file in commit HEAD matches *.py" in result.stderr
+    assert (repo / "Cargo.lock").exists()
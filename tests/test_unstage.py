@@ -37,3 +37,33 @@ def test_unstage_single_hunk(git_agent_exe, repo):
 def test_unstage_invalid_id(git_agent_exe, repo):
     result = run_git_agent(git_agent_exe, repo, "unstage", "invalid")
     assert result.returncode != 0
+
+
+def test_unstage_reports_remaining_staged(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+    run_git(repo, "add", "a.txt", "b.txt")
+    ids = _get_hunk_ids(git_agent_exe, repo, "--staged")
+
+    result = run_git_agent(git_agent_exe, repo, "unstage", ids[1])
+    assert result.returncode == 0, result.stderr
+    lines = result.stderr.splitlines()
+    assert lines[0] == f"unstaged 1 hunk: {ids[1]}"
+    assert lines[1] == "  b.txt | 1 hunk  +1 -1"
+    assert lines[2] == f"1 staged hunk remains: {ids[0]}"
+
+
+def test_unstage_all(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    modify_file(repo, "a.txt", "A\n")
+    modify_file(repo, "b.txt", "B\n")
+    run_git(repo, "add", "a.txt", "b.txt")
+
+    result = run_git_agent(git_agent_exe, repo, "unstage", "--all")
+    assert result.returncode == 0, result.stderr
+    assert result.stderr.splitlines()[0].startswith("unstaged 2 hunks: ")
+    assert run_git(repo, "diff", "--cached").stdout == ""
+    assert run_git(repo, "diff", "--name-only").stdout == "a.txt\nb.txt\n"
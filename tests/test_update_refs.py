@@ -0,0 +1,113 @@
+"""Tests for --update-refs, which moves branches along with a rewrite."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _sha(repo, ref="HEAD"):
+    return run_git(repo, "rev-parse", ref).stdout.strip()
+
+
+def _stack(repo):
+    """Three commits with a bookmark branch on the first two."""
+    create_file(repo, "a.txt", "a\n")
+    run_git(repo, "branch", "part-a")
+    create_file(repo, "b.txt", "b\n")
+    run_git(repo, "branch", "part-b")
+    create_file(repo, "c.txt", "c\n")
+
+
+def test_update_refs_moves_branches_at_rewritten_commits(git_agent_exe, repo):
+    _stack(repo)
+    old_a = _sha(repo, "part-a")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~2", "-m", "add a, reworded", "--update-refs"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _sha(repo, "part-a") == _sha(repo, "HEAD~2")
+    assert _sha(repo, "part-b") == _sha(repo, "HEAD~1")
+    assert "updated refs:" in result.stderr
+    new_a = _sha(repo, "part-a")
+    assert f"  part-a: {old_a[:7]} -> {new_a[:7]}" in result.stderr
+
+
+def test_branches_stay_without_update_refs(git_agent_exe, repo):
+    _stack(repo)
+    old_a = _sha(repo, "part-a")
+
+    result = run_git_agent(git_agent_exe, repo, "reword", "HEAD~2", "-m", "reworded")
+    assert result.returncode == 0, result.stderr
+    assert _sha(repo, "part-a") == old_a
+    assert "updated refs" not in result.stderr
+
+
+def test_update_refs_from_config(git_agent_exe, repo):
+    _stack(repo)
+    run_git(repo, "config", "surgeon.updateRefs", "true")
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "b and c")
+    assert result.returncode == 0, result.stderr
+    # part-b's commit was folded into the squash; part-a's is unchanged
+    assert "part-b points at" in result.stderr
+    assert _sha(repo, "part-a") == _sha(repo, "HEAD~1")
+
+
+def test_update_refs_leaves_checked_out_branches(git_agent_exe, repo):
+    _stack(repo)
+    old_b = _sha(repo, "part-b")
+    run_git(repo, "worktree", "add", "-q", str(repo / ".git" / "wt"), "part-b")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~2", "-m", "reworded", "--update-refs"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _sha(repo, "part-b") == old_b
+    assert _sha(repo, "part-a") == _sha(repo, "HEAD~2")
+
+
+def test_update_refs_after_split_picks(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+    run_git(repo, "commit", "-qam", "both")
+    run_git(repo, "branch", "mark")
+    create_file(repo, "c.txt", "c\n")
+    old_mark = _sha(repo, "mark")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD~1").stdout.split()[0]
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~1", "--pick", hunk_id, "-m", "one", "--update-refs"
+    )
+    assert result.returncode == 0, result.stderr
+    assert "updated refs:" in result.stderr
+    assert _sha(repo, "mark") != old_mark
+    assert run_git(repo, "merge-base", "--is-ancestor", "mark", "HEAD").returncode == 0
+
+
+def test_restore_backup_moves_branches_back(git_agent_exe, repo):
+    _stack(repo)
+    old_head = _sha(repo)
+    old_a = _sha(repo, "part-a")
+    old_b = _sha(repo, "part-b")
+
+    result = run_git_agent(
+        git_agent_exe, repo, "reword", "HEAD~2", "-m", "reworded", "--update-refs"
+    )
+    assert result.returncode == 0, result.stderr
+    new_head = _sha(repo)
+    new_a = _sha(repo, "part-a")
+    assert new_a != old_a
+
+    result = run_git_agent(git_agent_exe, repo, "restore-backup")
+    assert result.returncode == 0, result.stderr
+    assert _sha(repo) == old_head
+    assert _sha(repo, "part-a") == old_a
+    assert _sha(repo, "part-b") == old_b
+    assert f"restored part-a to {old_a[:7]}" in result.stderr
+
+    # The restore backed up where it moved them from, so it can be undone
+    result = run_git_agent(git_agent_exe, repo, "restore-backup")
+    assert result.returncode == 0, result.stderr
+    assert _sha(repo) == new_head
+    assert _sha(repo, "part-a") == new_a
@@ -0,0 +1,75 @@
+"""Tests for the --quiet and --verbose flags."""
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def test_quiet_prints_only_errors(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks").stdout.split()[0]
+
+    result = run_git_agent(git_agent_exe, repo, "-q", "stage", hunk_id)
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == ""
+    assert result.stderr == ""
+    assert run_git(repo, "diff", "--cached", "--name-only").stdout == "a.txt\n"
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "0000000", "--quiet")
+    assert result.returncode == 3
+    assert "not found" in result.stderr
+
+
+def test_verbose_traces_git_commands(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+
+    result = run_git_agent(git_agent_exe, repo, "-v", "hunks")
+    assert result.returncode == 0, result.stderr
+    traced = [l for l in result.stderr.splitlines() if l.startswith("+ git ")]
+    assert any(" diff " in l and l.endswith(" ms)") for l in traced)
+    assert "a.txt" in result.stdout
+
+    result = run_git_agent(git_agent_exe, repo, "-q", "-v", "hunks")
+    assert result.returncode == 2
+
+
+def test_very_verbose_shows_failed_command_output(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "a.txt").write_text("A\n")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks").stdout.split()[0]
+    run_git(repo, "commit", "-qam", "change")
+    (repo / "a.txt").write_text("something else\n")
+
+    result = run_git_agent(git_agent_exe, repo, "-vv", "undo", hunk_id, "--from", "HEAD")
+    assert result.returncode == 4
+    lines = result.stderr.splitlines()
+    apply = next(i for i, l in enumerate(lines) if l.startswith("+ git apply "))
+    assert lines[apply + 1] == "  exit status: 1"
+
+
+def test_flags_after_split_picks(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    (repo / "a.txt").write_text("A\n")
+    (repo / "b.txt").write_text("B\n")
+    run_git(repo, "commit", "-qam", "both")
+    hunk_id = run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout.split()[0]
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD", "--pick", hunk_id, "-m", "one",
+        "--rest-message", "two", "-q", "--timeout", "30",
+    )
+    assert result.returncode == 0, result.stderr
+    assert result.stdout == ""
+    assert result.stderr == ""
+    assert run_git(repo, "log", "--format=%s", "-2").stdout == "two\none\n"
+
+    result = run_git_agent(
+        git_agent_exe, repo, "split", "HEAD~1", "--pick", "0000000", "-m", "x", "-v",
+    )
+    assert result.returncode == 3
+    assert any(l.startswith("+ git ") for l in result.stderr.splitlines())
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", hunk_id, "-m", "x", "stray")
+    assert result.returncode == 2
+    assert "unexpected argument 'stray'" in result.stderr
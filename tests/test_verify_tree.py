@@ -0,0 +1,104 @@
+"""Tests for checking that rewrites keep the final tree unchanged."""
+
+import os
+
+from conftest import run_git_agent, run_git, create_file
+
+
+def _get_hunk_ids(output):
+    return [line.split()[0] for line in output.splitlines() if line and not line.startswith("  ")]
+
+
+def _head(repo):
+    return run_git(repo, "rev-parse", "HEAD").stdout.strip()
+
+
+def _install_tampering_hook(repo):
+    """A pre-commit hook that sneaks an extra change into every commit."""
+    hook = repo / ".git" / "hooks" / "pre-commit"
+    hook.parent.mkdir(parents=True, exist_ok=True)
+    hook.write_text("#!/bin/sh\necho tampered >> a.txt\ngit add a.txt\n")
+    os.chmod(hook, 0o755)
+
+
+def _setup(repo):
+    create_file(repo, "a.txt", "a\n")
+    create_file(repo, "b.txt", "b\n")
+    create_file(repo, "c.txt", "c\n")
+
+
+def test_squash_tree_change_rolled_back(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+    _install_tampering_hook(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "squash", "HEAD~1", "-m", "b and c")
+    assert result.returncode != 0
+    assert "squash changed the final tree" in result.stderr
+    assert "a.txt" in result.stderr
+    assert f"rolled back to {before[:7]}" in result.stderr
+    assert _head(repo) == before
+    assert (repo / "a.txt").read_text() == "a\n"
+
+
+def test_no_verify_tree_keeps_result(git_agent_exe, repo):
+    _setup(repo)
+    before = _head(repo)
+    _install_tampering_hook(repo)
+
+    result = run_git_agent(
+        git_agent_exe, repo, "squash", "HEAD~1", "-m", "b and c", "--no-verify-tree"
+    )
+    assert result.returncode == 0, result.stderr
+    assert _head(repo) != before
+    assert "tampered" in run_git(repo, "show", "HEAD:a.txt").stdout
+
+
+def test_split_tree_change_rolled_back(git_agent_exe, repo):
+    create_file(repo, "a.txt", "a\n")
+    (repo / "b.txt").write_text("b\n")
+    (repo / "c.txt").write_text("c\n")
+    run_git(repo, "add", ".")
+    run_git(repo, "commit", "-m", "add b and c")
+    before = _head(repo)
+    ids = _get_hunk_ids(run_git_agent(git_agent_exe, repo, "hunks", "--commit", "HEAD").stdout)
+    _install_tampering_hook(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "split", "HEAD", "--pick", ids[0], "-m", "add b")
+    assert result.returncode != 0
+    assert "split changed the final tree" in result.stderr
+    assert _head(repo) == before
+    assert run_git(repo, "status", "--porcelain").stdout == ""
+
+
+def test_reorder_bad_conflict_resolution_rolled_back(git_agent_exe, repo):
+    for content in ("1\n", "2\n", "3\n"):
+        (repo / "a.txt").write_text(content)
+        run_git(repo, "add", "a.txt")
+        run_git(repo, "commit", "-m", f"a.txt = {content.strip()}")
+    before = _head(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~2..HEAD", "--order", "2", "1")
+    assert result.returncode != 0
+
+    # Resolve every conflict so the end result differs from the original
+    for _ in range(3):
+        (repo / "a.txt").write_text("wrong\n")
+        run_git(repo, "add", "a.txt")
+        result = run_git_agent(git_agent_exe, repo, "continue")
+        if "still has unresolved problems" not in result.stderr:
+            break
+    assert result.returncode != 0
+    assert "reorder changed the final tree" in result.stderr
+    assert _head(repo) == before
+    assert (repo / "a.txt").read_text() == "3\n"
+    assert not (repo / ".git" / "surgeon" / "state").exists()
+
+
+def test_reorder_verified_when_tree_unchanged(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "reorder", "HEAD~2..HEAD", "--order", "2", "1")
+    assert result.returncode == 0, result.stderr
+    assert "changed the final tree" not in result.stderr
+    assert run_git(repo, "log", "-1", "--format=%s").stdout.strip() == "add b.txt"
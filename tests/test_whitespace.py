@@ -0,0 +1,42 @@
+"""Tests for telling whitespace-only hunks apart from real changes."""
+
+from conftest import run_git_agent, run_git, create_file, modify_file
+
+
+def _setup(repo):
+    """A reindented hunk at the top of a.txt and a real change at the bottom."""
+    create_file(repo, "a.txt", "if x:\n  y()\n" + "mid\n" * 20 + "old\n")
+    modify_file(repo, "a.txt", "if x:\n    y()\n\n" + "mid\n" * 20 + "new\n")
+
+
+def test_hunks_ignore_whitespace(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "--format=%first_line")
+    assert result.stdout.splitlines() == ["-  y()", "-old"]
+
+    result = run_git_agent(git_agent_exe, repo, "hunks", "-w", "--format=%first_line")
+    assert result.returncode == 0, result.stderr
+    assert result.stdout.splitlines() == ["-old"]
+    assert "(1 whitespace-only hunk(s) hidden)" in result.stderr
+
+
+def test_stage_whitespace_only(git_agent_exe, repo):
+    _setup(repo)
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "--whitespace-only")
+    assert result.returncode == 0, result.stderr
+    staged = run_git(repo, "diff", "--cached").stdout
+    assert "+    y()" in staged
+    assert "+new" not in staged
+
+    result = run_git_agent(git_agent_exe, repo, "stage", "--whitespace-only")
+    assert result.returncode != 0
+    assert "no unstaged hunk only changes whitespace" in result.stderr
+
+
+def test_stage_whitespace_only_takes_no_ids(git_agent_exe, repo):
+    _setup(repo)
+    result = run_git_agent(git_agent_exe, repo, "stage", "--whitespace-only", "@1")
+    assert result.returncode != 0
+    assert run_git(repo, "diff", "--cached").stdout == ""
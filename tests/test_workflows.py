@@ -0,0 +1,28 @@
+"""Tests for the workflows command."""
+
+import json
+
+from conftest import run_git_agent
+
+
+def test_workflows_lists_recipes(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "workflows")
+    assert result.returncode == 0, result.stderr
+    assert "Split a commit (split-commit)" in result.stdout
+    assert "$ git-surgeon fixup <sha>" in result.stdout
+    # Descriptions come from the CLI definition
+    assert "(split: Split a commit into multiple commits by hunk selection)" in result.stdout
+
+
+def test_workflows_json_single(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "workflows", "partial-revert", "--json")
+    assert result.returncode == 0, result.stderr
+    [recipe] = json.loads(result.stdout)
+    assert recipe["name"] == "partial-revert"
+    assert [s["command"] for s in recipe["steps"]] == ["hunks", "undo", "commit"]
+
+
+def test_workflows_unknown_name(git_agent_exe, repo):
+    result = run_git_agent(git_agent_exe, repo, "workflows", "bogus")
+    assert result.returncode != 0
+    assert "available: split-commit" in result.stderr